@@ -58,8 +58,15 @@ document.getElementById('btn-download-config').onclick = function() {
     a.click();
 }
 
-for (let key in objects.datasets) {
-    let element = objects.datasets[key];
+document.getElementById('btn-download-provenance').onclick = function() {
+    let blob = new Blob([JSON.stringify(objects.provenance.first, null, 2)], {type: 'application/json'});
+    var a = document.createElement('a');
+    a.href = URL.createObjectURL(blob);
+    a.download = 'provenance.json';
+    a.click();
+}
+
+function renderDataset(element) {
     if (element instanceof Bar) {
         let h = element;
         let ctx = document.getElementById('chart-bar-' + h.id);
@@ -145,7 +152,7 @@ for (let key in objects.datasets) {
             let opt = {
                 "actions": false,
             };
-            vegaEmbed(`#${CSS.escape(thisId)}`, copied_spec, opt).then(({ view, spec, vgSpec }) => {
+            vegaEmbed(`#${CSS.escape(thisId)}`, withDisplay(copied_spec, thisId), opt).then(({ view, spec, vgSpec }) => {
                 if (add_listeners) {
                     // Export PNG
                     let png_button = document.getElementById('btn-download-plot-png-' + h.id);
@@ -319,7 +326,7 @@ for (let key in objects.datasets) {
             let opt = {
                 "actions": false,
             };
-            vegaEmbed(`#${CSS.escape(thisId)}`, copied_spec, opt).then(({ view, spec, vgSpec }) => {
+            vegaEmbed(`#${CSS.escape(thisId)}`, withDisplay(copied_spec, thisId), opt).then(({ view, spec, vgSpec }) => {
                 if (add_listeners) {
                     // Export PNG
                     let png_button = document.getElementById('btn-download-plot-png-' + m.id);
@@ -358,7 +365,7 @@ for (let key in objects.datasets) {
             });
         }
 
-        render("linear", id, yourVlSpec, true);
+        render(getDisplay(id).log_y ? "log" : "linear", id, yourVlSpec, true);
     } else if (element instanceof SectionLine) {
         let m = element;
         var ctx = document.getElementById('chart-bar-' + m.id);
@@ -494,7 +501,7 @@ for (let key in objects.datasets) {
             let opt = {
                 "actions": false,
             };
-            vegaEmbed(`#${CSS.escape(thisId)}`, copied_spec, opt).then(({ view, spec, vgSpec }) => {
+            vegaEmbed(`#${CSS.escape(thisId)}`, withDisplay(copied_spec, thisId), opt).then(({ view, spec, vgSpec }) => {
                 if (add_listeners) {
                     // Export PNG
                     let png_button = document.getElementById('btn-download-plot-png-' + m.id);
@@ -533,7 +540,7 @@ for (let key in objects.datasets) {
             });
         }
 
-        render("linear", id, yourVlSpec, true);
+        render(getDisplay(id).log_y ? "log" : "linear", id, yourVlSpec, true);
     } else if (element instanceof Line) {
         let l = element;
         let thisId = 'chart-line-' + l.id;
@@ -580,7 +587,7 @@ for (let key in objects.datasets) {
         let opt = {
             "actions": false,
         };
-        vegaEmbed(`#${CSS.escape(thisId)}`, mySpec, opt).then(({ view, spec, vgSpec }) => {
+        vegaEmbed(`#${CSS.escape(thisId)}`, withDisplay(mySpec, thisId), opt).then(({ view, spec, vgSpec }) => {
             // Export PNG
             let png_button = document.getElementById('btn-download-plot-png-' + l.id);
             png_button.addEventListener('click', () => {
@@ -702,7 +709,7 @@ for (let key in objects.datasets) {
             let opt = {
                 "actions": false,
             };
-        vegaEmbed(`#${CSS.escape(thisId)}`, copied_spec, opt).then(({ view, spec, vgSpec }) => {
+        vegaEmbed(`#${CSS.escape(thisId)}`, withDisplay(copied_spec, thisId), opt).then(({ view, spec, vgSpec }) => {
 
             if (addListeners) {
 
@@ -760,6 +767,7 @@ for (let key in objects.datasets) {
     } else if (element instanceof Hexbin) {
         let h = element;
         let thisId = 'chart-hexbin-' + h.id;
+        const resolution = getDisplay(thisId).hexbin_resolution || [15, 9];
         // buildPlotDownload(myChart, h.id, fname);
         let mySpec = {
             "$schema": "https://vega.github.io/schema/vega-lite/v6.json",
@@ -771,7 +779,7 @@ for (let key in objects.datasets) {
             "mark": {
                 "type": "text",
                 "text": "⬢",
-                "size": 81,
+                "size": 81 * 15 / resolution[0],
                 "clip": true,
                 "tooltip": true,
             },
@@ -811,7 +819,7 @@ for (let key in objects.datasets) {
         let opt = {
             "actions": false,
         };
-        vegaEmbed(`#${CSS.escape(thisId)}`, mySpec, opt).then(({ view, spec, vgSpec }) => {
+        vegaEmbed(`#${CSS.escape(thisId)}`, withDisplay(mySpec, thisId), opt).then(({ view, spec, vgSpec }) => {
             let list_button = document.getElementById('btn-download-node-list-' + h.id);
             list_button.addEventListener('click', () => {
                 let ids = new Array();
@@ -919,7 +927,7 @@ for (let key in objects.datasets) {
         let opt = {
             "actions": false,
         };
-        vegaEmbed(`#${CSS.escape(thisId)}`, mySpec, opt).then(({ view, spec, vgSpec }) => {
+        vegaEmbed(`#${CSS.escape(thisId)}`, withDisplay(mySpec, thisId), opt).then(({ view, spec, vgSpec }) => {
             // Export PNG
             let png_button = document.getElementById('btn-download-plot-png-' + h.id);
             png_button.addEventListener('click', () => {
@@ -960,7 +968,7 @@ for (let key in objects.datasets) {
         let opt = {
             "actions": false,
         };
-        vegaEmbed(`#${CSS.escape(thisId)}`, v.jsonContent, opt).then(({ view, spec, vgSpec }) => {
+        vegaEmbed(`#${CSS.escape(thisId)}`, withDisplay(v.jsonContent, thisId), opt).then(({ view, spec, vgSpec }) => {
             // Export PNG
             let png_button = document.getElementById('btn-download-plot-png-' + v.id);
             png_button.addEventListener('click', () => {
@@ -1017,7 +1025,81 @@ for (let key in objects.datasets) {
     }
 }
 
+// Log scale toggles start in the state given by the display options
+for (let id in objects.display || {}) {
+    const pane = document.getElementById('nav-' + id);
+    if (pane !== null && objects.display[id].log_y !== null) {
+        pane.querySelectorAll('input[id^="btn-logscale-plot-"]').forEach(input => {
+            input.checked = objects.display[id].log_y;
+        });
+    }
+}
+
+for (let key in objects.datasets) {
+    renderDataset(objects.datasets[key]);
+}
+
+// Datasets of large sections are compressed (see --inline-limit) or in
+// sidecar files (see --sidecar-dir), they are rendered once their tab is shown
+const loadedSections = new Set();
+
+async function loadSection(id) {
+    const keys = (objects.sections || {})[id];
+    if (keys === undefined || loadedSections.has(id)) {
+        return;
+    }
+    loadedSections.add(id);
+    const sidecar = (objects.sidecars || {})[id];
+    if (sidecar !== undefined) {
+        await loadScript(sidecar);
+    }
+    for (const key of keys) {
+        if (sidecar !== undefined) {
+            renderDataset(panacusDatasets[key]);
+        } else {
+            const text = await decodeBlob(objects.blobs[key]);
+            renderDataset(Function('"use strict"; return (' + text + ');')());
+        }
+    }
+}
+
+document.addEventListener('shown.bs.tab', event => {
+    const target = event.target.getAttribute('data-bs-target') || '';
+    if (target.startsWith('#nav-')) {
+        loadSection(target.substring('#nav-'.length)).catch(console.error);
+    }
+});
+
 for (let key in objects.tables) {
     let table = objects.tables[key];
     buildTableDownload(table, key, key + '_' + fname);
 }
+
+for (let key in objects.table_blobs || {}) {
+    buildTableDownload(() => decodeBlob(objects.table_blobs[key]), key, key + '_' + fname);
+}
+
+for (let key in objects.sidecar_tables || {}) {
+    const src = objects.sidecar_tables[key];
+    buildTableDownload(() => loadScript(src).then(() => panacusTables[key]), key, key + '_' + fname);
+}
+
+// Sort report tables by a column when clicking on its header
+document.querySelectorAll('table.sortable-table').forEach(table => {
+    table.querySelectorAll('th').forEach((th, column) => {
+        th.addEventListener('click', () => {
+            const ascending = th.dataset.order !== 'asc';
+            table.querySelectorAll('th').forEach(other => delete other.dataset.order);
+            th.dataset.order = ascending ? 'asc' : 'desc';
+            const tbody = table.querySelector('tbody');
+            const rows = Array.from(tbody.querySelectorAll('tr'));
+            rows.sort((a, b) => {
+                const x = a.children[column].textContent;
+                const y = b.children[column].textContent;
+                const cmp = (isNaN(x) || isNaN(y)) ? x.localeCompare(y) : Number(x) - Number(y);
+                return ascending ? cmp : -cmp;
+            });
+            rows.forEach(row => tbody.appendChild(row));
+        });
+    });
+});
@@ -105,9 +105,11 @@ function buildPlotDownload(chart, obj, prefix) {
     };
 }
 
+// The table is either its content, or a function returning a promise of it
 function buildTableDownload(table, id, prefix) {
-    document.getElementById('btn-download-table-' + id).onclick = function() {
-        let blob = new Blob([table], {type: 'text/plain'});
+    document.getElementById('btn-download-table-' + id).onclick = async function() {
+        let content = (typeof table === 'function') ? await table() : table;
+        let blob = new Blob([content], {type: 'text/plain'});
         var a = document.createElement('a');
         a.href = URL.createObjectURL(blob);
         a.download = prefix + '_table.tsv';
@@ -154,3 +156,61 @@ function buildColorSlider(chart, name) {
         chart.update();
     });
 }
+
+// The display options of the section of the element (see `display:` in the
+// config), an empty object if there are none
+function getDisplay(elementId) {
+    const element = document.getElementById(elementId);
+    const pane = element ? element.closest('.tab-pane') : null;
+    if (pane === null) {
+        return {};
+    }
+    const display = (objects.display || {})[pane.id.substring('nav-'.length)];
+    return display || {};
+}
+
+// Applies the palette and dimensions of the display options to a Vega-Lite
+// spec. Concatenated views keep the dimensions of their subviews.
+function withDisplay(spec, elementId) {
+    const display = getDisplay(elementId);
+    if (!('vconcat' in spec) && !('hconcat' in spec)) {
+        if (display.width) {
+            spec.width = display.width;
+        }
+        if (display.height) {
+            spec.height = display.height;
+        }
+    }
+    if (display.palette && display.palette.length > 0) {
+        spec.config = spec.config || {};
+        spec.config.range = Object.assign({}, spec.config.range, {category: display.palette});
+        spec.config.mark = Object.assign({}, spec.config.mark, {color: display.palette[0]});
+    }
+    return spec;
+}
+
+// Filled by the sidecar files of the report (see --sidecar-dir)
+const panacusDatasets = {};
+const panacusTables = {};
+
+// Decodes a base64-encoded, gzip-compressed blob of the report
+async function decodeBlob(blob) {
+    const bytes = Uint8Array.from(atob(blob), c => c.charCodeAt(0));
+    const stream = new Blob([bytes]).stream().pipeThrough(new DecompressionStream('gzip'));
+    return await new Response(stream).text();
+}
+
+const loadedScripts = {};
+
+function loadScript(src) {
+    if (!(src in loadedScripts)) {
+        loadedScripts[src] = new Promise((resolve, reject) => {
+            const script = document.createElement('script');
+            script.src = src;
+            script.onload = resolve;
+            script.onerror = () => reject(new Error('could not load ' + src));
+            document.head.appendChild(script);
+        });
+    }
+    return loadedScripts[src];
+}
@@ -1,36 +1,58 @@
+pub mod copy_number;
 pub mod coverage_colors;
 pub mod coverage_line;
+pub mod custom;
 pub mod growth;
 pub mod hist;
 pub mod info;
+pub mod length_classes;
 pub mod node_distribution;
+pub mod non_reference_anchors;
 pub mod ordered_histgrowth;
+pub mod plugin;
 pub mod regional_growth;
 pub mod regional_helpers;
 pub mod regional_variation;
+pub mod result_table;
 pub mod section_growth;
 pub mod similarity;
+pub mod subgraph_layout;
 pub mod table;
 
 use std::collections::HashSet;
 
 use crate::{
-    coverage_matrix::CoverageMatrix, file_formats::gfa_parser::PathSegment, hist::Hist,
-    html_report::AnalysisSection, util::CountType,
+    coverage_matrix::CoverageMatrix,
+    error::PanacusError,
+    file_formats::gfa_parser::PathSegment,
+    hist::Hist,
+    html_report::{AnalysisSection, DisplayOptions},
+    util::CountType,
 };
+use result_table::ResultTable;
 
 pub trait HistBasedAnalysis {
-    fn generate_table(&mut self, hist: &Hist) -> anyhow::Result<String>;
-    fn generate_report_section(&mut self, hist: &Hist) -> anyhow::Result<Vec<AnalysisSection>>;
+    fn generate_table(&mut self, hist: &Hist) -> Result<String, PanacusError>;
+    fn generate_report_section(
+        &mut self,
+        hist: &Hist,
+    ) -> Result<Vec<AnalysisSection>, PanacusError>;
+    /// Results in the common format of `--output-format`
+    fn generate_result_tables(&mut self, hist: &Hist) -> Result<Vec<ResultTable>, PanacusError>;
     fn get_type(&self) -> String;
 }
 
 pub trait MatrixBasedAnalysis {
-    fn generate_table(&mut self, hist: &CoverageMatrix) -> anyhow::Result<String>;
+    fn generate_table(&mut self, hist: &CoverageMatrix) -> Result<String, PanacusError>;
     fn generate_report_section(
         &mut self,
         hist: &CoverageMatrix,
-    ) -> anyhow::Result<Vec<AnalysisSection>>;
+    ) -> Result<Vec<AnalysisSection>, PanacusError>;
+    /// Results in the common format of `--output-format`
+    fn generate_result_tables(
+        &mut self,
+        matrix: &CoverageMatrix,
+    ) -> Result<Vec<ResultTable>, PanacusError>;
     fn get_type(&self) -> String;
 }
 
@@ -39,11 +61,94 @@ pub enum Analysis {
     MatrixBased(Box<dyn MatrixBasedAnalysis>),
 }
 
+impl Analysis {
+    /// Applies the `display:` options to the report sections of the analysis
+    pub fn with_display(self, display: DisplayOptions) -> Self {
+        if display.is_default() {
+            return self;
+        }
+        match self {
+            Self::HistBased(analysis) => Self::HistBased(Box::new(Displayed { analysis, display })),
+            Self::MatrixBased(analysis) => {
+                Self::MatrixBased(Box::new(Displayed { analysis, display }))
+            }
+        }
+    }
+}
+
+/// An analysis whose report sections get the `display:` options of its config
+struct Displayed<A> {
+    analysis: A,
+    display: DisplayOptions,
+}
+
+impl<A> Displayed<A> {
+    fn apply(
+        &self,
+        sections: Result<Vec<AnalysisSection>, PanacusError>,
+    ) -> Result<Vec<AnalysisSection>, PanacusError> {
+        let mut sections = sections?;
+        for section in &mut sections {
+            section.apply_display(&self.display);
+        }
+        Ok(sections)
+    }
+}
+
+impl HistBasedAnalysis for Displayed<Box<dyn HistBasedAnalysis>> {
+    fn generate_table(&mut self, hist: &Hist) -> Result<String, PanacusError> {
+        self.analysis.generate_table(hist)
+    }
+
+    fn generate_report_section(
+        &mut self,
+        hist: &Hist,
+    ) -> Result<Vec<AnalysisSection>, PanacusError> {
+        let sections = self.analysis.generate_report_section(hist);
+        self.apply(sections)
+    }
+
+    fn generate_result_tables(&mut self, hist: &Hist) -> Result<Vec<ResultTable>, PanacusError> {
+        self.analysis.generate_result_tables(hist)
+    }
+
+    fn get_type(&self) -> String {
+        self.analysis.get_type()
+    }
+}
+
+impl MatrixBasedAnalysis for Displayed<Box<dyn MatrixBasedAnalysis>> {
+    fn generate_table(&mut self, matrix: &CoverageMatrix) -> Result<String, PanacusError> {
+        self.analysis.generate_table(matrix)
+    }
+
+    fn generate_report_section(
+        &mut self,
+        matrix: &CoverageMatrix,
+    ) -> Result<Vec<AnalysisSection>, PanacusError> {
+        let sections = self.analysis.generate_report_section(matrix);
+        self.apply(sections)
+    }
+
+    fn generate_result_tables(
+        &mut self,
+        matrix: &CoverageMatrix,
+    ) -> Result<Vec<ResultTable>, PanacusError> {
+        self.analysis.generate_result_tables(matrix)
+    }
+
+    fn get_type(&self) -> String {
+        self.analysis.get_type()
+    }
+}
+
 #[derive(PartialEq, Eq, Debug, Clone, Hash)]
 pub enum InputRequirement {
     Node,
     Edge,
     Bp,
+    Bubble,
+    Allele,
     PathLens,
     Hist,
     AbacusByGroup(CountType),
@@ -57,6 +162,8 @@ impl InputRequirement {
             CountType::Bp => HashSet::from([InputRequirement::Bp]),
             CountType::Node => HashSet::from([InputRequirement::Node]),
             CountType::Edge => HashSet::from([InputRequirement::Edge]),
+            CountType::Bubble => HashSet::from([InputRequirement::Bubble]),
+            CountType::Allele => HashSet::from([InputRequirement::Allele]),
         }
     }
 }
@@ -0,0 +1,420 @@
+use anyhow::anyhow;
+
+use crate::analyses::result_table::{to_values, Column, ResultTable, Value};
+use crate::error::PanacusError;
+use crate::{
+    analyses::MatrixBasedAnalysis,
+    coverage_matrix::CoverageMatrix,
+    html_report::{AnalysisSection, DisplayOptions, ReportItem},
+    util::get_default_plot_downloads,
+};
+
+/// Copy-number aware statistics, based on how often each path/group
+/// traverses a countable (instead of just whether it does). Reports a
+/// copy-number histogram per group, the repeat content of each group (i.e.
+/// the bp in countables traversed more than once) and all countables whose
+/// copy number differs between the groups traversing them. This helps
+/// finding collapsed or expanded regions in assemblies.
+pub struct CopyNumber {
+    max_copy_number: usize,
+    inner: Option<CopyNumberResult>,
+}
+
+/// Result of the `CopyNumber` analysis
+#[derive(Debug, Clone, PartialEq)]
+pub struct CopyNumberResult {
+    /// `hist[group][c - 1]` is the number of countables (or bp) that a
+    /// group traverses `c` times, the last entry also counts all higher
+    /// copy numbers if the maximum copy number was reached
+    pub hist: Vec<Vec<usize>>,
+    /// Repeat content of each group
+    pub repeats: Vec<RepeatRow>,
+    /// Countables with differing copy numbers between groups
+    pub variable: Vec<VariableRow>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RepeatRow {
+    pub group: String,
+    pub countables: usize,
+    pub repeated: usize,
+    pub bp: usize,
+    pub repeat_bp: usize,
+    pub max_copy_number: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VariableRow {
+    pub name: String,
+    pub length: usize,
+    pub min: usize,
+    pub max: usize,
+    pub carriers: usize,
+    pub repeated: usize,
+}
+
+impl MatrixBasedAnalysis for CopyNumber {
+    fn generate_result_tables(
+        &mut self,
+        matrix: &CoverageMatrix,
+    ) -> Result<Vec<ResultTable>, PanacusError> {
+        self.set_inner(matrix)?;
+        let inner = self.inner.as_ref().unwrap();
+        let k = matrix.get_feature_type();
+        let get_table = |name: &str, columns: Vec<Column>| {
+            ResultTable::new(
+                &self.get_type(),
+                name,
+                matrix.get_run_id(),
+                matrix.get_run_name(),
+                k,
+                columns,
+            )
+        };
+
+        let mut columns = vec![Column::new("copy-number", None)];
+        columns.extend(
+            matrix
+                .get_path_names()
+                .iter()
+                .map(|group| Column::new(group, Some(k))),
+        );
+        let mut values = vec![to_values(&self.get_hist_labels())];
+        values.extend(inner.hist.iter().map(|h| to_values(h)));
+        let hist = get_table("hist", columns).with_column_values(values);
+
+        let units = [None, Some(k), Some(k), Some("bp"), Some("bp"), None, None];
+        let mut repeats = get_table(
+            "repeats",
+            Self::get_repeat_header()
+                .iter()
+                .zip(units)
+                .map(|(name, unit)| Column::new(name, unit))
+                .collect(),
+        );
+        for row in &inner.repeats {
+            repeats.push_row(vec![
+                Value::from(row.group.as_str()),
+                Value::from(row.countables),
+                Value::from(row.repeated),
+                Value::from(row.bp),
+                Value::from(row.repeat_bp),
+                Value::from(row.get_repeat_fraction()),
+                Value::from(row.max_copy_number),
+            ]);
+        }
+
+        let units = [None, Some("bp"), None, None, None, None];
+        let mut variable = get_table(
+            "variable",
+            Self::get_variable_header(k)
+                .iter()
+                .zip(units)
+                .map(|(name, unit)| Column::new(name, unit))
+                .collect(),
+        );
+        for row in &inner.variable {
+            variable.push_row(vec![
+                Value::from(row.name.as_str()),
+                Value::from(row.length),
+                Value::from(row.min),
+                Value::from(row.max),
+                Value::from(row.carriers),
+                Value::from(row.repeated),
+            ]);
+        }
+        Ok(vec![hist, repeats, variable])
+    }
+
+    fn get_type(&self) -> String {
+        "CopyNumber".to_string()
+    }
+
+    fn generate_table(&mut self, matrix: &CoverageMatrix) -> Result<String, PanacusError> {
+        log::info!("reporting copy number table");
+        self.set_inner(matrix)?;
+        let inner = self.inner.as_ref().unwrap();
+        let k = matrix.get_feature_type();
+
+        let mut res = String::new();
+        res.push_str("# Warning! This table is a multi-table, i.e. it contains multiple tables concatenated together. They always have the form: <table-name>\t<values>\n");
+        res.push_str(&format!(
+            "hist\tcopy-number\t{}\n",
+            matrix.get_path_names().join("\t")
+        ));
+        for (label, i) in self.get_hist_labels().iter().zip(0..) {
+            let values: Vec<String> = inner.hist.iter().map(|h| h[i].to_string()).collect();
+            res.push_str(&format!("hist\t{}\t{}\n", label, values.join("\t")));
+        }
+        res.push_str(&format!(
+            "repeats\t{}\n",
+            Self::get_repeat_header().join("\t")
+        ));
+        for row in &inner.repeats {
+            res.push_str(&format!("repeats\t{}\n", row.to_values().join("\t")));
+        }
+        res.push_str(&format!(
+            "variable\t{}\n",
+            Self::get_variable_header(k).join("\t")
+        ));
+        for row in &inner.variable {
+            res.push_str(&format!("variable\t{}\n", row.to_values().join("\t")));
+        }
+        Ok(res)
+    }
+
+    fn generate_report_section(
+        &mut self,
+        matrix: &CoverageMatrix,
+    ) -> Result<Vec<AnalysisSection>, PanacusError> {
+        let table = self.generate_table(matrix)?;
+        let table = format!("`{}`", &table);
+        let inner = self.inner.as_ref().unwrap();
+        let id_prefix = format!(
+            "copy-number-{}",
+            matrix
+                .get_run_id()
+                .to_lowercase()
+                .replace([' ', '|', '\\'], "-")
+        );
+        let k = matrix.get_feature_type();
+        let items = vec![
+            ReportItem::MultiBar {
+                id: format!("{id_prefix}-{k}-hist"),
+                names: matrix.get_path_names().clone(),
+                x_label: "copy number".to_string(),
+                y_label: format!("#{}s", k),
+                labels: self.get_hist_labels(),
+                values: inner
+                    .hist
+                    .iter()
+                    .map(|h| h.iter().map(|c| *c as f64).collect())
+                    .collect(),
+                curve: None,
+                alpha: None,
+                log_toggle: true,
+            },
+            ReportItem::Table {
+                id: format!("{id_prefix}-{k}-repeats"),
+                header: Self::get_repeat_header(),
+                values: inner.repeats.iter().map(|row| row.to_values()).collect(),
+            },
+            ReportItem::Table {
+                id: format!("{id_prefix}-{k}-variable"),
+                header: Self::get_variable_header(k),
+                values: inner.variable.iter().map(|row| row.to_values()).collect(),
+            },
+        ];
+        Ok(vec![AnalysisSection {
+            id: format!("{id_prefix}-{k}"),
+            analysis: "Copy Number".to_string(),
+            run_name: matrix.get_run_name().to_owned(),
+            run_id: matrix.get_run_id().to_owned(),
+            countable: k.to_string(),
+            table: Some(table),
+            items,
+            plot_downloads: get_default_plot_downloads(),
+            display: DisplayOptions::default(),
+        }])
+    }
+}
+
+impl CopyNumber {
+    pub fn new(max_copy_number: usize) -> Self {
+        Self {
+            max_copy_number: max_copy_number.max(1),
+            inner: None,
+        }
+    }
+
+    /// Computes the copy number statistics (only on the first call, later
+    /// calls return the cached result)
+    pub fn get_result(
+        &mut self,
+        matrix: &CoverageMatrix,
+    ) -> Result<&CopyNumberResult, PanacusError> {
+        self.set_inner(matrix)?;
+        Ok(self.inner.as_ref().unwrap())
+    }
+
+    fn get_repeat_header() -> Vec<String> {
+        [
+            "group",
+            "countables",
+            "repeated",
+            "bp",
+            "repeat-bp",
+            "repeat-fraction",
+            "max-copy-number",
+        ]
+        .iter()
+        .map(|x| x.to_string())
+        .collect()
+    }
+
+    fn get_variable_header(k: &str) -> Vec<String> {
+        [k, "bp", "min", "max", "carriers", "repeated"]
+            .iter()
+            .map(|x| x.to_string())
+            .collect()
+    }
+
+    fn get_hist_labels(&self) -> Vec<String> {
+        let inner = self.inner.as_ref().unwrap();
+        let n = inner.hist.first().map(|h| h.len()).unwrap_or(0);
+        (1..=n)
+            .map(|c| {
+                if c == self.max_copy_number {
+                    format!("≥{}", c)
+                } else {
+                    c.to_string()
+                }
+            })
+            .collect()
+    }
+
+    fn set_inner(&mut self, matrix: &CoverageMatrix) -> anyhow::Result<()> {
+        if self.inner.is_some() {
+            return Ok(());
+        }
+        let sizes = matrix.get_feature_sizes();
+        if sizes.is_empty() && matrix.get_feature_count() > 0 {
+            return Err(anyhow!(
+                "Copy numbers are not available for {} counts",
+                matrix.get_feature_type()
+            ));
+        }
+        let lengths = matrix.get_feature_lengths();
+        let groups = matrix.get_path_names();
+        let mut hist = vec![vec![0; self.max_copy_number]; groups.len()];
+        let mut repeats: Vec<RepeatRow> = groups
+            .iter()
+            .map(|group| RepeatRow {
+                group: group.to_string(),
+                countables: 0,
+                repeated: 0,
+                bp: 0,
+                repeat_bp: 0,
+                max_copy_number: 0,
+            })
+            .collect();
+        let mut variable = Vec::new();
+        for feature in 0..matrix.get_feature_count() {
+            let counts = matrix.get_counts_for_feature(feature);
+            for (group, &count) in counts.iter().enumerate() {
+                if count == 0 {
+                    continue;
+                }
+                hist[group][count.min(self.max_copy_number) - 1] += lengths[feature];
+                let row = &mut repeats[group];
+                row.countables += 1;
+                row.bp += sizes[feature];
+                if count > 1 {
+                    row.repeated += 1;
+                    row.repeat_bp += sizes[feature];
+                }
+                row.max_copy_number = row.max_copy_number.max(count);
+            }
+            if let Some(row) = VariableRow::from_counts(
+                matrix.get_feature_name(feature).to_string(),
+                sizes[feature],
+                &counts,
+            ) {
+                variable.push(row);
+            }
+        }
+        // Only report copy numbers up to the largest one that actually occurs
+        let n = (1..=self.max_copy_number)
+            .rev()
+            .find(|c| hist.iter().any(|h| h[c - 1] > 0))
+            .unwrap_or(1);
+        hist.iter_mut().for_each(|h| h.truncate(n));
+        self.inner = Some(CopyNumberResult {
+            hist,
+            repeats,
+            variable,
+        });
+        Ok(())
+    }
+}
+
+impl RepeatRow {
+    /// Fraction of the bp of the group that is repeated
+    pub fn get_repeat_fraction(&self) -> f64 {
+        if self.bp > 0 {
+            self.repeat_bp as f64 / self.bp as f64
+        } else {
+            0.0
+        }
+    }
+
+    fn to_values(&self) -> Vec<String> {
+        let fraction = self.get_repeat_fraction();
+        vec![
+            self.group.clone(),
+            self.countables.to_string(),
+            self.repeated.to_string(),
+            self.bp.to_string(),
+            self.repeat_bp.to_string(),
+            format!("{:.4}", fraction),
+            self.max_copy_number.to_string(),
+        ]
+    }
+}
+
+impl VariableRow {
+    /// Creates a row if the copy number differs between the groups that
+    /// traverse the countable (groups not traversing it are ignored)
+    fn from_counts(name: String, length: usize, counts: &[usize]) -> Option<Self> {
+        let carrier_counts: Vec<usize> = counts.iter().copied().filter(|c| *c > 0).collect();
+        let min = *carrier_counts.iter().min()?;
+        let max = *carrier_counts.iter().max()?;
+        if min == max {
+            return None;
+        }
+        Some(Self {
+            name,
+            length,
+            min,
+            max,
+            carriers: carrier_counts.len(),
+            repeated: carrier_counts.iter().filter(|c| **c > 1).count(),
+        })
+    }
+
+    fn to_values(&self) -> Vec<String> {
+        vec![
+            self.name.clone(),
+            self.length.to_string(),
+            self.min.to_string(),
+            self.max.to_string(),
+            self.carriers.to_string(),
+            self.repeated.to_string(),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_variable_row_ignores_absent_groups() {
+        assert_eq!(
+            VariableRow::from_counts("1".to_string(), 4, &[0, 1, 1]),
+            None
+        );
+        assert_eq!(VariableRow::from_counts("1".to_string(), 4, &[0, 0]), None);
+        assert_eq!(
+            VariableRow::from_counts("2".to_string(), 2, &[2, 0, 1, 3]),
+            Some(VariableRow {
+                name: "2".to_string(),
+                length: 2,
+                min: 1,
+                max: 3,
+                carriers: 3,
+                repeated: 2,
+            })
+        );
+    }
+}
@@ -1,16 +1,20 @@
 use std::cmp;
-use std::io::Write;
-use std::process::Command;
+use std::collections::HashMap;
+use std::io::{BufRead, BufWriter, Write};
 
+use anyhow::anyhow;
 use once_cell::sync::Lazy;
-use tempfile::NamedTempFile;
 
+use crate::analyses::result_table::{Column, ResultTable, Value};
 use crate::analyses::MatrixBasedAnalysis;
+use crate::analysis_parameter::ColorBy;
 use crate::coverage_matrix::CoverageMatrix;
+use crate::error::PanacusError;
 use crate::html_report::ReportItem;
 use crate::util::CountType;
 
 use super::AnalysisSection;
+use crate::html_report::DisplayOptions;
 
 static VIRIDIS: Lazy<Vec<(f64, (f64, f64, f64))>> = Lazy::new(|| {
     vec![
@@ -22,33 +26,85 @@ static VIRIDIS: Lazy<Vec<(f64, (f64, f64, f64))>> = Lazy::new(|| {
     ]
 });
 
+/// Colors of the groups, repeated if there are more groups than colors
+pub const GROUP_COLORS: [&str; 10] = [
+    "#1F77B4", "#FF7F0E", "#2CA02C", "#D62728", "#9467BD", "#8C564B", "#E377C2", "#7F7F7F",
+    "#BCBD22", "#17BECF",
+];
+const UNCOVERED_COLOR: &str = "#AAAAAA";
+const SHARED_COLOR: &str = "#DDDDDD";
+const CORE_COLOR: &str = "#440154";
+const SHELL_COLOR: &str = "#21918C";
+const CLOUD_COLOR: &str = "#FDE725";
+
+/// Colors each node by its coverage, by whether a group covers it, by the
+/// group it is private to or by its core/shell/cloud class. The colors can be
+/// written as `CL:z:` tags into a copy of the GFA.
 pub struct CoverageColors {
-    inner: Option<InnerCoverageColors>,
+    color_by: ColorBy,
+    group: Option<String>,
+    output: Option<String>,
+    inner: Option<CoverageColorsResult>,
+}
+
+/// Result of the `CoverageColors` analysis
+#[derive(Debug, Clone, PartialEq)]
+pub struct CoverageColorsResult {
+    /// Name, coverage, color and class of each node
+    pub nodes: Vec<NodeColor>,
+    /// Number of paths/groups, i.e. the maximal coverage
+    pub max_cov: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeColor {
+    pub name: String,
+    pub coverage: u64,
+    pub color: String,
+    /// What the color stands for, e.g. `core` or the name of a group
+    pub class: String,
 }
 
 impl MatrixBasedAnalysis for CoverageColors {
+    fn generate_result_tables(
+        &mut self,
+        matrix: &CoverageMatrix,
+    ) -> Result<Vec<ResultTable>, PanacusError> {
+        let analysis = self.get_type();
+        let inner = self.get_result(matrix)?;
+        let mut table = ResultTable::new(
+            &analysis,
+            "colors",
+            matrix.get_run_id(),
+            matrix.get_run_name(),
+            matrix.get_feature_type(),
+            vec![
+                Column::new("name", None),
+                Column::new("coverage", None),
+                Column::new("color", None),
+                Column::new("class", None),
+            ],
+        );
+        for node in inner.nodes.iter() {
+            table.push_row(vec![
+                Value::from(node.name.as_str()),
+                Value::from(node.coverage as usize),
+                Value::from(node.color.as_str()),
+                Value::from(node.class.as_str()),
+            ]);
+        }
+        Ok(vec![table])
+    }
+
     fn get_type(&self) -> String {
         "CoverageColors".to_string()
     }
-    fn generate_table(&mut self, matrix: &CoverageMatrix) -> anyhow::Result<String> {
-        self.set_inner(matrix)?;
+    fn generate_table(&mut self, matrix: &CoverageMatrix) -> Result<String, PanacusError> {
+        let inner = self.get_result(matrix)?;
         let mut result = String::new();
         result.push_str("Name,Color\n");
-        for (id, cov) in self
-            .inner
-            .as_ref()
-            .expect("Setting inner worked for coverage colors")
-            .coverages
-            .iter()
-        {
-            let color = convert_to_color(
-                *cov,
-                self.inner
-                    .as_ref()
-                    .expect("Setting inner worked for coverage colors")
-                    .max_cov,
-            );
-            result.push_str(&format!("{},{}\n", id, color));
+        for node in inner.nodes.iter() {
+            result.push_str(&format!("{},{}\n", node.name, node.color));
         }
         Ok(result)
     }
@@ -56,7 +112,7 @@ impl MatrixBasedAnalysis for CoverageColors {
     fn generate_report_section(
         &mut self,
         matrix: &CoverageMatrix,
-    ) -> anyhow::Result<Vec<AnalysisSection>> {
+    ) -> Result<Vec<AnalysisSection>, PanacusError> {
         self.set_inner(matrix)?;
         let table = self.generate_table(matrix)?;
         let table = format!("`{}`", &table);
@@ -67,78 +123,215 @@ impl MatrixBasedAnalysis for CoverageColors {
                 .to_lowercase()
                 .replace(&[' ', '|', '\\'], "-")
         );
-        let output_svg = self.get_image(matrix);
-        let growth_tabs = vec![AnalysisSection {
+        let legend = ReportItem::Table {
+            id: format!("{id_prefix}-legend"),
+            header: vec![
+                "color".to_string(),
+                "class".to_string(),
+                "#nodes".to_string(),
+            ],
+            values: self.get_legend(),
+        };
+        Ok(vec![AnalysisSection {
             analysis: "Coverage Colors".to_string(),
             run_name: matrix.get_run_name().to_owned(),
             run_id: matrix.get_run_id().to_owned(),
             countable: CountType::Node.to_string(),
             table: Some(table),
-            id: format!("{id_prefix}"),
-            plot_downloads: vec![("svg".to_string(), "Download as svg".to_string())],
-            items: match output_svg {
-                Ok(svg_path) => vec![ReportItem::Svg {
-                    id: format!("svg-{id_prefix}"),
-                    file: svg_path,
-                }],
-                Err(e) => {
-                    log::error!("Error running bandage: {e}");
-                    Vec::new()
-                }
-            },
-        }];
-        Ok(growth_tabs)
+            id: id_prefix,
+            plot_downloads: Vec::new(),
+            items: vec![legend],
+            display: DisplayOptions::default(),
+        }])
     }
 }
 
 impl CoverageColors {
-    pub fn new() -> Self {
-        Self { inner: None }
+    pub fn new(color_by: ColorBy, group: Option<String>, output: Option<String>) -> Self {
+        Self {
+            color_by,
+            group,
+            output,
+            inner: None,
+        }
+    }
+
+    /// Colors all nodes and writes the colored GFA (only on the first call,
+    /// later calls return the cached result)
+    pub fn get_result(
+        &mut self,
+        matrix: &CoverageMatrix,
+    ) -> Result<&CoverageColorsResult, PanacusError> {
+        self.set_inner(matrix)?;
+        Ok(self.inner.as_ref().unwrap())
     }
 
     fn set_inner(&mut self, matrix: &CoverageMatrix) -> anyhow::Result<()> {
         if self.inner.is_some() {
             return Ok(());
         }
+        if matrix.get_feature_type() != "node" {
+            return Err(anyhow!(
+                "Coverage colors can only be computed for node counts, not {}",
+                matrix.get_feature_type()
+            ));
+        }
 
-        let coverages = matrix.get_feature_counts();
-        let node_names = matrix.get_feature_names();
-
-        let coverages: Vec<(String, u64)> = coverages
+        let groups = matrix.get_path_names();
+        let group = match (self.color_by, &self.group) {
+            (ColorBy::Group, None) => return Err(anyhow!("coloring by group needs a group")),
+            (ColorBy::Group, Some(group)) => Some(
+                groups
+                    .iter()
+                    .position(|g| g == group)
+                    .ok_or_else(|| anyhow!("{} is not a group of the graph", group))?,
+            ),
+            _ => None,
+        };
+        let max_cov = groups.len() as u64;
+        let nodes = matrix
+            .get_feature_names()
             .into_iter()
             .enumerate()
-            .map(|(id, cov)| (node_names[id].clone(), cov as u64))
+            .map(|(id, name)| {
+                let counts = matrix.get_counts_for_feature(id);
+                let (color, class) = match self.color_by {
+                    ColorBy::Coverage => {
+                        let coverage = matrix.get_count_of_feature(id) as u64;
+                        (convert_to_color(coverage, max_cov), coverage.to_string())
+                    }
+                    ColorBy::Group => {
+                        let group = group.expect("group is known");
+                        color_by_group(&counts, group, &groups[group])
+                    }
+                    ColorBy::Private => color_by_private(&counts, groups),
+                    ColorBy::Class => color_by_class(&counts),
+                };
+                NodeColor {
+                    name,
+                    coverage: matrix.get_count_of_feature(id) as u64,
+                    color,
+                    class,
+                }
+            })
             .collect();
-        let max_cov = matrix.get_path_names().len() as u64;
 
-        let coverages = InnerCoverageColors { coverages, max_cov };
-        self.inner = Some(coverages);
+        let result = CoverageColorsResult { nodes, max_cov };
+        if let Some(output) = &self.output {
+            let graph_file = matrix.get_graph_file().ok_or_else(|| {
+                anyhow!("colored graphs can only be written for runs on a GFA file")
+            })?;
+            let colors: HashMap<&str, &str> = result
+                .nodes
+                .iter()
+                .map(|n| (n.name.as_str(), n.color.as_str()))
+                .collect();
+            log::info!("writing colored graph to {}", output);
+            let reader = crate::io::bufreader_from_compressed_gfa(graph_file)?;
+            let file = std::fs::File::create(output)
+                .map_err(|e| anyhow!("cannot create colored graph {}: {}", output, e))?;
+            write_colored_gfa(reader, &mut BufWriter::new(file), &colors)?;
+        }
+        self.inner = Some(result);
         Ok(())
     }
 
-    fn get_image(&mut self, matrix: &CoverageMatrix) -> anyhow::Result<String> {
-        if self.inner.is_none() {
-            panic!("get_image can only be called if coverage colors was already calculated");
+    /// Color, class and number of nodes of each class, for the report
+    fn get_legend(&self) -> Vec<Vec<String>> {
+        // Entries in order of the first node of each class, indexed by class
+        let mut legend: Vec<(&str, &str, usize)> = Vec::new();
+        let mut indices: HashMap<(&str, &str), usize> = HashMap::new();
+        for node in self.inner.as_ref().unwrap().nodes.iter() {
+            let key = (node.color.as_str(), node.class.as_str());
+            let index = *indices.entry(key).or_insert_with(|| {
+                legend.push((key.0, key.1, 0));
+                legend.len() - 1
+            });
+            legend[index].2 += 1;
         }
+        legend
+            .into_iter()
+            .map(|(color, class, count)| {
+                vec![color.to_string(), class.to_string(), count.to_string()]
+            })
+            .collect()
+    }
+}
 
-        let mut csv = NamedTempFile::with_suffix(".csv")?;
-        let table = self.generate_table(matrix)?;
-        write!(csv, "{}", table)?;
-        let csv_path = csv.path().to_str().unwrap();
-        let svg = NamedTempFile::with_suffix(".svg")?;
-        let svg_path = svg.path().to_str().unwrap();
-        // TODO get file name
-        let graph_path = matrix.get_run_name();
-        Command::new("Bandage")
-            .args(["image", &graph_path, svg_path, "--colors", csv_path])
-            .output()?;
-        let copied_path = svg_path.to_string();
-        svg.keep()?;
-        Ok(copied_path)
+fn group_color(group: usize) -> String {
+    GROUP_COLORS[group % GROUP_COLORS.len()].to_string()
+}
+
+fn color_by_group(counts: &[usize], group: usize, name: &str) -> (String, String) {
+    if counts[group] > 0 {
+        (group_color(group), name.to_string())
+    } else {
+        (UNCOVERED_COLOR.to_string(), format!("not {}", name))
+    }
+}
+
+fn color_by_private(counts: &[usize], groups: &[String]) -> (String, String) {
+    let mut covering = counts.iter().enumerate().filter(|(_, c)| **c > 0);
+    match (covering.next(), covering.next()) {
+        (None, _) => (UNCOVERED_COLOR.to_string(), "uncovered".to_string()),
+        (Some((group, _)), None) => (group_color(group), groups[group].clone()),
+        _ => (SHARED_COLOR.to_string(), "shared".to_string()),
+    }
+}
+
+fn color_by_class(counts: &[usize]) -> (String, String) {
+    let covering = counts.iter().filter(|c| **c > 0).count();
+    let (color, class) = if covering == 0 {
+        (UNCOVERED_COLOR, "uncovered")
+    } else if covering == counts.len() {
+        (CORE_COLOR, "core")
+    } else if covering == 1 {
+        (CLOUD_COLOR, "cloud")
+    } else {
+        (SHELL_COLOR, "shell")
+    };
+    (color.to_string(), class.to_string())
+}
+
+/// Copies the GFA, replacing the `CL:z:` tags of all colored segments
+fn write_colored_gfa<R: BufRead, W: Write>(
+    mut reader: R,
+    writer: &mut W,
+    colors: &HashMap<&str, &str>,
+) -> anyhow::Result<()> {
+    let mut buf = Vec::new();
+    while reader.read_until(b'\n', &mut buf)? > 0 {
+        let line = buf.strip_suffix(b"\n").unwrap_or(&buf);
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        let color = if line.starts_with(b"S\t") {
+            line.split(|c| *c == b'\t')
+                .nth(1)
+                .and_then(|name| std::str::from_utf8(name).ok())
+                .and_then(|name| colors.get(name))
+        } else {
+            None
+        };
+        match color {
+            Some(color) => {
+                let mut fields = line
+                    .split(|c| *c == b'\t')
+                    .filter(|f| !f.starts_with(b"CL:z:"));
+                writer.write_all(fields.next().unwrap())?;
+                for field in fields {
+                    writer.write_all(b"\t")?;
+                    writer.write_all(field)?;
+                }
+                writeln!(writer, "\tCL:z:{}", color)?;
+            }
+            None => writer.write_all(&buf)?,
+        }
+        buf.clear();
     }
+    writer.flush()?;
+    Ok(())
 }
 
-fn convert_to_color(cov: u64, max_cov: u64) -> String {
+pub fn convert_to_color(cov: u64, max_cov: u64) -> String {
     // Handle case of coverage == 1
     if cov == 1 {
         return "#FF0000".to_string();
@@ -148,6 +341,12 @@ fn convert_to_color(cov: u64, max_cov: u64) -> String {
         return "#AAAAAA".to_string();
     }
 
+    // With at most two paths/groups, any coverage above 1 is the maximum
+    if max_cov <= 2 {
+        let (r, g, b) = VIRIDIS[VIRIDIS.len() - 1].1;
+        return format!("#{:02X}{:02X}{:02X}", r as u64, g as u64, b as u64);
+    }
+
     // Trim value
     let value = cmp::max(cmp::min(cov, max_cov), 2);
     let relative_value = (value - 2) as f64 / (max_cov - 2) as f64;
@@ -164,7 +363,46 @@ fn convert_to_color(cov: u64, max_cov: u64) -> String {
     "#000000".to_string()
 }
 
-struct InnerCoverageColors {
-    coverages: Vec<(String, u64)>,
-    max_cov: u64,
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_color_by_class() {
+        assert_eq!(color_by_class(&[1, 2, 1]).1, "core");
+        assert_eq!(color_by_class(&[1, 0, 3]).1, "shell");
+        assert_eq!(color_by_class(&[0, 0, 1]).1, "cloud");
+        assert_eq!(color_by_class(&[0, 0, 0]).1, "uncovered");
+    }
+
+    #[test]
+    fn test_color_by_private() {
+        let groups = vec!["a".to_string(), "b".to_string()];
+        assert_eq!(
+            color_by_private(&[0, 2], &groups),
+            (GROUP_COLORS[1].to_string(), "b".to_string())
+        );
+        assert_eq!(color_by_private(&[1, 2], &groups).1, "shared");
+        assert_eq!(color_by_private(&[0, 0], &groups).1, "uncovered");
+    }
+
+    #[test]
+    fn test_convert_to_color_with_few_paths() {
+        let top = convert_to_color(5, 5);
+        assert_eq!(convert_to_color(2, 2), top);
+        assert_eq!(convert_to_color(3, 1), top);
+        assert_eq!(convert_to_color(1, 2), "#FF0000");
+    }
+
+    #[test]
+    fn test_write_colored_gfa() {
+        let gfa = "H\tVN:Z:1.0\nS\t1\tACGT\tCL:z:red\nS\t2\tA\nS\t3\tC\nL\t1\t+\t2\t+\t0M\n";
+        let colors = HashMap::from([("1", "#FF0000"), ("2", "#00FF00")]);
+        let mut out = Vec::new();
+        write_colored_gfa(gfa.as_bytes(), &mut out, &colors).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "H\tVN:Z:1.0\nS\t1\tACGT\tCL:z:#FF0000\nS\t2\tA\tCL:z:#00FF00\nS\t3\tC\nL\t1\t+\t2\t+\t0M\n"
+        );
+    }
 }
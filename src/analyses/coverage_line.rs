@@ -1,26 +1,50 @@
+use crate::analyses::result_table::{to_values, Column, ResultTable, Value};
+use crate::error::PanacusError;
 use crate::{
     analyses::MatrixBasedAnalysis,
     coverage_matrix::CoverageMatrix,
     hist::Hist,
-    html_report::{AnalysisSection, ReportItem},
+    html_report::{AnalysisSection, DisplayOptions, ReportItem},
     io::write_table_with_start_index,
     util::get_default_plot_downloads,
 };
 
+/// Coverage histogram of the countables (or of the countables on a
+/// reference path), plotted on a log-log scale
 pub struct CoverageLine {
     reference: Option<String>,
     hist: Option<Hist>,
 }
 
 impl MatrixBasedAnalysis for CoverageLine {
+    fn generate_result_tables(
+        &mut self,
+        matrix: &CoverageMatrix,
+    ) -> Result<Vec<ResultTable>, PanacusError> {
+        let k = matrix.get_feature_type();
+        let analysis = self.get_type();
+        let values = &self.get_result(matrix)?.get_hist_values()[1..];
+        Ok(vec![ResultTable::new(
+            &analysis,
+            "coverage-line",
+            matrix.get_run_id(),
+            matrix.get_run_name(),
+            k,
+            vec![Column::new("coverage", None), Column::new("hist", Some(k))],
+        )
+        .with_column_values(vec![
+            (1..=values.len()).map(Value::from).collect(),
+            to_values(values),
+        ])])
+    }
+
     fn get_type(&self) -> String {
         "CoverageLine".to_string()
     }
 
-    fn generate_table(&mut self, matrix: &CoverageMatrix) -> anyhow::Result<String> {
+    fn generate_table(&mut self, matrix: &CoverageMatrix) -> Result<String, PanacusError> {
         log::info!("reporting coverage line table");
         let mut res = String::new();
-        res.push_str(&crate::io::write_metadata_comments()?);
         if self.hist.is_none() {
             self.set_inner(matrix);
         }
@@ -59,7 +83,7 @@ impl MatrixBasedAnalysis for CoverageLine {
     fn generate_report_section(
         &mut self,
         matrix: &CoverageMatrix,
-    ) -> anyhow::Result<Vec<crate::html_report::AnalysisSection>> {
+    ) -> Result<Vec<crate::html_report::AnalysisSection>, PanacusError> {
         let table = self.generate_table(matrix)?;
         let table = format!("`{}`", &table);
         let id_prefix = format!(
@@ -99,6 +123,7 @@ impl MatrixBasedAnalysis for CoverageLine {
                 log_y: true,
             }],
             plot_downloads: get_default_plot_downloads(),
+            display: DisplayOptions::default(),
         };
         Ok(vec![coverage_line_tabs])
     }
@@ -112,6 +137,15 @@ impl CoverageLine {
         }
     }
 
+    /// Computes the coverage histogram (only on the first call, later calls
+    /// return the cached result)
+    pub fn get_result(&mut self, matrix: &CoverageMatrix) -> Result<&Hist, PanacusError> {
+        if self.hist.is_none() {
+            self.set_inner(matrix);
+        }
+        Ok(self.hist.as_ref().unwrap())
+    }
+
     fn set_inner(&mut self, matrix: &CoverageMatrix) {
         if let Some(r) = self.reference.as_ref() {
             self.hist = Some(matrix.get_hist_for_reference(r));
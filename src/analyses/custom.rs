@@ -0,0 +1,58 @@
+use crate::analyses::result_table::ResultTable;
+use crate::error::PanacusError;
+
+use super::{AnalysisSection, HistBasedAnalysis};
+
+/// User-supplied content (image, Vega-Lite specification or table) shown as
+/// a section of the run it is configured for. It does not depend on the
+/// graph, it is hist-based so that it never requires a coverage matrix.
+pub struct Custom {
+    name: String,
+    file: String,
+    description: Option<String>,
+}
+
+impl HistBasedAnalysis for Custom {
+    fn generate_result_tables(
+        &mut self,
+        _hist: &crate::hist::Hist,
+    ) -> Result<Vec<ResultTable>, PanacusError> {
+        Ok(Vec::new())
+    }
+
+    fn get_type(&self) -> String {
+        "Custom".to_string()
+    }
+
+    /// Tables are passed through, other content has no table
+    fn generate_table(&mut self, _hist: &crate::hist::Hist) -> Result<String, PanacusError> {
+        if self.file.ends_with(".tsv") || self.file.ends_with(".csv") {
+            std::fs::read_to_string(&self.file).map_err(|e| PanacusError::file_read(&self.file, e))
+        } else {
+            Ok(String::new())
+        }
+    }
+
+    fn generate_report_section(
+        &mut self,
+        hist: &crate::hist::Hist,
+    ) -> Result<Vec<AnalysisSection>, PanacusError> {
+        Ok(vec![AnalysisSection::generate_custom_section(
+            &self.name,
+            &self.file,
+            self.description.as_deref(),
+            hist.get_run_name(),
+            hist.get_run_id(),
+        )?])
+    }
+}
+
+impl Custom {
+    pub fn new(name: String, file: String, description: Option<String>) -> Self {
+        Self {
+            name,
+            file,
+            description,
+        }
+    }
+}
@@ -2,7 +2,9 @@ use std::cmp;
 
 use ml_helpers::linear_regression::huber_regressor::{solve, HuberRegressor};
 
+use crate::analyses::result_table::{get_threshold_columns, to_values, Column, ResultTable, Value};
 use crate::analyses::HistBasedAnalysis;
+use crate::error::PanacusError;
 use crate::file_formats::gfa_parser::ThresholdContainer;
 use crate::hist::Hist;
 use crate::html_report::ReportItem;
@@ -11,22 +13,61 @@ use crate::util::{get_default_plot_downloads, Threshold};
 use rayon::prelude::*;
 
 use super::AnalysisSection;
+use crate::html_report::DisplayOptions;
 
+/// Pangenome growth curves, computed from the coverage histogram for each
+/// pair of coverage and quorum thresholds
 pub struct Growth {
     coverage: Option<String>,
     quorum: Option<String>,
     add_hist: bool,
     add_alpha: bool,
-    growths: Option<Vec<Vec<f64>>>,
-    thresholds: Option<ThresholdContainer>,
+    inner: Option<GrowthResult>,
+}
+
+/// Result of the `Growth` analysis
+#[derive(Debug, Clone)]
+pub struct GrowthResult {
+    /// The i-th growth curve uses `coverage[i]` and `quorum[i]`
+    pub thresholds: ThresholdContainer,
+    /// `growths[i][m]` is the expected number of countables in `m` taxa
+    /// (`NaN` for `m = 0`)
+    pub growths: Vec<Vec<f64>>,
 }
 
 impl HistBasedAnalysis for Growth {
+    fn generate_result_tables(&mut self, hist: &Hist) -> Result<Vec<ResultTable>, PanacusError> {
+        self.set_inner(hist)?;
+        let GrowthResult {
+            thresholds,
+            growths,
+        } = self.inner.as_ref().unwrap();
+        let k = hist.get_feature_type();
+        let hist_values = hist.get_hist_values();
+        let mut columns = vec![Column::new("taxa", None)];
+        let mut values: Vec<Vec<Value>> = vec![(0..hist_values.len()).map(Value::from).collect()];
+        if self.add_hist {
+            columns.push(Column::new("hist", Some(k)));
+            values.push(to_values(hist_values));
+        }
+        columns.extend(get_threshold_columns("growth", Some(k), thresholds));
+        values.extend(growths.iter().map(|growth| to_values(growth)));
+        Ok(vec![ResultTable::new(
+            &self.get_type(),
+            "growth",
+            hist.get_run_id(),
+            hist.get_run_name(),
+            k,
+            columns,
+        )
+        .with_column_values(values)])
+    }
+
     fn get_type(&self) -> String {
         "Growth".to_string()
     }
 
-    fn generate_table(&mut self, hist: &Hist) -> anyhow::Result<String> {
+    fn generate_table(&mut self, hist: &Hist) -> Result<String, PanacusError> {
         log::info!(
             "reporting hist table, {}, {}",
             self.add_hist,
@@ -34,14 +75,12 @@ impl HistBasedAnalysis for Growth {
         );
 
         self.set_inner(hist)?;
-        let growths = self.growths.as_ref().unwrap();
-        let thresholds = self.thresholds.as_ref().unwrap();
+        let GrowthResult {
+            thresholds,
+            growths,
+        } = self.inner.as_ref().unwrap();
 
         let mut res = String::new();
-        res.push_str(&format!(
-            "# {}\n",
-            std::env::args().collect::<Vec<String>>().join(" ")
-        ));
         let mut header_cols = vec![vec![
             "panacus".to_string(),
             "count".to_string(),
@@ -86,9 +125,12 @@ impl HistBasedAnalysis for Growth {
         Ok(res)
     }
 
-    fn generate_report_section(&mut self, hist: &Hist) -> anyhow::Result<Vec<AnalysisSection>> {
+    fn generate_report_section(
+        &mut self,
+        hist: &Hist,
+    ) -> Result<Vec<AnalysisSection>, PanacusError> {
         self.set_inner(hist)?;
-        let hist_aux = &self.thresholds.as_ref().unwrap();
+        let hist_aux = &self.inner.as_ref().unwrap().thresholds;
         let growth_labels = (0..hist_aux.coverage.len())
             .map(|i| {
                 format!(
@@ -110,7 +152,7 @@ impl HistBasedAnalysis for Growth {
                 .replace(&[' ', '|', '\\'], "-")
         );
         let k = hist.get_feature_type();
-        let growths = self.growths.as_ref().unwrap();
+        let growths = &self.inner.as_ref().unwrap().growths;
         let growth_tabs = vec![AnalysisSection {
             id: format!("{id_prefix}-{k}"),
             analysis: "Pangenome Growth".to_string(),
@@ -137,6 +179,7 @@ impl HistBasedAnalysis for Growth {
                 log_toggle: false,
             }],
             plot_downloads: get_default_plot_downloads(),
+            display: DisplayOptions::default(),
         }];
         Ok(growth_tabs)
     }
@@ -154,13 +197,19 @@ impl Growth {
             quorum,
             add_hist,
             add_alpha,
-            growths: None,
-            thresholds: None,
+            inner: None,
         }
     }
 
+    /// Computes the growth curves (only on the first call, later calls
+    /// return the cached result)
+    pub fn get_result(&mut self, hist: &Hist) -> Result<&GrowthResult, PanacusError> {
+        self.set_inner(hist)?;
+        Ok(self.inner.as_ref().unwrap())
+    }
+
     fn set_inner(&mut self, hist: &Hist) -> anyhow::Result<()> {
-        if self.growths.is_some() {
+        if self.inner.is_some() {
             return Ok(());
         }
         let quorum = self.quorum.to_owned().unwrap_or("0".to_string());
@@ -168,8 +217,10 @@ impl Growth {
         let hist_aux = ThresholdContainer::parse_params(&quorum, &coverage)?;
 
         let growths = calc_all_growths(hist, &hist_aux, true);
-        self.growths = Some(growths);
-        self.thresholds = Some(hist_aux);
+        self.inner = Some(GrowthResult {
+            thresholds: hist_aux,
+            growths,
+        });
         Ok(())
     }
 }
@@ -209,6 +260,12 @@ fn get_regression(hist: &Vec<f64>) -> (f64, f64) {
     (alpha, params[1])
 }
 
+/// Computes the expected number of countables in `m` of the `n` groups, for
+/// each `m` in `1..=n`. Growth curves are sums over the coverage histogram
+/// of hypergeometric probabilities (drawing `m` of the `n` groups, of which
+/// `i` cover a countable). These are computed with log-gamma-based binomial
+/// coefficients, so that they neither overflow nor lose precision for tens
+/// of thousands of groups, and take O(n) time for each `m`.
 pub fn calc_growth(hist: &Hist, t_coverage: &Threshold, t_quorum: &Threshold) -> Vec<f64> {
     let n = hist.get_maximum_coverage();
 
@@ -253,126 +310,234 @@ pub fn calc_all_growths(
     growths
 }
 
-fn calc_growth_union(hist: &Hist, t_coverage: &Threshold) -> Vec<f64> {
-    let n = hist.get_maximum_coverage(); // hist array has length n+1: from 0..n (both included)
-    let c = usize::max(1, t_coverage.to_absolute(n));
+/// Natural logarithms of the factorials `0!..=n!`
+struct LnFactorials(Vec<f64>);
+
+impl LnFactorials {
+    fn new(n: usize) -> Self {
+        // 0! and 1! are exactly 1, so that e.g. binomial(i, i) is exactly 1
+        Self(
+            (0..=n)
+                .map(|k| if k < 2 { 0.0 } else { ln_gamma(k as f64 + 1.0) })
+                .collect(),
+        )
+    }
 
-    let mut pangrowth: Vec<f64> = vec![0.0; n];
-    let mut n_fall_m: f64 = 0.0;
-    let tot = hist.get_hist_values()[c..].iter().sum::<usize>() as f64;
-
-    // perc_mult[i] contains the percentage of combinations that
-    // have an item of multiplicity i
-    let mut perc_mult: Vec<f64> = Vec::with_capacity(n + 1);
-    perc_mult.resize(n + 1, 0.0);
-
-    for m in 1..n + 1 {
-        let mut y: f64 = 0.0;
-        n_fall_m += (n as f64 - m as f64 + 1.0).log2();
-        for i in c..n - m + 1 {
-            perc_mult[i] += (n as f64 - m as f64 - i as f64 + 1.0).log2();
-            y += ((hist.get_hist_values()[i] as f64).log2() + perc_mult[i] - n_fall_m).exp2();
+    /// Logarithm of the binomial coefficient, `-inf` if `k > n`
+    fn ln_choose(&self, n: usize, k: usize) -> f64 {
+        if k > n {
+            return f64::NEG_INFINITY;
         }
+        self.0[n] - self.0[k] - self.0[n - k]
+    }
 
-        pangrowth[m - 1] = tot - y;
+    /// Probability to draw exactly `k` marked items when drawing `draws` of
+    /// `total` items, of which `marked` are marked
+    fn hypergeometric(&self, total: usize, marked: usize, draws: usize, k: usize) -> f64 {
+        if k > marked || draws < k || draws - k > total - marked {
+            return 0.0;
+        }
+        (self.ln_choose(marked, k) + self.ln_choose(total - marked, draws - k)
+            - self.ln_choose(total, draws))
+        .exp()
     }
+}
 
-    pangrowth
+/// Logarithm of the gamma function for `x >= 1`, using Stirling's series
+/// (after shifting small arguments with `ln Γ(x) = ln Γ(x + 1) - ln x`)
+fn ln_gamma(x: f64) -> f64 {
+    let mut x = x;
+    let mut shift = 0.0;
+    while x < 10.0 {
+        shift += x.ln();
+        x += 1.0;
+    }
+    let x2 = x * x;
+    let series = 1.0 / (12.0 * x) - 1.0 / (360.0 * x * x2) + 1.0 / (1260.0 * x * x2 * x2)
+        - 1.0 / (1680.0 * x * x2 * x2 * x2);
+    (x - 0.5) * x.ln() - x + 0.5 * (2.0 * std::f64::consts::PI).ln() + series - shift
 }
 
-fn calc_growth_core(hist: &Hist, t_coverage: &Threshold) -> Vec<f64> {
-    let n = hist.get_maximum_coverage(); // hist array has length n+1: from 0..n (both included)
-    let c = usize::max(1, t_coverage.to_absolute(n + 1));
-    let mut n_fall_m: f64 = 0.0;
-    let mut pangrowth: Vec<f64> = vec![0.0; n];
-
-    // In perc_mult[i] is contained the percentage of combinations
-    // that have an item of multiplicity i
-    let mut perc_mult: Vec<f64> = Vec::with_capacity(n + 1);
-    perc_mult.resize(n + 1, 0.0);
-
-    for m in 1..n + 1 {
-        let mut y: f64 = 0.0;
-        n_fall_m += (n as f64 - m as f64 + 1.0).log2();
-        for i in usize::max(m, c)..n + 1 {
-            perc_mult[i] += (i as f64 - m as f64 + 1.0).log2();
-            y += ((hist.get_hist_values()[i] as f64).log2() + perc_mult[i] - n_fall_m).exp2();
+/// Probabilities `P(K_i >= t)` for all `i` in `0..=n`, where `K_i` is the
+/// number of drawn marked items when drawing `m` of `n` items of which `i` are
+/// marked. Uses the recurrence `P(K_{i+1} >= t) = P(K_i >= t) + m/n *
+/// P(K'_i = t - 1)`, where `K'_i` draws `m - 1` of `n - 1` items: a new marked
+/// item only makes a difference if it is drawn and exactly `t - 1` of the
+/// others are.
+struct HypergeometricTail<'a> {
+    ln_factorials: &'a LnFactorials,
+    n: usize,
+    m: usize,
+    t: usize,
+    i: usize,
+    tail: f64,
+}
+
+impl<'a> HypergeometricTail<'a> {
+    fn new(ln_factorials: &'a LnFactorials, n: usize, m: usize, t: usize) -> Self {
+        Self {
+            ln_factorials,
+            n,
+            m,
+            t,
+            i: 0,
+            tail: if t == 0 { 1.0 } else { 0.0 },
         }
-        pangrowth[m - 1] = y;
     }
+}
+
+impl Iterator for HypergeometricTail<'_> {
+    type Item = f64;
+
+    fn next(&mut self) -> Option<f64> {
+        if self.i > self.n {
+            return None;
+        }
+        let res = self.tail.min(1.0);
+        if self.t > 0 && self.i < self.n {
+            self.tail += self.m as f64 / self.n as f64
+                * self
+                    .ln_factorials
+                    .hypergeometric(self.n - 1, self.i, self.m - 1, self.t - 1);
+        }
+        self.i += 1;
+        Some(res)
+    }
+}
+
+/// Sums the coverage histogram weighted by `weight(m, i, tails)` for each `m`
+/// (in parallel), where `tails` holds `P(K_i >= t)` for each threshold `t`
+/// given by `thresholds(m)`
+fn sum_over_hist<T, W>(hist: &Hist, thresholds: T, weight: W) -> Vec<f64>
+where
+    T: Fn(usize) -> Vec<usize> + Sync,
+    W: Fn(usize, usize, &[f64]) -> f64 + Sync,
+{
+    let n = hist.get_maximum_coverage();
+    let ln_factorials = LnFactorials::new(n);
+    let values = hist.get_hist_values();
+    (1..=n)
+        .into_par_iter()
+        .map(|m| {
+            let mut tails: Vec<_> = thresholds(m)
+                .into_iter()
+                .map(|t| HypergeometricTail::new(&ln_factorials, n, m, t))
+                .collect();
+            let mut probabilities = vec![0.0; tails.len()];
+            let mut res = 0.0;
+            for (i, &count) in values.iter().enumerate() {
+                for (p, tail) in probabilities.iter_mut().zip(&mut tails) {
+                    *p = tail.next().expect("tail is defined for 0..=n");
+                }
+                if count > 0 {
+                    res += count as f64 * weight(m, i, &probabilities);
+                }
+            }
+            res
+        })
+        .collect()
+}
+
+/// Countables of coverage at least `c` in at least one of the `m` groups
+fn calc_growth_union(hist: &Hist, t_coverage: &Threshold) -> Vec<f64> {
+    let n = hist.get_maximum_coverage(); // hist array has length n+1: from 0..n (both included)
+    let c = usize::max(1, t_coverage.to_absolute(n));
+    sum_over_hist(hist, |_| vec![1], |_, i, p| if i >= c { p[0] } else { 0.0 })
+}
 
-    pangrowth
+/// Countables of coverage at least `c` in all of the `m` groups
+fn calc_growth_core(hist: &Hist, t_coverage: &Threshold) -> Vec<f64> {
+    let n = hist.get_maximum_coverage(); // hist array has length n+1: from 0..n (both included)
+    let c = usize::max(1, t_coverage.to_absolute(n + 1));
+    sum_over_hist(hist, |m| vec![m], |_, i, p| if i >= c { p[0] } else { 0.0 })
 }
 
+/// Countables in all of the `m` groups (with coverage at least `c`), and in
+/// at least `max(c, quorum * m)` but not all of the `m` groups
 fn calc_growth_quorum(hist: &Hist, t_coverage: &Threshold, t_quorum: &Threshold) -> Vec<f64> {
     let n = hist.get_maximum_coverage(); // hist array has length n+1: from [0..n]
     let c = usize::max(1, t_coverage.to_absolute(n));
     let quorum = t_quorum.to_relative(n);
-    let mut pangrowth: Vec<f64> = vec![0.0; n];
-
-    let mut n_fall_m: f64 = 0.0;
-    let mut m_fact: f64 = 0.0;
-
-    let mut perc_mult: Vec<f64> = vec![0.0; n + 1];
-    let mut q: Vec<Vec<f64>> = vec![vec![0.0; n + 1]; n + 1];
-
-    for m in 1..n + 1 {
-        m_fact += (m as f64).log2();
-        let m_quorum = (m as f64 * quorum).ceil() as usize;
+    let get_m_quorum = |m: usize| usize::max((m as f64 * quorum).ceil() as usize, c);
+    sum_over_hist(
+        hist,
+        |m| vec![m, get_m_quorum(m)],
+        |m, i, p| {
+            let core = if i >= c { p[0] } else { 0.0 };
+            let partial = if get_m_quorum(m) < m {
+                p[1] - p[0]
+            } else {
+                0.0
+            };
+            core + partial
+        },
+    )
+}
 
-        //100% quorum
-        let mut yl: f64 = 0.0;
-        n_fall_m += (n as f64 - m as f64 + 1.0).log2();
-        for i in usize::max(m, c)..n + 1 {
-            perc_mult[i] += (i as f64 - m as f64 + 1.0).log2();
-            yl += ((hist.get_hist_values()[i] as f64).log2() + perc_mult[i] - n_fall_m).exp2();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_bigint::BigUint;
+    use num_traits::{One, ToPrimitive, Zero};
+
+    fn assert_almost_eq(actual: &[f64], expected: &[f64], msg: &str) {
+        assert_eq!(actual.len(), expected.len(), "{}: length differs", msg);
+        for (m, (a, e)) in actual.iter().zip(expected).enumerate() {
+            let tolerance = 1e-9 * f64::max(1.0, e.abs());
+            assert!(
+                (a - e).abs() <= tolerance,
+                "{}: at m={} got {} but expected {}",
+                msg,
+                m + 1,
+                a,
+                e
+            );
         }
+    }
 
-        //[m_quorum, 100) quorum
-        let mut yr: f64 = 0.0;
-        for i in m_quorum..n {
-            let mut sum_q = 0.0;
-            let mut add = false;
-            for j in usize::max(m_quorum, c)..m {
-                if n + j + 1 > i + m && j <= i {
-                    if q[i][j] == 0.0 {
-                        q[i][j] = choose(i, j);
-                    }
-                    q[i][j] += (n as f64 - i as f64 - m as f64 + 1.0 + j as f64).log2();
-                    q[i][j] -= (m as f64 - j as f64).log2();
-                    sum_q += (q[i][j] + m_fact - n_fall_m).exp2();
-                    add = true;
-                }
-            }
-            if add {
-                yr += ((hist.get_hist_values()[i] as f64).log2() + sum_q.log2()).exp2();
-            }
+    fn big_choose(n: usize, k: usize) -> BigUint {
+        if k > n {
+            return BigUint::zero();
         }
-        pangrowth[m - 1] = yl + yr;
+        (0..k).fold(BigUint::one(), |acc, j| acc * (n - j) / (j + 1))
     }
-    pangrowth
-}
 
-fn choose(n: usize, k: usize) -> f64 {
-    let mut res: f64 = 0.0;
-    if k > n {
-        return 0.0;
+    fn big_to_f64(numerator: &BigUint, denominator: &BigUint) -> f64 {
+        // scale before dividing to keep precision for large coefficients
+        let scale = BigUint::one() << 128;
+        let scaled: BigUint = numerator * &scale / denominator;
+        scaled.to_f64().unwrap() / 2f64.powi(128)
     }
 
-    let k = if k > n - k { n - k } else { k };
-
-    let n = n as f64;
-
-    for i in 0..k {
-        res += (n - i as f64).log2();
-        res -= (i as f64 + 1.0).log2();
+    /// Exact growth: for each m, sums h[i] times the number of m-subsets of
+    /// groups in which a countable of coverage i is present in `j` groups with
+    /// `accept(m, i, j)`, divided by the number of m-subsets
+    fn exact_growth<F: Fn(usize, usize, usize) -> bool>(hist: &[usize], accept: F) -> Vec<f64> {
+        let n = hist.len() - 1;
+        (1..=n)
+            .map(|m| {
+                let mut numerator = BigUint::zero();
+                for (i, &count) in hist.iter().enumerate() {
+                    for j in 0..=usize::min(i, m) {
+                        if accept(m, i, j) {
+                            numerator += big_choose(i, j) * big_choose(n - i, m - j) * count;
+                        }
+                    }
+                }
+                big_to_f64(&numerator, &big_choose(n, m))
+            })
+            .collect()
     }
-    res
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    fn test_hists() -> Vec<Vec<usize>> {
+        vec![
+            vec![0, 5, 3, 2],
+            vec![0, 5, 3, 2, 3, 5, 0, 4, 2, 1],
+            vec![3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 7],
+            (0..=40).map(|i| (i * 37 + 11) % 23).collect(),
+            (0..=90).map(|i| (i * 13 + 5) % 31).collect(),
+        ]
+    }
 
     #[test]
     fn test_hist_calc_growth_union() {
@@ -381,7 +546,7 @@ mod tests {
         let t_coverage = Threshold::Absolute(0);
         let test_growth: Vec<f64> = vec![5.666666666666667, 8.333333333333334, 10.0];
         let growth = calc_growth_union(&hist, &t_coverage);
-        assert_eq!(growth, test_growth, "Wrong growth union");
+        assert_almost_eq(&growth, &test_growth, "Wrong growth union");
     }
 
     #[test]
@@ -391,7 +556,7 @@ mod tests {
         let t_coverage = Threshold::Absolute(0);
         let test_core: Vec<f64> = vec![5.666666666666666, 3.0, 2.0];
         let core = calc_growth_core(&hist, &t_coverage);
-        assert_eq!(core, test_core, "Wrong growth core");
+        assert_almost_eq(&core, &test_core, "Wrong growth core");
     }
 
     #[test]
@@ -412,6 +577,77 @@ mod tests {
             1.0,
         ];
         let growth = calc_growth_quorum(&hist, &t_coverage, &t_quorum);
-        assert_eq!(growth, test_growth, "Wrong growth quorum");
+        assert_almost_eq(&growth, &test_growth, "Wrong growth quorum");
+    }
+
+    #[test]
+    fn test_calc_growth_matches_exact_union_and_core() {
+        for values in test_hists() {
+            let n = values.len() - 1;
+            let hist = Hist::from_values(values.clone());
+            for coverage in [0, 1, 2, n / 2, n] {
+                let t_coverage = Threshold::Absolute(coverage);
+                let c = usize::max(1, coverage);
+                let union = exact_growth(&values, |_, i, j| i >= c && j >= 1);
+                assert_almost_eq(
+                    &calc_growth_union(&hist, &t_coverage),
+                    &union,
+                    &format!("union of n={} with coverage {}", n, coverage),
+                );
+                let c = usize::max(1, t_coverage.to_absolute(n + 1));
+                let core = exact_growth(&values, |m, i, j| i >= c && j == m);
+                assert_almost_eq(
+                    &calc_growth_core(&hist, &t_coverage),
+                    &core,
+                    &format!("core of n={} with coverage {}", n, coverage),
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_calc_growth_matches_exact_quorum() {
+        for values in test_hists() {
+            let n = values.len() - 1;
+            let hist = Hist::from_values(values.clone());
+            for coverage in [0, 2, n / 2] {
+                for quorum in [0.1, 0.25, 0.5, 0.9] {
+                    let t_coverage = Threshold::Absolute(coverage);
+                    let t_quorum = Threshold::Relative(quorum);
+                    let c = usize::max(1, coverage);
+                    let quorum_growth = exact_growth(&values, |m, i, j| {
+                        let m_quorum = usize::max((m as f64 * quorum).ceil() as usize, c);
+                        (i >= c && j == m) || (m_quorum < m && j >= m_quorum && j < m)
+                    });
+                    assert_almost_eq(
+                        &calc_growth_quorum(&hist, &t_coverage, &t_quorum),
+                        &quorum_growth,
+                        &format!("quorum {} of n={} with coverage {}", quorum, n, coverage),
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_calc_growth_large_cohort() {
+        let n = 2_000;
+        let values: Vec<usize> = (0..=n).map(|i| 1 + (i * 7919) % 101).collect();
+        let hist = Hist::from_values(values.clone());
+        let total: usize = values[1..].iter().sum();
+
+        let union = calc_growth(&hist, &Threshold::Absolute(0), &Threshold::Absolute(0));
+        let core = calc_growth(&hist, &Threshold::Absolute(0), &Threshold::Relative(1.0));
+        let quorum = calc_growth(&hist, &Threshold::Absolute(0), &Threshold::Relative(0.5));
+        for growth in [&union, &core, &quorum] {
+            assert_eq!(growth.len(), n);
+            assert!(growth.iter().all(|x| x.is_finite() && *x >= 0.0));
+        }
+        assert!(union.windows(2).all(|w| w[0] <= w[1] + 1e-6));
+        assert!(core.windows(2).all(|w| w[0] + 1e-6 >= w[1]));
+        assert!((union[n - 1] - total as f64).abs() <= 1e-6 * total as f64);
+        assert!((union[0] - core[0]).abs() <= 1e-6 * total as f64);
+        assert!((core[n - 1] - values[n] as f64).abs() <= 1e-6);
+        assert!(quorum.iter().zip(&core).all(|(q, c)| q + 1e-6 >= *c));
     }
 }
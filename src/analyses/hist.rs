@@ -1,20 +1,42 @@
-use crate::html_report::ReportItem;
+use crate::analyses::result_table::{to_values, Column, ResultTable, Value};
+use crate::error::PanacusError;
+use crate::html_report::{DisplayOptions, ReportItem};
 use crate::{io::write_table, util::get_default_plot_downloads};
 
 use super::{AnalysisSection, HistBasedAnalysis};
 
+/// Reports the coverage histogram. There is no separate result, the values
+/// are those of `crate::hist::Hist::get_hist_values`.
 pub struct Hist {}
 
 impl HistBasedAnalysis for Hist {
+    fn generate_result_tables(
+        &mut self,
+        hist: &crate::hist::Hist,
+    ) -> Result<Vec<ResultTable>, PanacusError> {
+        let k = hist.get_feature_type();
+        let values = hist.get_hist_values();
+        Ok(vec![ResultTable::new(
+            &self.get_type(),
+            "hist",
+            hist.get_run_id(),
+            hist.get_run_name(),
+            k,
+            vec![Column::new("coverage", None), Column::new("hist", Some(k))],
+        )
+        .with_column_values(vec![
+            (0..values.len()).map(Value::from).collect(),
+            to_values(values),
+        ])])
+    }
+
     fn get_type(&self) -> String {
         "Hist".to_string()
     }
 
-    fn generate_table(&mut self, hist: &crate::hist::Hist) -> anyhow::Result<String> {
+    fn generate_table(&mut self, hist: &crate::hist::Hist) -> Result<String, PanacusError> {
         log::info!("reporting hist table");
         let mut res = String::new();
-        res.push_str(&crate::io::write_metadata_comments()?);
-
         let mut header_cols = vec![vec![
             "panacus".to_string(),
             "count".to_string(),
@@ -36,7 +58,7 @@ impl HistBasedAnalysis for Hist {
     fn generate_report_section(
         &mut self,
         hist: &crate::hist::Hist,
-    ) -> anyhow::Result<Vec<AnalysisSection>> {
+    ) -> Result<Vec<AnalysisSection>, PanacusError> {
         let table = self.generate_table(hist)?;
         let table = format!("`{}`", &table);
         let id_prefix = format!(
@@ -64,6 +86,7 @@ impl HistBasedAnalysis for Hist {
                 log_toggle: true,
             }],
             plot_downloads: get_default_plot_downloads(),
+            display: DisplayOptions::default(),
         };
         Ok(vec![histogram_tabs])
     }
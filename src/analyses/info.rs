@@ -2,27 +2,98 @@ use std::collections::HashMap;
 
 use itertools::Itertools;
 
+use crate::analyses::result_table::{Column, ResultTable, Value};
+use crate::error::PanacusError;
 use crate::{
     analyses::{AnalysisSection, MatrixBasedAnalysis},
     coverage_matrix::CoverageMatrix,
-    html_report::ReportItem,
+    html_report::{DisplayOptions, ReportItem},
     util::get_default_plot_downloads,
 };
 
+/// Reports statistics of the input graph. There is no separate result, the
+/// statistics are collected in the `FileInfo` of the coverage matrix (see
+/// `CoverageMatrix::get_file_info`).
 pub struct Info {}
 
 impl MatrixBasedAnalysis for Info {
-    fn generate_table(&mut self, matrix: &CoverageMatrix) -> anyhow::Result<String> {
-        let mut res = format!(
-            "# {}\n",
-            std::env::args().collect::<Vec<String>>().join(" ")
-        );
-        res.push_str("# Warning! This table is a multi-table, i.e. it might contain multiple tables concatenated together. They always have the form: <table-name>\t<key>\t<value>");
+    fn generate_table(&mut self, matrix: &CoverageMatrix) -> Result<String, PanacusError> {
+        let mut res = String::from("# Warning! This table is a multi-table, i.e. it might contain multiple tables concatenated together. They always have the form: <table-name>\t<key>\t<value>");
         res.push_str("table-name\tkey\tvalue");
         res.push_str(matrix.get_file_info().to_string().as_str());
         Ok(res)
     }
 
+    fn generate_result_tables(
+        &mut self,
+        matrix: &CoverageMatrix,
+    ) -> Result<Vec<ResultTable>, PanacusError> {
+        let k = matrix.get_feature_type();
+        let file_info = matrix.get_file_info();
+        let get_table = |name: &str, columns: Vec<Column>| {
+            ResultTable::new(
+                &self.get_type(),
+                name,
+                matrix.get_run_id(),
+                matrix.get_run_name(),
+                k,
+                columns,
+            )
+        };
+        let mut info = get_table(
+            "info",
+            vec![Column::new("key", None), Column::new("value", None)],
+        );
+        for (key, value) in file_info.iterate_infos() {
+            info.push_row(vec![Value::from(key), Value::from(value)]);
+        }
+        let mut tables = vec![info];
+        for (name, values) in file_info.iterate_tables() {
+            let mut table = get_table(
+                name,
+                vec![Column::new("key", None), Column::new("value", None)],
+            );
+            for (key, value) in values {
+                table.push_row(vec![Value::from(key.as_str()), Value::from(*value)]);
+            }
+            tables.push(table);
+        }
+        let path_stats = file_info.get_path_stats();
+        if !path_stats.is_empty() {
+            let mut table = get_table(
+                "path-stats",
+                PathStats::get_header()
+                    .into_iter()
+                    .zip([
+                        None,
+                        None,
+                        Some("bp"),
+                        None,
+                        None,
+                        Some("bp"),
+                        Some("bp"),
+                        None,
+                    ])
+                    .map(|(name, unit)| Column::new(name, unit))
+                    .collect(),
+            );
+            for stats in path_stats {
+                table.push_row(vec![
+                    Value::from(stats.group.as_str()),
+                    Value::from(stats.nodes),
+                    Value::from(stats.bp),
+                    Value::from(stats.unique_nodes),
+                    Value::from(stats.segments),
+                    Value::from(stats.n_bp),
+                    Value::from(stats.private_bp),
+                    Value::from(stats.reference_coverage.unwrap_or(f64::NAN)),
+                ]);
+            }
+            tables.push(table);
+        }
+        Ok(tables)
+    }
+
     fn get_type(&self) -> String {
         "Info".to_string()
     }
@@ -30,7 +101,7 @@ impl MatrixBasedAnalysis for Info {
     fn generate_report_section(
         &mut self,
         matrix: &CoverageMatrix,
-    ) -> anyhow::Result<Vec<AnalysisSection>> {
+    ) -> Result<Vec<AnalysisSection>, PanacusError> {
         let table = self.generate_table(matrix)?;
         let table = format!("`{}`", &table);
 
@@ -52,6 +123,20 @@ impl MatrixBasedAnalysis for Info {
         let run_name = matrix.get_run_name();
         let run_id = matrix.get_run_id();
         let safe_run_name = run_id.to_lowercase().replace(&[' ', '|', '\\'], "-");
+        let path_stats = matrix.get_file_info().get_path_stats();
+        if !path_stats.is_empty() {
+            plots.insert(
+                1,
+                ReportItem::Table {
+                    id: format!("{safe_run_name}-path-stats"),
+                    header: PathStats::get_header()
+                        .iter()
+                        .map(|x| x.to_string())
+                        .collect(),
+                    values: path_stats.iter().map(|x| x.to_values()).collect(),
+                },
+            );
+        }
         Ok(vec![AnalysisSection {
             id: format!("{safe_run_name}-file"),
             analysis: "Pangenome Info".to_string(),
@@ -61,6 +146,7 @@ impl MatrixBasedAnalysis for Info {
             table: Some(table.clone()),
             items: plots,
             plot_downloads: get_default_plot_downloads(),
+            display: DisplayOptions::default(),
         }])
     }
 }
@@ -76,15 +162,69 @@ pub struct FileInfo {
     infos: Vec<(String, String)>,
     plots: Vec<ReportItem>,
     tables: HashMap<String, Vec<(String, f64)>>,
+    path_stats: Vec<PathStats>,
     filetype: String,
 }
 
+/// Statistics of a single path/group, used to spot problematic assemblies
+/// (e.g. fragmented, gappy or highly divergent ones)
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathStats {
+    pub group: String,
+    /// Number of node traversals
+    pub nodes: usize,
+    /// Length of the node traversals in bp
+    pub bp: usize,
+    pub unique_nodes: usize,
+    /// Number of P/W lines (i.e. contigs or scaffolds)
+    pub segments: usize,
+    /// Length of the traversals of nodes whose sequence consists of Ns only
+    pub n_bp: usize,
+    /// Length of the nodes that are traversed by no other path/group
+    pub private_bp: usize,
+    /// Fraction of the reference bp that is traversed by the path/group,
+    /// only available if a reference is given
+    pub reference_coverage: Option<f64>,
+}
+
+impl PathStats {
+    pub fn get_header() -> Vec<&'static str> {
+        vec![
+            "group",
+            "nodes",
+            "bp",
+            "unique-nodes",
+            "segments",
+            "n-bp",
+            "private-bp",
+            "reference-coverage",
+        ]
+    }
+
+    pub fn to_values(&self) -> Vec<String> {
+        vec![
+            self.group.clone(),
+            self.nodes.to_string(),
+            self.bp.to_string(),
+            self.unique_nodes.to_string(),
+            self.segments.to_string(),
+            self.n_bp.to_string(),
+            self.private_bp.to_string(),
+            match self.reference_coverage {
+                Some(coverage) => format!("{:.4}", coverage),
+                None => "NA".to_string(),
+            },
+        ]
+    }
+}
+
 impl FileInfo {
     pub fn new(filetype: &str) -> Self {
         Self {
             infos: Vec::new(),
             plots: Vec::new(),
             tables: HashMap::new(),
+            path_stats: Vec::new(),
             filetype: filetype.to_string(),
         }
     }
@@ -101,6 +241,14 @@ impl FileInfo {
         self.tables.insert(name, table);
     }
 
+    pub fn set_path_stats(&mut self, path_stats: Vec<PathStats>) {
+        self.path_stats = path_stats;
+    }
+
+    pub fn get_path_stats(&self) -> &Vec<PathStats> {
+        &self.path_stats
+    }
+
     pub fn add_plot(&mut self, plot: ReportItem) {
         self.plots.push(plot);
     }
@@ -113,6 +261,13 @@ impl FileInfo {
         self.infos.iter().map(|(a, b)| (a.as_str(), b.as_str()))
     }
 
+    pub fn iterate_tables(&self) -> impl Iterator<Item = (&str, &Vec<(String, f64)>)> + '_ {
+        self.tables
+            .iter()
+            .sorted_by_key(|(name, _)| name.as_str())
+            .map(|(name, table)| (name.as_str(), table))
+    }
+
     #[cfg(test)]
     fn get_info_count(&self) -> usize {
         self.infos.len()
@@ -130,7 +285,7 @@ impl std::fmt::Display for FileInfo {
                 format!("info\t{}\t{}", k_c, v_c)
             })
             .join("\n");
-        write!(f, "{}\n", text)?;
+        writeln!(f, "{}", text)?;
         let table_text = self
             .tables
             .iter()
@@ -144,7 +299,14 @@ impl std::fmt::Display for FileInfo {
                     .join("\n")
             })
             .join("\n");
-        write!(f, "{}\n", table_text)
+        writeln!(f, "{}", table_text)?;
+        if !self.path_stats.is_empty() {
+            writeln!(f, "path-stats\t{}", PathStats::get_header().join("\t"))?;
+            for stats in &self.path_stats {
+                writeln!(f, "path-stats\t{}", stats.to_values().join("\t"))?;
+            }
+        }
+        Ok(())
     }
 }
 
@@ -184,6 +346,23 @@ mod tests {
         assert_eq!(&fileinfo.to_string(), text);
     }
 
+    #[test]
+    fn test_file_info_printing_path_stats() {
+        let mut fileinfo = create_simple_gfa_file_info();
+        fileinfo.set_path_stats(vec![PathStats {
+            group: "a#1".to_string(),
+            nodes: 4,
+            bp: 11,
+            unique_nodes: 4,
+            segments: 1,
+            n_bp: 4,
+            private_bp: 4,
+            reference_coverage: None,
+        }]);
+        let text = "info\tnumber-of-nodes\t12\n\npath-stats\tgroup\tnodes\tbp\tunique-nodes\tsegments\tn-bp\tprivate-bp\treference-coverage\npath-stats\ta#1\t4\t11\t4\t1\t4\t4\tNA\n";
+        assert_eq!(&fileinfo.to_string(), text);
+    }
+
     #[test]
     fn test_file_info_iterating() {
         let mut fileinfo = create_simple_gfa_file_info();
@@ -0,0 +1,317 @@
+use std::str::FromStr;
+
+use anyhow::anyhow;
+
+use crate::analyses::growth::calc_all_growths;
+use crate::analyses::result_table::{get_threshold_columns, to_values, Column, ResultTable, Value};
+use crate::analyses::MatrixBasedAnalysis;
+use crate::coverage_matrix::CoverageMatrix;
+use crate::error::PanacusError;
+use crate::file_formats::gfa_parser::ThresholdContainer;
+use crate::hist::Hist;
+use crate::html_report::{AnalysisSection, DisplayOptions, ReportItem};
+use crate::io::write_table;
+use crate::util::get_default_plot_downloads;
+
+/// Splits the features by their length in bp (node length for node/bp
+/// counts, allele length for allele/bubble counts) into classes and
+/// computes a histogram and growth curve for each of these classes
+pub struct LengthClassGrowth {
+    classes: String,
+    coverage: Option<String>,
+    quorum: Option<String>,
+    inner: Option<LengthClassGrowthResult>,
+}
+
+/// Result of the `LengthClassGrowth` analysis, with one entry per length
+/// class in `labels`, `hists` and `growths`
+#[derive(Debug, Clone)]
+pub struct LengthClassGrowthResult {
+    pub labels: Vec<String>,
+    pub hists: Vec<Hist>,
+    /// `growths[class][i]` is the growth curve of a class for `coverage[i]`
+    /// and `quorum[i]`
+    pub growths: Vec<Vec<Vec<f64>>>,
+    pub thresholds: ThresholdContainer,
+}
+
+impl MatrixBasedAnalysis for LengthClassGrowth {
+    fn generate_result_tables(
+        &mut self,
+        matrix: &CoverageMatrix,
+    ) -> Result<Vec<ResultTable>, PanacusError> {
+        self.set_inner(matrix)?;
+        let inner = self.inner.as_ref().unwrap();
+        let k = matrix.get_feature_type();
+        let taxa: Vec<Value> = (0..=matrix.get_path_names().len())
+            .map(Value::from)
+            .collect();
+
+        let mut columns = vec![Column::new("taxa", None)];
+        let mut values = vec![taxa.clone()];
+        for (label, hist) in inner.labels.iter().zip(&inner.hists) {
+            columns.push(Column::new(label, Some(k)));
+            values.push(to_values(hist.get_hist_values()));
+        }
+        let hists = ResultTable::new(
+            &self.get_type(),
+            "hist",
+            matrix.get_run_id(),
+            matrix.get_run_name(),
+            k,
+            columns,
+        )
+        .with_column_values(values);
+
+        let mut columns = vec![Column::new("taxa", None)];
+        let mut values = vec![taxa];
+        for (label, growths) in inner.labels.iter().zip(&inner.growths) {
+            columns.extend(get_threshold_columns(label, Some(k), &inner.thresholds));
+            values.extend(growths.iter().map(|growth| to_values(growth)));
+        }
+        let growths = ResultTable::new(
+            &self.get_type(),
+            "growth",
+            matrix.get_run_id(),
+            matrix.get_run_name(),
+            k,
+            columns,
+        )
+        .with_column_values(values);
+        Ok(vec![hists, growths])
+    }
+
+    fn get_type(&self) -> String {
+        "LengthClassGrowth".to_string()
+    }
+
+    fn generate_table(&mut self, matrix: &CoverageMatrix) -> Result<String, PanacusError> {
+        log::info!("reporting length class growth table");
+        self.set_inner(matrix)?;
+        let inner = self.inner.as_ref().unwrap();
+        let k = matrix.get_feature_type();
+
+        let mut res = String::new();
+        let mut header_cols = vec![vec![
+            "panacus".to_string(),
+            "count".to_string(),
+            "length".to_string(),
+            "coverage".to_string(),
+            "quorum".to_string(),
+        ]];
+        let mut output_columns: Vec<Vec<f64>> = Vec::new();
+        for ((label, hist), growths) in inner.labels.iter().zip(&inner.hists).zip(&inner.growths) {
+            output_columns.push(hist.get_hist_values().iter().map(|x| *x as f64).collect());
+            header_cols.push(vec![
+                "hist".to_string(),
+                k.to_string(),
+                label.to_string(),
+                String::new(),
+                String::new(),
+            ]);
+            for (i, growth) in growths.iter().enumerate() {
+                output_columns.push(
+                    growth
+                        .iter()
+                        .map(|el| if el.is_nan() { 0.0 } else { *el })
+                        .collect(),
+                );
+                header_cols.push(vec![
+                    "growth".to_string(),
+                    k.to_string(),
+                    label.to_string(),
+                    inner.thresholds.coverage[i].get_string(),
+                    inner.thresholds.quorum[i].get_string(),
+                ]);
+            }
+        }
+        res.push_str(&write_table(&header_cols, &output_columns)?);
+        Ok(res)
+    }
+
+    fn generate_report_section(
+        &mut self,
+        matrix: &CoverageMatrix,
+    ) -> Result<Vec<AnalysisSection>, PanacusError> {
+        let table = self.generate_table(matrix)?;
+        let table = format!("`{}`", &table);
+        let inner = self.inner.as_ref().unwrap();
+        let id_prefix = format!(
+            "length-class-growth-{}",
+            matrix
+                .get_run_id()
+                .to_lowercase()
+                .replace([' ', '|', '\\'], "-")
+        );
+        let k = matrix.get_feature_type();
+        let n = matrix.get_path_names().len();
+        let mut items = Vec::new();
+        for i in 0..inner.thresholds.coverage.len() {
+            items.push(ReportItem::MultiBar {
+                id: format!("{id_prefix}-{k}-growth-{i}"),
+                names: inner.labels.clone(),
+                x_label: format!(
+                    "taxa (coverage ≥ {}, quorum ≥ {})",
+                    inner.thresholds.coverage[i].get_string(),
+                    inner.thresholds.quorum[i].get_string()
+                ),
+                y_label: format!("#{}s", k),
+                labels: (0..=n).map(|i| i.to_string()).collect(),
+                values: inner
+                    .growths
+                    .iter()
+                    .map(|growths| {
+                        growths[i]
+                            .iter()
+                            .map(|el| if el.is_nan() { 0.0 } else { *el })
+                            .collect()
+                    })
+                    .collect(),
+                curve: None,
+                alpha: None,
+                log_toggle: false,
+            });
+        }
+        items.push(ReportItem::MultiBar {
+            id: format!("{id_prefix}-{k}-hist"),
+            names: inner.labels.clone(),
+            x_label: "taxa".to_string(),
+            y_label: format!("#{}s", k),
+            labels: (0..=n).map(|i| i.to_string()).collect(),
+            values: inner
+                .hists
+                .iter()
+                .map(|hist| hist.get_hist_values().iter().map(|c| *c as f64).collect())
+                .collect(),
+            curve: None,
+            alpha: None,
+            log_toggle: true,
+        });
+        Ok(vec![AnalysisSection {
+            id: format!("{id_prefix}-{k}"),
+            analysis: "Length-stratified Growth".to_string(),
+            run_name: matrix.get_run_name().to_owned(),
+            run_id: matrix.get_run_id().to_owned(),
+            countable: k.to_string(),
+            table: Some(table),
+            items,
+            plot_downloads: get_default_plot_downloads(),
+            display: DisplayOptions::default(),
+        }])
+    }
+}
+
+impl LengthClassGrowth {
+    pub fn new(classes: String, coverage: Option<String>, quorum: Option<String>) -> Self {
+        Self {
+            classes,
+            coverage,
+            quorum,
+            inner: None,
+        }
+    }
+
+    /// Computes the histograms and growth curves of all length classes
+    /// (only on the first call, later calls return the cached result)
+    pub fn get_result(
+        &mut self,
+        matrix: &CoverageMatrix,
+    ) -> Result<&LengthClassGrowthResult, PanacusError> {
+        self.set_inner(matrix)?;
+        Ok(self.inner.as_ref().unwrap())
+    }
+
+    fn set_inner(&mut self, matrix: &CoverageMatrix) -> anyhow::Result<()> {
+        if self.inner.is_some() {
+            return Ok(());
+        }
+        let sizes = matrix.get_feature_sizes();
+        if sizes.is_empty() && matrix.get_feature_count() > 0 {
+            return Err(anyhow!(
+                "Length classes are not available for {} counts",
+                matrix.get_feature_type()
+            ));
+        }
+        let boundaries = parse_length_classes(&self.classes)?;
+        let mut features: Vec<Vec<usize>> = vec![Vec::new(); boundaries.len() + 1];
+        for (feature, size) in sizes.iter().enumerate() {
+            let class = boundaries.iter().take_while(|b| *b <= size).count();
+            features[class].push(feature);
+        }
+        let mut labels = get_length_class_labels(&boundaries);
+        // Features shorter than the first boundary (e.g. deletion alleles)
+        // only get their own class if there are any
+        if features[0].is_empty() {
+            features.remove(0);
+            labels.remove(0);
+        }
+
+        let quorum = self.quorum.to_owned().unwrap_or("0".to_string());
+        let coverage = self.coverage.to_owned().unwrap_or("1".to_string());
+        let thresholds = ThresholdContainer::parse_params(&quorum, &coverage)?;
+        let hists: Vec<Hist> = features
+            .iter()
+            .map(|f| matrix.get_hist_for_features(f))
+            .collect();
+        let growths = hists
+            .iter()
+            .map(|hist| calc_all_growths(hist, &thresholds, true))
+            .collect();
+        self.inner = Some(LengthClassGrowthResult {
+            labels,
+            hists,
+            growths,
+            thresholds,
+        });
+        Ok(())
+    }
+}
+
+pub(crate) fn parse_length_classes(classes: &str) -> anyhow::Result<Vec<usize>> {
+    let boundaries = classes
+        .split(',')
+        .map(|x| {
+            usize::from_str(x.trim())
+                .map_err(|_| anyhow!("length class boundary \"{}\" is not an integer", x))
+        })
+        .collect::<anyhow::Result<Vec<usize>>>()?;
+    if boundaries.windows(2).any(|w| w[0] >= w[1]) {
+        return Err(anyhow!(
+            "length class boundaries \"{}\" are not strictly increasing",
+            classes
+        ));
+    }
+    Ok(boundaries)
+}
+
+fn get_length_class_labels(boundaries: &[usize]) -> Vec<String> {
+    let mut labels = vec![format!("<{}bp", boundaries[0])];
+    for w in boundaries.windows(2) {
+        labels.push(format!("{}-{}bp", w[0], w[1] - 1));
+    }
+    labels.push(format!("≥{}bp", boundaries[boundaries.len() - 1]));
+    labels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_length_classes() {
+        assert_eq!(
+            parse_length_classes("1,50,1000,10000").unwrap(),
+            vec![1, 50, 1000, 10000]
+        );
+        assert!(parse_length_classes("1,a").is_err());
+        assert!(parse_length_classes("50,1").is_err());
+    }
+
+    #[test]
+    fn test_length_class_labels() {
+        assert_eq!(
+            get_length_class_labels(&[1, 50, 1000]),
+            vec!["<1bp", "1-49bp", "50-999bp", "≥1000bp"]
+        );
+    }
+}
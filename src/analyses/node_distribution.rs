@@ -1,27 +1,63 @@
 use itertools::multizip;
 use itertools::Itertools;
 
+use crate::analyses::result_table::{Column, ResultTable, Value};
 use crate::analyses::MatrixBasedAnalysis;
 use crate::coverage_matrix::CoverageMatrix;
+use crate::error::PanacusError;
 use crate::{
-    html_report::{AnalysisSection, Bin, ReportItem},
+    html_report::{AnalysisSection, Bin, DisplayOptions, ReportItem},
     util::get_default_plot_downloads,
     util::CountType,
 };
 
+/// Number of hexagons along the coverage and length axis
+const DEFAULT_RESOLUTION: [u32; 2] = [15, 9];
+
+/// Hexagonal binning of the nodes by their coverage and (log) length
 pub struct NodeDistribution {
     bins: Vec<Bin>,
     min: (u32, f64),
     max: (u32, f64),
     threshold: usize,
+    resolution: [u32; 2],
 }
 
 impl MatrixBasedAnalysis for NodeDistribution {
+    fn generate_result_tables(
+        &mut self,
+        matrix: &CoverageMatrix,
+    ) -> Result<Vec<ResultTable>, PanacusError> {
+        let analysis = self.get_type();
+        let mut table = ResultTable::new(
+            &analysis,
+            "bins",
+            matrix.get_run_id(),
+            matrix.get_run_name(),
+            matrix.get_feature_type(),
+            vec![
+                Column::new("bin", None),
+                Column::new("coverage", None),
+                Column::new("log-length", Some("log10(bp)")),
+                Column::new("size", Some(matrix.get_feature_type())),
+            ],
+        );
+        for (i, bin) in self.get_result(matrix)?.iter().enumerate() {
+            table.push_row(vec![
+                Value::from(i),
+                Value::from(bin.x),
+                Value::from(bin.y),
+                Value::from(bin.size as usize),
+            ]);
+        }
+        Ok(vec![table])
+    }
+
     fn get_type(&self) -> String {
         "NodeDistribution".to_string()
     }
 
-    fn generate_table(&mut self, matrix: &CoverageMatrix) -> anyhow::Result<String> {
+    fn generate_table(&mut self, matrix: &CoverageMatrix) -> Result<String, PanacusError> {
         if self.bins.is_empty() {
             self.set_table(matrix);
         }
@@ -35,7 +71,7 @@ impl MatrixBasedAnalysis for NodeDistribution {
     fn generate_report_section(
         &mut self,
         matrix: &CoverageMatrix,
-    ) -> anyhow::Result<Vec<crate::html_report::AnalysisSection>> {
+    ) -> Result<Vec<crate::html_report::AnalysisSection>, PanacusError> {
         let table = self.generate_table(matrix)?;
         //let table = "".to_string();
         let table = format!("`{}`", &table);
@@ -59,6 +95,7 @@ impl MatrixBasedAnalysis for NodeDistribution {
                 threshold: self.threshold,
             }],
             plot_downloads: get_default_plot_downloads(),
+            display: DisplayOptions::default(),
         }];
         Ok(tab)
     }
@@ -91,18 +128,31 @@ impl NodeDistribution {
             node_lens.into_iter().copied(),
         ))
         .collect();
-        let bins = Bin::hexbin(&points, 15, 9);
+        let [nx, ny] = self.resolution;
+        let bins = Bin::hexbin(&points, nx, ny);
         self.bins = bins;
         self.min = (*cov_min as u32, *lens_min);
         self.max = (*cov_max as u32, *lens_max);
     }
 
-    pub fn new(_radius: u32, threshold: usize) -> Self {
+    /// Computes the bins of the nodes (only on the first call, later calls
+    /// return the cached result)
+    pub fn get_result(&mut self, matrix: &CoverageMatrix) -> Result<&[Bin], PanacusError> {
+        if self.bins.is_empty() {
+            self.set_table(matrix);
+        }
+        Ok(&self.bins)
+    }
+
+    /// The resolution is the number of hexagons along the coverage and
+    /// length axis (`display: hexbin_resolution`)
+    pub fn new(_radius: u32, threshold: usize, resolution: Option<[u32; 2]>) -> Self {
         Self {
             bins: Vec::new(),
             min: (u32::MIN, f64::MIN),
             max: (u32::MAX, f64::MAX),
             threshold,
+            resolution: resolution.unwrap_or(DEFAULT_RESOLUTION),
         }
     }
 }
@@ -0,0 +1,254 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::anyhow;
+
+use crate::analyses::result_table::{Column, ResultTable, Value};
+use crate::error::PanacusError;
+use crate::{
+    analyses::MatrixBasedAnalysis,
+    coverage_matrix::CoverageMatrix,
+    html_report::{AnalysisSection, DisplayOptions, ReportItem},
+    util::get_default_plot_downloads,
+};
+
+/// Reports clusters of non-reference nodes (i.e. sequence that is novel
+/// with respect to the reference) together with the reference node at which
+/// they are anchored. The anchors are computed while parsing the graph, so
+/// this analysis requires a `reference` to be set for the run.
+pub struct NonReferenceAnchors {
+    min_length: usize,
+    rows: Option<Vec<AnchorRow>>,
+}
+
+/// A cluster of non-reference nodes, the result of the `NonReferenceAnchors`
+/// analysis consists of one row per cluster
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnchorRow {
+    /// Reference path and the coordinates of the anchor node on it
+    pub reference: String,
+    pub start: usize,
+    pub end: usize,
+    pub anchor: String,
+    /// Number and total length of the non-reference nodes in the cluster
+    pub nodes: usize,
+    pub bp: usize,
+    /// Number of paths/groups traversing the cluster
+    pub haplotypes: usize,
+    /// Number of distinct node sets in which the cluster is traversed
+    pub alleles: usize,
+}
+
+impl MatrixBasedAnalysis for NonReferenceAnchors {
+    fn generate_result_tables(
+        &mut self,
+        matrix: &CoverageMatrix,
+    ) -> Result<Vec<ResultTable>, PanacusError> {
+        let analysis = self.get_type();
+        let units = [
+            None,
+            Some("bp"),
+            Some("bp"),
+            None,
+            None,
+            Some("bp"),
+            None,
+            None,
+        ];
+        let mut table = ResultTable::new(
+            &analysis,
+            "anchors",
+            matrix.get_run_id(),
+            matrix.get_run_name(),
+            matrix.get_feature_type(),
+            Self::get_header()
+                .iter()
+                .zip(units)
+                .map(|(name, unit)| Column::new(name, unit))
+                .collect(),
+        );
+        for row in self.get_result(matrix)? {
+            table.push_row(vec![
+                Value::from(row.reference.as_str()),
+                Value::from(row.start),
+                Value::from(row.end),
+                Value::from(row.anchor.as_str()),
+                Value::from(row.nodes),
+                Value::from(row.bp),
+                Value::from(row.haplotypes),
+                Value::from(row.alleles),
+            ]);
+        }
+        Ok(vec![table])
+    }
+
+    fn get_type(&self) -> String {
+        "NonReferenceAnchors".to_string()
+    }
+
+    fn generate_table(&mut self, matrix: &CoverageMatrix) -> Result<String, PanacusError> {
+        log::info!("reporting non-reference anchors table");
+        if self.rows.is_none() {
+            self.set_inner(matrix)?;
+        }
+        let mut res = String::new();
+        res.push_str(&format!("#{}\n", Self::get_header().join("\t")));
+        for row in self.rows.as_ref().unwrap() {
+            res.push_str(&format!("{}\n", row.to_values().join("\t")));
+        }
+        Ok(res)
+    }
+
+    fn generate_report_section(
+        &mut self,
+        matrix: &CoverageMatrix,
+    ) -> Result<Vec<AnalysisSection>, PanacusError> {
+        let table = self.generate_table(matrix)?;
+        let table = format!("`{}`", &table);
+        let id_prefix = format!(
+            "non-reference-anchors-{}",
+            matrix
+                .get_run_id()
+                .to_lowercase()
+                .replace([' ', '|', '\\'], "-")
+        );
+        let values = self
+            .rows
+            .as_ref()
+            .unwrap()
+            .iter()
+            .map(|row| row.to_values())
+            .collect();
+        let k = matrix.get_feature_type();
+        Ok(vec![AnalysisSection {
+            id: format!("{id_prefix}-{k}"),
+            analysis: "Non-reference Anchors".to_string(),
+            table: Some(table),
+            run_name: matrix.get_run_name().to_owned(),
+            run_id: matrix.get_run_id().to_owned(),
+            countable: k.to_string(),
+            items: vec![ReportItem::Table {
+                id: format!("{id_prefix}-{k}-table"),
+                header: Self::get_header(),
+                values,
+            }],
+            plot_downloads: get_default_plot_downloads(),
+            display: DisplayOptions::default(),
+        }])
+    }
+}
+
+impl NonReferenceAnchors {
+    pub fn new(min_length: usize) -> Self {
+        Self {
+            min_length,
+            rows: None,
+        }
+    }
+
+    /// Computes the non-reference clusters (only on the first call, later
+    /// calls return the cached result)
+    pub fn get_result(&mut self, matrix: &CoverageMatrix) -> Result<&[AnchorRow], PanacusError> {
+        if self.rows.is_none() {
+            self.set_inner(matrix)?;
+        }
+        Ok(self.rows.as_ref().unwrap())
+    }
+
+    fn get_header() -> Vec<String> {
+        [
+            "reference",
+            "start",
+            "end",
+            "anchor",
+            "nodes",
+            "bp",
+            "haplotypes",
+            "alleles",
+        ]
+        .iter()
+        .map(|x| x.to_string())
+        .collect()
+    }
+
+    fn set_inner(&mut self, matrix: &CoverageMatrix) -> anyhow::Result<()> {
+        let feature_type = matrix.get_feature_type();
+        if feature_type != "node" && feature_type != "bp" {
+            return Err(anyhow!(
+                "Non-reference anchors can only be computed for node or bp counts, not {}",
+                feature_type
+            ));
+        }
+        let anchors = matrix.get_reference_anchors();
+        if anchors.is_empty() {
+            log::warn!("no non-reference anchors found, make sure that a reference is given");
+        }
+        let feature_ids: HashMap<String, usize> = matrix
+            .get_feature_names()
+            .into_iter()
+            .enumerate()
+            .map(|(idx, name)| (name, idx))
+            .collect();
+        let number_of_groups = matrix.get_path_names().len();
+        let mut rows = Vec::new();
+        for anchor in anchors {
+            // For each group, the (sorted) cluster nodes it traverses
+            let mut alleles: Vec<Vec<usize>> = vec![Vec::new(); number_of_groups];
+            let mut nodes = 0;
+            let mut bp = 0;
+            for (node_idx, (name, length)) in anchor.nodes.iter().enumerate() {
+                // Features might be missing if they have been excluded
+                let feature = match feature_ids.get(name) {
+                    Some(feature) => *feature,
+                    None => continue,
+                };
+                let counts = matrix.get_counts_for_feature(feature);
+                if counts.iter().all(|&c| c == 0) {
+                    continue;
+                }
+                nodes += 1;
+                bp += length;
+                for (group, count) in counts.into_iter().enumerate() {
+                    if count > 0 {
+                        alleles[group].push(node_idx);
+                    }
+                }
+            }
+            if nodes == 0 || bp < self.min_length {
+                continue;
+            }
+            let carriers: Vec<Vec<usize>> = alleles.into_iter().filter(|a| !a.is_empty()).collect();
+            let haplotypes = carriers.len();
+            let alleles = carriers.into_iter().collect::<HashSet<_>>().len();
+            rows.push(AnchorRow {
+                reference: anchor.reference.clone(),
+                start: anchor.start,
+                end: anchor.end,
+                anchor: anchor.anchor.clone(),
+                nodes,
+                bp,
+                haplotypes,
+                alleles,
+            });
+        }
+        rows.sort_by(|a, b| {
+            (&a.reference, a.start, &a.anchor).cmp(&(&b.reference, b.start, &b.anchor))
+        });
+        self.rows = Some(rows);
+        Ok(())
+    }
+}
+
+impl AnchorRow {
+    fn to_values(&self) -> Vec<String> {
+        vec![
+            self.reference.clone(),
+            self.start.to_string(),
+            self.end.to_string(),
+            self.anchor.clone(),
+            self.nodes.to_string(),
+            self.bp.to_string(),
+            self.haplotypes.to_string(),
+            self.alleles.to_string(),
+        ]
+    }
+}
@@ -2,40 +2,87 @@ use std::fs::File;
 use std::io::{self, BufRead};
 use std::path::Path;
 
+use crate::analyses::result_table::{get_threshold_columns, to_values, Column, ResultTable, Value};
 use crate::analyses::MatrixBasedAnalysis;
 use crate::coverage_matrix::CoverageMatrix;
+use crate::error::PanacusError;
 use crate::file_formats::gfa_parser::ThresholdContainer;
 use crate::html_report::ReportItem;
-use crate::io::{write_metadata_comments, write_ordered_table};
+use crate::io::write_ordered_table;
 use crate::util::get_default_plot_downloads;
 use crate::util::Threshold;
 use rayon::prelude::*;
 
 use super::AnalysisSection;
+use crate::html_report::DisplayOptions;
 
 type Growths = Vec<Vec<f64>>;
 
+/// Growth curves for a fixed order in which the paths/groups are added
 pub struct OrderedHistgrowth {
     coverage: Option<String>,
     quorum: Option<String>,
-    path_order: Option<Vec<String>>,
     order: Option<String>,
-    inner: Option<InnerOrderedGrowth>,
+    inner: Option<OrderedGrowthResult>,
+}
+
+/// Result of the `OrderedHistgrowth` analysis
+#[derive(Debug, Clone)]
+pub struct OrderedGrowthResult {
+    /// Order in which the paths/groups are added
+    pub order: Vec<String>,
+    /// The i-th growth curve uses `coverage[i]` and `quorum[i]`
+    pub thresholds: ThresholdContainer,
+    /// `growths[i][j]` is the number of countables after adding the first
+    /// `j + 1` paths/groups of `order`
+    pub growths: Growths,
 }
 
 impl MatrixBasedAnalysis for OrderedHistgrowth {
+    fn generate_result_tables(
+        &mut self,
+        matrix: &CoverageMatrix,
+    ) -> Result<Vec<ResultTable>, PanacusError> {
+        self.set_inner(matrix)?;
+        let inner = self.inner.as_ref().unwrap();
+        let k = matrix.get_feature_type();
+        let mut columns = vec![Column::new("taxa", None), Column::new("path", None)];
+        columns.extend(get_threshold_columns(
+            "ordered-growth",
+            Some(k),
+            &inner.thresholds,
+        ));
+        let n = inner.growths.first().map(|g| g.len()).unwrap_or(0);
+        let mut values = vec![
+            (1..=n).map(Value::from).collect(),
+            (0..n)
+                .map(|i| Value::from(inner.order.get(i).cloned().unwrap_or_default()))
+                .collect(),
+        ];
+        values.extend(inner.growths.iter().map(|growth| to_values(growth)));
+        Ok(vec![ResultTable::new(
+            &self.get_type(),
+            "ordered-growth",
+            matrix.get_run_id(),
+            matrix.get_run_name(),
+            k,
+            columns,
+        )
+        .with_column_values(values)])
+    }
+
     fn get_type(&self) -> String {
         "OrderedHistgrowth".to_string()
     }
 
-    fn generate_table(&mut self, matrix: &CoverageMatrix) -> anyhow::Result<String> {
+    fn generate_table(&mut self, matrix: &CoverageMatrix) -> Result<String, PanacusError> {
         self.set_inner(matrix)?;
 
-        let mut res = write_metadata_comments()?;
+        let mut res = String::new();
 
         let inner = self.inner.as_ref().unwrap();
         // insert empty row for 0 element
-        let m = inner.hist_aux.coverage.len();
+        let m = inner.thresholds.coverage.len();
         let mut header_cols = vec![vec![
             "panacus".to_string(),
             "count".to_string(),
@@ -46,14 +93,14 @@ impl MatrixBasedAnalysis for OrderedHistgrowth {
             std::iter::repeat("ordered-growth")
                 .take(m)
                 .zip(std::iter::repeat(matrix.get_feature_type()).take(m))
-                .zip(inner.hist_aux.coverage.iter())
-                .zip(&inner.hist_aux.quorum)
+                .zip(inner.thresholds.coverage.iter())
+                .zip(&inner.thresholds.quorum)
                 .map(|(((p, t), c), q)| {
                     vec![p.to_string(), t.to_string(), c.get_string(), q.get_string()]
                 })
                 .collect::<Vec<Vec<String>>>(),
         );
-        let mut order = self.path_order.as_ref().unwrap().clone();
+        let mut order = inner.order.clone();
 
         // Insert zero element
         order.insert(0, "No_Path".to_string());
@@ -69,9 +116,9 @@ impl MatrixBasedAnalysis for OrderedHistgrowth {
     fn generate_report_section(
         &mut self,
         matrix: &CoverageMatrix,
-    ) -> anyhow::Result<Vec<AnalysisSection>> {
+    ) -> Result<Vec<AnalysisSection>, PanacusError> {
         self.set_inner(matrix)?;
-        let hist_aux = &self.inner.as_ref().unwrap().hist_aux;
+        let hist_aux = &self.inner.as_ref().unwrap().thresholds;
         let growth_labels = (0..hist_aux.coverage.len())
             .map(|i| {
                 format!(
@@ -113,6 +160,7 @@ impl MatrixBasedAnalysis for OrderedHistgrowth {
                 curve: None,
             }],
             plot_downloads: get_default_plot_downloads(),
+            display: DisplayOptions::default(),
         }];
         Ok(growth_tabs)
     }
@@ -124,10 +172,20 @@ impl OrderedHistgrowth {
             coverage,
             quorum,
             order,
-            path_order: None,
             inner: None,
         }
     }
+
+    /// Computes the ordered growth curves (only on the first call, later
+    /// calls return the cached result)
+    pub fn get_result(
+        &mut self,
+        matrix: &CoverageMatrix,
+    ) -> Result<&OrderedGrowthResult, PanacusError> {
+        self.set_inner(matrix)?;
+        Ok(self.inner.as_ref().unwrap())
+    }
+
     fn set_inner(&mut self, matrix: &CoverageMatrix) -> anyhow::Result<()> {
         if self.inner.is_some() {
             return Ok(());
@@ -154,17 +212,15 @@ impl OrderedHistgrowth {
                 calc_growth(matrix, c, q, &order)
             })
             .collect();
-        self.path_order = Some(order);
-        self.inner = Some(InnerOrderedGrowth { growths, hist_aux });
+        self.inner = Some(OrderedGrowthResult {
+            order,
+            thresholds: hist_aux,
+            growths,
+        });
         Ok(())
     }
 }
 
-struct InnerOrderedGrowth {
-    growths: Growths,
-    hist_aux: ThresholdContainer,
-}
-
 pub fn parse_list(filename: &str) -> anyhow::Result<Vec<String>> {
     let lines = read_lines(filename)?.map_while(Result::ok).collect();
     Ok(lines)
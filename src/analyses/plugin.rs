@@ -0,0 +1,424 @@
+//! Analyses computed by an external executable (`!Plugin`), so that
+//! in-house statistics can be part of the report without changing panacus.
+//!
+//! # Protocol
+//!
+//! The plugin gets its input as JSON on stdin or, if one of its arguments
+//! contains `{input}`, in a temporary file whose path replaces `{input}`.
+//! Plugins with `input: Hist` get the coverage histogram, `hist[i]` being the
+//! number of countables covered by `i` groups:
+//!
+//! ```json
+//! {"input": "hist", "run_id": "graph.gfa--", "run_name": "graph.gfa", "count_type": "node",
+//!  "hist": [5, 0, 10]}
+//! ```
+//!
+//! Plugins with `input: Matrix` get the coverage matrix in compressed sparse
+//! row format, with one row per countable. The groups covering countable `i`
+//! are `group_indices[row_offsets[i]..row_offsets[i + 1]]`, the entries of
+//! `values` at the same positions are the number of times they cover it:
+//!
+//! ```json
+//! {"input": "matrix", "run_id": "graph.gfa--", "run_name": "graph.gfa", "count_type": "node",
+//!  "groups": ["a", "b"], "countables": ["1", "2"],
+//!  "row_offsets": [0, 2, 3], "group_indices": [0, 1, 1], "values": [1, 2, 1]}
+//! ```
+//!
+//! The plugin writes a JSON array of report sections (or a single section)
+//! to stdout, in the format of `report --json`, and exits with status 0:
+//!
+//! ```json
+//! [{"countable": "node", "items": [{"Table": {"id": "my-table", "header": ["a", "b"], "values": [["1", "2"]]}}]}]
+//! ```
+//!
+//! Only `items` is required: `analysis` defaults to the name of the plugin,
+//! `run_name`, `run_id` and `countable` to those of the input, `id` to the id
+//! of the only item (or an id derived from the run and name), `table` (the
+//! downloadable table) to none and `plot_downloads` to the default downloads
+//! if there are plots. The sections are checked before they are added to the
+//! report, e.g. that all series of a plot have as many values as labels.
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use anyhow::anyhow;
+use serde_json::{json, Map, Value};
+
+use crate::analyses::result_table::ResultTable;
+use crate::coverage_matrix::CoverageMatrix;
+use crate::error::PanacusError;
+use crate::html_report::ReportItem;
+use crate::util::{get_default_plot_downloads, to_id};
+
+use super::{AnalysisSection, HistBasedAnalysis, MatrixBasedAnalysis};
+
+/// Replaced by the path of the input file in the arguments of a plugin
+pub const INPUT_PLACEHOLDER: &str = "{input}";
+
+pub struct Plugin {
+    name: String,
+    command: String,
+    args: Vec<String>,
+}
+
+impl HistBasedAnalysis for Plugin {
+    fn generate_result_tables(
+        &mut self,
+        _hist: &crate::hist::Hist,
+    ) -> Result<Vec<ResultTable>, PanacusError> {
+        Ok(Vec::new())
+    }
+
+    fn get_type(&self) -> String {
+        "Plugin".to_string()
+    }
+
+    fn generate_table(&mut self, hist: &crate::hist::Hist) -> Result<String, PanacusError> {
+        Ok(get_tables(&HistBasedAnalysis::generate_report_section(
+            self, hist,
+        )?))
+    }
+
+    fn generate_report_section(
+        &mut self,
+        hist: &crate::hist::Hist,
+    ) -> Result<Vec<AnalysisSection>, PanacusError> {
+        let input = json!({
+            "input": "hist",
+            "run_id": hist.get_run_id(),
+            "run_name": hist.get_run_name().trim(),
+            "count_type": hist.get_feature_type(),
+            "hist": hist.get_hist_values(),
+        });
+        let output = self.run(&input)?;
+        parse_output(
+            &output,
+            &self.name,
+            hist.get_run_name(),
+            hist.get_run_id(),
+            hist.get_feature_type(),
+        )
+    }
+}
+
+impl MatrixBasedAnalysis for Plugin {
+    fn generate_result_tables(
+        &mut self,
+        _matrix: &CoverageMatrix,
+    ) -> Result<Vec<ResultTable>, PanacusError> {
+        Ok(Vec::new())
+    }
+
+    fn get_type(&self) -> String {
+        "Plugin".to_string()
+    }
+
+    fn generate_table(&mut self, matrix: &CoverageMatrix) -> Result<String, PanacusError> {
+        Ok(get_tables(&MatrixBasedAnalysis::generate_report_section(
+            self, matrix,
+        )?))
+    }
+
+    fn generate_report_section(
+        &mut self,
+        matrix: &CoverageMatrix,
+    ) -> Result<Vec<AnalysisSection>, PanacusError> {
+        let (row_offsets, group_indices) = matrix.get_csr();
+        let input = json!({
+            "input": "matrix",
+            "run_id": matrix.get_run_id(),
+            "run_name": matrix.get_run_name().trim(),
+            "count_type": matrix.get_feature_type(),
+            "groups": matrix.get_path_names(),
+            "countables": matrix.get_feature_names(),
+            "row_offsets": row_offsets,
+            "group_indices": group_indices,
+            "values": matrix.get_csr_values(),
+        });
+        let output = self.run(&input)?;
+        parse_output(
+            &output,
+            &self.name,
+            matrix.get_run_name(),
+            matrix.get_run_id(),
+            matrix.get_feature_type(),
+        )
+    }
+}
+
+impl Plugin {
+    pub fn new(name: String, command: String, args: Vec<String>) -> Self {
+        Self {
+            name,
+            command,
+            args,
+        }
+    }
+
+    /// Runs the plugin on the input, returns its standard output
+    fn run(&self, input: &Value) -> Result<Vec<u8>, PanacusError> {
+        let input = serde_json::to_vec(input).map_err(anyhow::Error::from)?;
+        // The file is deleted when it is dropped after the plugin finished
+        let mut input_file = None;
+        let mut args = self.args.clone();
+        if args.iter().any(|arg| arg.contains(INPUT_PLACEHOLDER)) {
+            let mut file = tempfile::NamedTempFile::new()?;
+            file.write_all(&input)?;
+            file.flush()?;
+            let path = file.path().to_string_lossy().to_string();
+            for arg in args.iter_mut() {
+                *arg = arg.replace(INPUT_PLACEHOLDER, &path);
+            }
+            input_file = Some(file);
+        }
+        log::info!("running plugin {} {}", self.command, args.join(" "));
+        let mut child = Command::new(&self.command)
+            .args(&args)
+            .stdin(match input_file {
+                Some(_) => Stdio::null(),
+                None => Stdio::piped(),
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| anyhow!("could not run plugin {}: {}", self.command, e))?;
+        // Writing in a separate thread, so that a plugin that writes its
+        // output before reading all input does not block
+        let writer = child.stdin.take().map(|mut stdin| {
+            std::thread::spawn(move || match stdin.write_all(&input) {
+                // The plugin need not read its input
+                Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => Ok(()),
+                res => res,
+            })
+        });
+        let output = child.wait_with_output()?;
+        if let Some(writer) = writer {
+            writer
+                .join()
+                .map_err(|_| anyhow!("could not write input of plugin {}", self.command))??;
+        }
+        drop(input_file);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if !output.status.success() {
+            return Err(anyhow!(
+                "plugin {} failed ({}): {}",
+                self.command,
+                output.status,
+                stderr.trim()
+            )
+            .into());
+        }
+        if !stderr.trim().is_empty() {
+            log::info!("plugin {}: {}", self.command, stderr.trim());
+        }
+        Ok(output.stdout)
+    }
+}
+
+/// Parses the sections written by a plugin, filling in the fields it may
+/// omit, and checks them
+fn parse_output(
+    output: &[u8],
+    name: &str,
+    run_name: &str,
+    run_id: &str,
+    countable: &str,
+) -> Result<Vec<AnalysisSection>, PanacusError> {
+    let output: Value = serde_json::from_slice(output)
+        .map_err(|e| anyhow!("plugin {} wrote invalid JSON: {}", name, e))?;
+    let sections = match output {
+        Value::Array(sections) => sections,
+        section => vec![section],
+    };
+    let id_prefix = format!(
+        "plugin-{}-{}",
+        run_id.to_lowercase().replace([' ', '|', '\\'], "-"),
+        to_id(name)
+    );
+    let n = sections.len();
+    sections
+        .into_iter()
+        .enumerate()
+        .map(|(i, section)| {
+            let invalid = |msg: String| anyhow!("section {} of plugin {}: {}", i + 1, name, msg);
+            let Value::Object(mut section) = section else {
+                return Err(invalid("not a JSON object".to_string()).into());
+            };
+            let items = section
+                .get("items")
+                .ok_or_else(|| invalid("missing field `items`".to_string()))?;
+            let items: Vec<ReportItem> = serde_json::from_value(items.clone())
+                .map_err(|e| invalid(format!("invalid items: {}", e)))?;
+            let id = match (&items[..], n) {
+                ([item], _) => item.get_id(),
+                (_, 1) => id_prefix.clone(),
+                _ => format!("{}-{}", id_prefix, i + 1),
+            };
+            let has_plots = items.iter().any(|item| {
+                !matches!(
+                    item,
+                    ReportItem::Table { .. } | ReportItem::Text { .. } | ReportItem::Error { .. }
+                )
+            });
+            let plot_downloads = match has_plots {
+                true => get_default_plot_downloads(),
+                false => Vec::new(),
+            };
+            let defaults: Map<String, Value> = vec![
+                ("analysis", json!(name)),
+                ("run_name", json!(run_name)),
+                ("run_id", json!(run_id)),
+                ("countable", json!(countable)),
+                ("id", json!(id)),
+                ("table", Value::Null),
+                ("plot_downloads", json!(plot_downloads)),
+            ]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v))
+            .collect();
+            for (key, value) in defaults {
+                section.entry(key).or_insert(value);
+            }
+            let section: AnalysisSection = serde_json::from_value(Value::Object(section))
+                .map_err(|e| invalid(e.to_string()))?;
+            check_section(&section).map_err(invalid)?;
+            Ok(section)
+        })
+        .collect()
+}
+
+/// Checks that the section can be rendered: ids are used in the HTML and
+/// JavaScript of the report and all values of a plot need a label
+fn check_section(section: &AnalysisSection) -> Result<(), String> {
+    check_id(&section.id)?;
+    for item in &section.items {
+        check_id(&item.get_id())?;
+        let lengths_match = match item {
+            ReportItem::Bar { labels, values, .. } => labels.len() == values.len(),
+            ReportItem::MultiBar {
+                names,
+                labels,
+                values,
+                ..
+            }
+            | ReportItem::SectionLine {
+                names,
+                labels,
+                values,
+                ..
+            } => names.len() == values.len() && values.iter().all(|v| v.len() == labels.len()),
+            ReportItem::Table { header, values, .. } => {
+                values.iter().all(|row| row.len() == header.len())
+            }
+            ReportItem::Heatmap {
+                x_labels,
+                y_labels,
+                values,
+                ..
+            } => values.len() == y_labels.len() && values.iter().all(|v| v.len() == x_labels.len()),
+            ReportItem::Line {
+                x_values, y_values, ..
+            } => x_values.len() == y_values.len(),
+            _ => true,
+        };
+        if !lengths_match {
+            return Err(format!(
+                "the values of item {} do not match its labels",
+                item.get_id()
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn check_id(id: &str) -> Result<(), String> {
+    if id.is_empty() || id.contains(['\'', '"', '\\', '`', '<', '>']) {
+        return Err(format!(
+            "invalid id `{}`, ids must not be empty or contain quotes, backslashes or angle brackets",
+            id
+        ));
+    }
+    Ok(())
+}
+
+/// The tables of the sections, for the commands that write tables instead
+/// of reports
+fn get_tables(sections: &[AnalysisSection]) -> String {
+    sections
+        .iter()
+        .filter_map(|section| section.table.as_deref())
+        .map(|table| table.trim_matches('`'))
+        .collect::<Vec<_>>()
+        .join("\n\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(output: &str) -> Result<Vec<AnalysisSection>, PanacusError> {
+        parse_output(
+            output.as_bytes(),
+            "My Stat",
+            "graph.gfa",
+            "graph.gfa--",
+            "node",
+        )
+    }
+
+    #[test]
+    fn test_missing_fields_are_filled_in() {
+        let sections = parse(
+            r#"{"items": [{"Bar": {"id": "my-bar", "name": "x", "x_label": "a", "y_label": "b",
+                "labels": ["1", "2"], "values": [1.0, 2.0], "log_toggle": false}}]}"#,
+        )
+        .unwrap();
+        assert_eq!(sections.len(), 1);
+        assert_eq!(sections[0].analysis, "My Stat");
+        assert_eq!(sections[0].run_id, "graph.gfa--");
+        assert_eq!(sections[0].countable, "node");
+        assert_eq!(sections[0].id, "my-bar");
+        assert_eq!(sections[0].plot_downloads, get_default_plot_downloads());
+
+        let sections =
+            parse(r#"[{"analysis": "Other", "items": []}, {"items": [], "id": "second"}]"#)
+                .unwrap();
+        assert_eq!(sections[0].analysis, "Other");
+        assert_eq!(sections[0].id, "plugin-graph.gfa---my-stat-1");
+        assert_eq!(sections[1].id, "second");
+        assert!(sections[1].plot_downloads.is_empty());
+    }
+
+    #[test]
+    fn test_invalid_output_is_rejected() {
+        let err = parse("not json").unwrap_err();
+        assert!(err.to_string().contains("invalid JSON"));
+        let err = parse(r#"{"countable": "node"}"#).unwrap_err();
+        assert!(err.to_string().contains("missing field `items`"));
+        let err = parse(
+            r#"{"items": [{"Table": {"id": "t", "header": ["a", "b"], "values": [["1"]]}}]}"#,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("do not match its labels"));
+        let err = parse(r#"{"items": [], "id": "a'b"}"#).unwrap_err();
+        assert!(err.to_string().contains("invalid id"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_plugin_gets_input_on_stdin_or_in_file() {
+        let plugin = Plugin::new("cat".to_string(), "cat".to_string(), Vec::new());
+        let output = plugin.run(&json!({"input": "hist"})).unwrap();
+        assert_eq!(output, br#"{"input":"hist"}"#);
+
+        let plugin = Plugin::new(
+            "cat".to_string(),
+            "cat".to_string(),
+            vec![INPUT_PLACEHOLDER.to_string()],
+        );
+        let output = plugin.run(&json!({"input": "matrix"})).unwrap();
+        assert_eq!(output, br#"{"input":"matrix"}"#);
+
+        let plugin = Plugin::new("false".to_string(), "false".to_string(), Vec::new());
+        assert!(plugin.run(&json!({})).is_err());
+    }
+}
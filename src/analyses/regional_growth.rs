@@ -7,23 +7,29 @@ use varpro::{problem::SeparableProblemBuilder, solvers::levmar::LevMarSolver};
 
 use crate::analyses::growth::calc_growth;
 use crate::analyses::regional_helpers::sort_values;
+use crate::analyses::result_table::{Column, ResultTable, Value};
+use crate::error::PanacusError;
 use crate::util::Threshold;
 use crate::{
     analyses::MatrixBasedAnalysis,
     coverage_matrix::CoverageMatrix,
     hist::Hist,
-    html_report::{AnalysisSection, ReportItem, Window},
+    html_report::{AnalysisSection, DisplayOptions, ReportItem, Window},
     util::get_default_plot_downloads,
 };
 
+/// Result of the `RegionalGrowth` analysis: for each reference path, the
+/// growth predictions of all its windows together with their start and end
+pub type RegionalGrowthResult = Vec<(String, Vec<(WindowGrowth, usize, usize)>)>;
+
 pub struct RegionalGrowth {
     order: Option<String>,
     window_size: usize,
-    cache: OnceCell<Vec<(String, Vec<(Growth, usize, usize)>)>>,
+    cache: OnceCell<RegionalGrowthResult>,
 }
 
 impl MatrixBasedAnalysis for RegionalGrowth {
-    fn generate_table(&mut self, matrix: &CoverageMatrix) -> anyhow::Result<String> {
+    fn generate_table(&mut self, matrix: &CoverageMatrix) -> Result<String, PanacusError> {
         let data = self.get_cached_data(matrix);
 
         let mut text = format!("reference\tstart\tend\tHill0\tHill1\tHill2\n");
@@ -47,7 +53,7 @@ impl MatrixBasedAnalysis for RegionalGrowth {
     fn generate_report_section(
         &mut self,
         matrix: &CoverageMatrix,
-    ) -> anyhow::Result<Vec<crate::html_report::AnalysisSection>> {
+    ) -> Result<Vec<crate::html_report::AnalysisSection>, PanacusError> {
         let data = self.get_cached_data(matrix);
 
         let id_prefix = matrix
@@ -97,10 +103,45 @@ impl MatrixBasedAnalysis for RegionalGrowth {
             countable: matrix.get_feature_type().to_string(),
             items: chromosomal,
             plot_downloads: get_default_plot_downloads(),
+            display: DisplayOptions::default(),
         }];
         Ok(regional_variation_tabs)
     }
 
+    fn generate_result_tables(
+        &mut self,
+        matrix: &CoverageMatrix,
+    ) -> Result<Vec<ResultTable>, PanacusError> {
+        let mut table = ResultTable::new(
+            &self.get_type(),
+            "regional-growth",
+            matrix.get_run_id(),
+            matrix.get_run_name(),
+            matrix.get_feature_type(),
+            vec![
+                Column::new("reference", None),
+                Column::new("start", Some("bp")),
+                Column::new("end", Some("bp")),
+                Column::new("ratio-2x", None),
+                Column::new("ratio-3x", None),
+                Column::new("ratio-5x", None),
+            ],
+        );
+        for (sequence_id, sequence) in self.get_result(matrix)? {
+            for (growth, start, end) in sequence {
+                table.push_row(vec![
+                    Value::from(sequence_id.as_str()),
+                    Value::from(*start),
+                    Value::from(*end),
+                    Value::from(growth.0),
+                    Value::from(growth.1),
+                    Value::from(growth.2),
+                ]);
+            }
+        }
+        Ok(vec![table])
+    }
+
     fn get_type(&self) -> String {
         "RegionalGrowth".to_string()
     }
@@ -115,16 +156,22 @@ impl RegionalGrowth {
         }
     }
 
+    /// Computes the growth predictions of all windows (only on the first call,
+    /// later calls return the cached result)
+    pub fn get_result(
+        &self,
+        matrix: &CoverageMatrix,
+    ) -> Result<&RegionalGrowthResult, PanacusError> {
+        Ok(self.get_cached_data(matrix))
+    }
+
     fn sort_values(&self, reference_names: &mut Vec<String>, windows: &mut Vec<Vec<Window>>) {
         if let Some(filename) = self.order.as_ref() {
             sort_values(filename, reference_names, windows);
         }
     }
 
-    fn get_cached_data(
-        &self,
-        matrix: &CoverageMatrix,
-    ) -> &Vec<(String, Vec<(Growth, usize, usize)>)> {
+    fn get_cached_data(&self, matrix: &CoverageMatrix) -> &RegionalGrowthResult {
         self.cache.get_or_init(|| {
             matrix
                 .get_regional_hists(self.window_size, self.window_size)
@@ -136,7 +183,7 @@ impl RegionalGrowth {
                             }
                             let result = calc_growth_predictions(&hist);
                             match result {
-                                Ok((d2, d3, d5)) => Some((Growth(d2, d3, d5), start, end)),
+                                Ok((d2, d3, d5)) => Some((WindowGrowth(d2, d3, d5), start, end)),
                                 Err(e) => {
                                     log::warn!(
                                         "Could not fit window {}:{}-{} because of {}",
@@ -149,7 +196,7 @@ impl RegionalGrowth {
                                 }
                             }
                         })
-                        .collect::<Vec<(Growth, usize, usize)>>();
+                        .collect::<Vec<(WindowGrowth, usize, usize)>>();
                     (r, windows)
                 })
                 .collect::<Vec<_>>()
@@ -161,8 +208,8 @@ impl RegionalGrowth {
 /// Percent increase in terms of feature if haplotypes increase 2x
 /// Percent increase in terms of feature if haplotypes increase 3x
 /// Percent increase in terms of feature if haplotypes increase 5x
-#[derive(Debug)]
-struct Growth(f64, f64, f64);
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowGrowth(pub f64, pub f64, pub f64);
 
 pub fn calc_growth_predictions(hist: &Hist) -> anyhow::Result<(f64, f64, f64)> {
     let d = hist.get_hist_values().iter().sum::<usize>() as f64;
@@ -1,21 +1,27 @@
 use std::cell::OnceCell;
 
+use crate::analyses::result_table::{Column, ResultTable, Value};
+use crate::error::PanacusError;
 use crate::{
     analyses::{regional_helpers::sort_values, MatrixBasedAnalysis},
     coverage_matrix::CoverageMatrix,
     hist::Hist,
-    html_report::{AnalysisSection, ReportItem, Window},
+    html_report::{AnalysisSection, DisplayOptions, ReportItem, Window},
     util::get_default_plot_downloads,
 };
 
+/// Result of the `RegionalVariation` analysis: for each reference path, the
+/// Hill numbers of all its windows together with their start and end
+pub type RegionalVariationResult = Vec<(String, Vec<(WindowVariation, usize, usize)>)>;
+
 pub struct RegionalVariation {
     order: Option<String>,
     window_size: usize,
-    cache: OnceCell<Vec<(String, Vec<(Variation, usize, usize)>)>>,
+    cache: OnceCell<RegionalVariationResult>,
 }
 
 impl MatrixBasedAnalysis for RegionalVariation {
-    fn generate_table(&mut self, matrix: &CoverageMatrix) -> anyhow::Result<String> {
+    fn generate_table(&mut self, matrix: &CoverageMatrix) -> Result<String, PanacusError> {
         let data = self.get_cached_data(matrix);
 
         let mut text = format!("reference\tstart\tend\tHill0\tHill1\tHill2\n");
@@ -39,7 +45,7 @@ impl MatrixBasedAnalysis for RegionalVariation {
     fn generate_report_section(
         &mut self,
         matrix: &CoverageMatrix,
-    ) -> anyhow::Result<Vec<crate::html_report::AnalysisSection>> {
+    ) -> Result<Vec<crate::html_report::AnalysisSection>, PanacusError> {
         let data = self.get_cached_data(matrix);
 
         let id_prefix = matrix
@@ -89,10 +95,45 @@ impl MatrixBasedAnalysis for RegionalVariation {
             countable: matrix.get_feature_type().to_string(),
             items: chromosomal,
             plot_downloads: get_default_plot_downloads(),
+            display: DisplayOptions::default(),
         }];
         Ok(regional_variation_tabs)
     }
 
+    fn generate_result_tables(
+        &mut self,
+        matrix: &CoverageMatrix,
+    ) -> Result<Vec<ResultTable>, PanacusError> {
+        let mut table = ResultTable::new(
+            &self.get_type(),
+            "regional-variation",
+            matrix.get_run_id(),
+            matrix.get_run_name(),
+            matrix.get_feature_type(),
+            vec![
+                Column::new("reference", None),
+                Column::new("start", Some("bp")),
+                Column::new("end", Some("bp")),
+                Column::new("richness", None),
+                Column::new("shannon-entropy", None),
+                Column::new("simpson-index", None),
+            ],
+        );
+        for (sequence_id, sequence) in self.get_result(matrix)? {
+            for (variation, start, end) in sequence {
+                table.push_row(vec![
+                    Value::from(sequence_id.as_str()),
+                    Value::from(*start),
+                    Value::from(*end),
+                    Value::from(variation.0),
+                    Value::from(variation.1),
+                    Value::from(variation.2),
+                ]);
+            }
+        }
+        Ok(vec![table])
+    }
+
     fn get_type(&self) -> String {
         "RegionalVariation".to_string()
     }
@@ -107,16 +148,22 @@ impl RegionalVariation {
         }
     }
 
+    /// Computes the Hill numbers of all windows (only on the first call, later
+    /// calls return the cached result)
+    pub fn get_result(
+        &self,
+        matrix: &CoverageMatrix,
+    ) -> Result<&RegionalVariationResult, PanacusError> {
+        Ok(self.get_cached_data(matrix))
+    }
+
     fn sort_values(&self, reference_names: &mut Vec<String>, windows: &mut Vec<Vec<Window>>) {
         if let Some(filename) = self.order.as_ref() {
             sort_values(filename, reference_names, windows);
         }
     }
 
-    fn get_cached_data(
-        &self,
-        matrix: &CoverageMatrix,
-    ) -> &Vec<(String, Vec<(Variation, usize, usize)>)> {
+    fn get_cached_data(&self, matrix: &CoverageMatrix) -> &RegionalVariationResult {
         self.cache.get_or_init(|| {
             matrix
                 .get_regional_hists(self.window_size, self.window_size)
@@ -127,9 +174,9 @@ impl RegionalVariation {
                             let richness = calc_richness(&hist);
                             let shannon = calc_shannon_entropy(&hist);
                             let simpson = calc_simpson_index(&hist);
-                            (Variation(richness, shannon, simpson), start, end)
+                            (WindowVariation(richness, shannon, simpson), start, end)
                         })
-                        .collect::<Vec<(Variation, usize, usize)>>(),
+                        .collect::<Vec<(WindowVariation, usize, usize)>>(),
                     )
                 })
                 .collect::<Vec<_>>()
@@ -141,8 +188,8 @@ impl RegionalVariation {
 /// Hill number 0: count of features (sum of hist), Richness
 /// Hill number 1: Shannon entropy
 /// Hill number 2: Simpson index
-#[derive(Debug)]
-struct Variation(f64, f64, f64);
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowVariation(pub f64, pub f64, pub f64);
 
 /// Calculates the (species) richness (0th hill number) for a given hist
 pub fn calc_richness(hist: &Hist) -> f64 {
@@ -0,0 +1,337 @@
+//! Common, machine-readable result model of all analyses. It is written with
+//! `--output-format`:
+//!
+//! - `json`: an object with the `provenance` of the run and an array of all
+//!   result `tables` (see [`ResultTable`])
+//! - `jsonl`: the provenance (`{"provenance": ...}`) on the first line,
+//!   followed by one result table per line
+//! - `tsv`/`csv`: the provenance as `#` comment lines, followed by all values
+//!   in long format, one value per line, with the columns `analysis`,
+//!   `table`, `run`, `count`, `row`, `column`, `unit`, `coverage`, `quorum`
+//!   and `value`. `row` is the (0-based) index of the row in its table, so
+//!   that the tables can be pivoted back.
+//!
+//! Undefined values (e.g. the growth for 0 taxa) are `NaN` in tsv/csv and
+//! `null` in json.
+use std::fmt;
+
+use serde::Serialize;
+use strum_macros::{EnumString, EnumVariantNames};
+
+use crate::error::PanacusError;
+use crate::file_formats::gfa_parser::ThresholdContainer;
+use crate::provenance::Provenance;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString, EnumVariantNames)]
+#[strum(serialize_all = "lowercase")]
+pub enum OutputFormat {
+    Tsv,
+    Csv,
+    Json,
+    Jsonl,
+}
+
+/// A single table of results of an analysis
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ResultTable {
+    /// Type of the analysis, as given by `get_type`
+    pub analysis: String,
+    /// Name of the table, unique among the tables of an analysis
+    pub table: String,
+    pub run_id: String,
+    pub run_name: String,
+    /// Count type of the run (node, bp, edge, bubble or allele)
+    pub count: String,
+    pub columns: Vec<Column>,
+    /// Values of each row, in the order of `columns`
+    pub rows: Vec<Vec<Value>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Column {
+    pub name: String,
+    /// Unit of the values, e.g. the count type for numbers of countables
+    pub unit: Option<String>,
+    /// Coverage and quorum threshold of the values (for growth curves)
+    pub coverage: Option<String>,
+    pub quorum: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum Value {
+    Int(usize),
+    Float(f64),
+    Text(String),
+}
+
+impl ResultTable {
+    pub fn new(
+        analysis: &str,
+        table: &str,
+        run_id: &str,
+        run_name: &str,
+        count: &str,
+        columns: Vec<Column>,
+    ) -> Self {
+        Self {
+            analysis: analysis.to_string(),
+            table: table.to_string(),
+            run_id: run_id.to_string(),
+            run_name: run_name.to_string(),
+            count: count.to_string(),
+            columns,
+            rows: Vec::new(),
+        }
+    }
+
+    /// Fills the table column-wise, all columns need to have the same length
+    pub fn with_column_values(mut self, values: Vec<Vec<Value>>) -> Self {
+        let n = values.first().map(|c| c.len()).unwrap_or(0);
+        assert!(
+            values.len() == self.columns.len() && values.iter().all(|c| c.len() == n),
+            "columns of table {} have different lengths",
+            self.table
+        );
+        let mut columns: Vec<_> = values.into_iter().map(|c| c.into_iter()).collect();
+        self.rows = (0..n)
+            .map(|_| columns.iter_mut().map(|c| c.next().unwrap()).collect())
+            .collect();
+        self
+    }
+
+    pub fn push_row(&mut self, row: Vec<Value>) {
+        assert_eq!(
+            row.len(),
+            self.columns.len(),
+            "row does not fit table {}",
+            self.table
+        );
+        self.rows.push(row);
+    }
+}
+
+impl Column {
+    pub fn new(name: &str, unit: Option<&str>) -> Self {
+        Self {
+            name: name.to_string(),
+            unit: unit.map(|u| u.to_string()),
+            coverage: None,
+            quorum: None,
+        }
+    }
+
+    pub fn with_thresholds(
+        name: &str,
+        unit: Option<&str>,
+        coverage: String,
+        quorum: String,
+    ) -> Self {
+        Self {
+            coverage: Some(coverage),
+            quorum: Some(quorum),
+            ..Self::new(name, unit)
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Int(v) => write!(formatter, "{}", v),
+            Value::Float(v) => write!(formatter, "{}", v),
+            Value::Text(v) => write!(formatter, "{}", v),
+        }
+    }
+}
+
+impl From<usize> for Value {
+    fn from(value: usize) -> Self {
+        Value::Int(value)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Float(value)
+    }
+}
+
+impl From<f32> for Value {
+    fn from(value: f32) -> Self {
+        Value::Float(value as f64)
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::Text(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::Text(value.to_string())
+    }
+}
+
+/// Converts a list into the values of a column
+pub fn to_values<T: Clone + Into<Value>>(values: &[T]) -> Vec<Value> {
+    values.iter().cloned().map(|v| v.into()).collect()
+}
+
+/// One column per coverage/quorum threshold pair, e.g. for growth curves
+pub fn get_threshold_columns(
+    name: &str,
+    unit: Option<&str>,
+    thresholds: &ThresholdContainer,
+) -> Vec<Column> {
+    thresholds
+        .coverage
+        .iter()
+        .zip(&thresholds.quorum)
+        .map(|(c, q)| Column::with_thresholds(name, unit, c.get_string(), q.get_string()))
+        .collect()
+}
+
+/// Writes the tables in the format, preceded by the provenance if it is
+/// given (see the module documentation)
+pub fn write_result_tables(
+    tables: &[ResultTable],
+    format: OutputFormat,
+    provenance: Option<&Provenance>,
+) -> Result<String, PanacusError> {
+    let res = match format {
+        OutputFormat::Json => match provenance {
+            Some(provenance) => serde_json::to_string_pretty(&serde_json::json!({
+                "provenance": provenance,
+                "tables": tables,
+            })),
+            None => serde_json::to_string_pretty(tables),
+        }
+        .map_err(anyhow::Error::from)?,
+        OutputFormat::Jsonl => provenance
+            .map(|provenance| {
+                serde_json::to_string(&serde_json::json!({ "provenance": provenance }))
+            })
+            .into_iter()
+            .chain(tables.iter().map(serde_json::to_string))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(anyhow::Error::from)?
+            .join("\n"),
+        OutputFormat::Tsv | OutputFormat::Csv => {
+            let mut res = provenance.map(|p| p.to_comments()).unwrap_or_default();
+            match format {
+                OutputFormat::Tsv => {
+                    res.push_str(&write_long_format(tables, '\t', |field| field.to_string()))
+                }
+                _ => res.push_str(&write_long_format(tables, ',', escape_csv)),
+            }
+            res
+        }
+    };
+    Ok(res)
+}
+
+fn write_long_format(tables: &[ResultTable], sep: char, escape: fn(&str) -> String) -> String {
+    let header = [
+        "analysis", "table", "run", "count", "row", "column", "unit", "coverage", "quorum", "value",
+    ];
+    let mut res = header.join(&sep.to_string());
+    res.push('\n');
+    for table in tables {
+        for (i, row) in table.rows.iter().enumerate() {
+            for (column, value) in table.columns.iter().zip(row) {
+                let fields = [
+                    table.analysis.as_str(),
+                    table.table.as_str(),
+                    table.run_id.as_str(),
+                    table.count.as_str(),
+                    &i.to_string(),
+                    column.name.as_str(),
+                    column.unit.as_deref().unwrap_or(""),
+                    column.coverage.as_deref().unwrap_or(""),
+                    column.quorum.as_deref().unwrap_or(""),
+                    &value.to_string(),
+                ];
+                res.push_str(
+                    &fields
+                        .iter()
+                        .map(|f| escape(f))
+                        .collect::<Vec<_>>()
+                        .join(&sep.to_string()),
+                );
+                res.push('\n');
+            }
+        }
+    }
+    res
+}
+
+fn escape_csv(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_test_table() -> ResultTable {
+        ResultTable::new(
+            "Growth",
+            "growth",
+            "a.gfa-node",
+            "a.gfa",
+            "node",
+            vec![
+                Column::new("taxa", None),
+                Column::with_thresholds("growth", Some("node"), "1".to_string(), "0".to_string()),
+            ],
+        )
+        .with_column_values(vec![to_values(&[0, 1]), to_values(&[f64::NAN, 3.5])])
+    }
+
+    #[test]
+    fn test_tables_are_filled_column_wise() {
+        let table = get_test_table();
+        assert_eq!(table.rows.len(), 2);
+        assert_eq!(table.rows[1], vec![Value::Int(1), Value::Float(3.5)]);
+    }
+
+    #[test]
+    fn test_long_format() {
+        let tsv = write_result_tables(&[get_test_table()], OutputFormat::Tsv, None).unwrap();
+        let lines: Vec<&str> = tsv.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(
+            lines[2],
+            "Growth\tgrowth\ta.gfa-node\tnode\t0\tgrowth\tnode\t1\t0\tNaN"
+        );
+        assert_eq!(escape_csv("a,b"), "\"a,b\"");
+    }
+
+    #[test]
+    fn test_json_uses_null_for_nan() {
+        let jsonl = write_result_tables(&[get_test_table()], OutputFormat::Jsonl, None).unwrap();
+        assert_eq!(jsonl.lines().count(), 1);
+        assert!(jsonl.contains("\"rows\":[[0,null],[1,3.5]]"));
+    }
+
+    #[test]
+    fn test_provenance_precedes_tables() {
+        let provenance = crate::provenance::get();
+        let jsonl =
+            write_result_tables(&[get_test_table()], OutputFormat::Jsonl, Some(&provenance))
+                .unwrap();
+        assert_eq!(jsonl.lines().count(), 2);
+        assert!(jsonl.starts_with("{\"provenance\":{"));
+        let csv =
+            write_result_tables(&[get_test_table()], OutputFormat::Csv, Some(&provenance)).unwrap();
+        assert!(csv.starts_with("# "));
+        assert!(csv.contains("\nanalysis,table,run,"));
+    }
+}
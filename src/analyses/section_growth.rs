@@ -3,15 +3,20 @@ use std::fs::File;
 use std::io::{BufRead, BufReader};
 
 use crate::analyses::growth::calc_all_growths;
+use crate::analyses::result_table::{get_threshold_columns, Column, ResultTable, Value};
 use crate::analyses::MatrixBasedAnalysis;
 use crate::coverage_matrix::CoverageMatrix;
+use crate::error::PanacusError;
 use crate::file_formats::gfa_parser::{choose, ThresholdContainer};
 use crate::html_report::ReportItem;
 use crate::util::{get_default_plot_downloads, Threshold};
 use rayon::prelude::*;
 
 use super::AnalysisSection;
+use crate::html_report::DisplayOptions;
 
+/// Growth curves where the paths/groups are added section by section, as
+/// given in a file mapping each group to its section
 pub struct SectionGrowth {
     sections_file: String,
     // Map from section name to group index (depends on grouping!)
@@ -19,26 +24,55 @@ pub struct SectionGrowth {
     section_order: Vec<String>,
     coverage: String,
     quorum: String,
-    inner: Option<InnerGrowth>,
+    inner: Option<SectionGrowthResult>,
 }
 
 impl MatrixBasedAnalysis for SectionGrowth {
+    fn generate_result_tables(
+        &mut self,
+        matrix: &CoverageMatrix,
+    ) -> Result<Vec<ResultTable>, PanacusError> {
+        self.set_inner(matrix)?;
+        let inner = self.inner.as_ref().unwrap();
+        let k = matrix.get_feature_type();
+        let mut columns = vec![Column::new("taxa", None), Column::new("section", None)];
+        columns.extend(get_threshold_columns("growth", Some(k), &inner.thresholds));
+        let mut table = ResultTable::new(
+            &self.get_type(),
+            "section-growth",
+            matrix.get_run_id(),
+            matrix.get_run_name(),
+            k,
+            columns,
+        );
+        let mut section_index = 0;
+        for idx in 0..inner.growths[0].len() {
+            if section_index < inner.sections.len() - 1
+                && idx >= inner.sections[section_index + 1].1
+            {
+                section_index += 1;
+            }
+            let mut row = vec![
+                Value::from(idx),
+                Value::from(inner.sections[section_index].0.as_str()),
+            ];
+            row.extend(inner.growths.iter().map(|growth| Value::from(growth[idx])));
+            table.push_row(row);
+        }
+        Ok(vec![table])
+    }
+
     fn get_type(&self) -> String {
         "SectionGrowth".to_string()
     }
 
-    fn generate_table(&mut self, matrix: &CoverageMatrix) -> anyhow::Result<String> {
+    fn generate_table(&mut self, matrix: &CoverageMatrix) -> Result<String, PanacusError> {
         log::info!("reporting hist table");
 
         self.set_inner(matrix)?;
         let inner = self.inner.as_ref().unwrap();
         let mut res = String::new();
 
-        res.push_str(&format!(
-            "# {}\n",
-            std::env::args().collect::<Vec<String>>().join(" ")
-        ));
-
         res.push_str(&format!("panacus\tsection-growth\n"));
         res.push_str(&format!("count\t{}\n", matrix.get_feature_type()));
 
@@ -71,7 +105,7 @@ impl MatrixBasedAnalysis for SectionGrowth {
     fn generate_report_section(
         &mut self,
         matrix: &CoverageMatrix,
-    ) -> anyhow::Result<Vec<AnalysisSection>> {
+    ) -> Result<Vec<AnalysisSection>, PanacusError> {
         self.set_inner(matrix)?;
         let id = format!("section-growth-{}", matrix.get_run_id());
         let table = self.generate_table(matrix)?;
@@ -123,6 +157,7 @@ impl MatrixBasedAnalysis for SectionGrowth {
                 section_labels,
                 log_toggle: false,
             }],
+            display: DisplayOptions::default(),
         };
         Ok(vec![analysis_section])
     }
@@ -140,6 +175,16 @@ impl SectionGrowth {
         }
     }
 
+    /// Computes the section growth curves (only on the first call, later
+    /// calls return the cached result)
+    pub fn get_result(
+        &mut self,
+        matrix: &CoverageMatrix,
+    ) -> Result<&SectionGrowthResult, PanacusError> {
+        self.set_inner(matrix)?;
+        Ok(self.inner.as_ref().unwrap())
+    }
+
     fn get_growths_for_c_q(
         &self,
         c: Threshold,
@@ -269,7 +314,7 @@ impl SectionGrowth {
             *x += 1;
         }
 
-        self.inner = Some(InnerGrowth {
+        self.inner = Some(SectionGrowthResult {
             growths: full_growths,
             sections: section_index,
             thresholds: thresholds_sorted,
@@ -306,10 +351,16 @@ impl SectionGrowth {
     // }
 }
 
-struct InnerGrowth {
-    growths: Vec<Vec<f64>>,
-    sections: Vec<(String, usize)>,
-    thresholds: ThresholdContainer,
+/// Result of the `SectionGrowth` analysis
+#[derive(Debug, Clone)]
+pub struct SectionGrowthResult {
+    /// `growths[i][m]` is the expected number of countables in `m` taxa
+    /// (`NaN` for `m = 0`)
+    pub growths: Vec<Vec<f64>>,
+    /// Name of each section and the number of taxa at which it starts
+    pub sections: Vec<(String, usize)>,
+    /// The i-th growth curve uses `coverage[i]` and `quorum[i]`
+    pub thresholds: ThresholdContainer,
 }
 
 #[derive(Debug, Clone)]
@@ -1,31 +1,67 @@
 use itertools::Itertools;
 use kodama::{linkage, Dendrogram};
 
+use crate::analyses::result_table::{to_values, Column, ResultTable, Value};
 use crate::analyses::MatrixBasedAnalysis;
 use crate::analysis_parameter::ClusterMethod;
 use crate::coverage_matrix::CoverageMatrix;
+use crate::error::PanacusError;
+use crate::html_report::ReportItem;
 use crate::util::get_default_plot_downloads;
-use crate::{html_report::ReportItem, io::write_metadata_comments};
 use std::usize;
 
 use super::AnalysisSection;
+use crate::html_report::DisplayOptions;
 
+/// Pairwise Jaccard similarity (weighted by countable length) between all
+/// paths/groups, ordered by a hierarchical clustering
 pub struct Similarity {
     cluster_method: ClusterMethod,
-    table: Option<Vec<Vec<f32>>>,
-    labels: Option<Vec<String>>,
+    inner: Option<SimilarityResult>,
+}
+
+/// Result of the `Similarity` analysis
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimilarityResult {
+    /// Paths/groups in the order given by the clustering
+    pub labels: Vec<String>,
+    /// `values[i][j]` is the similarity of `labels[i]` and `labels[j]`
+    pub values: Vec<Vec<f32>>,
 }
 
 impl MatrixBasedAnalysis for Similarity {
-    fn generate_table(&mut self, matrix: &CoverageMatrix) -> anyhow::Result<String> {
-        if self.table.is_none() {
-            self.set_table(matrix);
+    fn generate_table(&mut self, matrix: &CoverageMatrix) -> Result<String, PanacusError> {
+        let result = self.get_result(matrix)?;
+        Ok(get_table_string(&result.values, &result.labels))
+    }
+
+    fn generate_result_tables(
+        &mut self,
+        matrix: &CoverageMatrix,
+    ) -> Result<Vec<ResultTable>, PanacusError> {
+        let analysis = self.get_type();
+        let result = self.get_result(matrix)?;
+        let mut columns = vec![Column::new("group", None)];
+        columns.extend(
+            result
+                .labels
+                .iter()
+                .map(|label| Column::new(label, Some("jaccard"))),
+        );
+        let mut table = ResultTable::new(
+            &analysis,
+            "similarity",
+            matrix.get_run_id(),
+            matrix.get_run_name(),
+            matrix.get_feature_type(),
+            columns,
+        );
+        for (label, values) in result.labels.iter().zip(&result.values) {
+            let mut row = vec![Value::from(label.as_str())];
+            row.extend(to_values(values));
+            table.push_row(row);
         }
-        let mut text = write_metadata_comments()?;
-        let table = self.table.as_ref().unwrap();
-        let labels = self.labels.as_ref().unwrap();
-        text.push_str(&get_table_string(table, labels));
-        Ok(text)
+        Ok(vec![table])
     }
 
     fn get_type(&self) -> String {
@@ -35,14 +71,11 @@ impl MatrixBasedAnalysis for Similarity {
     fn generate_report_section(
         &mut self,
         matrix: &CoverageMatrix,
-    ) -> anyhow::Result<Vec<AnalysisSection>> {
-        if self.table.is_none() {
-            self.set_table(matrix);
-        }
-
+    ) -> Result<Vec<AnalysisSection>, PanacusError> {
         let k = matrix.get_feature_type();
         let table = self.generate_table(matrix)?;
         let table = format!("`{}`", &table);
+        let result = self.get_result(matrix)?;
         let id_prefix = format!(
             "sim-heat-{}",
             matrix
@@ -60,11 +93,12 @@ impl MatrixBasedAnalysis for Similarity {
             items: vec![ReportItem::Heatmap {
                 id: format!("{id_prefix}-{k}"),
                 name: matrix.get_run_name().to_owned(),
-                x_labels: self.labels.as_ref().unwrap().clone(),
-                y_labels: self.labels.as_ref().unwrap().clone(),
-                values: self.table.as_ref().unwrap().clone(),
+                x_labels: result.labels.clone(),
+                y_labels: result.labels.clone(),
+                values: result.values.clone(),
             }],
             plot_downloads: get_default_plot_downloads(),
+            display: DisplayOptions::default(),
         }];
         Ok(tabs)
     }
@@ -74,9 +108,20 @@ impl Similarity {
     pub fn new(cluster_method: ClusterMethod) -> Self {
         Self {
             cluster_method,
-            table: None,
-            labels: None,
+            inner: None,
+        }
+    }
+
+    /// Computes the similarities (only on the first call, later calls
+    /// return the cached result)
+    pub fn get_result(
+        &mut self,
+        matrix: &CoverageMatrix,
+    ) -> Result<&SimilarityResult, PanacusError> {
+        if self.inner.is_none() {
+            self.set_table(matrix);
         }
+        Ok(self.inner.as_ref().unwrap())
     }
 
     fn set_table(&mut self, matrix: &CoverageMatrix) {
@@ -134,8 +179,10 @@ impl Similarity {
         }
         sort_by_indices(&mut labels, &order);
 
-        self.table = Some(table);
-        self.labels = Some(labels);
+        self.inner = Some(SimilarityResult {
+            labels,
+            values: table,
+        });
     }
 }
 
@@ -0,0 +1,474 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+
+use anyhow::anyhow;
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+use crate::analyses::coverage_colors::{convert_to_color, GROUP_COLORS};
+use crate::analyses::result_table::{Column, ResultTable, Value};
+use crate::analyses::MatrixBasedAnalysis;
+use crate::coverage_matrix::CoverageMatrix;
+use crate::error::PanacusError;
+use crate::file_formats::gfa_parser::{Orientation, Subgraph, Traversal};
+use crate::html_report::ReportItem;
+use crate::io::parse_bed_to_path_segments;
+use crate::plots::scene::{fmt_num, Anchor, Color, Scene};
+
+use super::AnalysisSection;
+use crate::html_report::DisplayOptions;
+
+/// Larger subgraphs are not laid out
+const MAX_NODES: usize = 2000;
+/// Number of following node ends (along each path) whose distance to a node
+/// end is optimized
+const TERM_WINDOW: usize = 64;
+const ETA_MIN: f64 = 0.01;
+const LAYOUT_SEED: u64 = 9399;
+const MARGIN: f64 = 20.0;
+const NODE_WIDTH: f64 = 6.0;
+const PATH_WIDTH: f64 = 1.5;
+/// Distance between the drawn traversals of different paths
+const PATH_OFFSET: f64 = 2.0;
+const LEGEND_ROW: f64 = 16.0;
+const FONT_SIZE: f64 = 11.0;
+/// Paths listed in the legend
+const MAX_LEGEND_PATHS: usize = 20;
+
+/// 2D layout of the subgraphs of small regions of a reference path, drawn
+/// with the nodes colored by coverage and the traversals of the paths
+pub struct SubgraphLayout {
+    regions_file: String,
+    iterations: usize,
+    width: f64,
+    height: Option<f64>,
+    inner: Option<Vec<RegionLayout>>,
+}
+
+/// Layout of the subgraph of one region
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegionLayout {
+    pub region: String,
+    pub subgraph: Subgraph,
+    /// Positions of the start and the end of each node
+    pub ends: Vec<((f64, f64), (f64, f64))>,
+    /// Coverage of each node
+    pub coverages: Vec<u64>,
+    /// Maximal coverage, i.e. the number of paths/groups
+    pub max_cov: u64,
+}
+
+impl MatrixBasedAnalysis for SubgraphLayout {
+    fn generate_result_tables(
+        &mut self,
+        matrix: &CoverageMatrix,
+    ) -> Result<Vec<ResultTable>, PanacusError> {
+        let analysis = self.get_type();
+        let mut table = ResultTable::new(
+            &analysis,
+            "layout",
+            matrix.get_run_id(),
+            matrix.get_run_name(),
+            matrix.get_feature_type(),
+            [
+                "region", "node", "length", "coverage", "x1", "y1", "x2", "y2",
+            ]
+            .iter()
+            .map(|c| Column::new(c, None))
+            .collect(),
+        );
+        for layout in self.get_result(matrix)? {
+            for (i, (name, len)) in layout.subgraph.nodes.iter().enumerate() {
+                let ((x1, y1), (x2, y2)) = layout.ends[i];
+                table.push_row(vec![
+                    Value::from(layout.region.as_str()),
+                    Value::from(name.as_str()),
+                    Value::from(*len),
+                    Value::from(layout.coverages[i] as usize),
+                    Value::from(x1),
+                    Value::from(y1),
+                    Value::from(x2),
+                    Value::from(y2),
+                ]);
+            }
+        }
+        Ok(vec![table])
+    }
+
+    fn get_type(&self) -> String {
+        "SubgraphLayout".to_string()
+    }
+
+    fn generate_table(&mut self, matrix: &CoverageMatrix) -> Result<String, PanacusError> {
+        let mut res = "region\tnode\tlength\tcoverage\tx1\ty1\tx2\ty2\n".to_string();
+        for layout in self.get_result(matrix)? {
+            for (i, (name, len)) in layout.subgraph.nodes.iter().enumerate() {
+                let ((x1, y1), (x2, y2)) = layout.ends[i];
+                res.push_str(&format!(
+                    "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+                    layout.region,
+                    name,
+                    len,
+                    layout.coverages[i],
+                    fmt_num(x1),
+                    fmt_num(y1),
+                    fmt_num(x2),
+                    fmt_num(y2)
+                ));
+            }
+        }
+        Ok(res)
+    }
+
+    fn generate_report_section(
+        &mut self,
+        matrix: &CoverageMatrix,
+    ) -> Result<Vec<AnalysisSection>, PanacusError> {
+        let table = format!("`{}`", self.generate_table(matrix)?);
+        let (width, height) = (self.width, self.height);
+        let id_prefix = format!(
+            "subgraph-layout-{}",
+            matrix
+                .get_run_id()
+                .to_lowercase()
+                .replace([' ', '|', '\\'], "-")
+        );
+        let mut items = Vec::new();
+        for (i, layout) in self.get_result(matrix)?.iter().enumerate() {
+            items.push(ReportItem::SvgContent {
+                id: format!("svg-{id_prefix}-{i}"),
+                svg: render_layout(layout, width, height).to_svg(),
+            });
+        }
+        Ok(vec![AnalysisSection {
+            id: id_prefix,
+            analysis: "Subgraph Layout".to_string(),
+            run_name: matrix.get_run_name().to_string(),
+            run_id: matrix.get_run_id().to_string(),
+            countable: matrix.get_feature_type().to_string(),
+            table: Some(table),
+            plot_downloads: vec![("svg".to_string(), "Download as svg".to_string())],
+            items,
+            display: DisplayOptions::default(),
+        }])
+    }
+}
+
+impl SubgraphLayout {
+    pub fn new(
+        regions_file: String,
+        iterations: usize,
+        width: Option<u32>,
+        height: Option<u32>,
+    ) -> Self {
+        Self {
+            regions_file,
+            iterations,
+            width: width.unwrap_or(800) as f64,
+            height: height.map(|h| h as f64),
+            inner: None,
+        }
+    }
+
+    /// Lays out the subgraphs of all regions (only on the first call, later
+    /// calls return the cached result)
+    pub fn get_result(&mut self, matrix: &CoverageMatrix) -> Result<&[RegionLayout], PanacusError> {
+        if self.inner.is_none() {
+            self.set_inner(matrix)?;
+        }
+        Ok(self.inner.as_ref().unwrap())
+    }
+
+    fn set_inner(&mut self, matrix: &CoverageMatrix) -> Result<(), PanacusError> {
+        let feature_type = matrix.get_feature_type();
+        if feature_type != "node" && feature_type != "bp" {
+            return Err(anyhow!(
+                "Subgraph layouts can only be computed for node or bp counts, not {}",
+                feature_type
+            )
+            .into());
+        }
+        let graph_file = matrix.get_graph_file().ok_or_else(|| {
+            anyhow!("subgraph layouts can only be computed for runs on a GFA file")
+        })?;
+        let file = File::open(&self.regions_file)
+            .map_err(|e| PanacusError::file_read(&self.regions_file, e))?;
+        let regions =
+            parse_bed_to_path_segments(&mut BufReader::new(file), &self.regions_file, false)?;
+        let feature_ids: HashMap<String, usize> = matrix
+            .get_feature_names()
+            .into_iter()
+            .enumerate()
+            .map(|(idx, name)| (name, idx))
+            .collect();
+        let max_cov = matrix.get_path_names().len() as u64;
+
+        let subgraphs = Subgraph::from_gfa(graph_file, &regions, MAX_NODES)?;
+        let mut layouts = Vec::new();
+        for (region, subgraph) in regions.iter().zip(subgraphs) {
+            log::info!("computing layout of region {}", region);
+            let ends = compute_layout(&subgraph, self.iterations);
+            let coverages = subgraph
+                .nodes
+                .iter()
+                .map(|(name, _)| {
+                    feature_ids
+                        .get(name)
+                        .map_or(0, |id| matrix.get_count_of_feature(*id) as u64)
+                })
+                .collect();
+            layouts.push(RegionLayout {
+                region: region.to_string(),
+                subgraph,
+                ends,
+                coverages,
+                max_cov,
+            });
+        }
+        self.inner = Some(layouts);
+        Ok(())
+    }
+}
+
+/// Node ends visited by a traversal (start of node `i` is `2 * i`, its end
+/// `2 * i + 1`) and their position along the traversal in bp
+fn get_visits(subgraph: &Subgraph, traversal: &Traversal) -> Vec<(usize, f64)> {
+    let mut visits = Vec::with_capacity(traversal.steps.len() * 2);
+    let mut pos = 0.0;
+    for (node, orientation) in traversal.steps.iter() {
+        let len = subgraph.nodes[*node].1 as f64;
+        let (first, second) = match orientation {
+            Orientation::Forward => (2 * node, 2 * node + 1),
+            Orientation::Backward => (2 * node + 1, 2 * node),
+        };
+        visits.push((first, pos));
+        visits.push((second, pos + len));
+        pos += len;
+    }
+    visits
+}
+
+/// Path-guided stochastic gradient descent (as `odgi layout`): the distance
+/// between two node ends visited by a path approaches their distance along
+/// the path. The ends start at their position along the first path that
+/// visits them, spread randomly along the y axis.
+fn compute_layout(subgraph: &Subgraph, iterations: usize) -> Vec<((f64, f64), (f64, f64))> {
+    let mut rng = StdRng::seed_from_u64(LAYOUT_SEED);
+    let spread = (subgraph.nodes.iter().map(|(_, len)| *len).sum::<usize>() as f64)
+        .sqrt()
+        .max(1.0);
+    let mut points: Vec<Option<(f64, f64)>> = vec![None; subgraph.nodes.len() * 2];
+    let mut terms: Vec<(usize, usize, f64)> = Vec::new();
+    for traversal in subgraph.traversals.iter() {
+        let visits = get_visits(subgraph, traversal);
+        // offset of the positions along this traversal to the x axis
+        let mut offset = 0.0;
+        for (point, pos) in visits.iter() {
+            match points[*point] {
+                Some((x, _)) => offset = x - pos,
+                None => points[*point] = Some((pos + offset, (rng.gen::<f64>() - 0.5) * spread)),
+            }
+        }
+        for (i, (a, pos_a)) in visits.iter().enumerate() {
+            for (b, pos_b) in visits.iter().skip(i + 1).take(TERM_WINDOW) {
+                if a != b {
+                    terms.push((*a, *b, (pos_b - pos_a).abs().max(1.0)));
+                }
+            }
+        }
+    }
+    let mut points: Vec<(f64, f64)> = points
+        .into_iter()
+        .map(|p| p.unwrap_or((0.0, 0.0)))
+        .collect();
+
+    let d_max = terms.iter().map(|t| t.2).fold(1.0, f64::max);
+    let eta_max = d_max * d_max;
+    let lambda = if iterations > 1 {
+        (eta_max / ETA_MIN).ln() / (iterations - 1) as f64
+    } else {
+        0.0
+    };
+    for t in 0..iterations {
+        let eta = eta_max * (-lambda * t as f64).exp();
+        terms.shuffle(&mut rng);
+        for (a, b, d) in terms.iter() {
+            let mu = (eta / (d * d)).min(1.0);
+            let (dx, dy) = (points[*a].0 - points[*b].0, points[*a].1 - points[*b].1);
+            let mag = (dx * dx + dy * dy).sqrt().max(1e-9);
+            let r = mu * (mag - d) / (2.0 * mag);
+            points[*a].0 -= r * dx;
+            points[*a].1 -= r * dy;
+            points[*b].0 += r * dx;
+            points[*b].1 += r * dy;
+        }
+    }
+    points.chunks(2).map(|p| (p[0], p[1])).collect()
+}
+
+/// Draws the traversals of the paths below the nodes, which are colored by
+/// coverage, followed by a legend of the paths and coverages
+fn render_layout(layout: &RegionLayout, width: f64, height: Option<f64>) -> Scene {
+    let points = layout.ends.iter().flat_map(|(a, b)| [*a, *b]);
+    let (min_x, max_x, min_y, max_y) = points.fold(
+        (f64::MAX, f64::MIN, f64::MAX, f64::MIN),
+        |(min_x, max_x, min_y, max_y), (x, y)| {
+            (min_x.min(x), max_x.max(x), min_y.min(y), max_y.max(y))
+        },
+    );
+    let (extent_x, extent_y) = ((max_x - min_x).max(1.0), (max_y - min_y).max(1.0));
+    let mut scale = (width - 2.0 * MARGIN) / extent_x;
+    if let Some(height) = height {
+        scale = scale.min((height - 2.0 * MARGIN) / extent_y);
+    }
+    let to_px = |(x, y): (f64, f64)| (MARGIN + (x - min_x) * scale, MARGIN + (y - min_y) * scale);
+
+    let mut paths: Vec<&str> = Vec::new();
+    for traversal in layout.subgraph.traversals.iter() {
+        if !paths.contains(&traversal.path.as_str()) {
+            paths.push(&traversal.path);
+        }
+    }
+    let mut coverages = layout.coverages.clone();
+    coverages.sort_unstable();
+    coverages.dedup();
+    let legend_paths =
+        paths.len().min(MAX_LEGEND_PATHS) + usize::from(paths.len() > MAX_LEGEND_PATHS);
+    let plot_height = extent_y * scale + 2.0 * MARGIN;
+    let legend_height = (legend_paths + coverages.len().min(10) + 2) as f64 * LEGEND_ROW;
+    let mut scene = Scene::new(width, plot_height + legend_height, "sans-serif");
+
+    let path_color = |path: &str| {
+        let idx = paths.iter().position(|p| *p == path).unwrap_or(0);
+        Color::from_hex(GROUP_COLORS[idx % GROUP_COLORS.len()])
+    };
+    for traversal in layout.subgraph.traversals.iter() {
+        let idx = paths.iter().position(|p| *p == traversal.path).unwrap_or(0);
+        let offset = (idx as f64 - (paths.len() - 1) as f64 / 2.0) * PATH_OFFSET;
+        let line = get_visits(&layout.subgraph, traversal)
+            .into_iter()
+            .map(|(point, _)| {
+                let (x, y) = to_px(if point % 2 == 0 {
+                    layout.ends[point / 2].0
+                } else {
+                    layout.ends[point / 2].1
+                });
+                (x, y + offset)
+            })
+            .collect();
+        scene.line(line, path_color(&traversal.path), PATH_WIDTH);
+    }
+    let node_color = |cov: u64| Color::from_hex(&convert_to_color(cov, layout.max_cov));
+    for (i, (start, end)) in layout.ends.iter().enumerate() {
+        scene.line(
+            vec![to_px(*start), to_px(*end)],
+            node_color(layout.coverages[i]),
+            NODE_WIDTH,
+        );
+    }
+
+    let mut y = plot_height + LEGEND_ROW;
+    let mut legend_row = |scene: &mut Scene, color: Option<Color>, text: &str| {
+        if let Some(color) = color {
+            scene.rect(MARGIN, y - 9.0, 10.0, 10.0, color);
+        }
+        scene.text(MARGIN + 16.0, y, text, FONT_SIZE, Anchor::Start);
+        y += LEGEND_ROW;
+    };
+    legend_row(
+        &mut scene,
+        None,
+        &format!("paths through {}", layout.region),
+    );
+    for path in paths.iter().take(MAX_LEGEND_PATHS) {
+        legend_row(&mut scene, Some(path_color(path)), path);
+    }
+    if paths.len() > MAX_LEGEND_PATHS {
+        let more = format!("and {} more", paths.len() - MAX_LEGEND_PATHS);
+        legend_row(&mut scene, None, &more);
+    }
+    legend_row(&mut scene, None, "node coverage");
+    let step = (coverages.len() + 9) / 10;
+    for cov in coverages.iter().step_by(step.max(1)) {
+        legend_row(&mut scene, Some(node_color(*cov)), &cov.to_string());
+    }
+    scene
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_formats::gfa_parser::PathSegment;
+
+    fn get_distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+        ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt()
+    }
+
+    #[test]
+    fn test_layout_keeps_node_lengths() {
+        let region = PathSegment::from_str_start_end("R", 0, 12);
+        let subgraph = Subgraph::from_gfa("tests/test_files/t_insertions.gfa", &[region], 100)
+            .unwrap()
+            .remove(0);
+        let ends = compute_layout(&subgraph, 30);
+        assert_eq!(ends.len(), subgraph.nodes.len());
+        for ((start, end), (_, len)) in ends.iter().zip(subgraph.nodes.iter()) {
+            let distance = get_distance(*start, *end);
+            assert!(
+                (distance - *len as f64).abs() < 0.5 * *len as f64,
+                "node of length {} is drawn with length {}",
+                len,
+                distance
+            );
+        }
+        // the layout is reproducible
+        assert_eq!(ends, compute_layout(&subgraph, 30));
+    }
+
+    #[test]
+    fn test_render_layout() {
+        let region = PathSegment::from_str_start_end("R", 0, 12);
+        let subgraph = Subgraph::from_gfa(
+            "tests/test_files/t_insertions.gfa",
+            std::slice::from_ref(&region),
+            100,
+        )
+        .unwrap()
+        .remove(0);
+        let ends = compute_layout(&subgraph, 10);
+        let layout = RegionLayout {
+            region: region.to_string(),
+            coverages: vec![1; subgraph.nodes.len()],
+            subgraph,
+            ends,
+            max_cov: 4,
+        };
+        let svg = render_layout(&layout, 400.0, None).to_svg();
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains(">b#1#c</text>"));
+        assert!(svg.contains(">paths through R:0-12</text>"));
+    }
+
+    #[test]
+    fn test_render_layout_of_two_groups() {
+        let region = PathSegment::from_str_start_end("R", 0, 12);
+        let subgraph = Subgraph::from_gfa(
+            "tests/test_files/t_insertions.gfa",
+            std::slice::from_ref(&region),
+            100,
+        )
+        .unwrap()
+        .remove(0);
+        let ends = compute_layout(&subgraph, 10);
+        let layout = RegionLayout {
+            region: region.to_string(),
+            coverages: vec![2; subgraph.nodes.len()],
+            subgraph,
+            ends,
+            max_cov: 2,
+        };
+        let svg = render_layout(&layout, 400.0, None).to_svg();
+        let top = Color::from_hex(&convert_to_color(5, 5)).to_hex();
+        assert!(svg.contains(&format!("stroke=\"{}\"", top)));
+    }
+}
@@ -1,24 +1,28 @@
+use crate::analyses::result_table::{to_values, Column, ResultTable, Value};
+use crate::error::PanacusError;
 use crate::{
     analyses::{ordered_histgrowth::parse_list, MatrixBasedAnalysis},
     coverage_matrix::CoverageMatrix,
-    io::write_metadata_comments,
 };
 
 use super::AnalysisSection;
 
+/// Writes the coverage matrix as a table. There is no separate result, the
+/// counts can be read directly from the `CoverageMatrix` (e.g. with
+/// `CoverageMatrix::get_counts_for_feature`).
 pub struct Table {
     total: bool,
     order: Option<String>,
+    multiplicity: bool,
 }
 
 impl MatrixBasedAnalysis for Table {
-    fn generate_table(&mut self, matrix: &CoverageMatrix) -> anyhow::Result<String> {
+    fn generate_table(&mut self, matrix: &CoverageMatrix) -> Result<String, PanacusError> {
         let order = match &self.order {
             Some(filename) => parse_list(&filename[..])?,
             None => matrix.get_path_names().clone(),
         };
-        let mut string = write_metadata_comments()?;
-        string.push_str("panacus\ttable\n");
+        let mut string = "panacus\ttable\n".to_string();
         string.push_str(matrix.get_feature_type());
         if self.total {
             string.push_str("\ttotal");
@@ -32,10 +36,19 @@ impl MatrixBasedAnalysis for Table {
         for i in 0..matrix.get_feature_count() {
             string.push_str(matrix.get_feature_name(i));
             if self.total {
-                let count = matrix.get_count_of_feature(i);
+                let count = if self.multiplicity {
+                    matrix.get_multiplicity_of_feature(i)
+                } else {
+                    matrix.get_count_of_feature(i)
+                };
                 string.push_str(&format!("\t{}", count));
             } else {
-                for value in matrix.get_counts_for_feature_in_order(i, &order) {
+                let values = if self.multiplicity {
+                    matrix.get_multiplicities_for_feature_in_order(i, &order)
+                } else {
+                    matrix.get_counts_for_feature_in_order(i, &order)
+                };
+                for value in values {
                     string.push_str("\t");
                     string.push_str(&format!("{}", value));
                 }
@@ -45,6 +58,54 @@ impl MatrixBasedAnalysis for Table {
         Ok(string)
     }
 
+    fn generate_result_tables(
+        &mut self,
+        matrix: &CoverageMatrix,
+    ) -> Result<Vec<ResultTable>, PanacusError> {
+        let k = matrix.get_feature_type();
+        let order = match &self.order {
+            Some(filename) => parse_list(&filename[..])?,
+            None => matrix.get_path_names().clone(),
+        };
+        let unit = if self.multiplicity {
+            "multiplicity"
+        } else {
+            "coverage"
+        };
+        let mut columns = vec![Column::new(k, None)];
+        if self.total {
+            columns.push(Column::new("total", Some(unit)));
+        } else {
+            columns.extend(order.iter().map(|path| Column::new(path, Some(unit))));
+        }
+        let mut table = ResultTable::new(
+            &self.get_type(),
+            "table",
+            matrix.get_run_id(),
+            matrix.get_run_name(),
+            k,
+            columns,
+        );
+        for i in 0..matrix.get_feature_count() {
+            let mut row = vec![Value::from(matrix.get_feature_name(i))];
+            if self.total {
+                row.push(Value::from(if self.multiplicity {
+                    matrix.get_multiplicity_of_feature(i)
+                } else {
+                    matrix.get_count_of_feature(i)
+                }));
+            } else {
+                row.extend(to_values(&if self.multiplicity {
+                    matrix.get_multiplicities_for_feature_in_order(i, &order)
+                } else {
+                    matrix.get_counts_for_feature_in_order(i, &order)
+                }));
+            }
+            table.push_row(row);
+        }
+        Ok(vec![table])
+    }
+
     fn get_type(&self) -> String {
         "Table".to_string()
     }
@@ -52,13 +113,17 @@ impl MatrixBasedAnalysis for Table {
     fn generate_report_section(
         &mut self,
         _matrix: &CoverageMatrix,
-    ) -> anyhow::Result<Vec<AnalysisSection>> {
+    ) -> Result<Vec<AnalysisSection>, PanacusError> {
         Ok(Vec::new())
     }
 }
 
 impl Table {
-    pub fn new(total: bool, order: Option<String>) -> Self {
-        Self { total, order }
+    pub fn new(total: bool, order: Option<String>, multiplicity: bool) -> Self {
+        Self {
+            total,
+            order,
+            multiplicity,
+        }
     }
 }
@@ -1,29 +1,102 @@
 use std::fmt;
 use std::fmt::Debug;
 use std::fmt::Display;
-use strum_macros::{EnumIter, EnumString, EnumVariantNames};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use anyhow::{anyhow, bail};
+use regex::Regex;
+use schemars::JsonSchema;
+use strum_macros::{EnumIter, EnumString, EnumVariantNames, IntoStaticStr};
 
 use serde::{Deserialize, Serialize};
 
+use crate::analyses::copy_number::CopyNumber;
 use crate::analyses::coverage_colors::CoverageColors;
 use crate::analyses::coverage_line::CoverageLine;
+use crate::analyses::custom::Custom;
 use crate::analyses::growth::Growth;
 use crate::analyses::hist::Hist;
 use crate::analyses::info::Info;
+use crate::analyses::length_classes::{parse_length_classes, LengthClassGrowth};
 use crate::analyses::node_distribution::NodeDistribution;
-use crate::analyses::ordered_histgrowth::OrderedHistgrowth;
+use crate::analyses::non_reference_anchors::NonReferenceAnchors;
+use crate::analyses::ordered_histgrowth::{parse_list, OrderedHistgrowth};
+use crate::analyses::plugin::Plugin;
 use crate::analyses::regional_growth::RegionalGrowth;
 use crate::analyses::regional_variation::RegionalVariation;
 use crate::analyses::section_growth::SectionGrowth;
 use crate::analyses::similarity::Similarity;
+use crate::analyses::subgraph_layout::SubgraphLayout;
 use crate::analyses::table::Table;
 use crate::analyses::Analysis;
+use crate::file_formats::gfa_parser::ThresholdContainer;
 use crate::file_formats::vcf_parser::VcfCountType;
+use crate::html_report::{check_hexbin_resolution, DisplayOptions};
 use crate::util::CountType;
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone, PartialOrd, Ord)]
+/// A config with a `report:` block. Configs are either just the list of runs,
+/// or a mapping of the `report:` block and the list of runs as `runs:`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ReportFile {
+    #[serde(default)]
+    pub report: ReportConfig,
+    pub runs: Vec<FileRun>,
+}
+
+impl ReportFile {
+    /// Reads a config in either form, the default `display:` options of the
+    /// `report:` block are applied to all analyses
+    pub fn from_yaml(text: &str) -> Result<Self, serde_yaml::Error> {
+        let mut file = if serde_yaml::from_str::<serde_yaml::Value>(text)?.is_mapping() {
+            serde_yaml::from_str(text)?
+        } else {
+            Self {
+                report: ReportConfig::default(),
+                runs: serde_yaml::from_str(text)?,
+            }
+        };
+        let defaults = file.report.display.clone();
+        for run in &mut file.runs {
+            for analysis in run.get_analyses_mut() {
+                analysis.get_display_mut().fill_defaults(&defaults);
+            }
+        }
+        Ok(file)
+    }
+}
+
+/// The `report:` block of a config
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ReportConfig {
+    /// Title of the report
+    pub title: Option<String>,
+    /// Text shown above the sections, in markdown
+    pub description: Option<String>,
+    /// Defaults of the `display:` options of all analyses (e.g. the palette
+    /// or the plot dimensions)
+    #[serde(default, skip_serializing_if = "DisplayOptions::is_default")]
+    pub display: DisplayOptions,
+}
+
+/// A block of the report config. The variants are given as YAML tags
+/// (`!Gfa`, `!Vcf`); since tags cannot be expressed in a JSON Schema, the
+/// schema (see `panacus config schema`) describes the content of the tagged
+/// values only.
+#[derive(
+    Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone, PartialOrd, Ord, JsonSchema,
+)]
+#[schemars(untagged)]
 pub enum FileRun {
+    /// `!Gfa`: analyses of a GFA file
     Gfa {
+        /// Optional name of the run, used to select runs with `--only` and
+        /// `--skip` and to refer to them in validation messages
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
         graph: String,
         #[serde(default)]
         subset: String,
@@ -36,7 +109,10 @@ pub enum FileRun {
         count_type: CountType,
         analyses: Vec<AnalysisParameter>,
     },
+    /// `!Vcf`: analyses of a VCF file
     Vcf {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
         file: String,
         #[serde(default)]
         split_haplotypes: bool,
@@ -47,9 +123,173 @@ pub enum FileRun {
     },
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone, PartialOrd, Ord)]
+impl FileRun {
+    pub fn get_name(&self) -> Option<&str> {
+        match self {
+            Self::Gfa { name, .. } | Self::Vcf { name, .. } => name.as_deref(),
+        }
+    }
+
+    pub fn get_analyses_mut(&mut self) -> &mut Vec<AnalysisParameter> {
+        match self {
+            Self::Gfa { analyses, .. } | Self::Vcf { analyses, .. } => analyses,
+        }
+    }
+
+    /// The files read by the run: its input, the subset/exclude/grouping
+    /// files and the files of its analyses (e.g. order files)
+    pub fn get_input_files(&self) -> Vec<&str> {
+        let mut files = Vec::new();
+        let analyses = match self {
+            Self::Gfa {
+                graph,
+                subset,
+                exclude,
+                grouping,
+                analyses,
+                ..
+            } => {
+                files.push(graph.as_str());
+                // Subsets and exclusions may also be regexes
+                for value in [subset, exclude] {
+                    if !value.is_empty() && Path::new(value).is_file() {
+                        files.push(value.as_str());
+                    }
+                }
+                if let Some(Grouping::Custom(file)) = grouping {
+                    files.push(file.as_str());
+                }
+                analyses
+            }
+            Self::Vcf {
+                file,
+                exclude,
+                analyses,
+                ..
+            } => {
+                files.push(file.as_str());
+                files.extend(exclude.as_deref());
+                analyses
+            }
+        };
+        files.extend(analyses.iter().flat_map(|a| a.get_input_file()));
+        files
+    }
+
+    /// Checks the run without computing anything: all input files need to
+    /// exist and all analysis parameters need to be valid. If the inputs of
+    /// a GFA run are fine, the groups of the graph are loaded with
+    /// `get_groups` and the groups listed in order and section files need to
+    /// be among them. Returns a description of each problem found.
+    pub fn validate<F>(&self, get_groups: F) -> Vec<String>
+    where
+        F: FnOnce() -> Result<Vec<String>, String>,
+    {
+        let mut problems = Vec::new();
+        let analyses = match self {
+            Self::Gfa {
+                graph,
+                subset,
+                exclude,
+                grouping,
+                analyses,
+                ..
+            } => {
+                check_file_exists("graph", graph, &mut problems);
+                for (parameter, value) in [("subset", subset), ("exclude", exclude)] {
+                    if !value.is_empty()
+                        && !Path::new(value).is_file()
+                        && Regex::new(value).is_err()
+                    {
+                        problems.push(format!(
+                            "{} \"{}\" is neither an existing file nor a valid regex",
+                            parameter, value
+                        ));
+                    }
+                }
+                if let Some(Grouping::Custom(file)) = grouping {
+                    check_file_exists("grouping", file, &mut problems);
+                }
+                analyses
+            }
+            Self::Vcf {
+                file,
+                exclude,
+                analyses,
+                ..
+            } => {
+                check_file_exists("VCF", file, &mut problems);
+                if let Some(exclude) = exclude {
+                    check_file_exists("exclude", exclude, &mut problems);
+                }
+                analyses
+            }
+        };
+        let mut groups = None;
+        if problems.is_empty() && matches!(self, Self::Gfa { .. }) {
+            match get_groups() {
+                Ok(g) => groups = Some(g),
+                Err(e) => problems.push(e),
+            }
+        }
+        for analysis in analyses {
+            let analysis_name: &'static str = analysis.into();
+            problems.extend(
+                analysis
+                    .validate(groups.as_deref())
+                    .into_iter()
+                    .map(|problem| format!("{}: {}", analysis_name, problem)),
+            );
+        }
+        problems
+    }
+}
+
+fn check_file_exists(kind: &str, file: &str, problems: &mut Vec<String>) {
+    if !Path::new(file).is_file() {
+        problems.push(format!("{} file {} does not exist", kind, file));
+    }
+}
+
+fn check_groups(
+    file: &str,
+    file_groups: &[String],
+    groups: Option<&[String]>,
+    problems: &mut Vec<String>,
+) {
+    if let Some(groups) = groups {
+        for group in file_groups {
+            if !groups.contains(group) {
+                problems.push(format!("{} in {} is not a group of the graph", group, file));
+            }
+        }
+    }
+}
+
+/// An analysis of a config block, given as YAML tag (e.g. `!Growth`), see
+/// `FileRun` for the schema. Analyses without parameters can also be given
+/// as plain string (e.g. `- Info`).
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    PartialEq,
+    Eq,
+    Hash,
+    Clone,
+    PartialOrd,
+    Ord,
+    IntoStaticStr,
+    JsonSchema,
+)]
+#[schemars(untagged)]
 pub enum AnalysisParameter {
-    Hist {},
+    /// `!Hist`: coverage histogram
+    Hist {
+        #[serde(default, skip_serializing_if = "DisplayOptions::is_default")]
+        display: DisplayOptions,
+    },
+    /// `!Growth`: growth curves for pairs of coverage and quorum thresholds
     Growth {
         coverage: Option<String>,
         quorum: Option<String>,
@@ -57,98 +297,454 @@ pub enum AnalysisParameter {
         add_hist: bool,
         #[serde(default)]
         add_alpha: bool,
+        #[serde(default, skip_serializing_if = "DisplayOptions::is_default")]
+        display: DisplayOptions,
     },
+    /// `!Table`: table of the coverage of each countable
     Table {
         total: bool,
         order: Option<String>,
+        #[serde(default)]
+        multiplicity: bool,
+        #[serde(default, skip_serializing_if = "DisplayOptions::is_default")]
+        display: DisplayOptions,
     },
+    /// `!NodeDistribution`: binning of the nodes by their coverage and length
     NodeDistribution {
         #[serde(default = "get_radius")]
         radius: u32,
         #[serde(default = "get_threshold")]
         threshold: usize,
+        #[serde(default, skip_serializing_if = "DisplayOptions::is_default")]
+        display: DisplayOptions,
     },
-    Info,
+    /// `!Info`: statistics of the graph and its paths
+    Info {
+        #[serde(default, skip_serializing_if = "DisplayOptions::is_default")]
+        display: DisplayOptions,
+    },
+    /// `!OrderedGrowth`: growth curves for a given order of the groups
     OrderedGrowth {
         coverage: Option<String>,
         quorum: Option<String>,
         order: Option<String>,
+        #[serde(default, skip_serializing_if = "DisplayOptions::is_default")]
+        display: DisplayOptions,
     },
+    /// `!CoverageLine`: coverage of the countables along a reference
     CoverageLine {
         reference: Option<String>,
+        #[serde(default, skip_serializing_if = "DisplayOptions::is_default")]
+        display: DisplayOptions,
     },
+    /// `!Similarity`: clustered similarity of all pairs of groups
     Similarity {
         #[serde(default)]
         cluster_method: ClusterMethod,
+        #[serde(default, skip_serializing_if = "DisplayOptions::is_default")]
+        display: DisplayOptions,
     },
+    /// `!Custom`: user-supplied image (png, svg, pdf), Vega-Lite
+    /// specification (json) or table (tsv, csv) with a title and an optional
+    /// description
     Custom {
         name: String,
         file: String,
+        description: Option<String>,
+        #[serde(default, skip_serializing_if = "DisplayOptions::is_default")]
+        display: DisplayOptions,
     },
+    /// `!RegionalVariation`: diversity indices in windows along the reference
     RegionalVariation {
         #[serde(default = "get_window_size")]
         window_size: usize,
         order: Option<String>,
+        #[serde(default, skip_serializing_if = "DisplayOptions::is_default")]
+        display: DisplayOptions,
     },
+    /// `!RegionalGrowth`: growth predictions in windows along the reference
     RegionalGrowth {
         #[serde(default = "get_window_size")]
         window_size: usize,
         order: Option<String>,
+        #[serde(default, skip_serializing_if = "DisplayOptions::is_default")]
+        display: DisplayOptions,
     },
+    /// `!SectionGrowth`: growth curves continued section by section
     SectionGrowth {
         sections: String,
         coverage: Option<String>,
         quorum: Option<String>,
+        #[serde(default, skip_serializing_if = "DisplayOptions::is_default")]
+        display: DisplayOptions,
+    },
+    /// `!CoverageColors`: colors of the nodes (by coverage, group membership
+    /// or core/shell/cloud class), as table for Bandage and optionally as
+    /// `CL:z:` tags of a copy of the GFA, which Bandage and gfaviz show
+    CoverageColors {
+        #[serde(default)]
+        color_by: ColorBy,
+        /// Group whose nodes are highlighted, needed for `color_by: Group`
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        group: Option<String>,
+        /// File the colored copy of the GFA is written to
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        output: Option<String>,
+        #[serde(default, skip_serializing_if = "DisplayOptions::is_default")]
+        display: DisplayOptions,
+    },
+    /// `!SubgraphLayout`: 2D layouts of the subgraphs of small regions of a
+    /// reference path (given as BED file), with the nodes colored by coverage
+    SubgraphLayout {
+        regions: String,
+        /// Rounds of the path-guided stochastic gradient descent
+        #[serde(default = "get_layout_iterations")]
+        iterations: usize,
+        #[serde(default, skip_serializing_if = "DisplayOptions::is_default")]
+        display: DisplayOptions,
+    },
+    /// `!NonReferenceAnchors`: clusters of non-reference nodes and their reference anchors
+    NonReferenceAnchors {
+        #[serde(default)]
+        min_length: usize,
+        #[serde(default, skip_serializing_if = "DisplayOptions::is_default")]
+        display: DisplayOptions,
+    },
+    /// `!LengthClassGrowth`: histograms and growth curves by length class
+    LengthClassGrowth {
+        #[serde(default = "get_length_classes")]
+        classes: String,
+        coverage: Option<String>,
+        quorum: Option<String>,
+        #[serde(default, skip_serializing_if = "DisplayOptions::is_default")]
+        display: DisplayOptions,
+    },
+    /// `!CopyNumber`: copy numbers of the countables in each group
+    CopyNumber {
+        #[serde(default = "get_max_copy_number")]
+        max_copy_number: usize,
+        #[serde(default, skip_serializing_if = "DisplayOptions::is_default")]
+        display: DisplayOptions,
+    },
+    /// `!Plugin`: sections computed by an external executable from the
+    /// coverage histogram or matrix, see `analyses::plugin` for the protocol
+    Plugin {
+        name: String,
+        command: String,
+        #[serde(default)]
+        args: Vec<String>,
+        #[serde(default)]
+        input: PluginInput,
+        #[serde(default, skip_serializing_if = "DisplayOptions::is_default")]
+        display: DisplayOptions,
     },
-    CoverageColors,
 }
 
 impl AnalysisParameter {
+    /// Whether the analysis only needs a coverage histogram (and no
+    /// coverage matrix), see `to_analysis`
+    pub fn is_hist_based(&self) -> bool {
+        matches!(
+            self,
+            Self::Hist { .. }
+                | Self::Growth { .. }
+                | Self::Custom { .. }
+                | Self::Plugin {
+                    input: PluginInput::Hist,
+                    ..
+                }
+        )
+    }
+
+    pub fn get_display(&self) -> &DisplayOptions {
+        match self {
+            Self::Hist { display }
+            | Self::Growth { display, .. }
+            | Self::Table { display, .. }
+            | Self::NodeDistribution { display, .. }
+            | Self::Info { display }
+            | Self::OrderedGrowth { display, .. }
+            | Self::CoverageLine { display, .. }
+            | Self::Similarity { display, .. }
+            | Self::Custom { display, .. }
+            | Self::RegionalVariation { display, .. }
+            | Self::RegionalGrowth { display, .. }
+            | Self::SectionGrowth { display, .. }
+            | Self::CoverageColors { display, .. }
+            | Self::SubgraphLayout { display, .. }
+            | Self::NonReferenceAnchors { display, .. }
+            | Self::LengthClassGrowth { display, .. }
+            | Self::CopyNumber { display, .. }
+            | Self::Plugin { display, .. } => display,
+        }
+    }
+
+    pub fn get_display_mut(&mut self) -> &mut DisplayOptions {
+        match self {
+            Self::Hist { display }
+            | Self::Growth { display, .. }
+            | Self::Table { display, .. }
+            | Self::NodeDistribution { display, .. }
+            | Self::Info { display }
+            | Self::OrderedGrowth { display, .. }
+            | Self::CoverageLine { display, .. }
+            | Self::Similarity { display, .. }
+            | Self::Custom { display, .. }
+            | Self::RegionalVariation { display, .. }
+            | Self::RegionalGrowth { display, .. }
+            | Self::SectionGrowth { display, .. }
+            | Self::CoverageColors { display, .. }
+            | Self::SubgraphLayout { display, .. }
+            | Self::NonReferenceAnchors { display, .. }
+            | Self::LengthClassGrowth { display, .. }
+            | Self::CopyNumber { display, .. }
+            | Self::Plugin { display, .. } => display,
+        }
+    }
+
     /// This match has to be extended when adding a new analysis. Each analysis
     /// needs to decide, whether it needs the full matrix or whether just the
     /// hist is fine. Just using the histogram is always preferred.
-    pub fn to_analysis(self) -> Analysis {
-        match self {
-            Self::Hist {} => Analysis::HistBased(Box::new(Hist::new())),
+    pub fn to_analysis(mut self) -> Analysis {
+        let display = std::mem::take(self.get_display_mut());
+        let analysis = match self {
+            Self::Hist { .. } => Analysis::HistBased(Box::new(Hist::new())),
             Self::Growth {
                 coverage,
                 quorum,
                 add_hist,
                 add_alpha,
+                ..
             } => Analysis::HistBased(Box::new(Growth::new(coverage, quorum, add_hist, add_alpha))),
-            Self::Table { total, order } => {
-                Analysis::MatrixBased(Box::new(Table::new(total, order)))
-            }
+            Self::Table {
+                total,
+                order,
+                multiplicity,
+                ..
+            } => Analysis::MatrixBased(Box::new(Table::new(total, order, multiplicity))),
             Self::OrderedGrowth {
                 coverage,
                 quorum,
                 order,
+                ..
             } => Analysis::MatrixBased(Box::new(OrderedHistgrowth::new(coverage, quorum, order))),
-            Self::NodeDistribution { radius, threshold } => {
-                Analysis::MatrixBased(Box::new(NodeDistribution::new(radius, threshold)))
-            }
-            Self::CoverageColors {} => Analysis::MatrixBased(Box::new(CoverageColors::new())),
-            Self::CoverageLine { reference } => {
+            Self::NodeDistribution {
+                radius, threshold, ..
+            } => Analysis::MatrixBased(Box::new(NodeDistribution::new(
+                radius,
+                threshold,
+                display.hexbin_resolution,
+            ))),
+            Self::CoverageColors {
+                color_by,
+                group,
+                output,
+                ..
+            } => Analysis::MatrixBased(Box::new(CoverageColors::new(color_by, group, output))),
+            Self::SubgraphLayout {
+                regions,
+                iterations,
+                ..
+            } => Analysis::MatrixBased(Box::new(SubgraphLayout::new(
+                regions,
+                iterations,
+                display.width,
+                display.height,
+            ))),
+            Self::CoverageLine { reference, .. } => {
                 Analysis::MatrixBased(Box::new(CoverageLine::new(reference)))
             }
             Self::SectionGrowth {
                 sections,
                 coverage,
                 quorum,
+                ..
             } => Analysis::MatrixBased(Box::new(SectionGrowth::new(sections, coverage, quorum))),
-            Self::Similarity { cluster_method } => {
+            Self::Similarity { cluster_method, .. } => {
                 Analysis::MatrixBased(Box::new(Similarity::new(cluster_method)))
             }
-            Self::Info => Analysis::MatrixBased(Box::new(Info::new())),
-            Self::RegionalVariation { window_size, order } => {
-                Analysis::MatrixBased(Box::new(RegionalVariation::new(window_size, order)))
+            Self::Info { .. } => Analysis::MatrixBased(Box::new(Info::new())),
+            Self::RegionalVariation {
+                window_size, order, ..
+            } => Analysis::MatrixBased(Box::new(RegionalVariation::new(window_size, order))),
+            Self::RegionalGrowth {
+                window_size, order, ..
+            } => Analysis::MatrixBased(Box::new(RegionalGrowth::new(window_size, order))),
+            Self::NonReferenceAnchors { min_length, .. } => {
+                Analysis::MatrixBased(Box::new(NonReferenceAnchors::new(min_length)))
+            }
+            Self::LengthClassGrowth {
+                classes,
+                coverage,
+                quorum,
+                ..
+            } => Analysis::MatrixBased(Box::new(LengthClassGrowth::new(classes, coverage, quorum))),
+            Self::CopyNumber {
+                max_copy_number, ..
+            } => Analysis::MatrixBased(Box::new(CopyNumber::new(max_copy_number))),
+            Self::Custom {
+                name,
+                file,
+                description,
+                ..
+            } => Analysis::HistBased(Box::new(Custom::new(name, file, description))),
+            Self::Plugin {
+                name,
+                command,
+                args,
+                input,
+                ..
+            } => {
+                let plugin = Box::new(Plugin::new(name, command, args));
+                match input {
+                    PluginInput::Hist => Analysis::HistBased(plugin),
+                    PluginInput::Matrix => Analysis::MatrixBased(plugin),
+                }
+            }
+        };
+        analysis.with_display(display)
+    }
+
+    /// The file read by the analysis, if any
+    fn get_input_file(&self) -> Option<&str> {
+        match self {
+            Self::Table {
+                order: Some(file), ..
+            }
+            | Self::OrderedGrowth {
+                order: Some(file), ..
+            }
+            | Self::RegionalVariation {
+                order: Some(file), ..
+            }
+            | Self::RegionalGrowth {
+                order: Some(file), ..
+            }
+            | Self::SectionGrowth { sections: file, .. }
+            | Self::SubgraphLayout { regions: file, .. }
+            | Self::Custom { file, .. } => Some(file),
+            Self::Plugin { command, .. } if command.contains(std::path::MAIN_SEPARATOR) => {
+                Some(command)
+            }
+            _ => None,
+        }
+    }
+
+    /// Checks the parameters of the analysis, see `FileRun::validate`
+    fn validate(&self, groups: Option<&[String]>) -> Vec<String> {
+        let mut problems = Vec::new();
+        let thresholds = match self {
+            Self::Growth {
+                coverage, quorum, ..
+            }
+            | Self::OrderedGrowth {
+                coverage, quorum, ..
+            }
+            | Self::SectionGrowth {
+                coverage, quorum, ..
+            }
+            | Self::LengthClassGrowth {
+                coverage, quorum, ..
+            } => Some((coverage, quorum)),
+            _ => None,
+        };
+        if let Some((coverage, quorum)) = thresholds {
+            let quorum = quorum.as_deref().unwrap_or("0");
+            let coverage = coverage.as_deref().unwrap_or("1");
+            if let Err(e) = ThresholdContainer::parse_params(quorum, coverage) {
+                problems.push(format!("invalid thresholds: {}", e));
+            }
+        }
+        match self {
+            Self::Table {
+                order: Some(order), ..
+            }
+            | Self::OrderedGrowth {
+                order: Some(order), ..
+            } => match parse_list(order) {
+                Ok(order_groups) => {
+                    let order_groups: Vec<String> =
+                        order_groups.into_iter().filter(|g| !g.is_empty()).collect();
+                    check_groups(order, &order_groups, groups, &mut problems);
+                }
+                Err(e) => problems.push(format!("cannot read order file {}: {}", order, e)),
+            },
+            Self::RegionalVariation {
+                order: Some(order), ..
+            }
+            | Self::RegionalGrowth {
+                order: Some(order), ..
+            } => check_file_exists("order", order, &mut problems),
+            Self::SectionGrowth { sections, .. } => match parse_sections(sections) {
+                Ok(section_groups) => {
+                    check_groups(sections, &section_groups, groups, &mut problems)
+                }
+                Err(e) => problems.push(e.to_string()),
+            },
+            Self::LengthClassGrowth { classes, .. } => {
+                if let Err(e) = parse_length_classes(classes) {
+                    problems.push(e.to_string());
+                }
+            }
+            Self::CoverageColors {
+                color_by: ColorBy::Group,
+                group,
+                ..
+            } => match group {
+                None => problems.push("coloring by group needs a group".to_string()),
+                Some(group) => {
+                    if groups.map_or(false, |groups| !groups.contains(group)) {
+                        problems.push(format!("{} is not a group of the graph", group));
+                    }
+                }
+            },
+            Self::SubgraphLayout { regions, .. } => {
+                check_file_exists("regions", regions, &mut problems)
+            }
+            Self::Plugin { command, .. } if command.contains(std::path::MAIN_SEPARATOR) => {
+                check_file_exists("plugin", command, &mut problems)
             }
-            Self::RegionalGrowth { window_size, order } => {
-                Analysis::MatrixBased(Box::new(RegionalGrowth::new(window_size, order)))
+            Self::Custom { file, .. } => {
+                check_file_exists("custom", file, &mut problems);
+                let extension = Path::new(file).extension().and_then(|e| e.to_str());
+                if !matches!(
+                    extension,
+                    Some("png" | "svg" | "pdf" | "json" | "tsv" | "csv")
+                ) {
+                    problems.push(format!(
+                        "custom file {} is not a png, svg, pdf, json, tsv or csv file",
+                        file
+                    ));
+                }
             }
-            _ => unimplemented!("Other analyses have not been yet implemented"),
+            _ => {}
         }
+        if let Err(e) = check_hexbin_resolution(&self.get_display().hexbin_resolution) {
+            problems.push(e);
+        }
+        problems
+    }
+}
+
+/// Returns the groups of a sections file (group and section per line)
+fn parse_sections(file: &str) -> anyhow::Result<Vec<String>> {
+    let reader = BufReader::new(
+        File::open(file).map_err(|e| anyhow!("cannot read sections file {}: {}", file, e))?,
+    );
+    let mut groups = Vec::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let fields: Vec<_> = line.split_whitespace().collect();
+        if fields.len() != 2 {
+            bail!(
+                "line {} of sections file {} does not have two columns",
+                i + 1,
+                file
+            );
+        }
+        groups.push(fields[0].to_string());
     }
+    Ok(groups)
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone, PartialOrd, Ord)]
@@ -158,6 +754,25 @@ pub enum Grouping {
     Custom(String),
 }
 
+/// `Sample` and `Haplotype` are plain strings, custom groupings are tagged
+/// (`!Custom FILE`), so the schema accepts any string
+impl JsonSchema for Grouping {
+    fn schema_name() -> String {
+        "Grouping".to_string()
+    }
+
+    fn json_schema(_gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        serde_json::from_value(serde_json::json!({
+            "description": "Sample, Haplotype or a custom grouping given as `!Custom FILE`",
+            "anyOf": [
+                { "enum": ["Sample", "Haplotype"] },
+                { "type": "string" }
+            ]
+        }))
+        .expect("schema of Grouping is valid")
+    }
+}
+
 impl Display for Grouping {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -180,6 +795,18 @@ fn get_threshold() -> usize {
     1_000
 }
 
+fn get_layout_iterations() -> usize {
+    30
+}
+
+fn get_length_classes() -> String {
+    "1,50,1000,10000".to_string()
+}
+
+fn get_max_copy_number() -> usize {
+    10
+}
+
 #[derive(
     Debug,
     Clone,
@@ -194,6 +821,7 @@ fn get_threshold() -> usize {
     Ord,
     Serialize,
     Deserialize,
+    JsonSchema,
 )]
 #[strum(serialize_all = "lowercase")]
 pub enum ClusterMethod {
@@ -206,6 +834,55 @@ pub enum ClusterMethod {
     Median,
 }
 
+/// Input of a plugin analysis: the coverage histogram or the coverage matrix
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, JsonSchema,
+)]
+pub enum PluginInput {
+    Hist,
+    Matrix,
+}
+
+impl Default for PluginInput {
+    fn default() -> Self {
+        Self::Hist
+    }
+}
+
+/// Coloring of the nodes by the `CoverageColors` analysis
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    EnumString,
+    EnumVariantNames,
+    Hash,
+    Eq,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+)]
+#[strum(serialize_all = "lowercase")]
+pub enum ColorBy {
+    /// Number of groups covering the node, on a viridis scale
+    Coverage,
+    /// Whether the node is covered by the given group
+    Group,
+    /// Nodes covered by a single group, in the color of that group
+    Private,
+    /// Core (all groups), shell and cloud (a single group) nodes
+    Class,
+}
+
+impl Default for ColorBy {
+    fn default() -> Self {
+        Self::Coverage
+    }
+}
+
 impl Default for ClusterMethod {
     fn default() -> Self {
         Self::Centroid
@@ -243,3 +920,27 @@ impl fmt::Display for ClusterMethod {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hexbin_resolution_needs_two_hexagons() {
+        let analysis: AnalysisParameter =
+            serde_yaml::from_str("!NodeDistribution\ndisplay:\n  hexbin_resolution: [2, 10]\n")
+                .unwrap();
+        assert!(analysis.validate(None).is_empty());
+        for resolution in ["[1, 10]", "[10, 0]"] {
+            let config = format!(
+                "!NodeDistribution\ndisplay:\n  hexbin_resolution: {}\n",
+                resolution
+            );
+            let err = serde_yaml::from_str::<AnalysisParameter>(&config).unwrap_err();
+            assert!(err.to_string().contains("at least 2 hexagons"), "{}", err);
+        }
+        let mut analysis = analysis;
+        analysis.get_display_mut().hexbin_resolution = Some([1, 10]);
+        assert_eq!(analysis.validate(None).len(), 1);
+    }
+}
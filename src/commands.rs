@@ -1,11 +1,17 @@
+pub mod config;
+pub mod copy_number;
 pub mod coverage_colors;
+pub mod export_multiqc;
+pub mod export_plots;
 pub mod growth;
 pub mod hist;
 pub mod info;
 pub mod node_distribution;
+pub mod non_reference_anchors;
 pub mod ordered_histgrowth;
 pub mod render;
 pub mod report;
 pub mod section_growth;
 pub mod similarity;
+pub mod subgraph_layout;
 pub mod table;
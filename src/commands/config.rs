@@ -0,0 +1,298 @@
+use std::io::Write;
+use std::path::Path;
+
+use clap::{arg, ArgMatches, Command};
+use itertools::Itertools;
+use schemars::gen::SchemaSettings;
+use schemars::schema::{InstanceType, SchemaObject, SingleOrVec};
+use schemars::visit::{visit_schema_object, Visitor};
+
+use crate::analysis_parameter::{AnalysisParameter, ClusterMethod, FileRun, Grouping, ReportFile};
+use crate::file_formats::gfa_parser::{PathSegment, SharedGraph};
+use crate::html_report::DisplayOptions;
+use crate::util::CountType;
+
+/// Similarity clustermaps of more groups are hardly readable
+const MAX_SIMILARITY_GROUPS: usize = 200;
+
+pub fn get_subcommand() -> Command {
+    Command::new("config")
+        .about("Create report YAML configs")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("schema")
+                .about("Print the JSON Schema of the report YAML config, e.g. for autocompletion in editors. As YAML tags (e.g. !Gfa or !Growth) cannot be expressed in a JSON Schema, the schema describes their content; editors need to be told about the tags (e.g. customTags of the YAML language server)"),
+        )
+        .subcommand(
+            Command::new("init")
+                .about("Inspect a graph and print a starter report YAML config for it")
+                .args(&[arg!(gfa_file: <GFA_FILE> "graph in GFA1 format, accepts also compressed (.gz) file")]),
+        )
+}
+
+/// Runs the `config` subcommand, which does not compute any analyses
+pub fn execute<W: Write>(args: &ArgMatches, out: &mut W) -> Option<anyhow::Result<()>> {
+    let args = args.subcommand_matches("config")?;
+    Some(match args.subcommand() {
+        Some(("schema", _)) => write_schema(out),
+        Some(("init", args)) => {
+            let graph = args
+                .get_one::<String>("gfa_file")
+                .expect("init subcommand has gfa file");
+            write_init_config(graph, out)
+        }
+        _ => unreachable!("config subcommand is required"),
+    })
+}
+
+/// Tagged values without content (e.g. `- !Hist`) are null in YAML, so
+/// objects without required properties may also be null
+#[derive(Debug, Clone)]
+struct NullableOptionalObjects;
+
+impl Visitor for NullableOptionalObjects {
+    fn visit_schema_object(&mut self, schema: &mut SchemaObject) {
+        visit_schema_object(self, schema);
+        let has_required = schema
+            .object
+            .as_ref()
+            .map_or(false, |o| !o.required.is_empty());
+        let is_object = matches!(&schema.instance_type, Some(SingleOrVec::Single(t)) if **t == InstanceType::Object);
+        if is_object && !has_required {
+            schema.instance_type = Some(SingleOrVec::Vec(vec![
+                InstanceType::Object,
+                InstanceType::Null,
+            ]));
+        }
+    }
+}
+
+/// The two forms of a config: just the list of runs, or the runs together
+/// with a `report:` block. Only used for the schema, configs are read by
+/// [`ReportFile::from_yaml`].
+#[derive(schemars::JsonSchema)]
+#[serde(untagged)]
+#[allow(dead_code)]
+enum ConfigForm {
+    Runs(Vec<FileRun>),
+    Report(ReportFile),
+}
+
+pub fn get_config_schema() -> schemars::schema::RootSchema {
+    SchemaSettings::draft07()
+        .with_visitor(NullableOptionalObjects)
+        .into_generator()
+        .into_root_schema_for::<ConfigForm>()
+}
+
+fn write_schema<W: Write>(out: &mut W) -> anyhow::Result<()> {
+    writeln!(
+        out,
+        "{}",
+        serde_json::to_string_pretty(&get_config_schema())?
+    )?;
+    Ok(())
+}
+
+/// Samples with a haplotype 0, which PanSN reserves for haploid assemblies
+/// like reference genomes
+fn get_reference_candidates(paths: &[PathSegment]) -> Vec<String> {
+    paths
+        .iter()
+        .filter(|p| p.haplotype.as_deref() == Some("0"))
+        .map(|p| p.sample.clone())
+        .sorted()
+        .dedup()
+        .collect()
+}
+
+fn get_init_config(graph: &str, paths: &[PathSegment]) -> anyhow::Result<String> {
+    let is_pansn = !paths.is_empty() && paths.iter().all(|p| p.haplotype.is_some());
+    let samples: Vec<&str> = paths
+        .iter()
+        .map(|p| p.sample.as_str())
+        .sorted()
+        .dedup()
+        .collect();
+    let references = get_reference_candidates(paths);
+    let reference = match references.as_slice() {
+        [reference] => Some(reference.clone()),
+        _ => None,
+    };
+
+    let mut header = vec![
+        format!(
+            "Starter config for {}, created by `panacus config init`",
+            graph
+        ),
+        "Run it with `panacus report CONFIG > report.html`".to_string(),
+        String::new(),
+    ];
+    let (grouping, group_count) = if is_pansn {
+        let haplotypes = paths
+            .iter()
+            .map(|p| (&p.sample, &p.haplotype))
+            .unique()
+            .count();
+        header.push(format!(
+            "{} paths/walks of {} samples with {} haplotypes (PanSN path names)",
+            paths.len(),
+            samples.len(),
+            haplotypes
+        ));
+        header.push(format!(
+            "Paths are grouped by haplotype, use `grouping: Sample` to get {} groups",
+            samples.len()
+        ));
+        (Some(Grouping::Haplotype), haplotypes)
+    } else {
+        let path_ids = paths.iter().map(|p| p.id()).unique().count();
+        header.push(format!(
+            "{} paths/walks, path names are not in PanSN format (sample#haplotype#sequence)",
+            paths.len()
+        ));
+        header.push(format!(
+            "Each path is its own group ({} groups), use `grouping: !Custom FILE` to group them",
+            path_ids
+        ));
+        (None, path_ids)
+    };
+    match (&reference, references.len()) {
+        (Some(reference), _) => header.push(format!("Reference: {}", reference)),
+        (None, 0) => header.push(format!(
+            "No reference found (PanSN haplotype 0), set `reference:` to one of the samples to get reference-based analyses: {}",
+            samples.iter().take(10).join(", ")
+        )),
+        (None, _) => header.push(format!(
+            "Several references found, set `reference:` to one of them to get reference-based analyses: {}",
+            references.join(", ")
+        )),
+    }
+
+    let mut analyses = vec![
+        AnalysisParameter::Info {
+            display: DisplayOptions::default(),
+        },
+        AnalysisParameter::Hist {
+            display: DisplayOptions::default(),
+        },
+        AnalysisParameter::Growth {
+            coverage: Some("1,1,2".to_string()),
+            quorum: Some("0,0.9,0".to_string()),
+            add_hist: false,
+            add_alpha: false,
+            display: DisplayOptions::default(),
+        },
+    ];
+    if (2..=MAX_SIMILARITY_GROUPS).contains(&group_count) {
+        analyses.push(AnalysisParameter::Similarity {
+            cluster_method: ClusterMethod::default(),
+            display: DisplayOptions::default(),
+        });
+    }
+    if let Some(reference) = &reference {
+        analyses.push(AnalysisParameter::CoverageLine {
+            reference: Some(reference.clone()),
+            display: DisplayOptions::default(),
+        });
+        analyses.push(AnalysisParameter::NonReferenceAnchors {
+            min_length: 0,
+            display: DisplayOptions::default(),
+        });
+    }
+    let name = Path::new(graph)
+        .file_name()
+        .and_then(|f| f.to_str())
+        .and_then(|f| f.split('.').next())
+        .filter(|f| !f.is_empty())
+        .map(|f| f.to_string());
+    let run = FileRun::Gfa {
+        name,
+        graph: graph.to_string(),
+        subset: String::new(),
+        exclude: String::new(),
+        reference,
+        grouping,
+        nice: false,
+        count_type: CountType::Bp,
+        analyses,
+    };
+
+    let mut res: String = header
+        .iter()
+        .map(|line| format!("# {}", line).trim_end().to_string() + "\n")
+        .collect();
+    res.push('\n');
+    res.push_str(&serde_yaml::to_string(&vec![run])?);
+    Ok(res)
+}
+
+fn write_init_config<W: Write>(graph: &str, out: &mut W) -> anyhow::Result<()> {
+    let shared_graph = SharedGraph::from_gfa(graph, false)?;
+    write!(
+        out,
+        "{}",
+        get_init_config(graph, shared_graph.get_path_segments())?
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_paths(names: &[&str]) -> Vec<PathSegment> {
+        names.iter().map(|n| PathSegment::from_str(n)).collect()
+    }
+
+    #[test]
+    fn test_reference_candidates() {
+        let paths = get_paths(&[
+            "GRCh38#0#chr1",
+            "GRCh38#0#chr2",
+            "HG002#1#chr1",
+            "HG002#2#chr1",
+        ]);
+        assert_eq!(get_reference_candidates(&paths), vec!["GRCh38".to_string()]);
+        assert!(get_reference_candidates(&get_paths(&["a", "b"])).is_empty());
+    }
+
+    #[test]
+    fn test_init_config_is_valid() {
+        let paths = get_paths(&["GRCh38#0#chr1", "HG002#1#chr1", "HG002#2#chr1"]);
+        let config = get_init_config("graphs/hprc.gfa.gz", &paths).unwrap();
+        let runs: Vec<FileRun> = serde_yaml::from_str(&config).unwrap();
+        match &runs[..] {
+            [FileRun::Gfa {
+                name,
+                reference,
+                grouping,
+                analyses,
+                ..
+            }] => {
+                assert_eq!(name.as_deref(), Some("hprc"));
+                assert_eq!(reference.as_deref(), Some("GRCh38"));
+                assert_eq!(grouping, &Some(Grouping::Haplotype));
+                assert!(analyses.contains(&AnalysisParameter::Similarity {
+                    cluster_method: ClusterMethod::default(),
+                    display: DisplayOptions::default(),
+                }));
+            }
+            _ => panic!("config needs to contain a single GFA run"),
+        }
+    }
+
+    #[test]
+    fn test_schema_describes_tagged_content() {
+        let schema = serde_json::to_value(get_config_schema()).unwrap();
+        let analyses = &schema["definitions"]["AnalysisParameter"]["anyOf"];
+        // `- !Hist` has no content
+        assert_eq!(analyses[0]["type"], serde_json::json!(["object", "null"]));
+        let gfa = &schema["definitions"]["FileRun"]["anyOf"][0];
+        assert!(gfa["properties"]["graph"].is_object());
+        assert!(gfa["properties"]["name"].is_object());
+        let report = &schema["definitions"]["ReportFile"];
+        assert!(report["properties"]["report"].is_object());
+        assert_eq!(schema["anyOf"][0]["type"], "array");
+    }
+}
@@ -0,0 +1,72 @@
+use crate::clap_enum_variants_no_all;
+use clap::{arg, Arg, ArgMatches, Command};
+use strum::VariantNames;
+
+use crate::analysis_parameter::{AnalysisParameter, FileRun, Grouping};
+use crate::html_report::DisplayOptions;
+use crate::util::CountType;
+
+pub fn get_subcommand() -> Command {
+    Command::new("copy-number")
+        .about("Report copy-number aware statistics: copy-number histogram, repeat content per path/group and copy-number variable countables")
+        .args(&[
+            arg!(gfa_file: <GFA_FILE> "graph in GFA1 format, accepts also compressed (.gz) file"),
+            arg!(-s --subset <FILE> "Produce counts by subsetting the graph to a given list of paths (1-column list) or path coordinates (3- or 12-column BED file)"),
+            arg!(-e --exclude <FILE> "Exclude bp/node/edge in growth count that intersect with paths (1-column list) or path coordinates (3- or 12-column BED-file) provided by the given file; all intersecting bp/node/edge will be exluded also in other paths not part of the given list"),
+            arg!(-g --groupby <FILE> "Merge counts from paths by path-group mapping from given tab-separated two-column file"),
+            arg!(-H --"groupby-haplotype" "Merge counts from paths belonging to same haplotype"),
+            arg!(-S --"groupby-sample" "Merge counts from paths belonging to same sample"),
+            Arg::new("max_copy_number").help("Copy numbers larger than this are reported in the last bin of the histogram").short('m').long("max-copy-number").default_value("10").value_parser(clap::value_parser!(usize)),
+            Arg::new("count").help("Graph quantity to be counted").default_value("node").ignore_case(true).short('c').long("count").value_parser(clap_enum_variants_no_all!(CountType)),
+        ])
+}
+
+pub fn get_instructions(args: &ArgMatches) -> Option<anyhow::Result<Vec<FileRun>>> {
+    if let Some(args) = args.subcommand_matches("copy-number") {
+        let count = args
+            .get_one::<CountType>("count")
+            .expect("copy-number subcommand has count type")
+            .to_owned();
+        let max_copy_number = *args
+            .get_one::<usize>("max_copy_number")
+            .expect("copy-number subcommand has max copy number");
+        let graph = args
+            .get_one::<String>("gfa_file")
+            .expect("copy-number subcommand has gfa file")
+            .to_owned();
+        let subset = args
+            .get_one::<String>("subset")
+            .cloned()
+            .unwrap_or_default();
+        let exclude = args
+            .get_one::<String>("exclude")
+            .cloned()
+            .unwrap_or_default();
+        let grouping = args.get_one::<String>("groupby").cloned();
+        let grouping = if args.get_flag("groupby-sample") {
+            Some(Grouping::Sample)
+        } else if args.get_flag("groupby-haplotype") {
+            Some(Grouping::Haplotype)
+        } else {
+            grouping.map(Grouping::Custom)
+        };
+        let parameters = vec![FileRun::Gfa {
+            name: None,
+            graph,
+            subset,
+            exclude,
+            grouping,
+            reference: None,
+            nice: false,
+            count_type: count,
+            analyses: vec![AnalysisParameter::CopyNumber {
+                max_copy_number,
+                display: DisplayOptions::default(),
+            }],
+        }];
+        log::info!("{parameters:?}");
+        Some(Ok(parameters))
+    } else {
+        None
+    }
+}
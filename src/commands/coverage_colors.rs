@@ -1,10 +1,13 @@
-use clap::{arg, ArgMatches, Command};
+use crate::clap_enum_variants_no_all;
+use clap::{arg, Arg, ArgMatches, Command};
+use strum::VariantNames;
 
-use crate::analysis_parameter::{AnalysisParameter, FileRun, Grouping};
+use crate::analysis_parameter::{AnalysisParameter, ColorBy, FileRun, Grouping};
+use crate::html_report::DisplayOptions;
 
 pub fn get_subcommand() -> Command {
     Command::new("coverage-colors")
-        .about("Return general graph and paths info")
+        .about("Color the nodes by coverage, group membership or core/shell/cloud class, optionally writing them as CL:z: tags into a copy of the graph")
         .args(&[
             arg!(gfa_file: <GFA_FILE> "graph in GFA1 format, accepts also compressed (.gz) file"),
             arg!(-s --subset <FILE> "Produce counts by subsetting the graph to a given list of paths (1-column list) or path coordinates (3- or 12-column BED file)"),
@@ -12,6 +15,9 @@ pub fn get_subcommand() -> Command {
             arg!(-g --groupby <FILE> "Merge counts from paths by path-group mapping from given tab-separated two-column file"),
             arg!(-H --"groupby-haplotype" "Merge counts from paths belonging to same haplotype"),
             arg!(-S --"groupby-sample" "Merge counts from paths belonging to same sample"),
+            Arg::new("color_by").help("What the colors show: coverage, group (nodes covered by --group), private (nodes covered by a single group) or class (core/shell/cloud)").default_value("coverage").ignore_case(true).short('b').long("color-by").value_parser(clap_enum_variants_no_all!(ColorBy)),
+            arg!(--group <NAME> "Group whose nodes are colored, needed for --color-by group"),
+            arg!(--output <FILE> "Write a copy of the graph with the colors as CL:z: tags of the segments to the given file"),
         ])
 }
 
@@ -37,7 +43,14 @@ pub fn get_instructions(args: &ArgMatches) -> Option<Result<Vec<FileRun>, anyhow
         } else {
             grouping.map(|g| Grouping::Custom(g))
         };
+        let color_by = args
+            .get_one::<ColorBy>("color_by")
+            .expect("coverage-colors subcommand has color-by")
+            .to_owned();
+        let group = args.get_one::<String>("group").cloned();
+        let output = args.get_one::<String>("output").cloned();
         let parameters = vec![FileRun::Gfa {
+            name: None,
             graph,
             subset,
             exclude,
@@ -45,7 +58,12 @@ pub fn get_instructions(args: &ArgMatches) -> Option<Result<Vec<FileRun>, anyhow
             reference: None,
             nice: false,
             count_type: crate::util::CountType::Node,
-            analyses: vec![AnalysisParameter::CoverageColors],
+            analyses: vec![AnalysisParameter::CoverageColors {
+                color_by,
+                group,
+                output,
+                display: DisplayOptions::default(),
+            }],
         }];
         log::info!("{parameters:?}");
         Some(Ok(parameters))
@@ -0,0 +1,38 @@
+use std::path::Path;
+
+use clap::{Arg, ArgMatches, Command};
+
+pub fn get_subcommand() -> Command {
+    Command::new("export-multiqc")
+        .about("Export one or more JSON result files (see report --json) as MultiQC custom content files")
+        .args(&[Arg::new("json_files")
+            .required(true)
+            .num_args(1..)
+            .help("Specifies one or more JSON files")])
+        .arg(
+            Arg::new("output_dir")
+                .short('o')
+                .long("output-dir")
+                .value_name("DIR")
+                .default_value(".")
+                .help("Directory the *_mqc.json/*_mqc.tsv files are written to, point MultiQC to it"),
+        )
+}
+
+/// Runs the `export-multiqc` subcommand, which does not compute any analyses
+pub fn execute(args: &ArgMatches) -> Option<anyhow::Result<()>> {
+    let args = args.subcommand_matches("export-multiqc")?;
+    let dir = args.get_one::<String>("output_dir").expect("has default");
+    let json_files: Vec<String> = args
+        .get_many::<String>("json_files")
+        .expect("is required")
+        .cloned()
+        .collect();
+    Some(export_multiqc(&json_files, Path::new(dir)))
+}
+
+fn export_multiqc(json_files: &[String], dir: &Path) -> anyhow::Result<()> {
+    let sections = super::render::read_json_files(json_files)?;
+    crate::multiqc::write_multiqc(dir, &sections)?;
+    Ok(())
+}
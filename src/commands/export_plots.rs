@@ -0,0 +1,118 @@
+use std::path::PathBuf;
+
+use clap::{Arg, ArgAction, ArgMatches, Command};
+
+use crate::clap_enum_variants;
+use crate::plots::{PlotExport, PlotFormat, PlotOptions};
+
+pub fn get_subcommand() -> Command {
+    Command::new("export-plots")
+        .about("Export the plots of one or more JSON result files (see report --json) as static SVG, PNG or PDF files")
+        .args(&[Arg::new("json_files")
+            .required(true)
+            .num_args(1..)
+            .help("Specifies one or more JSON files")])
+        .arg(
+            Arg::new("output_dir")
+                .short('o')
+                .long("output-dir")
+                .value_name("DIR")
+                .default_value(".")
+                .help("Directory the plots are written to, files are named after the report sections"),
+        )
+        .args(get_plot_args())
+}
+
+/// Options of the plot files, shared with `report --plots`
+pub fn get_plot_args() -> Vec<Arg> {
+    vec![
+        Arg::new("plot_format")
+            .long("plot-format")
+            .value_name("FORMAT")
+            .value_delimiter(',')
+            .default_value("svg")
+            .ignore_case(true)
+            .value_parser(clap_enum_variants!(PlotFormat))
+            .help("Formats of the plot files, comma-separated (svg, png, pdf)"),
+        Arg::new("plot_width")
+            .long("plot-width")
+            .value_name("PIXELS")
+            .default_value("800")
+            .value_parser(clap::value_parser!(f64))
+            .help("Width of the plots"),
+        Arg::new("plot_height")
+            .long("plot-height")
+            .value_name("PIXELS")
+            .default_value("500")
+            .value_parser(clap::value_parser!(f64))
+            .help("Height of the plots"),
+        Arg::new("plot_font")
+            .long("plot-font")
+            .value_name("FAMILY")
+            .default_value("sans-serif")
+            .help("Font family of SVG and PNG plots (PDF plots use Helvetica)"),
+        Arg::new("plot_font_size")
+            .long("plot-font-size")
+            .value_name("PIXELS")
+            .default_value("12")
+            .value_parser(clap::value_parser!(f64))
+            .help("Font size of the plots"),
+        Arg::new("plot_log_x")
+            .long("plot-log-x")
+            .action(ArgAction::SetTrue)
+            .help("Use a log scale for numeric x axes (line plots)"),
+        Arg::new("plot_log_y")
+            .long("plot-log-y")
+            .action(ArgAction::SetTrue)
+            .help("Use a log scale for the y axes of bar and line plots"),
+        Arg::new("plot_scale")
+            .long("plot-scale")
+            .value_name("FACTOR")
+            .default_value("2")
+            .value_parser(clap::value_parser!(f64))
+            .help("Resolution of PNG plots in pixels per plot pixel"),
+    ]
+}
+
+/// Reads the plot options given by the arguments of `get_plot_args`
+pub fn get_plot_export(args: &ArgMatches, dir: PathBuf) -> PlotExport {
+    PlotExport {
+        dir,
+        formats: args
+            .get_many::<PlotFormat>("plot_format")
+            .expect("plot format has default")
+            .copied()
+            .collect(),
+        options: PlotOptions {
+            width: *args.get_one("plot_width").expect("has default"),
+            height: *args.get_one("plot_height").expect("has default"),
+            font_family: args
+                .get_one::<String>("plot_font")
+                .expect("has default")
+                .clone(),
+            font_size: *args.get_one("plot_font_size").expect("has default"),
+            log_x: args.get_flag("plot_log_x"),
+            log_y: args.get_flag("plot_log_y"),
+            scale: *args.get_one("plot_scale").expect("has default"),
+        },
+    }
+}
+
+/// Runs the `export-plots` subcommand, which does not compute any analyses
+pub fn execute(args: &ArgMatches) -> Option<anyhow::Result<()>> {
+    let args = args.subcommand_matches("export-plots")?;
+    let dir = PathBuf::from(args.get_one::<String>("output_dir").expect("has default"));
+    let export = get_plot_export(args, dir);
+    let json_files: Vec<String> = args
+        .get_many::<String>("json_files")
+        .expect("is required")
+        .cloned()
+        .collect();
+    Some(export_plots(&json_files, &export))
+}
+
+fn export_plots(json_files: &[String], export: &PlotExport) -> anyhow::Result<()> {
+    let sections = super::render::read_json_files(json_files)?;
+    export.write(&sections)?;
+    Ok(())
+}
@@ -3,6 +3,7 @@ use clap::{arg, Arg, ArgMatches, Command};
 use crate::{
     analysis_parameter::{AnalysisParameter, FileRun, Grouping},
     clap_enum_variants,
+    html_report::DisplayOptions,
     util::CountType,
 };
 
@@ -19,6 +20,7 @@ pub fn get_subcommand() -> Command {
             arg!(-S --"groupby-sample" "Merge counts from paths belonging to same sample (ONLY IN GFA MODE)"),
             arg!(-i --hist "Also include histogram in output (ONLY IN GFA MODE)"),
             arg!(-a --alpha "Include alpha value as a comment in the output"),
+            arg!(-L --"length-classes" <CLASSES> "Split the countables by their length in bp (node length, or allele length when counting alleles/bubbles) into classes given by a comma-separated list of class boundaries (e.g., 1,50,1000,10000) and report hist and growth for each class; cannot be combined with --hist or --alpha").conflicts_with_all(["hist", "alpha"]),
             Arg::new("count").help("Graph quantity to be counted").default_value("node").ignore_case(true).short('c').long("count").value_parser(clap_enum_variants!(CountType)),
             Arg::new("coverage").help("Ignore all countables with a coverage lower than the specified threshold. The coverage of a countable corresponds to the number of path/walk that contain it. Repeated appearances of a countable in the same path/walk are counted as one. You can pass a comma-separated list of coverage thresholds, each one will produce a separated growth curve (e.g., --coverage 2,3). Use --quorum to set a threshold in conjunction with each coverage (e.g., --quorum 0.5,0.9)")
             .short('l').long("coverage").default_value("1"),
@@ -34,6 +36,7 @@ pub fn get_instructions(args: &ArgMatches) -> Option<Result<Vec<FileRun>, anyhow
         let quorum = args.get_one::<String>("quorum").cloned();
         let add_hist = args.get_flag("hist");
         let add_alpha = args.get_flag("alpha");
+        let length_classes = args.get_one::<String>("length-classes").cloned();
         let count = args
             .get_one::<CountType>("count")
             .expect("hist subcommand has count type")
@@ -59,6 +62,7 @@ pub fn get_instructions(args: &ArgMatches) -> Option<Result<Vec<FileRun>, anyhow
             grouping.map(|g| Grouping::Custom(g))
         };
         Some(Ok(vec![FileRun::Gfa {
+            name: None,
             graph,
             subset,
             exclude,
@@ -66,11 +70,20 @@ pub fn get_instructions(args: &ArgMatches) -> Option<Result<Vec<FileRun>, anyhow
             reference: None,
             nice: false,
             count_type: count,
-            analyses: vec![AnalysisParameter::Growth {
-                coverage,
-                quorum,
-                add_hist,
-                add_alpha,
+            analyses: vec![match length_classes {
+                Some(classes) => AnalysisParameter::LengthClassGrowth {
+                    classes,
+                    coverage,
+                    quorum,
+                    display: DisplayOptions::default(),
+                },
+                None => AnalysisParameter::Growth {
+                    coverage,
+                    quorum,
+                    add_hist,
+                    add_alpha,
+                    display: DisplayOptions::default(),
+                },
             }],
         }]))
     } else {
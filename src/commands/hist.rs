@@ -2,6 +2,7 @@ use crate::clap_enum_variants;
 use clap::{arg, Arg, ArgMatches, Command};
 
 use crate::analysis_parameter::{AnalysisParameter, FileRun, Grouping};
+use crate::html_report::DisplayOptions;
 use crate::util::CountType;
 
 pub fn get_subcommand() -> Command {
@@ -45,6 +46,7 @@ pub fn get_instructions(args: &ArgMatches) -> Option<Result<Vec<FileRun>, anyhow
             grouping.map(|g| Grouping::Custom(g))
         };
         Some(Ok(vec![FileRun::Gfa {
+            name: None,
             graph,
             subset,
             exclude,
@@ -52,7 +54,9 @@ pub fn get_instructions(args: &ArgMatches) -> Option<Result<Vec<FileRun>, anyhow
             reference: None,
             nice: false,
             count_type: count,
-            analyses: vec![AnalysisParameter::Hist {}],
+            analyses: vec![AnalysisParameter::Hist {
+                display: DisplayOptions::default(),
+            }],
         }]))
     } else {
         None
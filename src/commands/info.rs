@@ -1,6 +1,7 @@
 use clap::{arg, ArgMatches, Command};
 
 use crate::analysis_parameter::{AnalysisParameter, FileRun, Grouping};
+use crate::html_report::DisplayOptions;
 
 pub fn get_subcommand() -> Command {
     Command::new("info")
@@ -12,6 +13,7 @@ pub fn get_subcommand() -> Command {
             arg!(-g --groupby <FILE> "Merge counts from paths by path-group mapping from given tab-separated two-column file"),
             arg!(-H --"groupby-haplotype" "Merge counts from paths belonging to same haplotype"),
             arg!(-S --"groupby-sample" "Merge counts from paths belonging to same sample"),
+            arg!(-r --reference <NAME> "Name of the reference path(s) (e.g. GRCh38 or CHM13), used to report the fraction of the reference covered by each path/group"),
         ])
 }
 
@@ -29,6 +31,7 @@ pub fn get_instructions(args: &ArgMatches) -> Option<Result<Vec<FileRun>, anyhow
             .get_one::<String>("exclude")
             .cloned()
             .unwrap_or_default();
+        let reference = args.get_one::<String>("reference").cloned();
         let grouping = args.get_one::<String>("groupby").cloned();
         let grouping = if args.get_flag("groupby-sample") {
             Some(Grouping::Sample)
@@ -38,14 +41,17 @@ pub fn get_instructions(args: &ArgMatches) -> Option<Result<Vec<FileRun>, anyhow
             grouping.map(|g| Grouping::Custom(g))
         };
         let parameters = vec![FileRun::Gfa {
+            name: None,
             graph,
             subset,
             exclude,
             grouping,
-            reference: None,
+            reference,
             nice: false,
             count_type: crate::util::CountType::Node,
-            analyses: vec![AnalysisParameter::Info],
+            analyses: vec![AnalysisParameter::Info {
+                display: DisplayOptions::default(),
+            }],
         }];
         log::info!("{parameters:?}");
         Some(Ok(parameters))
@@ -1,6 +1,7 @@
 use clap::{arg, Arg, ArgMatches, Command};
 
 use crate::analysis_parameter::{AnalysisParameter, FileRun, Grouping};
+use crate::html_report::DisplayOptions;
 
 pub fn get_subcommand() -> Command {
     Command::new("node-distribution")
@@ -48,6 +49,7 @@ pub fn get_instructions(args: &ArgMatches) -> Option<Result<Vec<FileRun>, anyhow
             grouping.map(|g| Grouping::Custom(g))
         };
         let parameters = vec![FileRun::Gfa {
+            name: None,
             graph,
             subset,
             exclude,
@@ -58,6 +60,7 @@ pub fn get_instructions(args: &ArgMatches) -> Option<Result<Vec<FileRun>, anyhow
             analyses: vec![AnalysisParameter::NodeDistribution {
                 radius,
                 threshold: 1000,
+                display: DisplayOptions::default(),
             }],
         }];
         log::info!("{parameters:?}");
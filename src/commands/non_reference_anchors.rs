@@ -0,0 +1,66 @@
+use clap::{arg, Arg, ArgMatches, Command};
+
+use crate::analysis_parameter::{AnalysisParameter, FileRun, Grouping};
+use crate::html_report::DisplayOptions;
+
+pub fn get_subcommand() -> Command {
+    Command::new("non-reference-anchors")
+        .about("List clusters of non-reference sequence and the reference positions they are anchored at (BED-like table)")
+        .args(&[
+            arg!(gfa_file: <GFA_FILE> "graph in GFA1 format, accepts also compressed (.gz) file"),
+            arg!(-r --reference <NAME> "Name of the reference path(s), e.g. GRCh38 or CHM13; all paths belonging to this prefix are used").required(true),
+            arg!(-s --subset <FILE> "Produce counts by subsetting the graph to a given list of paths (1-column list) or path coordinates (3- or 12-column BED file)"),
+            arg!(-e --exclude <FILE> "Exclude bp/node/edge in growth count that intersect with paths (1-column list) or path coordinates (3- or 12-column BED-file) provided by the given file; all intersecting bp/node/edge will be exluded also in other paths not part of the given list"),
+            arg!(-g --groupby <FILE> "Merge counts from paths by path-group mapping from given tab-separated two-column file"),
+            arg!(-H --"groupby-haplotype" "Merge counts from paths belonging to same haplotype"),
+            arg!(-S --"groupby-sample" "Merge counts from paths belonging to same sample"),
+            Arg::new("min_length").help("Only report clusters with at least this many bp of non-reference sequence").short('m').long("min-length").default_value("0").value_parser(clap::value_parser!(usize)),
+        ])
+}
+
+pub fn get_instructions(args: &ArgMatches) -> Option<Result<Vec<FileRun>, anyhow::Error>> {
+    if let Some(args) = args.subcommand_matches("non-reference-anchors") {
+        let graph = args
+            .get_one::<String>("gfa_file")
+            .expect("non-reference-anchors subcommand has gfa file")
+            .to_owned();
+        let reference = args.get_one::<String>("reference").cloned();
+        let min_length = *args
+            .get_one::<usize>("min_length")
+            .expect("non-reference-anchors subcommand has min length");
+        let subset = args
+            .get_one::<String>("subset")
+            .cloned()
+            .unwrap_or_default();
+        let exclude = args
+            .get_one::<String>("exclude")
+            .cloned()
+            .unwrap_or_default();
+        let grouping = args.get_one::<String>("groupby").cloned();
+        let grouping = if args.get_flag("groupby-sample") {
+            Some(Grouping::Sample)
+        } else if args.get_flag("groupby-haplotype") {
+            Some(Grouping::Haplotype)
+        } else {
+            grouping.map(Grouping::Custom)
+        };
+        let parameters = vec![FileRun::Gfa {
+            name: None,
+            graph,
+            subset,
+            exclude,
+            grouping,
+            reference,
+            nice: false,
+            count_type: crate::util::CountType::Node,
+            analyses: vec![AnalysisParameter::NonReferenceAnchors {
+                min_length,
+                display: DisplayOptions::default(),
+            }],
+        }];
+        log::info!("{parameters:?}");
+        Some(Ok(parameters))
+    } else {
+        None
+    }
+}
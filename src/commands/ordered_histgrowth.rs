@@ -3,6 +3,7 @@ use clap::{arg, Arg, ArgMatches, Command};
 use strum::VariantNames;
 
 use crate::analysis_parameter::{AnalysisParameter, FileRun, Grouping};
+use crate::html_report::DisplayOptions;
 use crate::util::CountType;
 
 pub fn get_subcommand() -> Command {
@@ -54,6 +55,7 @@ pub fn get_instructions(args: &ArgMatches) -> Option<anyhow::Result<Vec<FileRun>
             grouping.map(|g| Grouping::Custom(g))
         };
         let parameters = vec![FileRun::Gfa {
+            name: None,
             graph,
             subset,
             exclude,
@@ -65,6 +67,7 @@ pub fn get_instructions(args: &ArgMatches) -> Option<anyhow::Result<Vec<FileRun>
                 coverage,
                 quorum,
                 order,
+                display: DisplayOptions::default(),
             }],
         }];
         log::info!("{parameters:?}");
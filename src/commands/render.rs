@@ -1,4 +1,13 @@
-use clap::{Arg, Command};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use serde::{Deserialize, Serialize};
+
+use crate::html_report::{AnalysisSection, ReportOptions};
+use crate::provenance::Provenance;
+use crate::util::parse_memory_size;
 
 pub fn get_subcommand() -> Command {
     Command::new("render")
@@ -8,4 +17,104 @@ pub fn get_subcommand() -> Command {
             .num_args(1..)
             .trailing_var_arg(true)
             .help("Specifies one or more JSON files")])
+        .arg(
+            Arg::new("compare")
+                .long("compare")
+                .action(ArgAction::SetTrue)
+                .help("Add comparisons of the runs: sections of the same analysis and count type are overlaid in one plot, with tables of their differences to the first run"),
+        )
+        .arg(
+            Arg::new("normalize")
+                .long("normalize")
+                .requires("compare")
+                .action(ArgAction::SetTrue)
+                .help("Normalize the compared values to their maximum, which also compares different count types (e.g. nodes and bps)"),
+        )
+        .args(get_report_data_args())
+}
+
+/// Options controlling the size of the HTML report data, shared with `report`
+pub fn get_report_data_args() -> Vec<Arg> {
+    vec![
+        Arg::new("max_plot_points")
+            .long("max-plot-points")
+            .value_name("N")
+            .value_parser(clap::value_parser!(usize))
+            .help("Reduce each plot to about N values: curves and windows are downsampled, heatmaps and bars of groups keep their largest entries"),
+        Arg::new("inline_limit")
+            .long("inline-limit")
+            .value_name("SIZE")
+            .default_value("64K")
+            .value_parser(parse_memory_size)
+            .help("Sections with more data (e.g. 512K, 2M) are stored compressed in the report and only decoded when their tab is opened"),
+        Arg::new("sidecar_dir")
+            .long("sidecar-dir")
+            .value_name("DIR")
+            .help("Write the data of all sections to JavaScript files in DIR, which are loaded when a tab is opened. The files are referenced relative to the directory of --report-path"),
+        Arg::new("report_path")
+            .long("report-path")
+            .value_name("FILE")
+            .requires("sidecar_dir")
+            .help("Path the report is saved to (it is written to stdout), so that the files of --sidecar-dir are found from its directory [default: a report in the current directory]"),
+    ]
+}
+
+/// Reads the options given by the arguments of `get_report_data_args`
+pub fn get_report_options(args: &ArgMatches) -> ReportOptions {
+    ReportOptions {
+        max_plot_points: args.get_one::<usize>("max_plot_points").copied(),
+        inline_limit: *args.get_one("inline_limit").expect("has default"),
+        sidecar_dir: args.get_one::<String>("sidecar_dir").map(PathBuf::from),
+        report_dir: args.get_one::<String>("report_path").map(|path| {
+            Path::new(path)
+                .parent()
+                .unwrap_or(Path::new(""))
+                .to_path_buf()
+        }),
+        ..ReportOptions::default()
+    }
+}
+
+/// A JSON result file of `report --json`, one per run
+#[derive(Serialize, Deserialize)]
+pub struct JsonReport {
+    pub provenance: Provenance,
+    pub sections: Vec<AnalysisSection>,
+}
+
+/// Reads the sections of JSON result files, as written by `report --json`
+/// (one report per run)
+pub fn read_json_files(json_files: &[String]) -> anyhow::Result<Vec<AnalysisSection>> {
+    Ok(read_json_reports(json_files)?.0)
+}
+
+/// Reads the sections of JSON result files together with their provenance.
+/// Files of older versions are plain arrays of sections, without provenance.
+/// The reports of the runs of one config share their provenance, only the
+/// last one (with the longest runtime) is kept.
+pub fn read_json_reports(
+    json_files: &[String],
+) -> anyhow::Result<(Vec<AnalysisSection>, Vec<Provenance>)> {
+    let mut sections = Vec::new();
+    let mut provenances: Vec<Provenance> = Vec::new();
+    for file_path in json_files {
+        let reader = BufReader::new(File::open(file_path)?);
+        for report in serde_json::Deserializer::from_reader(reader).into_iter() {
+            let report: serde_json::Value = report?;
+            if report.is_array() {
+                sections.extend(serde_json::from_value::<Vec<AnalysisSection>>(report)?);
+                continue;
+            }
+            let JsonReport {
+                provenance,
+                sections: report_sections,
+            } = serde_json::from_value(report)?;
+            sections.extend(report_sections);
+            match provenances.iter_mut().find(|p| p.is_same_run(&provenance)) {
+                Some(p) => *p = provenance,
+                None => provenances.push(provenance),
+            }
+        }
+    }
+    Ok((sections, provenances))
 }
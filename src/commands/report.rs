@@ -1,10 +1,15 @@
+use std::collections::HashMap;
 use std::io::BufReader;
+use std::path::{Path, PathBuf};
 use std::{fs::File, io::Read};
 
 use anyhow::anyhow;
 use clap::{Arg, ArgAction, ArgMatches, Command};
+use regex::{Captures, Regex};
+use serde_yaml::Value;
 
-use crate::analysis_parameter::FileRun;
+use crate::analysis_parameter::{FileRun, ReportConfig, ReportFile};
+use crate::ConfigParseError;
 
 pub fn get_subcommand() -> Command {
     Command::new("report")
@@ -28,10 +33,26 @@ pub fn get_subcommand() -> Command {
                 .help(
                     "Instead of an HTML report, a json result will be delivered. These can later be combined and rendered as a single HTML.",
             )])
-        .arg(Arg::new("variable").short('r').long("variable").action(ArgAction::Append).help("A variable in the format --variable MYVAR=\"test_text\". This will replace the string \"{{MYVAR}}\" in the YAML file with \"test_text\""))
+        .args(&[Arg::new("validate")
+            .required(false)
+            .long("validate")
+            .action(ArgAction::SetTrue)
+            .help(
+                "If set, no actual computation is done, only the config is checked: all files need to exist, thresholds need to be valid and order/section files need to refer to groups of the graph",
+            )])
+        .arg(Arg::new("variable").short('r').long("variable").action(ArgAction::Append).help("A variable in the format --variable MYVAR=\"test_text\". This will replace the string \"{{MYVAR}}\" in the YAML file with \"test_text\". A default can be given in the YAML file as \"{{MYVAR=default_text}}\", variables without value and default are left unchanged"))
+        .arg(Arg::new("only").long("only").value_name("NAME").action(ArgAction::Append).help("Only run the config block with the given name (can be given multiple times)"))
+        .arg(Arg::new("skip").long("skip").value_name("NAME").action(ArgAction::Append).help("Skip the config block with the given name (can be given multiple times)"))
+        .arg(Arg::new("multiqc").long("multiqc").value_name("DIR").help("Additionally write the results as MultiQC custom content files to DIR (see export-multiqc)"))
+        .arg(Arg::new("plots").long("plots").value_name("DIR").help("Additionally export the plots of the report as static files to DIR (see export-plots)"))
+        .args(super::export_plots::get_plot_args())
+        .args(super::render::get_report_data_args())
 }
 
-pub fn get_instructions(args: &ArgMatches) -> Option<Result<Vec<FileRun>, anyhow::Error>> {
+/// Returns the selected runs of the config, together with its `report:` block
+pub fn get_instructions(
+    args: &ArgMatches,
+) -> Option<Result<(Vec<FileRun>, ReportConfig), anyhow::Error>> {
     if let Some(args) = args.subcommand_matches("report") {
         Some(parse_report_args(args))
     } else {
@@ -39,40 +60,42 @@ pub fn get_instructions(args: &ArgMatches) -> Option<Result<Vec<FileRun>, anyhow
     }
 }
 
-fn parse_report_args(args: &ArgMatches) -> Result<Vec<FileRun>, anyhow::Error> {
+fn parse_report_args(args: &ArgMatches) -> Result<(Vec<FileRun>, ReportConfig), anyhow::Error> {
     if let Some(yaml_file) = args.get_one::<String>("yaml_file").cloned() {
-        let f = File::open(yaml_file)?;
-        let mut reader = BufReader::new(f);
-        let mut buffer = String::new();
-        reader.read_to_string(&mut buffer)?;
-        let variables = args
-            .get_many::<String>("variable")
-            .unwrap_or_default()
-            .map(|v| v.as_str())
-            .collect::<Vec<_>>();
-        for variable in variables {
-            let mut iter = variable.split("=");
-            let key = iter.next().ok_or_else(|| {
-                anyhow!("Empty variable parameter, use it like --variable KEY=value")
-            })?;
-            let value = iter.next().ok_or_else(|| {
+        let mut variables = HashMap::new();
+        for variable in args.get_many::<String>("variable").unwrap_or_default() {
+            let (key, value) = variable.split_once('=').ok_or_else(|| {
                 anyhow!("Variable parameter without '=', use it like --variable KEY=value")
             })?;
-            // Turns into "{{KEY}}"
-            let text = format!("{{{{{key}}}}}");
-            buffer = buffer.replace(text.as_str(), value);
+            if key.is_empty() {
+                return Err(anyhow!(
+                    "Empty variable parameter, use it like --variable KEY=value"
+                ));
+            }
+            variables.insert(key.to_string(), value.to_string());
         }
-        let contents = serde_yaml::from_str(&buffer)?;
-        Ok(contents)
+        let config = read_config(Path::new(&yaml_file), &variables, &mut Vec::new())?;
+        let file = ReportFile::from_yaml(&config)?;
+        let only: Vec<&String> = args
+            .get_many::<String>("only")
+            .unwrap_or_default()
+            .collect();
+        let skip: Vec<&String> = args
+            .get_many::<String>("skip")
+            .unwrap_or_default()
+            .collect();
+        Ok((select_runs(file.runs, &only, &skip)?, file.report))
     } else {
         println!(
             "
 # Missing YAML file!
 #
 # Example YAML:
-# To get started copy this into a .yaml file and edit it
+# To get started copy this into a .yaml file and edit it, or create a
+# config for your graph with `panacus config init GRAPH`
 
 - graph: ../graphs/test_graph.gfa
+  name: my-run
   grouping: Haplotype
   count_type: Bp
   analyses:
@@ -87,3 +110,319 @@ fn parse_report_args(args: &ArgMatches) -> Result<Vec<FileRun>, anyhow::Error> {
         Err(anyhow!("Missing YAML configuration!"))
     }
 }
+
+/// Reads a config file and substitutes its variables. Config blocks given as
+/// `- !Include FILE` (in the list of config blocks, or in its `runs:` if the
+/// config has a `report:` block) are replaced by the config blocks of the
+/// given config file (relative to the including file). An included config
+/// file has to be a plain list of config blocks, paths within it are not
+/// changed. An `!Include` anywhere else is an error.
+fn read_config(
+    path: &Path,
+    variables: &HashMap<String, String>,
+    included_by: &mut Vec<PathBuf>,
+) -> anyhow::Result<String> {
+    let canonical_path = path
+        .canonicalize()
+        .map_err(|e| anyhow!("cannot read config file {}: {}", path.display(), e))?;
+    if included_by.contains(&canonical_path) {
+        return Err(ConfigParseError::CyclicInclude {
+            file: path.display().to_string(),
+        }
+        .into());
+    }
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut buffer = String::new();
+    reader.read_to_string(&mut buffer)?;
+    let buffer = substitute_variables(&buffer, variables)?;
+    let mut config: Value = serde_yaml::from_str(&buffer)?;
+    if !contains_include(&config) {
+        return Ok(buffer);
+    }
+    let mut run_includes = 0;
+    if let Some(runs) = get_runs_mut(&mut config) {
+        let n_runs = runs.len();
+        runs.retain(|run| !is_include(run));
+        run_includes = n_runs - runs.len();
+    }
+    let misplaced_include = || ConfigParseError::MisplacedInclude {
+        file: path.display().to_string(),
+    };
+    if contains_include(&config) {
+        return Err(misplaced_include().into());
+    }
+
+    included_by.push(canonical_path);
+    let mut res = String::new();
+    let mut includes = 0;
+    let include = Regex::new(r"^(\s*)-\s+!Include\s+(.+?)\s*$").unwrap();
+    for line in buffer.lines() {
+        match include.captures(line) {
+            Some(captures) => {
+                let file = path.parent().unwrap_or(Path::new("")).join(&captures[2]);
+                let included = read_config(&file, variables, included_by)?;
+                if !matches!(serde_yaml::from_str(&included)?, Value::Sequence(_)) {
+                    return Err(anyhow!(
+                        "included config file {} is not a list of config blocks",
+                        file.display()
+                    ));
+                }
+                for included_line in included.lines() {
+                    res.push_str(&captures[1]);
+                    res.push_str(included_line);
+                    res.push('\n');
+                }
+                includes += 1;
+            }
+            None => {
+                res.push_str(line);
+                res.push('\n');
+            }
+        }
+    }
+    included_by.pop();
+    // Includes that are not on a line of their own (e.g. in flow style)
+    // cannot be replaced
+    if includes != run_includes {
+        return Err(misplaced_include().into());
+    }
+    Ok(res)
+}
+
+/// Returns the list of config blocks of a config
+fn get_runs_mut(config: &mut Value) -> Option<&mut Vec<Value>> {
+    match config {
+        Value::Sequence(runs) => Some(runs),
+        Value::Mapping(config) => config.get_mut("runs")?.as_sequence_mut(),
+        _ => None,
+    }
+}
+
+fn is_include(value: &Value) -> bool {
+    matches!(value, Value::Tagged(tagged) if tagged.tag == "Include")
+}
+
+fn contains_include(value: &Value) -> bool {
+    match value {
+        Value::Tagged(tagged) => is_include(value) || contains_include(&tagged.value),
+        Value::Sequence(values) => values.iter().any(contains_include),
+        Value::Mapping(mapping) => mapping
+            .iter()
+            .any(|(k, v)| contains_include(k) || contains_include(v)),
+        _ => false,
+    }
+}
+
+/// Replaces all `{{KEY}}` and `{{KEY=default}}` in the text by the value of
+/// the variable, or its default if it is not set. Defaults apply to all
+/// occurrences of a variable in the text. A `{{KEY}}` without value and
+/// default is left as it is.
+fn substitute_variables(
+    text: &str,
+    variables: &HashMap<String, String>,
+) -> Result<String, ConfigParseError> {
+    let re = Regex::new(r"\{\{([A-Za-z0-9_-]+)(?:=([^}]*))?\}\}").unwrap();
+    let mut defaults: HashMap<&str, &str> = HashMap::new();
+    for captures in re.captures_iter(text) {
+        if let Some(default) = captures.get(2) {
+            let name = captures.get(1).unwrap().as_str();
+            if *defaults.entry(name).or_insert(default.as_str()) != default.as_str() {
+                return Err(ConfigParseError::ConflictingDefaults {
+                    name: name.to_string(),
+                });
+            }
+        }
+    }
+    let res = re.replace_all(text, |captures: &Captures| {
+        let name = &captures[1];
+        match variables
+            .get(name)
+            .map(|v| v.as_str())
+            .or_else(|| defaults.get(name).copied())
+        {
+            Some(value) => value.to_string(),
+            None => {
+                log::warn!(
+                    "variable {} is not set and has no default, set it with --variable {}=value",
+                    name,
+                    name
+                );
+                captures[0].to_string()
+            }
+        }
+    });
+    Ok(res.into_owned())
+}
+
+/// Selects runs by name, all given names need to exist
+fn select_runs(
+    runs: Vec<FileRun>,
+    only: &[&String],
+    skip: &[&String],
+) -> Result<Vec<FileRun>, ConfigParseError> {
+    let mut names: Vec<&str> = runs.iter().filter_map(|r| r.get_name()).collect();
+    names.sort();
+    if let Some(name) = names.windows(2).find(|w| w[0] == w[1]) {
+        return Err(ConfigParseError::DuplicateName {
+            name: name[0].to_string(),
+        });
+    }
+    if let Some(name) = only
+        .iter()
+        .chain(skip)
+        .find(|n| !names.contains(&n.as_str()))
+    {
+        return Err(ConfigParseError::NameNotFound {
+            name: name.to_string(),
+        });
+    }
+    Ok(runs
+        .into_iter()
+        .filter(|r| {
+            let name = r.get_name().unwrap_or_default();
+            (only.is_empty() || only.iter().any(|n| *n == name)) && !skip.iter().any(|n| *n == name)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis_parameter::AnalysisParameter;
+    use crate::html_report::DisplayOptions;
+    use crate::util::CountType;
+
+    fn named_run(name: Option<&str>) -> FileRun {
+        FileRun::Gfa {
+            name: name.map(|n| n.to_string()),
+            graph: "a.gfa".to_string(),
+            subset: String::new(),
+            exclude: String::new(),
+            grouping: None,
+            reference: None,
+            nice: false,
+            count_type: CountType::Node,
+            analyses: vec![AnalysisParameter::Hist {
+                display: DisplayOptions::default(),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_variables_with_defaults() {
+        let text = "graph: {{DIR=graphs}}/{{NAME}}.gfa\nout: {{DIR}}";
+        let variables = HashMap::from([("NAME".to_string(), "x".to_string())]);
+        assert_eq!(
+            substitute_variables(text, &variables).unwrap(),
+            "graph: graphs/x.gfa\nout: graphs"
+        );
+        let variables = HashMap::from([
+            ("NAME".to_string(), "x".to_string()),
+            ("DIR".to_string(), "other".to_string()),
+        ]);
+        assert_eq!(
+            substitute_variables(text, &variables).unwrap(),
+            "graph: other/x.gfa\nout: other"
+        );
+        // Variables without value and default are kept (e.g. literal braces)
+        assert_eq!(
+            substitute_variables(text, &HashMap::new()).unwrap(),
+            "graph: graphs/{{NAME}}.gfa\nout: graphs"
+        );
+        assert!(matches!(
+            substitute_variables("{{A=1}} {{A=2}}", &HashMap::new()),
+            Err(ConfigParseError::ConflictingDefaults { .. })
+        ));
+    }
+
+    #[test]
+    fn test_includes() {
+        let config = read_config(
+            Path::new("tests/test_files/t_named_runs.yaml"),
+            &HashMap::new(),
+            &mut Vec::new(),
+        )
+        .unwrap();
+        let runs: Vec<FileRun> = serde_yaml::from_str(&config).unwrap();
+        assert_eq!(runs.len(), 5);
+        let config = read_config(
+            Path::new("tests/test_files/t_report_include.yaml"),
+            &HashMap::new(),
+            &mut Vec::new(),
+        )
+        .unwrap();
+        let file = ReportFile::from_yaml(&config).unwrap();
+        assert_eq!(file.report.title.as_deref(), Some("Included runs"));
+        assert_eq!(file.runs.len(), 4);
+        let err = read_config(
+            Path::new("tests/test_files/t_misplaced_include.yaml"),
+            &HashMap::new(),
+            &mut Vec::new(),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<ConfigParseError>(),
+            Some(ConfigParseError::MisplacedInclude { .. })
+        ));
+        let err = read_config(
+            Path::new("tests/test_files/t_cyclic_include.yaml"),
+            &HashMap::new(),
+            &mut Vec::new(),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<ConfigParseError>(),
+            Some(ConfigParseError::CyclicInclude { .. })
+        ));
+    }
+
+    #[test]
+    fn test_runs_are_selected_by_name() {
+        let runs = vec![named_run(Some("a")), named_run(Some("b")), named_run(None)];
+        let (a, b, c) = ("a".to_string(), "b".to_string(), "c".to_string());
+        let names = |runs: Vec<FileRun>| -> Vec<Option<String>> {
+            runs.iter()
+                .map(|r| r.get_name().map(|n| n.to_string()))
+                .collect()
+        };
+        assert_eq!(
+            names(select_runs(runs.clone(), &[&a], &[]).unwrap()),
+            vec![Some(a.clone())]
+        );
+        assert_eq!(
+            names(select_runs(runs.clone(), &[], &[&a]).unwrap()),
+            vec![Some(b.clone()), None]
+        );
+        assert!(matches!(
+            select_runs(runs.clone(), &[&c], &[]),
+            Err(ConfigParseError::NameNotFound { .. })
+        ));
+        assert!(matches!(
+            select_runs(vec![named_run(Some("a")), named_run(Some("a"))], &[], &[]),
+            Err(ConfigParseError::DuplicateName { .. })
+        ));
+    }
+
+    #[test]
+    fn test_config_with_report_block() {
+        let runs = "- !Gfa\n  graph: a.gfa\n  count_type: Node\n  analyses:\n    - !Info\n";
+        let file = ReportFile::from_yaml(runs).unwrap();
+        assert_eq!(file.report, ReportConfig::default());
+        assert_eq!(file.runs.len(), 1);
+
+        let text = format!(
+            "report:\n  title: Pangenome\n  display:\n    palette: [red, blue]\n    width: 500\nruns:\n{}    - !Hist\n      display:\n        title: Coverage\n        width: 300\n",
+            runs
+        );
+        let mut file = ReportFile::from_yaml(&text).unwrap();
+        assert_eq!(file.report.title.as_deref(), Some("Pangenome"));
+        let analyses = file.runs[0].get_analyses_mut();
+        let palette = Some(vec!["red".to_string(), "blue".to_string()]);
+        assert_eq!(analyses[0].get_display_mut().palette, palette);
+        assert_eq!(analyses[0].get_display_mut().width, Some(500));
+        let hist = analyses[1].get_display_mut();
+        assert_eq!(hist.title.as_deref(), Some("Coverage"));
+        assert_eq!(hist.palette, palette);
+        assert_eq!(hist.width, Some(300));
+    }
+}
@@ -2,6 +2,7 @@ use crate::{clap_enum_variants, util::CountType};
 use clap::{arg, Arg, ArgMatches, Command};
 
 use crate::analysis_parameter::{AnalysisParameter, FileRun, Grouping};
+use crate::html_report::DisplayOptions;
 
 pub fn get_subcommand() -> Command {
     Command::new("section-growth")
@@ -55,6 +56,7 @@ pub fn get_instructions(args: &ArgMatches) -> Option<Result<Vec<FileRun>, anyhow
             grouping.map(|g| Grouping::Custom(g))
         };
         Some(Ok(vec![FileRun::Gfa {
+            name: None,
             graph,
             subset,
             exclude,
@@ -66,6 +68,7 @@ pub fn get_instructions(args: &ArgMatches) -> Option<Result<Vec<FileRun>, anyhow
                 sections: section_file,
                 coverage,
                 quorum,
+                display: DisplayOptions::default(),
             }],
         }]))
     } else {
@@ -3,6 +3,7 @@ use clap::{arg, Arg, ArgMatches, Command};
 use strum::VariantNames;
 
 use crate::analysis_parameter::{AnalysisParameter, ClusterMethod, FileRun, Grouping};
+use crate::html_report::DisplayOptions;
 use crate::util::CountType;
 
 pub fn get_subcommand() -> Command {
@@ -52,6 +53,7 @@ pub fn get_instructions(args: &ArgMatches) -> Option<anyhow::Result<Vec<FileRun>
             grouping.map(|g| Grouping::Custom(g))
         };
         let parameters = vec![FileRun::Gfa {
+            name: None,
             graph,
             subset,
             exclude,
@@ -59,7 +61,10 @@ pub fn get_instructions(args: &ArgMatches) -> Option<anyhow::Result<Vec<FileRun>
             reference: None,
             nice: false,
             count_type: count,
-            analyses: vec![AnalysisParameter::Similarity { cluster_method }],
+            analyses: vec![AnalysisParameter::Similarity {
+                cluster_method,
+                display: DisplayOptions::default(),
+            }],
         }];
         // log::info!("{parameters:?}");
         Some(Ok(parameters))
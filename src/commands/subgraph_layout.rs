@@ -0,0 +1,70 @@
+use clap::{arg, Arg, ArgMatches, Command};
+
+use crate::analysis_parameter::{AnalysisParameter, FileRun, Grouping};
+use crate::html_report::DisplayOptions;
+
+pub fn get_subcommand() -> Command {
+    Command::new("subgraph-layout")
+        .about("Compute a 2D layout of the subgraphs of small regions of a reference path (table of node end positions, drawn as SVG in the report)")
+        .args(&[
+            arg!(gfa_file: <GFA_FILE> "graph in GFA1 format, accepts also compressed (.gz) file"),
+            arg!(regions: <BED_FILE> "Regions on a reference path (3-column BED file) whose subgraphs are laid out"),
+            arg!(-s --subset <FILE> "Produce counts by subsetting the graph to a given list of paths (1-column list) or path coordinates (3- or 12-column BED file)"),
+            arg!(-e --exclude <FILE> "Exclude bp/node/edge in growth count that intersect with paths (1-column list) or path coordinates (3- or 12-column BED-file) provided by the given file; all intersecting bp/node/edge will be exluded also in other paths not part of the given list"),
+            arg!(-g --groupby <FILE> "Merge counts from paths by path-group mapping from given tab-separated two-column file"),
+            arg!(-H --"groupby-haplotype" "Merge counts from paths belonging to same haplotype"),
+            arg!(-S --"groupby-sample" "Merge counts from paths belonging to same sample"),
+            Arg::new("iterations").help("Rounds of the path-guided stochastic gradient descent").short('i').long("iterations").default_value("30").value_parser(clap::value_parser!(usize)),
+        ])
+}
+
+pub fn get_instructions(args: &ArgMatches) -> Option<Result<Vec<FileRun>, anyhow::Error>> {
+    if let Some(args) = args.subcommand_matches("subgraph-layout") {
+        let graph = args
+            .get_one::<String>("gfa_file")
+            .expect("subgraph-layout subcommand has gfa file")
+            .to_owned();
+        let regions = args
+            .get_one::<String>("regions")
+            .expect("subgraph-layout subcommand has regions file")
+            .to_owned();
+        let iterations = *args
+            .get_one::<usize>("iterations")
+            .expect("subgraph-layout subcommand has iterations");
+        let subset = args
+            .get_one::<String>("subset")
+            .cloned()
+            .unwrap_or_default();
+        let exclude = args
+            .get_one::<String>("exclude")
+            .cloned()
+            .unwrap_or_default();
+        let grouping = args.get_one::<String>("groupby").cloned();
+        let grouping = if args.get_flag("groupby-sample") {
+            Some(Grouping::Sample)
+        } else if args.get_flag("groupby-haplotype") {
+            Some(Grouping::Haplotype)
+        } else {
+            grouping.map(Grouping::Custom)
+        };
+        let parameters = vec![FileRun::Gfa {
+            name: None,
+            graph,
+            subset,
+            exclude,
+            grouping,
+            reference: None,
+            nice: false,
+            count_type: crate::util::CountType::Node,
+            analyses: vec![AnalysisParameter::SubgraphLayout {
+                regions,
+                iterations,
+                display: DisplayOptions::default(),
+            }],
+        }];
+        log::info!("{parameters:?}");
+        Some(Ok(parameters))
+    } else {
+        None
+    }
+}
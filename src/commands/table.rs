@@ -3,6 +3,7 @@ use clap::{arg, Arg, ArgMatches, Command};
 use strum::VariantNames;
 
 use crate::analysis_parameter::{AnalysisParameter, FileRun, Grouping};
+use crate::html_report::DisplayOptions;
 use crate::util::CountType;
 
 pub fn get_subcommand() -> Command {
@@ -16,6 +17,7 @@ pub fn get_subcommand() -> Command {
             arg!(-H --"groupby-haplotype" "Merge counts from paths belonging to same haplotype"),
             arg!(-S --"groupby-sample" "Merge counts from paths belonging to same sample"),
             arg!(-a --"total" "Summarize by totaling presence/absence over all groups"),
+            arg!(-m --multiplicity "Report the number of times each path/group traverses a countable instead of presence/absence (copy-number aware counting)"),
             arg!(-O --order <FILE> "The ordered histogram will be produced according to order of paths/groups in the supplied file (1-column list). If this option is not used, the order is determined by the rank of paths/groups in the subset list, and if that option is not used, the order is determined by the rank of paths/groups in the GFA file."),
             Arg::new("count").help("Graph quantity to be counted").default_value("node").ignore_case(true).short('c').long("count").value_parser(clap_enum_variants_no_all!(CountType)),
         ])
@@ -28,6 +30,7 @@ pub fn get_instructions(args: &ArgMatches) -> Option<anyhow::Result<Vec<FileRun>
             .expect("hist subcommand has count type")
             .to_owned();
         let total = args.get_flag("total");
+        let multiplicity = args.get_flag("multiplicity");
         let order = args.get_one::<String>("order").cloned();
         let graph = args
             .get_one::<String>("gfa_file")
@@ -50,6 +53,7 @@ pub fn get_instructions(args: &ArgMatches) -> Option<anyhow::Result<Vec<FileRun>
             grouping.map(|g| Grouping::Custom(g))
         };
         let parameters = vec![FileRun::Gfa {
+            name: None,
             graph,
             subset,
             exclude,
@@ -57,7 +61,12 @@ pub fn get_instructions(args: &ArgMatches) -> Option<anyhow::Result<Vec<FileRun>
             reference: None,
             nice: false,
             count_type: count,
-            analyses: vec![AnalysisParameter::Table { total, order }],
+            analyses: vec![AnalysisParameter::Table {
+                total,
+                order,
+                multiplicity,
+                display: DisplayOptions::default(),
+            }],
         }];
         log::info!("{parameters:?}");
         Some(Ok(parameters))
@@ -21,6 +21,9 @@ pub struct CoverageMatrix {
     run_id: String,
     run_name: String,
     file_info: FileInfo,
+    reference_anchors: Vec<ReferenceAnchor>,
+    feature_sizes: Vec<usize>,
+    graph_file: Option<String>,
 }
 
 impl CoverageMatrix {
@@ -42,6 +45,9 @@ impl CoverageMatrix {
             run_id,
             run_name,
             file_info,
+            reference_anchors: Vec::new(),
+            feature_sizes: Vec::new(),
+            graph_file: None,
         }
     }
 
@@ -53,6 +59,23 @@ impl CoverageMatrix {
         self.file_info = file_info;
     }
 
+    /// Sets the length in bp of each feature (e.g. the node length when
+    /// counting nodes or the allele length when counting alleles). In
+    /// contrast to the feature lengths, these do not depend on the count
+    /// type and are empty if there is no meaningful length (e.g. for edges).
+    pub fn set_feature_sizes(&mut self, feature_sizes: Vec<usize>) {
+        self.feature_sizes = feature_sizes;
+    }
+
+    /// Sets the GFA file the matrix was computed from
+    pub fn set_graph_file(&mut self, graph_file: String) {
+        self.graph_file = Some(graph_file);
+    }
+
+    pub fn set_reference_anchors(&mut self, reference_anchors: Vec<ReferenceAnchor>) {
+        self.reference_anchors = reference_anchors;
+    }
+
     /// Calculates the histogram from the matrix. This should
     /// only be used if the CoverageMatrix is needed anyways.
     /// If it isn't needed generate the hist directly from the
@@ -186,7 +209,7 @@ impl CoverageMatrix {
         feature_lengths: Vec<usize>,
         feature_positions: Positions,
         feature_names: Vec<String>,
-        item_table: ItemTable,
+        item_table: &ItemTable,
     ) {
         self.path_names = path_names;
         self.feature_lengths = feature_lengths;
@@ -240,6 +263,10 @@ impl CoverageMatrix {
         self.matrix.get_csr()
     }
 
+    pub fn get_csr_values(&self) -> Vec<usize> {
+        self.matrix.get_csr_values()
+    }
+
     pub fn get_abacus_and_used_features_with_coverage(
         &self,
         features: Option<&Vec<usize>>,
@@ -360,6 +387,35 @@ impl CoverageMatrix {
             .collect()
     }
 
+    /// Returns how often the feature is traversed by each path/group (in the
+    /// given order), i.e. in contrast to `get_counts_for_feature_in_order`
+    /// repeated appearances in the same path/group are not counted as one
+    pub fn get_multiplicities_for_feature_in_order(
+        &self,
+        id: usize,
+        order: &[String],
+    ) -> Vec<usize> {
+        let translation_table: HashMap<&String, usize> = self
+            .path_names
+            .iter()
+            .enumerate()
+            .map(|(idx, path_name)| (path_name, idx))
+            .collect();
+        order
+            .iter()
+            .map(|path_name| {
+                let translated_idx = translation_table[path_name];
+                self.matrix.get_value(id, translated_idx as u64) as usize
+            })
+            .collect()
+    }
+
+    /// Returns the total number of traversals of the feature over all
+    /// paths/groups
+    pub fn get_multiplicity_of_feature(&self, feature: usize) -> usize {
+        self.matrix.get_feature_count(feature) as usize
+    }
+
     pub fn get_path_names(&self) -> &Vec<String> {
         &self.path_names
     }
@@ -380,9 +436,35 @@ impl CoverageMatrix {
         &self.run_name
     }
 
+    /// The GFA file the matrix was computed from, if any
+    pub fn get_graph_file(&self) -> Option<&str> {
+        self.graph_file.as_deref()
+    }
+
     pub fn get_file_info(&self) -> &FileInfo {
         &self.file_info
     }
+
+    pub fn get_feature_sizes(&self) -> &Vec<usize> {
+        &self.feature_sizes
+    }
+
+    pub fn get_reference_anchors(&self) -> &Vec<ReferenceAnchor> {
+        &self.reference_anchors
+    }
+}
+
+/// A reference node together with all non-reference nodes for which it is
+/// the closest reference node (see `regional_helpers::get_close_nodes`).
+/// Nodes are stored by name and bp length, since the feature ids of the
+/// matrix depend on the count type and on excluded features.
+#[derive(Debug, Clone)]
+pub struct ReferenceAnchor {
+    pub reference: String,
+    pub start: usize,
+    pub end: usize,
+    pub anchor: String,
+    pub nodes: Vec<(String, usize)>,
 }
 
 #[derive(Debug)]
@@ -0,0 +1,64 @@
+use thiserror::Error;
+
+/// Errors of the input parsers and analyses. Errors of external crates
+/// that have no dedicated variant are wrapped into `Other`.
+#[derive(Error, Debug)]
+pub enum PanacusError {
+    #[error("could not read file {file}: {source}")]
+    FileRead {
+        file: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("error in {file}, line {line}: {msg}")]
+    Parse {
+        file: String,
+        line: usize,
+        msg: String,
+    },
+    #[error("path {path} is not part of graph {file}")]
+    PathNotFound { path: String, file: String },
+    #[error("path {path} is not assigned to any group")]
+    GroupNotFound { path: String },
+    #[error("analysis {analysis} failed for {run}: {msg}")]
+    Analysis {
+        analysis: String,
+        run: String,
+        msg: String,
+    },
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+impl PanacusError {
+    pub fn parse(file: &str, line: usize, msg: impl Into<String>) -> Self {
+        Self::Parse {
+            file: file.to_owned(),
+            line,
+            msg: msg.into(),
+        }
+    }
+
+    pub fn file_read(file: &str, source: std::io::Error) -> Self {
+        Self::FileRead {
+            file: file.to_owned(),
+            source,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_error_contains_file_and_line() {
+        let error = PanacusError::parse("regions.bed", 3, "`x` is not an usize");
+        assert_eq!(
+            error.to_string(),
+            "error in regions.bed, line 3: `x` is not an usize"
+        );
+    }
+}
@@ -1,9 +1,9 @@
-use crate::{coverage_matrix::CoverageMatrix, hist::Hist};
+use crate::{coverage_matrix::CoverageMatrix, error::PanacusError, hist::Hist};
 
 pub mod gfa_parser;
 pub mod vcf_parser;
 
 pub trait FileFormatParser {
-    fn generate_hist(self: Box<Self>) -> Hist;
-    fn generate_matrix(self: Box<Self>) -> CoverageMatrix;
+    fn generate_hist(self: Box<Self>) -> Result<Hist, PanacusError>;
+    fn generate_matrix(self: Box<Self>) -> Result<CoverageMatrix, PanacusError>;
 }
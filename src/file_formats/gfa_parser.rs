@@ -1,11 +1,20 @@
-use anyhow::Error;
 use itertools::Itertools;
 
 use crate::{
-    analyses::info::FileInfo,
-    coverage_matrix::{CoverageMatrix, Positions},
+    analyses::{
+        info::{FileInfo, PathStats},
+        regional_helpers::get_close_nodes,
+    },
+    coverage_matrix::{CoverageMatrix, Positions, ReferenceAnchor},
+    error::PanacusError,
     file_formats::{
-        gfa_parser::{grammar::Grammar, graph::GraphStorage, util::parse_gfa_paths_walks},
+        gfa_parser::{
+            bubbles::{parse_gfa_bubble_traversals, BubbleIndex},
+            grammar::Grammar,
+            graph::GraphStorage,
+            path_tables::PathTables,
+            util::{parse_gfa_paths_walks, PathLengths},
+        },
         FileFormatParser,
     },
     hist::Hist,
@@ -16,19 +25,25 @@ use crate::{
     },
 };
 
-use std::collections::{HashSet, VecDeque};
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::sync::Arc;
 use std::{collections::HashMap, str};
 
 pub use abacus::AbacusByTotal;
 
-use crate::io::bufreader_from_compressed_gfa;
+use crate::profile;
 use crate::util::{ActiveTable, IntervalContainer, ItemTable};
 
 mod abacus;
+mod bubbles;
 mod grammar;
 mod graph;
 mod hist;
+mod low_memory;
+mod path_tables;
 mod sparse_matrix;
+mod subgraph;
 mod util;
 mod walk_splitter;
 
@@ -41,7 +56,42 @@ pub use graph::PathSegment;
 pub use hist::choose;
 pub use hist::Hist3D;
 pub use hist::ThresholdContainer;
+pub use low_memory::{CompactGraph, LowMemoryGfaParser};
 pub use sparse_matrix::SparseMatrix;
+pub use subgraph::{Subgraph, Traversal};
+
+/// Item table, path (or group) names, feature lengths and names, path and
+/// group lengths, feature positions, reference anchors, feature sizes and
+/// path statistics, ready to be inserted into a coverage matrix
+type CleanedItemTable<'a> = (
+    Cow<'a, ItemTable>,
+    Vec<String>,
+    Vec<usize>,
+    Vec<String>,
+    PathLengths,
+    HashMap<String, f64>,
+    Positions,
+    Vec<ReferenceAnchor>,
+    Vec<usize>,
+    Vec<PathStats>,
+);
+
+/// Abacus, number of nodes and bps of each path and the collected paths
+pub type AbacusAndPaths = (
+    AbacusByTotal,
+    PathLengths,
+    HashMap<PathSegment, Vec<(ItemId, Orientation)>>,
+);
+
+/// Path tables of a run that are based on the item table shared between
+/// runs (see `GfaParser::get_shared_item_table`)
+struct SharedItemTable<'a> {
+    item_table: &'a ItemTable,
+    exclude_table: Option<ActiveTable>,
+    subset_covered_bps: Option<IntervalContainer>,
+    paths_len: PathLengths,
+    collected_paths: HashMap<PathSegment, Vec<(ItemId, Orientation)>>,
+}
 
 pub struct GfaParser {
     // Inputs
@@ -51,13 +101,49 @@ pub struct GfaParser {
     reference: Option<String>,
 
     // Generated
-    graph_storage: GraphStorage,
+    graph_storage: Arc<GraphStorage>,
     graph_mask: GraphMask,
     grammar: Grammar,
+    // Item tables shared with other runs on the same graph
+    path_tables: Option<Arc<PathTables>>,
+}
+
+/// A graph that is parsed once and shared between all runs on the same GFA
+/// file (see `GfaParser::from_shared`)
+pub struct SharedGraph {
+    filename: String,
+    is_nice: bool,
+    graph_storage: Arc<GraphStorage>,
+    has_meta_node: bool,
+}
+
+impl SharedGraph {
+    pub fn from_gfa(filename: &str, is_nice: bool) -> Result<Self, PanacusError> {
+        let (graph_storage, has_meta_node) = GraphStorage::from_gfa(filename, is_nice)?;
+        Ok(Self {
+            filename: filename.to_owned(),
+            is_nice,
+            graph_storage: Arc::new(graph_storage),
+            has_meta_node,
+        })
+    }
+
+    pub fn get_filename(&self) -> &str {
+        &self.filename
+    }
+
+    pub fn get_path_segments(&self) -> &[PathSegment] {
+        &self.graph_storage.path_segments
+    }
 }
 
 impl FileFormatParser for GfaParser {
-    fn generate_hist(self: Box<Self>) -> Hist {
+    fn generate_hist(self: Box<Self>) -> Result<Hist, PanacusError> {
+        if self.is_bubble_based() {
+            // Bubbles and alleles are only known after parsing the paths,
+            // so there is no abacus for them
+            return Ok(self.generate_matrix()?.get_hist());
+        }
         let number_of_groups = self.graph_mask.count_groups();
         let mut hist = Hist::from_maximum_coverage(
             number_of_groups,
@@ -65,7 +151,7 @@ impl FileFormatParser for GfaParser {
             self.get_run_id(),
             self.get_run_name(),
         );
-        let (abacus, _, _) = self.get_abacus_by_total();
+        let (abacus, _, _) = self.get_abacus_by_total()?;
         // Idx should be the id inside ItemId
         for (idx, feature_coverage) in abacus.countable.iter().enumerate().skip(1) {
             // If we look at nodes or basepairs, make sure this isn't a meta-node
@@ -86,10 +172,10 @@ impl FileFormatParser for GfaParser {
         }
         let call_count = self.graph_storage.get_call_count();
         log::info!("Called get_node_id {} times", call_count);
-        hist
+        Ok(hist)
     }
 
-    fn generate_matrix(self: Box<Self>) -> CoverageMatrix {
+    fn generate_matrix(self: Box<Self>) -> Result<CoverageMatrix, PanacusError> {
         let paths_to_collect = match self.reference.as_ref() {
             Some(r) => vec![PathSegment::from_str(r)],
             None => vec![],
@@ -102,16 +188,21 @@ impl FileFormatParser for GfaParser {
             path_lengths,
             group_lengths,
             mut positions,
-        ) = self
-            .get_cleaned_item_table(
+            reference_anchors,
+            feature_sizes,
+            path_stats,
+        ) = if self.is_bubble_based() {
+            self.get_bubble_item_table(&self.graph_mask, self.count_type, &paths_to_collect)
+        } else {
+            self.get_cleaned_item_table(
                 &self.graph_mask,
                 &self.graph_storage,
                 self.count_type,
                 &paths_to_collect,
             )
-            .expect("Can parse GFA file");
+        }?;
         positions.cleanup();
-        let file_info = self.get_file_info_value(path_lengths, group_lengths);
+        let file_info = self.get_file_info_value(path_lengths, group_lengths, path_stats);
         let mut matrix = CoverageMatrix::new(
             self.count_type.to_string(),
             self.get_run_id(),
@@ -123,99 +214,237 @@ impl FileFormatParser for GfaParser {
             feature_lengths,
             positions,
             feature_names,
-            item_table,
+            &item_table,
         );
-        matrix
+        matrix.set_reference_anchors(reference_anchors);
+        matrix.set_feature_sizes(feature_sizes);
+        matrix.set_graph_file(self.filename.clone());
+        Ok(matrix)
     }
 }
 
-fn get_close_nodes(
-    ref_nodes: &Vec<(ItemId, Orientation)>,
-    neighbors: &HashMap<(ItemId, Orientation), HashSet<ItemId>>,
-) -> HashMap<(ItemId, Orientation), Vec<ItemId>> {
-    let mut queue: VecDeque<ItemId> = VecDeque::new();
-    let mut closest_ref: HashMap<ItemId, (ItemId, Orientation)> = HashMap::new();
-    let ref_node_set: HashSet<ItemId> = ref_nodes.iter().map(|(x, _)| x).copied().collect();
-    for (ref_node, orientation) in ref_nodes {
-        closest_ref.insert(*ref_node, (*ref_node, *orientation));
-        for neighbor in neighbors
-            .get(&(*ref_node, *orientation))
-            .unwrap_or(&HashSet::new())
-        {
-            if !ref_node_set.contains(neighbor) && !closest_ref.contains_key(neighbor) {
-                closest_ref.insert(*neighbor, (*ref_node, *orientation));
-                queue.push_front(*neighbor);
+/// Makes sure that the reference path (if given) is part of the graph
+fn check_reference(
+    filename: &str,
+    graph_storage: &GraphStorage,
+    reference: &Option<String>,
+) -> Result<(), PanacusError> {
+    let Some(reference) = reference else {
+        return Ok(());
+    };
+    let reference_segment = PathSegment::from_str(reference).clear_coords();
+    if graph_storage
+        .path_segments
+        .iter()
+        .any(|p| p.clear_coords().is_part_of(&reference_segment))
+    {
+        Ok(())
+    } else {
+        Err(PanacusError::PathNotFound {
+            path: reference.to_owned(),
+            file: filename.to_owned(),
+        })
+    }
+}
+
+fn get_group(graph_mask: &GraphMask, path: &PathSegment) -> Result<String, PanacusError> {
+    graph_mask
+        .groups
+        .get(&path.clear_coords())
+        .cloned()
+        .ok_or_else(|| PanacusError::GroupNotFound {
+            path: path.to_string(),
+        })
+}
+
+fn get_neighbors(graph_storage: &GraphStorage) -> HashMap<(ItemId, Orientation), HashSet<ItemId>> {
+    let edge2id = graph_storage.edge2id.as_ref().expect("Edges need to exist");
+    edge2id
+        .keys()
+        .flat_map(|x| [((x.0, x.1), x.2), ((x.2, x.3), x.0)])
+        .fold(HashMap::new(), |mut acc, (k, v)| {
+            acc.entry(k).or_default().insert(v);
+            acc
+        })
+}
+
+/// Returns the start position of each node of a collected reference path
+fn get_reference_node_positions(
+    reference: &PathSegment,
+    nodes: &[(ItemId, Orientation)],
+    graph_storage: &GraphStorage,
+) -> Vec<usize> {
+    let initial_start = reference.start.unwrap_or(0);
+    nodes
+        .iter()
+        .scan(initial_start, |acc, (node, _)| {
+            let length = graph_storage.node_lens[node.0 as usize] as usize;
+            let old_acc = *acc;
+            *acc += length;
+            Some(old_acc)
+        })
+        .collect()
+}
+
+fn get_reference_anchors(
+    collected_paths: &HashMap<PathSegment, Vec<(ItemId, Orientation)>>,
+    graph_storage: &GraphStorage,
+) -> Vec<ReferenceAnchor> {
+    let neighbors = get_neighbors(graph_storage);
+    let id2node: HashMap<ItemId, &str> = graph_storage
+        .node2id
+        .iter()
+        .map(|(k, v)| (*v, std::str::from_utf8(k).unwrap()))
+        .collect();
+    let mut anchors = Vec::new();
+    for (reference, nodes) in collected_paths.iter() {
+        let positions = get_reference_node_positions(reference, nodes, graph_storage);
+        let ref_node_set: HashSet<ItemId> = nodes.iter().map(|(x, _)| *x).collect();
+        let close_nodes = get_close_nodes(nodes, &neighbors);
+        // Each reference node can be visited multiple times by the reference
+        // path, the non-reference nodes are only reported at the first visit
+        let mut reported: HashSet<ItemId> = HashSet::new();
+        for (node, position) in nodes.iter().zip(positions.iter()) {
+            if !reported.insert(node.0) {
+                continue;
             }
-        }
-        for neighbor in neighbors
-            .get(&(*ref_node, orientation.flip()))
-            .unwrap_or(&HashSet::new())
-        {
-            if !ref_node_set.contains(neighbor) && !closest_ref.contains_key(neighbor) {
-                closest_ref.insert(*neighbor, (*ref_node, orientation.flip()));
-                queue.push_front(*neighbor);
+            let off_ref_nodes: Vec<(String, usize)> = [node.1, node.1.flip()]
+                .iter()
+                .filter_map(|o| close_nodes.get(&(node.0, *o)))
+                .flatten()
+                .filter(|x| !ref_node_set.contains(x))
+                .map(|x| {
+                    (
+                        id2node[x].to_string(),
+                        graph_storage.node_lens[x.0 as usize] as usize,
+                    )
+                })
+                .collect();
+            if off_ref_nodes.is_empty() {
+                continue;
             }
+            anchors.push(ReferenceAnchor {
+                reference: reference.to_string(),
+                start: *position,
+                end: *position + graph_storage.node_lens[node.0 .0 as usize] as usize,
+                anchor: id2node[&node.0].to_string(),
+                nodes: off_ref_nodes,
+            });
         }
     }
-    while let Some(current_node) = queue.pop_back() {
-        for neighbor in neighbors
-            .get(&(current_node, Orientation::Forward))
-            .unwrap_or(&HashSet::new())
-        {
-            if !closest_ref.contains_key(neighbor) {
-                let current_closest_ref = closest_ref
-                    .get(&current_node)
-                    .expect("Current is in closest ref");
-                closest_ref.insert(*neighbor, *current_closest_ref);
-                queue.push_front(*neighbor);
-            }
+    anchors
+}
+
+/// Computes the statistics of each group (the paths of a group have to be
+/// consecutive in `path_order`) from the item table of a node or bp count
+/// before it is collapsed into groups
+fn get_path_stats(
+    item_table: &ItemTable,
+    path_order: &[(ItemIdSize, &str)],
+    exclude_table: &Option<ActiveTable>,
+    collected_paths: &HashMap<PathSegment, Vec<(ItemId, Orientation)>>,
+    graph_storage: &GraphStorage,
+) -> Vec<PathStats> {
+    let is_counted = |node: usize| {
+        graph_storage.node2rule_id[node] == usize::MAX
+            && !exclude_table
+                .as_ref()
+                .map(|e| e.items[node])
+                .unwrap_or(false)
+    };
+    let get_nodes = |paths: &[usize]| {
+        paths
+            .iter()
+            .flat_map(|&path| {
+                let start = item_table.id_prefsum[path] as usize;
+                let end = item_table.id_prefsum[path + 1] as usize;
+                item_table.items[start..end].iter().map(|x| *x as usize)
+            })
+            .filter(|x| is_counted(*x))
+            .collect::<Vec<usize>>()
+    };
+
+    let mut groups: Vec<(&str, Vec<usize>)> = Vec::new();
+    for (path_id, group) in path_order {
+        match groups.last_mut() {
+            Some((g, paths)) if g == group => paths.push(*path_id as usize),
+            _ => groups.push((group, vec![*path_id as usize])),
         }
-        for neighbor in neighbors
-            .get(&(current_node, Orientation::Backward))
-            .unwrap_or(&HashSet::new())
-        {
-            if !closest_ref.contains_key(neighbor) {
-                let current_closest_ref = closest_ref
-                    .get(&current_node)
-                    .expect("Current is in closest ref");
-                closest_ref.insert(*neighbor, *current_closest_ref);
-                queue.push_front(*neighbor);
+    }
+
+    let mut is_reference = vec![false; graph_storage.node_count + 1];
+    for (node, _) in collected_paths.values().flatten() {
+        is_reference[node.0 as usize] = true;
+    }
+    let reference_bp: usize = (1..is_reference.len())
+        .filter(|&node| is_reference[node] && is_counted(node))
+        .map(|node| graph_storage.node_lens[node] as usize)
+        .sum();
+
+    // Number of groups traversing each node
+    let mut coverage = vec![0; graph_storage.node_count + 1];
+    let mut last_group = vec![usize::MAX; graph_storage.node_count + 1];
+    for (group_idx, (_, paths)) in groups.iter().enumerate() {
+        for node in get_nodes(paths) {
+            if last_group[node] != group_idx {
+                last_group[node] = group_idx;
+                coverage[node] += 1;
             }
         }
     }
-    let mut flipped: HashMap<(ItemId, Orientation), Vec<ItemId>> = HashMap::new();
-    for (entry_node, ref_node) in closest_ref {
-        flipped.entry(ref_node).or_default().push(entry_node);
-    }
-    flipped
+
+    let mut last_group = vec![usize::MAX; graph_storage.node_count + 1];
+    groups
+        .iter()
+        .enumerate()
+        .map(|(group_idx, (group, paths))| {
+            let mut stats = PathStats {
+                group: group.to_string(),
+                nodes: 0,
+                bp: 0,
+                unique_nodes: 0,
+                segments: paths.len(),
+                n_bp: 0,
+                private_bp: 0,
+                reference_coverage: None,
+            };
+            let mut covered_reference_bp = 0;
+            for node in get_nodes(paths) {
+                let len = graph_storage.node_lens[node] as usize;
+                stats.nodes += 1;
+                stats.bp += len;
+                if graph_storage.n_nodes.contains(&ItemId(node as ItemIdSize)) {
+                    stats.n_bp += len;
+                }
+                if last_group[node] == group_idx {
+                    continue;
+                }
+                last_group[node] = group_idx;
+                stats.unique_nodes += 1;
+                if coverage[node] == 1 {
+                    stats.private_bp += len;
+                }
+                if is_reference[node] {
+                    covered_reference_bp += len;
+                }
+            }
+            if reference_bp > 0 {
+                stats.reference_coverage = Some(covered_reference_bp as f64 / reference_bp as f64);
+            }
+            stats
+        })
+        .collect()
 }
 
 fn get_positions_nodes(
     collected_paths: &HashMap<PathSegment, Vec<(ItemId, Orientation)>>,
     graph_storage: &GraphStorage,
 ) -> Positions {
-    let edge2id = graph_storage.edge2id.as_ref().expect("Edges need to exist");
-
-    let neighbors: HashMap<(ItemId, Orientation), HashSet<ItemId>> = edge2id
-        .keys()
-        .flat_map(|x| [((x.0, x.1), x.2), ((x.2, x.3), x.0)])
-        .fold(HashMap::new(), |mut acc, (k, v)| {
-            acc.entry(k).or_default().insert(v);
-            acc
-        });
+    let neighbors = get_neighbors(graph_storage);
     let mut all_positions = Positions::with_size(graph_storage.node_count);
     for (reference, nodes) in collected_paths.iter() {
-        let initial_start = reference.start.unwrap_or(0);
         // Contains the position of each reference node
-        let positions: Vec<usize> = nodes
-            .iter()
-            .scan(initial_start, |acc, (node, _)| {
-                let length = graph_storage.node_lens[node.0 as usize] as usize;
-                let old_acc = *acc;
-                *acc += length;
-                Some(old_acc)
-            })
-            .collect();
+        let positions = get_reference_node_positions(reference, nodes, graph_storage);
         let close_nodes = get_close_nodes(nodes, &neighbors);
         for (node, position) in nodes.iter().zip(positions.iter()) {
             all_positions.set(
@@ -258,11 +487,37 @@ fn get_positions(
     count_type: CountType,
     collected_paths: &HashMap<PathSegment, Vec<(ItemId, Orientation)>>,
     graph_storage: &GraphStorage,
-) -> Positions {
+) -> Result<Positions, PanacusError> {
     match count_type {
-        CountType::Node | CountType::Bp => get_positions_nodes(collected_paths, graph_storage),
-        CountType::Edge => get_positions_edges(collected_paths, graph_storage),
+        CountType::Node | CountType::Bp => Ok(get_positions_nodes(collected_paths, graph_storage)),
+        CountType::Edge => Ok(get_positions_edges(collected_paths, graph_storage)),
+        CountType::Bubble | CountType::Allele => {
+            Err(anyhow::anyhow!("positions of {}s are not computed", count_type).into())
+        }
+    }
+}
+
+/// Bubble and allele counts only support subsets of whole paths
+fn check_bubble_mask(
+    count_type: CountType,
+    graph_mask_parameters: &GraphMaskParameters,
+    graph_mask: &GraphMask,
+) -> Result<(), PanacusError> {
+    if matches!(count_type, CountType::Bubble | CountType::Allele)
+        && (!graph_mask_parameters.negative_list.is_empty()
+            || graph_mask
+                .include_coords
+                .iter()
+                .flatten()
+                .any(|p| p.coords().is_some()))
+    {
+        return Err(anyhow::anyhow!(
+            "{} counts do not support excluded paths and subset coordinates, only subsets of whole paths",
+            count_type
+        )
+        .into());
     }
+    Ok(())
 }
 
 impl GfaParser {
@@ -272,29 +527,172 @@ impl GfaParser {
         graph_mask_parameters: GraphMaskParameters,
         reference: Option<String>,
         is_nice: bool,
-    ) -> Result<Self, Error> {
+    ) -> Result<Self, PanacusError> {
         let mut grammar = Grammar::from(count_type);
-        let (mut graph_storage, has_meta_node) = GraphStorage::from_gfa(filename, is_nice);
+        let (mut graph_storage, has_meta_node) = GraphStorage::from_gfa(filename, is_nice)?;
         if has_meta_node {
-            grammar.parse_gfa_parallel(filename, &mut graph_storage);
+            grammar.parse_gfa_parallel(filename, &mut graph_storage)?;
             log::info!("found {} rules", grammar.len());
         }
-        let graph_mask = GraphMask::from_datamgr(&graph_mask_parameters, &graph_storage)?;
+        check_reference(filename, &graph_storage, &reference)?;
+        let graph_mask = profile::stage("masking", || {
+            GraphMask::from_datamgr(&graph_mask_parameters, &graph_storage)
+        })?;
+        check_bubble_mask(count_type, &graph_mask_parameters, &graph_mask)?;
         Ok(Self {
             filename: filename.to_owned(),
             count_type,
             graph_mask_parameters,
             reference,
-            graph_storage,
+            graph_storage: Arc::new(graph_storage),
             graph_mask,
             grammar,
+            path_tables: None,
+        })
+    }
+
+    /// Creates a parser on an already parsed graph. Graphs with meta nodes
+    /// cannot be shared, as their grammar depends on the count type.
+    pub fn from_shared(
+        shared: &SharedGraph,
+        count_type: CountType,
+        graph_mask_parameters: GraphMaskParameters,
+        reference: Option<String>,
+    ) -> Result<Self, PanacusError> {
+        if shared.has_meta_node {
+            return Self::new(
+                &shared.filename,
+                count_type,
+                graph_mask_parameters,
+                reference,
+                shared.is_nice,
+            );
+        }
+        check_reference(&shared.filename, &shared.graph_storage, &reference)?;
+        let graph_mask = profile::stage("masking", || {
+            GraphMask::from_datamgr(&graph_mask_parameters, &shared.graph_storage)
+        })?;
+        check_bubble_mask(count_type, &graph_mask_parameters, &graph_mask)?;
+        Ok(Self {
+            filename: shared.filename.clone(),
+            count_type,
+            graph_mask_parameters,
+            reference,
+            graph_storage: Arc::clone(&shared.graph_storage),
+            graph_mask,
+            grammar: Grammar::from(count_type),
+            path_tables: None,
         })
     }
 
+    /// Names of all groups of the graph (after grouping), sorted
+    pub fn get_group_names(&self) -> Vec<String> {
+        self.graph_mask
+            .groups
+            .values()
+            .cloned()
+            .sorted()
+            .dedup()
+            .collect()
+    }
+
+    /// Computes the item tables of all parsers that work on the same shared
+    /// graph in a single pass over the paths/walks. Parsers using path
+    /// coordinates or exclusions keep parsing the paths on their own.
+    pub fn share_path_tables(parsers: &mut [&mut GfaParser]) -> Result<(), PanacusError> {
+        let mut sharing: Vec<&mut &mut GfaParser> = parsers
+            .iter_mut()
+            .filter(|p| p.can_share_path_tables())
+            .collect();
+        if sharing.len() < 2
+            || sharing
+                .iter()
+                .any(|p| !Arc::ptr_eq(&p.graph_storage, &sharing[0].graph_storage))
+        {
+            return Ok(());
+        }
+        let count_types: Vec<CountType> = sharing.iter().map(|p| p.count_type).unique().collect();
+        let paths_to_collect: Vec<PathSegment> = sharing
+            .iter()
+            .filter_map(|p| p.reference.as_ref())
+            .unique()
+            .map(|r| PathSegment::from_str(r))
+            .collect();
+        let path_tables = profile::stage("path parsing", || {
+            PathTables::from_gfa(
+                &sharing[0].filename,
+                &sharing[0].graph_storage,
+                &sharing[0].grammar,
+                &count_types,
+                &paths_to_collect,
+            )
+        })
+        .map(Arc::new)?;
+        for parser in sharing.iter_mut() {
+            parser.path_tables = Some(Arc::clone(&path_tables));
+        }
+        Ok(())
+    }
+
+    fn can_share_path_tables(&self) -> bool {
+        !self.is_bubble_based()
+            && self.graph_mask.exclude_coords.is_none()
+            && self
+                .graph_mask
+                .include_coords
+                .iter()
+                .flatten()
+                .all(|p| p.coords().is_none())
+    }
+
+    /// Returns the same as `parse_gfa_paths_walks` would, but based on the
+    /// shared path tables (if there are any). The item table is borrowed from
+    /// the shared tables instead of being copied for each run.
+    fn get_shared_item_table(
+        &self,
+        count: CountType,
+        paths_to_collect: &[PathSegment],
+    ) -> Result<Option<SharedItemTable<'_>>, PanacusError> {
+        let Some(path_tables) = self.path_tables.as_ref() else {
+            return Ok(None);
+        };
+        let Some(item_table) = path_tables.get_item_table(count) else {
+            return Ok(None);
+        };
+        log::info!("using shared {} item table", count);
+        let (subset_covered_bps, exclude_table, include_map, _) = self
+            .graph_mask
+            .load_optional_subsetting(&self.graph_storage, &count)?;
+        // Paths that are not part of the subset are skipped
+        let is_included = |p: &PathSegment| {
+            self.graph_mask.include_coords.is_none() || include_map.contains_key(&p.id())
+        };
+        let paths_len = path_tables
+            .paths_len
+            .iter()
+            .filter(|(p, _)| is_included(p))
+            .map(|(p, l)| (p.clone(), *l))
+            .collect();
+        let collected_paths = path_tables
+            .collected_paths
+            .iter()
+            .filter(|(p, _)| is_included(p) && paths_to_collect.iter().any(|r| p.is_part_of(r)))
+            .map(|(p, path)| (p.clone(), path.clone()))
+            .collect();
+        Ok(Some(SharedItemTable {
+            item_table,
+            exclude_table,
+            subset_covered_bps,
+            paths_len,
+            collected_paths,
+        }))
+    }
+
     fn get_file_info_value(
         &self,
         paths_len: HashMap<PathSegment, (u32, u32)>,
         group_lens: HashMap<String, f64>,
+        path_stats: Vec<PathStats>,
     ) -> FileInfo {
         // Created here to satisfy the borrow checker
         let empty_degree = vec![0, 0];
@@ -316,7 +714,8 @@ impl GfaParser {
         let mut group_lens: Vec<(String, f64)> = group_lens.into_iter().collect();
         group_lens.sort_by(|a, b| (b.1).partial_cmp(&a.1).unwrap());
         let table = group_lens.clone();
-        let (group_names, group_bp_lens): (Vec<String>, Vec<f64>) = group_lens.into_iter().unzip();
+        let (group_names, group_bp_lens): (Vec<String>, Vec<f64>) =
+            group_lens.iter().cloned().unzip();
 
         // group_count: gb.get_group_count(),
         let mut file_info = FileInfo::new("gfa");
@@ -440,18 +839,17 @@ impl GfaParser {
             (averageu32(&paths_bp_len)).to_string().as_str(),
         );
 
-        file_info.add_info(
-            "Longest group length in bps",
-            &format!("{} ({})", group_bp_lens[0], group_names[0]),
-        );
-        file_info.add_info(
-            "Shortest group length in bps",
-            &format!(
-                "{} ({})",
-                group_bp_lens[group_bp_lens.len() - 1],
-                group_names[group_names.len() - 1]
-            ),
-        );
+        // Path lengths are not known for all count types
+        if let (Some(longest), Some(shortest)) = (group_lens.first(), group_lens.last()) {
+            file_info.add_info(
+                "Longest group length in bps",
+                &format!("{} ({})", longest.1, longest.0),
+            );
+            file_info.add_info(
+                "Shortest group length in bps",
+                &format!("{} ({})", shortest.1, shortest.0),
+            );
+        }
         file_info.add_info(
             "Average group length in bps",
             (averagef64(&group_bp_lens).round() as u64)
@@ -471,10 +869,15 @@ impl GfaParser {
         file_info.add_plot(paths_plot);
 
         file_info.add_table("group-lengths".to_string(), table);
+        file_info.set_path_stats(path_stats);
 
         file_info
     }
 
+    fn is_bubble_based(&self) -> bool {
+        matches!(self.count_type, CountType::Bubble | CountType::Allele)
+    }
+
     fn get_run_name(&self) -> String {
         format!(
             "{} {} {}",
@@ -493,23 +896,27 @@ impl GfaParser {
         )
     }
 
-    pub fn get_abacus_by_total(
-        &self,
-    ) -> (
-        AbacusByTotal,
-        HashMap<PathSegment, (u32, u32)>,
-        HashMap<PathSegment, Vec<(ItemId, Orientation)>>,
-    ) {
-        let mut data = bufreader_from_compressed_gfa(&self.filename);
+    pub fn get_abacus_by_total(&self) -> Result<AbacusAndPaths, PanacusError> {
+        if let Some(shared) = self.get_shared_item_table(self.count_type, &[])? {
+            let abacus = AbacusByTotal::from_item_table(
+                &self.graph_mask,
+                &self.graph_storage,
+                self.count_type,
+                shared.item_table,
+                shared.exclude_table,
+                shared.subset_covered_bps,
+            )?;
+            return Ok((abacus, shared.paths_len, HashMap::new()));
+        }
         let (abacus, path_lens) = AbacusByTotal::from_gfa(
-            &mut data,
+            &self.filename,
             &self.graph_mask,
             &self.graph_storage,
             &self.grammar,
             self.count_type,
-        );
+        )?;
         let collected_paths = HashMap::new();
-        (abacus, path_lens, collected_paths)
+        Ok((abacus, path_lens, collected_paths))
     }
 
     fn get_cleaned_item_table(
@@ -517,32 +924,46 @@ impl GfaParser {
         graph_mask: &GraphMask,
         graph_storage: &GraphStorage,
         count: CountType,
-        paths_to_collect: &Vec<PathSegment>,
-    ) -> anyhow::Result<(
-        ItemTable,
-        Vec<String>,
-        Vec<usize>,
-        Vec<String>,
-        HashMap<PathSegment, (u32, u32)>,
-        HashMap<String, f64>,
-        Positions,
-    )> {
-        log::info!("parsing path + walk sequences");
-        let mut data = bufreader_from_compressed_gfa(&self.filename);
-        let (item_table, exclude_table, subset_covered_bps, paths_len, collected_paths) =
-            parse_gfa_paths_walks(
-                &mut data,
-                graph_mask,
-                graph_storage,
-                &self.grammar,
-                &count,
-                paths_to_collect,
-            );
+        paths_to_collect: &[PathSegment],
+    ) -> anyhow::Result<CleanedItemTable<'_>> {
+        let (item_table, exclude_table, subset_covered_bps, paths_len, collected_paths) = match self
+            .get_shared_item_table(count, paths_to_collect)?
+        {
+            Some(shared) => (
+                Cow::Borrowed(shared.item_table),
+                shared.exclude_table,
+                shared.subset_covered_bps,
+                shared.paths_len,
+                shared.collected_paths,
+            ),
+            None => {
+                log::info!("parsing path + walk sequences");
+                let (item_table, exclude_table, subset_covered_bps, paths_len, collected_paths) =
+                    profile::stage("path parsing", || {
+                        parse_gfa_paths_walks(
+                            &self.filename,
+                            graph_mask,
+                            graph_storage,
+                            &self.grammar,
+                            &count,
+                            paths_to_collect,
+                        )
+                    })?;
+                (
+                    Cow::Owned(item_table),
+                    exclude_table,
+                    subset_covered_bps,
+                    paths_len,
+                    collected_paths,
+                )
+            }
+        };
 
         let mut path_order: Vec<(ItemIdSize, GroupSize)> = Vec::new();
         let mut groups: Vec<String> = Vec::new();
+        let path_group_order = graph_mask.get_path_order(&graph_storage.path_segments);
 
-        for (path_id, group_id) in graph_mask.get_path_order(&graph_storage.path_segments) {
+        for &(path_id, group_id) in path_group_order.iter() {
             log::debug!(
                 "processing path {} (group {})",
                 &graph_storage.path_segments[path_id as usize],
@@ -561,7 +982,23 @@ impl GfaParser {
         let mut positions = if collected_paths.is_empty() {
             Positions::with_size(graph_storage.node_count)
         } else {
-            get_positions(count, &collected_paths, graph_storage)
+            get_positions(count, &collected_paths, graph_storage)?
+        };
+        let reference_anchors = match count {
+            CountType::Node | CountType::Bp if !collected_paths.is_empty() => {
+                get_reference_anchors(&collected_paths, graph_storage)
+            }
+            _ => Vec::new(),
+        };
+        let path_stats = match count {
+            CountType::Node | CountType::Bp => get_path_stats(
+                &item_table,
+                &path_group_order,
+                &exclude_table,
+                &collected_paths,
+                graph_storage,
+            ),
+            _ => Vec::new(),
         };
 
         let path_names: Vec<String> = self
@@ -574,26 +1011,14 @@ impl GfaParser {
             .graph_storage
             .path_segments
             .iter()
-            .map(|x| {
-                let x = &x.clear_coords();
-                if let Some(name) = graph_mask.groups.get(x) {
-                    name.clone()
-                } else {
-                    panic!("Could not get {} from {:?}", x, graph_mask.groups)
-                }
-            })
-            .collect_vec();
+            .map(|x| get_group(graph_mask, x))
+            .collect::<Result<Vec<String>, PanacusError>>()?;
         let mut group_lens: HashMap<String, f64> = HashMap::new();
-        paths_len.iter().for_each(|(k, v)| {
-            let p = &k.clear_coords();
-            let g = if let Some(name) = graph_mask.groups.get(p) {
-                name.clone()
-            } else {
-                panic!("Could not get {} from {:?}", p, graph_mask.groups)
-            };
+        for (k, v) in paths_len.iter() {
+            let g = get_group(graph_mask, k)?;
             let bp_len = v.1 as f64;
             *group_lens.entry(g).or_default() += bp_len;
-        });
+        }
         let mgroup_names: HashSet<&str> = graph_mask
             .get_path_order(&self.graph_storage.path_segments)
             .into_iter()
@@ -605,7 +1030,9 @@ impl GfaParser {
         {
             (item_table, group_names)
         } else {
-            Self::collapse_item_table(item_table, group_names, mgroup_names, &exclude_table)
+            let (item_table, groups) =
+                Self::collapse_item_table(&item_table, group_names, mgroup_names, &exclude_table);
+            (Cow::Owned(item_table), groups)
         };
         let (feature_lengths, feature_names) = Self::get_feature_lengths(
             graph_storage,
@@ -613,7 +1040,19 @@ impl GfaParser {
             &subset_covered_bps,
             count,
             &mut positions,
-        );
+        )?;
+        // The length of the nodes in bp, independent of the count type (and
+        // of exclusions/subsets), edges have no length
+        let feature_sizes = match count {
+            CountType::Node | CountType::Bp => feature_names
+                .iter()
+                .map(|x| {
+                    let item_id = graph_storage.get_node_id(x.as_bytes()).unwrap();
+                    graph_storage.node_len(&item_id) as usize
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
 
         Ok((
             item_table,
@@ -623,6 +1062,64 @@ impl GfaParser {
             paths_len,
             group_lens,
             positions,
+            reference_anchors,
+            feature_sizes,
+            path_stats,
+        ))
+    }
+
+    /// Equivalent of `get_cleaned_item_table` for bubble and allele counts,
+    /// in which each feature is a bubble (or a bubble traversal) instead of
+    /// a node or edge
+    fn get_bubble_item_table(
+        &self,
+        graph_mask: &GraphMask,
+        count: CountType,
+        paths_to_collect: &[PathSegment],
+    ) -> anyhow::Result<CleanedItemTable<'_>> {
+        let bubble_index = BubbleIndex::from_graph(&self.graph_storage);
+        let (item_table, feature_names, feature_sizes, paths_len, _collected_paths) =
+            profile::stage("path parsing", || {
+                parse_gfa_bubble_traversals(
+                    &self.filename,
+                    &self.graph_storage,
+                    &self.grammar,
+                    &bubble_index,
+                    count,
+                    paths_to_collect,
+                )
+            })?;
+        let group_names: Vec<String> = self
+            .graph_storage
+            .path_segments
+            .iter()
+            .map(|x| graph_mask.groups[&x.clear_coords()].clone())
+            .collect();
+        let mut group_lens: HashMap<String, f64> = HashMap::new();
+        paths_len.iter().for_each(|(k, v)| {
+            let g = graph_mask.groups[&k.clear_coords()].clone();
+            *group_lens.entry(g).or_default() += v.1 as f64;
+        });
+        let mgroup_names: HashSet<&str> = graph_mask
+            .get_path_order(&self.graph_storage.path_segments)
+            .into_iter()
+            .map(|x| x.1)
+            .collect();
+        let (item_table, groups) =
+            Self::collapse_item_table(&item_table, group_names, mgroup_names, &None);
+        let feature_lengths = vec![1; feature_names.len()];
+        let positions = Positions::with_size(feature_names.len());
+        Ok((
+            Cow::Owned(item_table),
+            groups,
+            feature_lengths,
+            feature_names,
+            paths_len,
+            group_lens,
+            positions,
+            Vec::new(),
+            feature_sizes,
+            Vec::new(),
         ))
     }
 
@@ -632,7 +1129,7 @@ impl GfaParser {
         subset_covered_bps: &Option<IntervalContainer>,
         count_type: CountType,
         positions: &mut Positions,
-    ) -> (Vec<usize>, Vec<String>) {
+    ) -> Result<(Vec<usize>, Vec<String>), PanacusError> {
         let mut feature_lengths = match count_type {
             CountType::Node => vec![1; graph_storage.node_count],
             CountType::Edge => vec![1; graph_storage.edge_count],
@@ -642,6 +1139,13 @@ impl GfaParser {
                 .skip(1)
                 .map(|x| *x as usize)
                 .collect_vec(),
+            CountType::Bubble | CountType::Allele => {
+                return Err(anyhow::anyhow!(
+                    "lengths of {}s are computed with their item table",
+                    count_type
+                )
+                .into())
+            }
         };
         let mut feature_names = match count_type {
             CountType::Node | CountType::Bp => {
@@ -668,6 +1172,13 @@ impl GfaParser {
                     .collect();
                 names
             }
+            CountType::Bubble | CountType::Allele => {
+                return Err(anyhow::anyhow!(
+                    "names of {}s are computed with their item table",
+                    count_type
+                )
+                .into())
+            }
         };
 
         if let Some(e) = exclude_table.as_ref() {
@@ -725,11 +1236,11 @@ impl GfaParser {
             positions.apply_mask(&to_keep);
         }
 
-        (feature_lengths, feature_names)
+        Ok((feature_lengths, feature_names))
     }
 
     fn collapse_item_table(
-        item_table: ItemTable,
+        item_table: &ItemTable,
         groups: Vec<String>,
         masked_groups: HashSet<&str>,
         exclude_table: &Option<ActiveTable>,
@@ -10,13 +10,15 @@ use std::path::Path;
 use regex::Regex;
 use std::collections::{HashMap, HashSet};
 
+use crate::error::PanacusError;
 use crate::file_formats::gfa_parser::grammar::Grammar;
 /* private use */
 use crate::io::*;
+use crate::profile;
 use crate::util::*;
 
 use super::graph::{GraphStorage, PathSegment};
-use super::util::parse_gfa_paths_walks;
+use super::util::{parse_gfa_paths_walks, PathLengths};
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct GraphMaskParameters {
@@ -129,7 +131,8 @@ impl GraphMask {
         log::info!("loading coordinates from {}", file_name);
         let mut data = BufReader::new(fs::File::open(file_name)?);
         let use_block_info = true;
-        let coords = parse_bed_to_path_segments(&mut data, use_block_info);
+        let coords = parse_bed_to_path_segments(&mut data, file_name, use_block_info)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
         log::debug!("loaded {} coordinates", coords.len());
         Ok(Some(coords))
     }
@@ -314,12 +317,15 @@ impl GraphMask {
         &self,
         graph_storage: &GraphStorage,
         count: &CountType,
-    ) -> (
-        Option<IntervalContainer>,
-        Option<ActiveTable>,
-        HashMap<String, Vec<(usize, usize)>>,
-        HashMap<String, Vec<(usize, usize)>>,
-    ) {
+    ) -> Result<
+        (
+            Option<IntervalContainer>,
+            Option<ActiveTable>,
+            HashMap<String, Vec<(usize, usize)>>,
+            HashMap<String, Vec<(usize, usize)>>,
+        ),
+        PanacusError,
+    > {
         // *only relevant for bps count in combination with subset option*
         // this table stores the number of bps of nodes that are *partially* uncovered by subset
         // coordinates
@@ -331,12 +337,13 @@ impl GraphMask {
             };
 
         // this table stores information about excluded nodes *if* the exclude setting is used
-        let exclude_table = self.exclude_coords.as_ref().map(|_| {
-            ActiveTable::new(
-                graph_storage.number_of_items(count) + 1,
+        let exclude_table = match self.exclude_coords {
+            Some(_) => Some(ActiveTable::new(
+                graph_storage.number_of_items(count)? + 1,
                 count == &CountType::Bp,
-            )
-        });
+            )),
+            None => None,
+        };
 
         // build "include" lookup table
         let include_map = match &self.include_coords {
@@ -350,19 +357,22 @@ impl GraphMask {
             Some(coords) => Self::build_subpath_map(coords),
         };
 
-        (subset_covered_bps, exclude_table, include_map, exclude_map)
+        Ok((subset_covered_bps, exclude_table, include_map, exclude_map))
     }
 
     pub fn load_optional_subsetting_multiple(
         &self,
         graph_storage: &GraphStorage,
         count_types: &Vec<CountType>,
-    ) -> (
-        Option<IntervalContainer>,
-        Vec<Option<ActiveTable>>,
-        HashMap<String, Vec<(usize, usize)>>,
-        HashMap<String, Vec<(usize, usize)>>,
-    ) {
+    ) -> Result<
+        (
+            Option<IntervalContainer>,
+            Vec<Option<ActiveTable>>,
+            HashMap<String, Vec<(usize, usize)>>,
+            HashMap<String, Vec<(usize, usize)>>,
+        ),
+        PanacusError,
+    > {
         // *only relevant for bps count in combination with subset option*
         // this table stores the number of bps of nodes that are *partially* uncovered by subset
         // coordinates
@@ -376,15 +386,14 @@ impl GraphMask {
         // this table stores information about excluded nodes *if* the exclude setting is used
         let exclude_tables: Vec<_> = count_types
             .iter()
-            .map(|count| {
-                self.exclude_coords.as_ref().map(|_| {
-                    ActiveTable::new(
-                        graph_storage.number_of_items(count) + 1,
-                        count == &CountType::Bp,
-                    )
-                })
+            .map(|count| match self.exclude_coords {
+                Some(_) => Ok(Some(ActiveTable::new(
+                    graph_storage.number_of_items(count)? + 1,
+                    count == &CountType::Bp,
+                ))),
+                None => Ok(None),
             })
-            .collect();
+            .collect::<Result<_, PanacusError>>()?;
 
         // build "include" lookup table
         let include_map = match &self.include_coords {
@@ -398,7 +407,7 @@ impl GraphMask {
             Some(coords) => Self::build_subpath_map(coords),
         };
 
-        (subset_covered_bps, exclude_tables, include_map, exclude_map)
+        Ok((subset_covered_bps, exclude_tables, include_map, exclude_map))
     }
 }
 
@@ -411,40 +420,43 @@ pub struct AbacusByTotal {
 }
 
 impl AbacusByTotal {
-    pub fn from_gfa<R: std::io::Read>(
-        data: &mut BufReader<R>,
+    pub fn from_gfa(
+        gfa_file: &str,
         graph_mask: &GraphMask,
         graph_storage: &GraphStorage,
         grammar: &Grammar,
         count_type: CountType,
-    ) -> (Self, HashMap<PathSegment, (u32, u32)>) {
-        let (item_table, exclude_table, subset_covered_bps, paths_len, _) = parse_gfa_paths_walks(
-            data,
-            graph_mask,
-            graph_storage,
-            grammar,
-            &count_type,
-            &Vec::new(),
-        );
+    ) -> Result<(Self, PathLengths), PanacusError> {
+        let (item_table, exclude_table, subset_covered_bps, paths_len, _) =
+            profile::stage("path parsing", || {
+                parse_gfa_paths_walks(
+                    gfa_file,
+                    graph_mask,
+                    graph_storage,
+                    grammar,
+                    &count_type,
+                    &Vec::new(),
+                )
+            })?;
         let abacus = Self::item_table_to_abacus(
             graph_mask,
             graph_storage,
             count_type,
-            item_table,
+            &item_table,
             exclude_table,
             subset_covered_bps,
-        );
-        (abacus, paths_len)
+        )?;
+        Ok((abacus, paths_len))
     }
 
     pub fn from_item_table(
         graph_mask: &GraphMask,
         graph_storage: &GraphStorage,
         count: CountType,
-        item_table: ItemTable,
+        item_table: &ItemTable,
         exclude_table: Option<ActiveTable>,
         subset_covered_bps: Option<IntervalContainer>,
-    ) -> Self {
+    ) -> Result<Self, PanacusError> {
         Self::item_table_to_abacus(
             graph_mask,
             graph_storage,
@@ -468,17 +480,17 @@ impl AbacusByTotal {
         graph_mask: &GraphMask,
         graph_storage: &GraphStorage,
         count: CountType,
-        item_table: ItemTable,
+        item_table: &ItemTable,
         exclude_table: Option<ActiveTable>,
         subset_covered_bps: Option<IntervalContainer>,
-    ) -> Self {
+    ) -> Result<Self, PanacusError> {
         log::info!("counting abacus entries..");
+        let number_of_items = graph_storage.number_of_items(&count)?;
         // first element in countable is "zero" element. It is ignored in counting
-        let mut countable: Vec<CountSize> = vec![0; graph_storage.number_of_items(&count) + 1];
+        let mut countable: Vec<CountSize> = vec![0; number_of_items + 1];
         // countable with ID "0" is special and should not be considered in coverage histogram
         countable[0] = CountSize::MAX;
-        let mut last: Vec<ItemIdSize> =
-            vec![ItemIdSize::MAX; graph_storage.number_of_items(&count) + 1];
+        let mut last: Vec<ItemIdSize> = vec![ItemIdSize::MAX; number_of_items + 1];
 
         let mut groups = Vec::new();
         for (path_id, group_id) in graph_mask.get_path_order(&graph_storage.path_segments) {
@@ -488,7 +500,7 @@ impl AbacusByTotal {
             AbacusByTotal::coverage(
                 &mut countable,
                 &mut last,
-                &item_table,
+                item_table,
                 &exclude_table,
                 path_id,
                 groups.len() as ItemIdSize - 1,
@@ -501,7 +513,7 @@ impl AbacusByTotal {
             countable.len() - 1
         );
 
-        Self {
+        Ok(Self {
             count,
             countable,
             uncovered_bps: Some(quantify_uncovered_bps(
@@ -510,7 +522,7 @@ impl AbacusByTotal {
                 graph_storage,
             )),
             groups,
-        }
+        })
     }
 
     pub fn coverage(
@@ -0,0 +1,359 @@
+use std::collections::{HashMap, HashSet};
+
+use rayon::prelude::*;
+
+use crate::error::PanacusError;
+use crate::io::{for_each_gfa_chunk, get_lines};
+use crate::util::{CountType, ItemTable};
+
+use super::{
+    grammar::Grammar,
+    graph::GraphStorage,
+    util::{parse_path_or_walk, PathLengths},
+    ItemId, Orientation, PathSegment,
+};
+
+/// Bubbles with more interior nodes than this are not reported (this keeps
+/// the detection linear in practice, even for tangled regions)
+const MAX_BUBBLE_SIZE: usize = 1_000;
+
+type Handle = (ItemId, Orientation);
+
+/// Item table, names and sizes of the features, number of nodes and bps of
+/// each path and the collected paths
+pub type BubbleTraversals = (
+    ItemTable,
+    Vec<String>,
+    Vec<usize>,
+    PathLengths,
+    HashMap<PathSegment, Vec<Handle>>,
+);
+
+/// Identifier, steps and bubble traversals of a path/walk
+type ParsedTraversals = (PathSegment, Vec<Handle>, Vec<(usize, Vec<Handle>)>);
+
+fn flip(handle: &Handle) -> Handle {
+    (handle.0, handle.1.flip())
+}
+
+fn handle_to_string(handle: &Handle) -> String {
+    format!("{}{}", handle.1, handle.0)
+}
+
+/// A variant site, given by its (oriented) entrance and exit node
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Bubble {
+    pub start: Handle,
+    pub end: Handle,
+}
+
+impl Bubble {
+    /// Every bubble can be read in two directions, the canonical one is the
+    /// one with the smaller entrance
+    fn canonical(start: Handle, end: Handle) -> Self {
+        let (rev_start, rev_end) = (flip(&end), flip(&start));
+        if (rev_start.0, rev_start.1) < (start.0, start.1) {
+            Self {
+                start: rev_start,
+                end: rev_end,
+            }
+        } else {
+            Self { start, end }
+        }
+    }
+}
+
+/// Superbubbles of the graph, detected with the algorithm of Onodera et al.
+/// (as implemented e.g. in BubbleGun) on the bidirected graph
+#[derive(Debug, Clone)]
+pub struct BubbleIndex {
+    pub bubbles: Vec<Bubble>,
+    // Entrance handle -> (bubble id, whether the bubble is read in reverse)
+    entrances: HashMap<Handle, (usize, bool)>,
+}
+
+impl BubbleIndex {
+    pub fn from_graph(graph_storage: &GraphStorage) -> Self {
+        let successors = get_successors(graph_storage);
+        let mut bubbles: HashSet<Bubble> = HashSet::new();
+        let mut handles: Vec<Handle> = successors.keys().copied().collect();
+        handles.sort();
+        for handle in handles {
+            if let Some(end) = find_superbubble(handle, &successors) {
+                bubbles.insert(Bubble::canonical(handle, end));
+            }
+        }
+        let mut bubbles: Vec<Bubble> = bubbles.into_iter().collect();
+        bubbles.sort_by_key(|b| (b.start, b.end));
+        let mut entrances = HashMap::new();
+        for (idx, bubble) in bubbles.iter().enumerate() {
+            entrances.insert(bubble.start, (idx, false));
+            entrances.insert(flip(&bubble.end), (idx, true));
+        }
+        log::info!("found {} bubbles", bubbles.len());
+        Self { bubbles, entrances }
+    }
+
+    pub fn len(&self) -> usize {
+        self.bubbles.len()
+    }
+
+    pub fn get_bubble_name(&self, idx: usize) -> String {
+        let bubble = &self.bubbles[idx];
+        format!(
+            "{}{}",
+            handle_to_string(&bubble.start),
+            handle_to_string(&bubble.end)
+        )
+    }
+
+    /// Returns all bubble traversals of a path as (bubble id, allele), the
+    /// allele being the interior of the bubble in canonical direction
+    pub fn get_traversals(&self, path: &[Handle]) -> Vec<(usize, Vec<Handle>)> {
+        let mut traversals = Vec::new();
+        for (i, handle) in path.iter().enumerate() {
+            let (idx, reverse) = match self.entrances.get(handle) {
+                Some(entrance) => *entrance,
+                None => continue,
+            };
+            let bubble = &self.bubbles[idx];
+            let exit = if reverse {
+                flip(&bubble.start)
+            } else {
+                bubble.end
+            };
+            let max_end = std::cmp::min(path.len(), i + MAX_BUBBLE_SIZE + 2);
+            if let Some(offset) = path[i + 1..max_end].iter().position(|h| *h == exit) {
+                let mut allele = path[i + 1..i + 1 + offset].to_vec();
+                if reverse {
+                    allele = allele.iter().rev().map(flip).collect();
+                }
+                traversals.push((idx, allele));
+            }
+        }
+        traversals
+    }
+}
+
+fn get_successors(graph_storage: &GraphStorage) -> HashMap<Handle, Vec<Handle>> {
+    let mut successors: HashMap<Handle, Vec<Handle>> = HashMap::new();
+    if let Some(edge2id) = graph_storage.edge2id.as_ref() {
+        for edge in edge2id.keys() {
+            successors
+                .entry((edge.0, edge.1))
+                .or_default()
+                .push((edge.2, edge.3));
+            successors
+                .entry((edge.2, edge.3.flip()))
+                .or_default()
+                .push((edge.0, edge.1.flip()));
+        }
+    }
+    successors
+}
+
+fn get_predecessors<'a>(
+    handle: &Handle,
+    successors: &'a HashMap<Handle, Vec<Handle>>,
+) -> impl Iterator<Item = Handle> + 'a {
+    successors
+        .get(&flip(handle))
+        .into_iter()
+        .flatten()
+        .map(flip)
+}
+
+fn find_superbubble(start: Handle, successors: &HashMap<Handle, Vec<Handle>>) -> Option<Handle> {
+    if successors.get(&start).map(|s| s.len()).unwrap_or(0) < 2 {
+        return None;
+    }
+    let mut visited: HashSet<Handle> = HashSet::new();
+    let mut seen: HashSet<Handle> = HashSet::from([start]);
+    let mut stack: Vec<Handle> = vec![start];
+    while let Some(current) = stack.pop() {
+        visited.insert(current);
+        seen.remove(&current);
+        if visited.len() > MAX_BUBBLE_SIZE {
+            return None;
+        }
+        let children = match successors.get(&current) {
+            Some(children) if !children.is_empty() => children,
+            // tip
+            _ => return None,
+        };
+        for child in children {
+            // cycles (also via inversions) are not part of a superbubble
+            if child.0 == start.0 || visited.contains(&flip(child)) {
+                return None;
+            }
+            seen.insert(*child);
+            if get_predecessors(child, successors).all(|p| visited.contains(&p)) {
+                stack.push(*child);
+            }
+        }
+        if stack.len() == 1 && seen.len() == 1 && seen.contains(&stack[0]) {
+            let end = stack[0];
+            if successors
+                .get(&end)
+                .map(|s| s.contains(&start))
+                .unwrap_or(false)
+            {
+                return None;
+            }
+            return Some(end);
+        }
+    }
+    None
+}
+
+/// Parses all paths/walks and translates them into bubble (or allele)
+/// traversals. Returns the item table (one item per traversal), the names and
+/// sizes in bp (longest allele for bubbles) of the features, the path lengths
+/// in nodes and bps, and the collected paths.
+pub fn parse_gfa_bubble_traversals(
+    gfa_file: &str,
+    graph_storage: &GraphStorage,
+    grammar: &Grammar,
+    bubble_index: &BubbleIndex,
+    count: CountType,
+    paths_to_collect: &[PathSegment],
+) -> Result<BubbleTraversals, PanacusError> {
+    log::info!("parsing bubble traversals of path + walk sequences");
+    let mut item_table = ItemTable::new(graph_storage.path_segments.len());
+    let mut alleles: HashMap<(usize, Vec<Handle>), usize> = HashMap::new();
+    let mut feature_names: Vec<String> = match count {
+        CountType::Bubble => (0..bubble_index.len())
+            .map(|idx| bubble_index.get_bubble_name(idx))
+            .collect(),
+        _ => Vec::new(),
+    };
+    let mut feature_sizes: Vec<usize> = vec![0; feature_names.len()];
+    let mut paths_len = HashMap::new();
+    let mut collected_paths = HashMap::new();
+    let mut num_path = 0;
+    let parse_path = |line: &[u8]| -> Result<ParsedTraversals, String> {
+        let (path_seg, sids) = parse_path_or_walk(line, graph_storage, grammar)?;
+        log::debug!("processing bubble traversals of path {}", &path_seg);
+        let traversals = bubble_index.get_traversals(&sids);
+        Ok((path_seg, sids, traversals))
+    };
+    let mut line_count = 0;
+    for_each_gfa_chunk(gfa_file, |chunk| {
+        let lines = get_lines(chunk);
+        let paths: Vec<ParsedTraversals> = lines
+            .par_iter()
+            .enumerate()
+            .filter(|(_, line)| line[0] == b'P' || line[0] == b'W')
+            .map(|(i, line)| {
+                parse_path(line)
+                    .map_err(|msg| PanacusError::parse(gfa_file, line_count + i + 1, msg))
+            })
+            .collect::<Result<_, _>>()?;
+        line_count += lines.len();
+        for (path_seg, sids, traversals) in paths {
+            let bp_len: u32 = sids.iter().map(|(s, _)| graph_storage.node_len(s)).sum();
+            for (bubble, allele) in traversals {
+                let allele_size: usize = allele
+                    .iter()
+                    .map(|(s, _)| graph_storage.node_len(s) as usize)
+                    .sum();
+                let feature = match count {
+                    CountType::Bubble => {
+                        feature_sizes[bubble] = feature_sizes[bubble].max(allele_size);
+                        bubble
+                    }
+                    _ => {
+                        let next_id = alleles.len();
+                        *alleles
+                            .entry((bubble, allele))
+                            .or_insert_with_key(|(b, a)| {
+                                let allele_name = if a.is_empty() {
+                                    "-".to_string()
+                                } else {
+                                    a.iter().map(handle_to_string).collect()
+                                };
+                                feature_names.push(format!(
+                                    "{}:{}",
+                                    bubble_index.get_bubble_name(*b),
+                                    allele_name
+                                ));
+                                feature_sizes.push(allele_size);
+                                next_id
+                            })
+                    }
+                };
+                item_table.items.push(feature as u64 + 1);
+            }
+            item_table.id_prefsum[num_path + 1] = item_table.items.len() as u64;
+            paths_len.insert(path_seg.clone(), (sids.len() as u32, bp_len));
+            if paths_to_collect.iter().any(|p| path_seg.is_part_of(p)) {
+                collected_paths.insert(path_seg, sids);
+            }
+            num_path += 1;
+        }
+        Ok(())
+    })?;
+    log::info!(
+        "found {} {}s in {} paths",
+        feature_names.len(),
+        count,
+        num_path
+    );
+    Ok((
+        item_table,
+        feature_names,
+        feature_sizes,
+        paths_len,
+        collected_paths,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_test_graph() -> GraphStorage {
+        let (graph_storage, _) =
+            GraphStorage::from_gfa("tests/test_files/t_insertions.gfa", false).unwrap();
+        graph_storage
+    }
+
+    fn handle(graph_storage: &GraphStorage, name: &str) -> Handle {
+        (
+            graph_storage.get_node_id(name.as_bytes()).unwrap(),
+            Orientation::Forward,
+        )
+    }
+
+    #[test]
+    fn test_bubbles_are_detected() {
+        let graph_storage = get_test_graph();
+        let index = BubbleIndex::from_graph(&graph_storage);
+        let bubble_names: Vec<String> = (0..index.len())
+            .map(|idx| index.get_bubble_name(idx))
+            .collect();
+        assert_eq!(bubble_names, vec![">1>3".to_string(), ">3>5".to_string()]);
+    }
+
+    #[test]
+    fn test_traversals_are_canonical() {
+        let graph_storage = get_test_graph();
+        let index = BubbleIndex::from_graph(&graph_storage);
+        let path: Vec<Handle> = ["1", "2", "6", "3", "5"]
+            .iter()
+            .map(|n| handle(&graph_storage, n))
+            .collect();
+        let reverse_path: Vec<Handle> = path.iter().rev().map(flip).collect();
+        let expected = vec![
+            (
+                0,
+                vec![handle(&graph_storage, "2"), handle(&graph_storage, "6")],
+            ),
+            (1, vec![]),
+        ];
+        assert_eq!(index.get_traversals(&path), expected);
+        let mut reverse_traversals = index.get_traversals(&reverse_path);
+        reverse_traversals.sort();
+        assert_eq!(reverse_traversals, expected);
+    }
+}
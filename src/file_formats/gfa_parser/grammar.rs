@@ -8,6 +8,7 @@ use regex::Regex;
 
 use crate::util::CountType;
 use crate::{
+    error::PanacusError,
     file_formats::gfa_parser::{
         graph::GraphStorage,
         walk_splitter::{WalkByteSplitter, WalkSplitter},
@@ -152,14 +153,24 @@ impl Grammar {
         &self.child_orientations[start..end]
     }
 
-    pub fn parse_gfa(&mut self, filename: &str, graph_storage: &GraphStorage) {
-        let buf = bufreader_from_compressed_gfa(filename);
+    pub fn parse_gfa(
+        &mut self,
+        filename: &str,
+        graph_storage: &GraphStorage,
+    ) -> Result<(), PanacusError> {
+        let buf = bufreader_from_compressed_gfa(filename)?;
         self.parse_buffer(buf, graph_storage);
+        Ok(())
     }
 
-    pub fn parse_gfa_parallel(&mut self, filename: &str, graph_storage: &mut GraphStorage) {
-        let buf = bufreader_from_compressed_gfa(filename);
+    pub fn parse_gfa_parallel(
+        &mut self,
+        filename: &str,
+        graph_storage: &mut GraphStorage,
+    ) -> Result<(), PanacusError> {
+        let buf = bufreader_from_compressed_gfa(filename)?;
         self.parse_buffer_in_parallel(buf, graph_storage);
+        Ok(())
     }
 
     pub fn is_empty(&self) -> bool {
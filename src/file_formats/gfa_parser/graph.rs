@@ -2,17 +2,19 @@
 use once_cell::sync::Lazy;
 use regex::Regex;
 // use rustc_hash::FxHashMap;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::hash::Hash;
-use std::io::BufRead;
 use std::str::{self, FromStr};
 use std::sync::atomic::{AtomicUsize, Ordering};
 
 /* private use */
-use crate::io::bufreader_from_compressed_gfa;
+use crate::error::PanacusError;
+use crate::io::{for_each_gfa_chunk, get_lines};
+use crate::profile;
 use crate::util::*;
 use crate::util::{CountType, ItemIdSize};
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 
 static PATHID_PANSN: Lazy<Regex> =
@@ -97,45 +99,66 @@ impl fmt::Display for ItemId {
     }
 }
 
+/// Orientation at position `pos` of a link, if it is '+' or '-'
+fn get_link_orientation(data: &[u8], pos: usize) -> Option<Orientation> {
+    match data.get(pos) {
+        Some(b'+') | Some(b'-') => Some(Orientation::from_pm(data[pos])),
+        _ => None,
+    }
+}
+
 #[derive(Default, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Edge(pub ItemId, pub Orientation, pub ItemId, pub Orientation);
 
 impl Edge {
-    pub fn from_link(data: &[u8], node2id: &HashMap<Vec<u8>, ItemId>, canonical: bool) -> Self {
+    /// Parses the edge of an L-line (or of the part of it after the record
+    /// type). Returns an error message if the line is malformed or refers
+    /// to an unknown node.
+    pub fn from_link(
+        data: &[u8],
+        node2id: &HashMap<Vec<u8>, ItemId>,
+        canonical: bool,
+    ) -> Result<Self, String> {
+        let malformed = || {
+            format!(
+                "malformed link {}",
+                String::from_utf8_lossy(data).trim_end()
+            )
+        };
         let (start, mut iter) = match data[0] {
             b'L' => (2, data[2..].iter()),
             _ => (0, data.iter()),
         };
 
-        let end = start + iter.position(|&x| x == b'\t').unwrap();
-        let u = node2id.get(&data[start..end]).unwrap_or_else(|| {
-            panic!(
+        let end = start + iter.position(|&x| x == b'\t').ok_or_else(malformed)?;
+        let u = node2id.get(&data[start..end]).ok_or_else(|| {
+            format!(
                 "unknown node {}",
-                str::from_utf8(&data[start..end]).unwrap()
+                String::from_utf8_lossy(&data[start..end])
             )
-        });
+        })?;
 
         // we know that 3rd colum is either '+' or '-', so it has always length 1; still, we
         // need to advance in the buffer (and  therefore call iter.position(..))
         iter.position(|&x| x == b'\t');
-        let o1 = Orientation::from_pm(data[end + 1]);
+        let o1 = get_link_orientation(data, end + 1).ok_or_else(malformed)?;
 
         let start = end + 3;
-        let end = start + iter.position(|&x| x == b'\t').unwrap();
+        let end = start + iter.position(|&x| x == b'\t').ok_or_else(malformed)?;
 
-        let v = node2id.get(&data[start..end]).unwrap_or_else(|| {
-            panic!(
+        let v = node2id.get(&data[start..end]).ok_or_else(|| {
+            format!(
                 "unknown node {}",
-                str::from_utf8(&data[start..end]).unwrap()
+                String::from_utf8_lossy(&data[start..end])
             )
-        });
-        let o2 = Orientation::from_pm(data[end + 1]);
+        })?;
+        let o2 = get_link_orientation(data, end + 1).ok_or_else(malformed)?;
 
-        if canonical {
+        Ok(if canonical {
             Self::canonical(*u, o1, *v, o2)
         } else {
             Self(*u, o1, *v, o2)
-        }
+        })
     }
 
     #[allow(dead_code)]
@@ -174,6 +197,8 @@ pub struct GraphStorage {
     pub node2rule_id: Vec<usize>,
     is_nice: bool,
     pub node_lens: Vec<u32>,
+    // Nodes whose sequence consists of Ns only (e.g. scaffolding gaps)
+    pub n_nodes: HashSet<ItemId>,
     pub edge2id: Option<HashMap<Edge, ItemId>>,
     pub path_segments: Vec<PathSegment>,
     pub node_count: usize,
@@ -182,13 +207,45 @@ pub struct GraphStorage {
     // pub extremities: Option<Vec<(u64, u64)>>,
 }
 
+/// Edge ids, number of edges and the degree of each node
+type EdgeIndex = (HashMap<Edge, ItemId>, usize, Vec<u32>);
+
+/// Node ids, rule ids of the meta nodes, path segments, node lengths, the
+/// nodes consisting of Ns only, node extremities (if requested) and whether
+/// there was a meta node
+type NodeIndex = (
+    HashMap<Vec<u8>, ItemId>,
+    Vec<usize>,
+    Vec<PathSegment>,
+    Vec<u32>,
+    HashSet<ItemId>,
+    Option<Vec<(u64, u64)>>,
+    bool,
+);
+
+/// A line of a GFA file as needed to index the nodes and paths
+enum NodeRecord<'a> {
+    Segment {
+        name: &'a [u8],
+        len: u32,
+        is_n: bool,
+        extremities: Option<(u64, u64)>,
+    },
+    MetaSegment {
+        name: &'a [u8],
+    },
+    Path(PathSegment),
+    Other,
+}
+
 impl GraphStorage {
-    #[cfg(test)]
+    /// A graph without nodes and edges, e.g. to load the groups of paths
     pub fn from_path_segments(path_segments: Vec<PathSegment>) -> Self {
         Self {
             node2id: HashMap::default(),
             node2rule_id: Vec::new(),
             node_lens: Vec::new(),
+            n_nodes: HashSet::new(),
             edge2id: None,
             path_segments,
             node_count: 0,
@@ -198,12 +255,13 @@ impl GraphStorage {
         }
     }
 
-    pub fn from_gfa(gfa_file: &str, is_nice: bool) -> (Self, bool) {
-        let (node2id, node2rule_id, path_segments, node_lens, _extremities, has_meta_node) =
-            Self::parse_nodes_gfa(gfa_file, None);
+    pub fn from_gfa(gfa_file: &str, is_nice: bool) -> Result<(Self, bool), PanacusError> {
+        let (node2id, node2rule_id, path_segments, node_lens, n_nodes, _extremities, has_meta_node) =
+            profile::stage("node parsing", || Self::parse_nodes_gfa(gfa_file, None))?;
         let index_edges: bool = true;
         let (edge2id, edge_count, degree) = if index_edges {
-            let (edge2id, edge_count, degree) = Self::parse_edge_gfa(gfa_file, &node2id);
+            let (edge2id, edge_count, degree) =
+                profile::stage("edge parsing", || Self::parse_edge_gfa(gfa_file, &node2id))?;
             (Some(edge2id), edge_count, Some(degree))
         } else {
             (None, 0, None)
@@ -211,12 +269,13 @@ impl GraphStorage {
         let node_count = node2id.len();
         log::debug!("Done creating GraphStorage");
 
-        (
+        Ok((
             Self {
                 node2id,
                 node2rule_id,
                 is_nice,
                 node_lens,
+                n_nodes,
                 edge2id,
                 path_segments,
                 node_count,
@@ -225,7 +284,7 @@ impl GraphStorage {
                 // extremities,
             },
             has_meta_node,
-        )
+        ))
     }
 
     #[inline]
@@ -262,26 +321,41 @@ impl GraphStorage {
         self.node_lens[v.0 as usize]
     }
 
-    pub fn number_of_items(&self, c: &CountType) -> usize {
+    pub fn number_of_items(&self, c: &CountType) -> Result<usize, PanacusError> {
         match c {
-            &CountType::Node | &CountType::Bp => self.node_count,
-            &CountType::Edge => self.edge_count,
+            &CountType::Node | &CountType::Bp => Ok(self.node_count),
+            &CountType::Edge => Ok(self.edge_count),
+            &CountType::Bubble | &CountType::Allele => Err(anyhow::anyhow!(
+                "the number of {}s is only known after parsing the paths",
+                c
+            )
+            .into()),
         }
     }
 
     pub fn parse_edge_gfa(
         gfa_file: &str,
         node2id: &HashMap<Vec<u8>, ItemId>,
-    ) -> (HashMap<Edge, ItemId>, usize, Vec<u32>) {
+    ) -> Result<EdgeIndex, PanacusError> {
         let mut edge2id = HashMap::default();
         let mut degree: Vec<u32> = vec![0; node2id.len() + 1];
         let mut edge_id: ItemIdSize = 1;
 
-        let mut buf = vec![];
-        let mut data = bufreader_from_compressed_gfa(gfa_file);
-        while data.read_until(b'\n', &mut buf).unwrap_or(0) > 0 {
-            if buf[0] == b'L' {
-                let edge = Edge::from_link(&buf[..], node2id, true);
+        let mut line_count = 0;
+        for_each_gfa_chunk(gfa_file, |chunk| {
+            // Links are parsed in parallel, but get their ids in order of the file
+            let lines = get_lines(chunk);
+            let edges: Vec<Edge> = lines
+                .par_iter()
+                .enumerate()
+                .filter(|(_, line)| line[0] == b'L')
+                .map(|(i, line)| {
+                    Edge::from_link(line, node2id, true)
+                        .map_err(|msg| PanacusError::parse(gfa_file, line_count + i + 1, msg))
+                })
+                .collect::<Result<_, _>>()?;
+            line_count += lines.len();
+            for edge in edges {
                 if let std::collections::hash_map::Entry::Vacant(e) = edge2id.entry(edge) {
                     degree[edge.0 .0 as usize] += 1;
                     //if e.0.0 != e.2.0 {
@@ -293,34 +367,53 @@ impl GraphStorage {
                     log::warn!("edge {} is duplicated in GFA", &edge);
                 }
             }
-            buf.clear();
-        }
+            Ok(())
+        })?;
         let edge_count = edge2id.len();
         log::info!("found: {} edges", edge_count);
 
-        (edge2id, edge_count, degree)
+        Ok((edge2id, edge_count, degree))
     }
 
-    pub fn parse_nodes_gfa(
-        gfa_file: &str,
-        k: Option<usize>,
-    ) -> (
-        HashMap<Vec<u8>, ItemId>,
-        Vec<usize>,
-        Vec<PathSegment>,
-        Vec<u32>,
-        Option<Vec<(u64, u64)>>,
-        bool, // Whether there was a meta node
-    ) {
-        let mut buf = vec![];
-        let mut data = bufreader_from_compressed_gfa(gfa_file);
-        let mut count_nodes = 0;
-        while data.read_until(b'\n', &mut buf).unwrap_or(0) > 0 {
-            if buf[0] == b'S' || buf[0] == b'Q' {
-                count_nodes += 1;
+    fn parse_node_record(line: &[u8], k: Option<usize>) -> NodeRecord<'_> {
+        match line[0] {
+            b'S' => {
+                let mut iter = line[2..].iter();
+                let offset = iter.position(|&x| x == b'\t').unwrap();
+                let name = &line[2..offset + 2];
+                let start_sequence = offset + 3;
+                let offset = iter
+                    .position(|&x| x == b'\t' || x == b'\n' || x == b'\r')
+                    .unwrap_or(line.len() - start_sequence);
+                let sequence = &line[start_sequence..start_sequence + offset];
+                NodeRecord::Segment {
+                    name,
+                    len: offset as u32,
+                    is_n: !sequence.is_empty() && sequence.iter().all(|x| *x == b'N' || *x == b'n'),
+                    extremities: k.map(|k| get_extremities(sequence, k)),
+                }
+            }
+            b'Q' => {
+                let offset = line[2..].iter().position(|&x| x == b'\t').unwrap();
+                NodeRecord::MetaSegment {
+                    name: &line[2..offset + 2],
+                }
             }
-            buf.clear();
+            b'P' => NodeRecord::Path(Self::parse_path_segment(line)),
+            b'W' => NodeRecord::Path(Self::parse_walk_segment(line)),
+            _ => NodeRecord::Other,
         }
+    }
+
+    pub fn parse_nodes_gfa(gfa_file: &str, k: Option<usize>) -> Result<NodeIndex, PanacusError> {
+        let mut count_nodes = 0;
+        for_each_gfa_chunk(gfa_file, |chunk| {
+            count_nodes += get_lines(chunk)
+                .par_iter()
+                .filter(|line| line[0] == b'S' || line[0] == b'Q')
+                .count();
+            Ok(())
+        })?;
         log::info!("Found {} nodes", count_nodes);
 
         let mut node2id: HashMap<Vec<u8>, ItemId> =
@@ -328,6 +421,7 @@ impl GraphStorage {
         let mut node2rule_id: Vec<usize> = vec![usize::MAX];
         let mut path_segments: Vec<PathSegment> = Vec::new();
         let mut node_lens: Vec<u32> = Vec::new();
+        let mut n_nodes: HashSet<ItemId> = HashSet::new();
         let mut extremities: Vec<(u64, u64)> = Vec::new();
 
         log::info!("constructing indexes for node/edge IDs, node lengths, and P/W lines..");
@@ -335,52 +429,64 @@ impl GraphStorage {
         let mut node_id = 1; // important: id must be > 0, otherwise counting procedure will produce errors
         let mut meta_node_id = 0;
 
-        let mut buf = vec![];
-        let mut data = bufreader_from_compressed_gfa(gfa_file);
-        while data.read_until(b'\n', &mut buf).unwrap_or(0) > 0 {
-            if buf[0] == b'S' {
-                let mut iter = buf[2..].iter();
-                let offset = iter.position(|&x| x == b'\t').unwrap();
-                let text = buf[2..offset + 2].to_vec();
-                if node2id.insert(text, ItemId(node_id)).is_some() {
-                    panic!(
-                        "Segment with ID {} occurs multiple times in GFA",
-                        str::from_utf8(&buf[2..offset + 2]).unwrap()
-                    )
-                }
-                let start_sequence = offset + 3;
-                let offset = iter
-                    .position(|&x| x == b'\t' || x == b'\n' || x == b'\r')
-                    .unwrap();
-                if k.is_some() {
-                    let (left, right) =
-                        get_extremities(&buf[start_sequence..start_sequence + offset], k.unwrap());
-                    extremities.push((left, right));
-                }
-                node_lens.push(offset as u32);
-                node2rule_id.push(usize::MAX);
-                node_id += 1;
-            } else if buf[0] == b'Q' {
-                let mut iter = buf[2..].iter();
-                let offset = iter.position(|&x| x == b'\t').unwrap();
-                let text = buf[2..offset + 2].to_vec();
-                if node2id.insert(text, ItemId(node_id)).is_some() {
-                    panic!(
-                        "Meta-Segment with ID {} occurs multiple times in GFA",
-                        str::from_utf8(&buf[2..offset + 2]).unwrap()
-                    )
+        let mut line = 0;
+        for_each_gfa_chunk(gfa_file, |chunk| {
+            // Records are parsed in parallel, but get their ids in order of the file
+            let records: Vec<NodeRecord> = get_lines(chunk)
+                .par_iter()
+                .map(|line| Self::parse_node_record(line, k))
+                .collect();
+            for record in records {
+                line += 1;
+                match record {
+                    NodeRecord::Segment {
+                        name,
+                        len,
+                        is_n,
+                        extremities: node_extremities,
+                    } => {
+                        if node2id.insert(name.to_vec(), ItemId(node_id)).is_some() {
+                            return Err(PanacusError::parse(
+                                gfa_file,
+                                line,
+                                format!(
+                                    "segment with ID {} occurs multiple times",
+                                    String::from_utf8_lossy(name)
+                                ),
+                            ));
+                        }
+                        if let Some(node_extremities) = node_extremities {
+                            extremities.push(node_extremities);
+                        }
+                        if is_n {
+                            n_nodes.insert(ItemId(node_id));
+                        }
+                        node_lens.push(len);
+                        node2rule_id.push(usize::MAX);
+                        node_id += 1;
+                    }
+                    NodeRecord::MetaSegment { name } => {
+                        if node2id.insert(name.to_vec(), ItemId(node_id)).is_some() {
+                            return Err(PanacusError::parse(
+                                gfa_file,
+                                line,
+                                format!(
+                                    "meta-segment with ID {} occurs multiple times",
+                                    String::from_utf8_lossy(name)
+                                ),
+                            ));
+                        }
+                        node2rule_id.push(meta_node_id);
+                        node_lens.push(0);
+                        node_id += 1;
+                        meta_node_id += 1;
+                    }
+                    NodeRecord::Path(path_segment) => path_segments.push(path_segment),
+                    NodeRecord::Other => {}
                 }
-                node2rule_id.push(meta_node_id);
-                node_lens.push(0);
-                node_id += 1;
-                meta_node_id += 1;
-            } else if buf[0] == b'P' {
-                path_segments.push(Self::parse_path_segment(&buf));
-            } else if buf[0] == b'W' {
-                path_segments.push(Self::parse_walk_segment(&buf));
             }
-            buf.clear();
-        }
+            Ok(())
+        })?;
 
         log::info!(
             "found: {} paths/walks, {} nodes, ({} are meta_nodes)",
@@ -392,14 +498,15 @@ impl GraphStorage {
             log::warn!("graph does not contain any annotated paths (P/W lines)");
         }
 
-        (
+        Ok((
             node2id,
             node2rule_id,
             path_segments,
             node_lens,
+            n_nodes,
             if k.is_none() { None } else { Some(extremities) },
             meta_node_id > 0,
-        )
+        ))
     }
 
     pub fn parse_path_segment(data: &[u8]) -> PathSegment {
@@ -602,3 +709,38 @@ impl fmt::Display for PathSegment {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    use super::*;
+
+    #[test]
+    fn test_link_with_unknown_node() {
+        let node2id = HashMap::from([(b"1".to_vec(), ItemId(1)), (b"2".to_vec(), ItemId(2))]);
+        let edge = Edge::from_link(b"L\t1\t+\t2\t-\t0M\n", &node2id, false);
+        assert_eq!(
+            edge,
+            Ok(Edge(
+                ItemId(1),
+                Orientation::Forward,
+                ItemId(2),
+                Orientation::Backward
+            ))
+        );
+        assert!(Edge::from_link(b"L\t1\t+\t3\t+\t0M\n", &node2id, false).is_err());
+        assert!(Edge::from_link(b"L\t1\t+\t2\t*\t0M\n", &node2id, false).is_err());
+        assert!(Edge::from_link(b"L\t1\n", &node2id, false).is_err());
+
+        let mut f = NamedTempFile::new().unwrap();
+        write!(
+            f,
+            "S\t1\tA\nS\t2\tC\nL\t1\t+\t2\t+\t0M\nL\t1\t+\t3\t+\t0M\n"
+        )
+        .unwrap();
+        let result = GraphStorage::from_gfa(f.path().to_str().unwrap(), false);
+        assert!(matches!(result, Err(PanacusError::Parse { line: 4, .. })));
+    }
+}
@@ -36,7 +36,9 @@ impl Hist3D {
         Self {
             count: a.count,
             coverage: match a.count {
-                CountType::Node | CountType::Edge => Self::construct_hist(a, b),
+                CountType::Node | CountType::Edge | CountType::Bubble | CountType::Allele => {
+                    Self::construct_hist(a, b)
+                }
                 CountType::Bp => Self::construct_hist_bps(
                     a,
                     b,
@@ -281,6 +283,7 @@ pub fn parse_threshold_cli(
     Ok(thresholds)
 }
 
+#[derive(Debug, Clone)]
 pub struct ThresholdContainer {
     pub quorum: Vec<Threshold>,
     pub coverage: Vec<Threshold>,
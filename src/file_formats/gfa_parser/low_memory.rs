@@ -0,0 +1,576 @@
+//! Low-memory computation of node/bp coverage histograms for graphs that do
+//! not fit into memory in the standard mode. Segment IDs need to be numeric;
+//! they are remapped to dense ids without keeping the names. The GFA file is
+//! read twice: the first pass collects the node lengths and paths/walks, the
+//! second pass counts the coverage of each node with compact counters.
+use std::collections::{HashMap, HashSet};
+use std::io::BufRead;
+use std::sync::Arc;
+
+use memchr::{memchr2_iter, memchr_iter};
+
+use crate::coverage_matrix::CoverageMatrix;
+use crate::error::PanacusError;
+use crate::file_formats::FileFormatParser;
+use crate::hist::Hist;
+use crate::io::bufreader_from_compressed_gfa;
+use crate::profile;
+use crate::util::CountType;
+
+use super::abacus::{GraphMask, GraphMaskParameters};
+use super::graph::{GraphStorage, PathSegment};
+
+// Rough memory usage of the standard mode (in bytes), see
+// `CompactGraph::estimate_standard_memory`
+/// Entry in node2id (reserved for twice the number of nodes, with the
+/// allocation of the name), length, rule id, degree and abacus counters
+const STANDARD_BYTES_PER_NODE: usize = 2 * 48 + 4 + 8 + 4 + 4 + 8;
+/// Entry in edge2id
+const STANDARD_BYTES_PER_EDGE: usize = 64;
+/// Entry in the item table
+const STANDARD_BYTES_PER_STEP: usize = 8;
+/// Upper bound of the memory usage per byte of an uncompressed GFA file:
+/// S-lines take at least 6 bytes per node, L-lines 14 bytes per edge and
+/// paths/walks 2 bytes per step, names are counted once
+const STANDARD_BYTES_PER_FILE_BYTE: usize = STANDARD_BYTES_PER_NODE / 6 + 1;
+
+/// Maps numeric segment IDs to dense ids (in order of the S-lines)
+#[derive(Debug, Clone, PartialEq)]
+enum NodeRemap {
+    /// IDs in a small range (e.g. 1..=n), indexed by their offset to the
+    /// smallest ID
+    Range { min: u64, ids: Vec<u32> },
+    /// Sorted IDs with their dense ids, for sparse IDs
+    Sorted(Vec<(u64, u32)>),
+}
+
+impl NodeRemap {
+    /// Returns the first duplicated ID as error
+    fn new(names: &[u64]) -> Result<Self, u64> {
+        let (Some(&min), Some(&max)) = (names.iter().min(), names.iter().max()) else {
+            return Ok(Self::Sorted(Vec::new()));
+        };
+        if max - min < 2 * names.len() as u64 {
+            let mut ids = vec![u32::MAX; (max - min + 1) as usize];
+            for (id, &name) in names.iter().enumerate() {
+                let entry = &mut ids[(name - min) as usize];
+                if *entry != u32::MAX {
+                    return Err(name);
+                }
+                *entry = id as u32;
+            }
+            Ok(Self::Range { min, ids })
+        } else {
+            let mut ids: Vec<(u64, u32)> = names
+                .iter()
+                .enumerate()
+                .map(|(id, &name)| (name, id as u32))
+                .collect();
+            ids.sort_unstable();
+            if let Some(w) = ids.windows(2).find(|w| w[0].0 == w[1].0) {
+                return Err(w[0].0);
+            }
+            Ok(Self::Sorted(ids))
+        }
+    }
+
+    fn get(&self, name: u64) -> Option<u32> {
+        match self {
+            Self::Range { min, ids } => name
+                .checked_sub(*min)
+                .and_then(|i| ids.get(i as usize))
+                .copied()
+                .filter(|&id| id != u32::MAX),
+            Self::Sorted(ids) => ids
+                .binary_search_by_key(&name, |&(n, _)| n)
+                .ok()
+                .map(|i| ids[i].1),
+        }
+    }
+
+    fn memory(&self) -> usize {
+        match self {
+            Self::Range { ids, .. } => ids.len() * std::mem::size_of::<u32>(),
+            Self::Sorted(ids) => ids.len() * std::mem::size_of::<(u64, u32)>(),
+        }
+    }
+}
+
+/// Parses a numeric segment ID without allocating
+fn parse_node_name(name: &[u8]) -> Option<u64> {
+    if name.is_empty() {
+        return None;
+    }
+    name.iter().try_fold(0u64, |acc, &c| {
+        if c.is_ascii_digit() {
+            acc.checked_mul(10)?.checked_add((c - b'0') as u64)
+        } else {
+            None
+        }
+    })
+}
+
+/// Returns the n-th tab-separated column of a line (without line break)
+fn get_column(line: &[u8], n: usize) -> &[u8] {
+    let column = line.split(|&c| c == b'\t').nth(n).unwrap_or_default();
+    let end = column
+        .iter()
+        .position(|&c| c == b'\n' || c == b'\r')
+        .unwrap_or(column.len());
+    &column[..end]
+}
+
+/// Returns the segment IDs of the steps of a P- or W-line
+fn get_steps(line: &[u8]) -> Box<dyn Iterator<Item = &[u8]> + '_> {
+    if line[0] == b'P' {
+        Box::new(
+            get_column(line, 2)
+                .split(|&c| c == b',')
+                .map(|s| &s[..s.len().saturating_sub(1)]),
+        )
+    } else {
+        Box::new(
+            get_column(line, 6)
+                .split(|&c| c == b'>' || c == b'<')
+                .skip(1),
+        )
+    }
+}
+
+/// Node lengths and paths/walks of a graph, without node names and edges
+#[derive(Debug)]
+pub struct CompactGraph {
+    filename: String,
+    remap: NodeRemap,
+    node_lens: Vec<u32>,
+    path_segments: Vec<PathSegment>,
+    edge_count: usize,
+    step_count: usize,
+    name_bytes: usize,
+}
+
+impl CompactGraph {
+    /// First pass over the GFA file. Fails if a segment ID is not numeric or
+    /// the graph contains meta-segments.
+    pub fn from_gfa(filename: &str) -> Result<Self, PanacusError> {
+        let mut names: Vec<u64> = Vec::new();
+        let mut node_lens: Vec<u32> = Vec::new();
+        let mut path_segments = Vec::new();
+        let mut edge_count = 0;
+        let mut step_count = 0;
+        let mut name_bytes = 0;
+
+        let mut data = bufreader_from_compressed_gfa(filename)?;
+        let mut buf = vec![];
+        let mut line = 0;
+        while data
+            .read_until(b'\n', &mut buf)
+            .map_err(|e| PanacusError::file_read(filename, e))?
+            > 0
+        {
+            line += 1;
+            match buf[0] {
+                b'S' => {
+                    let name = get_column(&buf, 1);
+                    let id = parse_node_name(name).ok_or_else(|| {
+                        PanacusError::parse(
+                            filename,
+                            line,
+                            format!(
+                                "segment ID {} is not numeric, low-memory mode needs numeric segment IDs",
+                                String::from_utf8_lossy(name)
+                            ),
+                        )
+                    })?;
+                    names.push(id);
+                    name_bytes += name.len();
+                    node_lens.push(get_column(&buf, 2).len() as u32);
+                }
+                b'Q' => {
+                    return Err(PanacusError::parse(
+                        filename,
+                        line,
+                        "meta-segments are not supported in low-memory mode",
+                    ))
+                }
+                b'L' => edge_count += 1,
+                b'P' => {
+                    path_segments.push(GraphStorage::parse_path_segment(&buf));
+                    step_count += memchr_iter(b',', get_column(&buf, 2)).count() + 1;
+                }
+                b'W' => {
+                    path_segments.push(GraphStorage::parse_walk_segment(&buf));
+                    step_count += memchr2_iter(b'>', b'<', get_column(&buf, 6)).count();
+                }
+                _ => {}
+            }
+            buf.clear();
+        }
+        if names.len() >= u32::MAX as usize {
+            return Err(PanacusError::parse(
+                filename,
+                line,
+                "too many segments for low-memory mode",
+            ));
+        }
+        let remap = NodeRemap::new(&names).map_err(|name| {
+            PanacusError::parse(
+                filename,
+                line,
+                format!("segment with ID {} occurs multiple times", name),
+            )
+        })?;
+        log::info!(
+            "found: {} paths/walks, {} nodes, {} edges, {} steps",
+            path_segments.len(),
+            node_lens.len(),
+            edge_count,
+            step_count
+        );
+        Ok(Self {
+            filename: filename.to_owned(),
+            remap,
+            node_lens,
+            path_segments,
+            edge_count,
+            step_count,
+            name_bytes,
+        })
+    }
+
+    /// Upper bound of the memory (in bytes) that the standard mode needs to
+    /// compute a node/bp histogram of the graph in the file, based on the
+    /// file size only. `None` for compressed files.
+    pub fn estimate_max_standard_memory(filename: &str) -> Option<usize> {
+        if filename.ends_with(".gz") {
+            return None;
+        }
+        let size = std::fs::metadata(filename).ok()?.len() as usize;
+        Some(size.saturating_mul(STANDARD_BYTES_PER_FILE_BYTE))
+    }
+
+    /// Rough estimate of the memory (in bytes) that the standard mode needs
+    /// to compute a node/bp histogram of this graph
+    pub fn estimate_standard_memory(&self) -> usize {
+        self.node_lens.len() * STANDARD_BYTES_PER_NODE
+            + self.name_bytes
+            + self.edge_count * STANDARD_BYTES_PER_EDGE
+            + self.step_count * STANDARD_BYTES_PER_STEP
+    }
+
+    fn get_node_id(&self, name: &[u8]) -> Option<u32> {
+        parse_node_name(name).and_then(|name| self.remap.get(name))
+    }
+}
+
+/// Coverage (number of groups) of each node
+enum CoverageCounter {
+    /// The paths of each group are consecutive in the file, so a node is
+    /// covered by a new group if its last group was a different one
+    Consecutive { coverage: Vec<u32>, last: Vec<u32> },
+    /// One bit per group for each node
+    Bitsets { words: usize, bits: Vec<u64> },
+}
+
+impl CoverageCounter {
+    fn new(number_of_nodes: usize, number_of_groups: usize, is_consecutive: bool) -> Self {
+        if is_consecutive {
+            Self::Consecutive {
+                coverage: vec![0; number_of_nodes],
+                last: vec![u32::MAX; number_of_nodes],
+            }
+        } else {
+            let words = (number_of_groups + 63) / 64;
+            Self::Bitsets {
+                words,
+                bits: vec![0; number_of_nodes * words],
+            }
+        }
+    }
+
+    fn memory(number_of_nodes: usize, number_of_groups: usize, is_consecutive: bool) -> usize {
+        if is_consecutive {
+            number_of_nodes * 2 * std::mem::size_of::<u32>()
+        } else {
+            number_of_nodes * ((number_of_groups + 63) / 64) * std::mem::size_of::<u64>()
+        }
+    }
+
+    fn add(&mut self, node: u32, group: u32) {
+        let node = node as usize;
+        match self {
+            Self::Consecutive { coverage, last } => {
+                if last[node] != group {
+                    last[node] = group;
+                    coverage[node] += 1;
+                }
+            }
+            Self::Bitsets { words, bits } => {
+                bits[node * *words + group as usize / 64] |= 1 << (group % 64);
+            }
+        }
+    }
+
+    fn coverage(&self, node: usize) -> usize {
+        match self {
+            Self::Consecutive { coverage, .. } => coverage[node] as usize,
+            Self::Bitsets { words, bits } => bits[node * words..(node + 1) * words]
+                .iter()
+                .map(|w| w.count_ones() as usize)
+                .sum(),
+        }
+    }
+}
+
+/// Computes node/bp histograms on a `CompactGraph`. Subsets and exclusions
+/// of paths are not supported, neither are coverage matrices.
+pub struct LowMemoryGfaParser {
+    graph: Arc<CompactGraph>,
+    count_type: CountType,
+    graph_mask_parameters: GraphMaskParameters,
+    // Group index of each path/walk, in order of the file
+    path_groups: Vec<u32>,
+    number_of_groups: usize,
+    is_consecutive: bool,
+}
+
+impl LowMemoryGfaParser {
+    pub fn new(
+        graph: Arc<CompactGraph>,
+        count_type: CountType,
+        graph_mask_parameters: GraphMaskParameters,
+    ) -> Result<Self, PanacusError> {
+        if !matches!(count_type, CountType::Node | CountType::Bp) {
+            return Err(anyhow::anyhow!(
+                "low-memory mode only counts nodes and bps, not {}",
+                count_type
+            )
+            .into());
+        }
+        let graph_storage = GraphStorage::from_path_segments(graph.path_segments.clone());
+        let graph_mask = profile::stage("masking", || {
+            GraphMask::from_datamgr(&graph_mask_parameters, &graph_storage)
+        })?;
+        let mut group_ids: HashMap<&str, u32> = HashMap::new();
+        let mut path_groups = Vec::with_capacity(graph.path_segments.len());
+        for path in &graph.path_segments {
+            let group = graph_mask.groups.get(&path.clear_coords()).ok_or_else(|| {
+                PanacusError::GroupNotFound {
+                    path: path.to_string(),
+                }
+            })?;
+            let next_id = group_ids.len() as u32;
+            path_groups.push(*group_ids.entry(group).or_insert(next_id));
+        }
+        let number_of_groups = group_ids.len();
+        let mut seen = HashSet::new();
+        let is_consecutive = path_groups
+            .iter()
+            .enumerate()
+            .all(|(i, g)| (i > 0 && path_groups[i - 1] == *g) || seen.insert(*g));
+        Ok(Self {
+            graph,
+            count_type,
+            graph_mask_parameters,
+            path_groups,
+            number_of_groups,
+            is_consecutive,
+        })
+    }
+
+    /// Estimate of the memory (in bytes) needed to compute the histogram
+    pub fn estimate_memory(&self) -> usize {
+        self.graph.remap.memory()
+            + self.graph.node_lens.len() * std::mem::size_of::<u32>()
+            + self.path_groups.len() * std::mem::size_of::<u32>()
+            + CoverageCounter::memory(
+                self.graph.node_lens.len(),
+                self.number_of_groups,
+                self.is_consecutive,
+            )
+    }
+
+    fn get_run_name(&self) -> String {
+        format!(
+            "{} {} {}",
+            self.graph.filename,
+            self.graph_mask_parameters.positive_list,
+            self.graph_mask_parameters.groupby
+        )
+    }
+
+    fn get_run_id(&self) -> String {
+        format!(
+            "{}-{}-{}",
+            self.graph.filename,
+            self.graph_mask_parameters.positive_list,
+            self.graph_mask_parameters.groupby
+        )
+    }
+
+    /// Second pass over the GFA file, counting the groups covering each node
+    fn count_coverage(&self) -> Result<CoverageCounter, PanacusError> {
+        let filename = &self.graph.filename;
+        let mut counter = CoverageCounter::new(
+            self.graph.node_lens.len(),
+            self.number_of_groups,
+            self.is_consecutive,
+        );
+        let mut data = bufreader_from_compressed_gfa(filename)?;
+        let mut buf = vec![];
+        let mut line = 0;
+        let mut path = 0;
+        while data
+            .read_until(b'\n', &mut buf)
+            .map_err(|e| PanacusError::file_read(filename, e))?
+            > 0
+        {
+            line += 1;
+            if buf[0] == b'P' || buf[0] == b'W' {
+                let group = self.path_groups[path];
+                path += 1;
+                for step in get_steps(&buf) {
+                    let node = self.graph.get_node_id(step).ok_or_else(|| {
+                        PanacusError::parse(
+                            filename,
+                            line,
+                            format!(
+                                "path step refers to unknown segment {}",
+                                String::from_utf8_lossy(step)
+                            ),
+                        )
+                    })?;
+                    counter.add(node, group);
+                }
+            }
+            buf.clear();
+        }
+        Ok(counter)
+    }
+}
+
+impl FileFormatParser for LowMemoryGfaParser {
+    fn generate_hist(self: Box<Self>) -> Result<Hist, PanacusError> {
+        log::info!(
+            "counting coverage of {} nodes in {} groups ({})",
+            self.graph.node_lens.len(),
+            self.number_of_groups,
+            if self.is_consecutive {
+                "consecutive groups"
+            } else {
+                "bitsets"
+            }
+        );
+        let counter = profile::stage("path parsing", || self.count_coverage())?;
+        let mut hist = Hist::from_maximum_coverage(
+            self.number_of_groups,
+            self.count_type.to_string(),
+            self.get_run_id(),
+            self.get_run_name(),
+        );
+        for (node, &length) in self.graph.node_lens.iter().enumerate() {
+            if self.count_type == CountType::Bp {
+                hist.insert_feature_of_coverage_and_length(counter.coverage(node), length as usize);
+            } else {
+                hist.insert_feature_of_coverage(counter.coverage(node));
+            }
+        }
+        Ok(hist)
+    }
+
+    fn generate_matrix(self: Box<Self>) -> Result<CoverageMatrix, PanacusError> {
+        Err(anyhow::anyhow!(
+            "coverage matrices of {} cannot be computed in low-memory mode",
+            self.graph.filename
+        )
+        .into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_formats::gfa_parser::GfaParser;
+
+    fn get_hists(file: &str, count_type: CountType, params: GraphMaskParameters) -> (Hist, Hist) {
+        let graph = Arc::new(CompactGraph::from_gfa(file).unwrap());
+        let low_memory =
+            Box::new(LowMemoryGfaParser::new(graph, count_type, params.clone()).unwrap());
+        let standard = Box::new(GfaParser::new(file, count_type, params, None, false).unwrap());
+        (
+            low_memory.generate_hist().unwrap(),
+            standard.generate_hist().unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_node_remap() {
+        let remap = NodeRemap::new(&[3, 1, 2]).unwrap();
+        assert!(matches!(remap, NodeRemap::Range { min: 1, .. }));
+        assert_eq!(remap.get(3), Some(0));
+        assert_eq!(remap.get(0), None);
+        assert_eq!(remap.get(4), None);
+        let remap = NodeRemap::new(&[1000, 7, 1 << 40]).unwrap();
+        assert!(matches!(remap, NodeRemap::Sorted(_)));
+        assert_eq!(remap.get(1 << 40), Some(2));
+        assert_eq!(remap.get(8), None);
+        assert_eq!(NodeRemap::new(&[1, 2, 1]), Err(1));
+        assert_eq!(NodeRemap::new(&[5, 1 << 40, 5]), Err(5));
+    }
+
+    #[test]
+    fn test_parse_node_name() {
+        assert_eq!(parse_node_name(b"123"), Some(123));
+        assert_eq!(parse_node_name(b""), None);
+        assert_eq!(parse_node_name(b"12a"), None);
+        assert_eq!(parse_node_name(b"99999999999999999999"), None);
+    }
+
+    #[test]
+    fn test_steps_of_paths_and_walks() {
+        let path = b"P\tx\t12+,13-\t*\n";
+        assert_eq!(get_steps(path).collect::<Vec<_>>(), vec![b"12", b"13"]);
+        let walk = b"W\ts\t1\tc\t0\t5\t>12<13\n";
+        assert_eq!(get_steps(walk).collect::<Vec<_>>(), vec![b"12", b"13"]);
+    }
+
+    #[test]
+    fn test_hist_equals_standard_mode() {
+        for count_type in [CountType::Node, CountType::Bp] {
+            let (low_memory, standard) = get_hists(
+                "tests/test_files/t_groups.gfa",
+                count_type,
+                GraphMaskParameters::default(),
+            );
+            assert_eq!(low_memory.get_hist_values(), standard.get_hist_values());
+            assert_eq!(low_memory.get_run_id(), standard.get_run_id());
+        }
+    }
+
+    #[test]
+    fn test_bitsets_for_non_consecutive_groups() {
+        let file = "tests/test_files/t_groups.gfa";
+        let params = GraphMaskParameters {
+            groupby_sample: true,
+            ..GraphMaskParameters::default()
+        };
+        let graph = Arc::new(CompactGraph::from_gfa(file).unwrap());
+        let mut parser = LowMemoryGfaParser::new(graph, CountType::Node, params.clone()).unwrap();
+        assert!(parser.is_consecutive);
+        parser.is_consecutive = false;
+        let hist = Box::new(parser).generate_hist().unwrap();
+        let (_, standard) = get_hists(file, CountType::Node, params);
+        assert_eq!(hist.get_hist_values(), standard.get_hist_values());
+    }
+
+    #[test]
+    fn test_estimates_of_standard_memory() {
+        let file = "tests/test_files/t_groups.gfa";
+        let graph = Arc::new(CompactGraph::from_gfa(file).unwrap());
+        let max_memory = CompactGraph::estimate_max_standard_memory(file).unwrap();
+        assert!(graph.estimate_standard_memory() <= max_memory);
+        assert_eq!(CompactGraph::estimate_max_standard_memory("t.gfa.gz"), None);
+        let params = GraphMaskParameters::default();
+        assert!(LowMemoryGfaParser::new(graph, CountType::Edge, params).is_err());
+    }
+}
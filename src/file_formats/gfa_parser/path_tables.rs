@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+
+use itertools::Itertools;
+use rayon::prelude::*;
+
+use crate::error::PanacusError;
+use crate::io::{for_each_gfa_chunk, get_lines};
+use crate::util::{CountType, ItemIdSize, ItemTable};
+
+use super::{
+    grammar::Grammar, graph::GraphStorage, util::parse_path_or_walk, Edge, ItemId, Orientation,
+    PathSegment,
+};
+
+/// Identifier, steps and (for edge counts) edge ids of a path/walk
+type ParsedPath = (
+    PathSegment,
+    Vec<(ItemId, Orientation)>,
+    Option<Vec<ItemIdSize>>,
+);
+
+/// Node and edge item tables of all paths/walks of a graph (without any
+/// subset, exclusion or grouping applied). They are computed in a single pass
+/// over the P/W lines, so that several runs on the same graph (e.g. with
+/// different count types or groupings) do not need to re-read the paths.
+/// Bp counts use the node table.
+#[derive(Debug, Clone)]
+pub struct PathTables {
+    nodes: Option<ItemTable>,
+    edges: Option<ItemTable>,
+    pub paths_len: HashMap<PathSegment, (u32, u32)>,
+    pub collected_paths: HashMap<PathSegment, Vec<(ItemId, Orientation)>>,
+}
+
+impl PathTables {
+    pub fn from_gfa(
+        gfa_file: &str,
+        graph_storage: &GraphStorage,
+        grammar: &Grammar,
+        count_types: &[CountType],
+        paths_to_collect: &[PathSegment],
+    ) -> Result<Self, PanacusError> {
+        log::info!(
+            "parsing path + walk sequences for count types {}",
+            count_types.iter().join(", ")
+        );
+        let number_of_paths = graph_storage.path_segments.len();
+        let with_nodes = count_types
+            .iter()
+            .any(|c| matches!(c, CountType::Node | CountType::Bp));
+        let with_edges = count_types.contains(&CountType::Edge);
+        let mut nodes = with_nodes.then(|| ItemTable::new(number_of_paths));
+        let mut edges = with_edges.then(|| ItemTable::new(number_of_paths));
+        let mut paths_len = HashMap::new();
+        let mut collected_paths = HashMap::new();
+        let mut num_path = 0;
+        let parse_path = |line: &[u8]| -> Result<ParsedPath, String> {
+            let (path_seg, sids) = parse_path_or_walk(line, graph_storage, grammar)?;
+            log::debug!("processing path {}", &path_seg);
+            let edge_ids = match with_edges {
+                true => {
+                    let edge2id = graph_storage
+                        .edge2id
+                        .as_ref()
+                        .expect("edge counts require edge2id map in GraphStorage");
+                    let edge_ids = sids
+                        .iter()
+                        .tuple_windows()
+                        .map(|((sid1, o1), (sid2, o2))| {
+                            let e = Edge::canonical(*sid1, *o1, *sid2, *o2);
+                            match edge2id.get(&e) {
+                                Some(eid) => Ok(eid.0),
+                                None => Err(format!("unknown edge {}", &e)),
+                            }
+                        })
+                        .collect::<Result<_, _>>()?;
+                    Some(edge_ids)
+                }
+                false => None,
+            };
+            Ok((path_seg, sids, edge_ids))
+        };
+        let mut line_count = 0;
+        for_each_gfa_chunk(gfa_file, |chunk| {
+            let lines = get_lines(chunk);
+            let paths: Vec<ParsedPath> = lines
+                .par_iter()
+                .enumerate()
+                .filter(|(_, line)| line[0] == b'P' || line[0] == b'W')
+                .map(|(i, line)| {
+                    parse_path(line)
+                        .map_err(|msg| PanacusError::parse(gfa_file, line_count + i + 1, msg))
+                })
+                .collect::<Result<_, _>>()?;
+            line_count += lines.len();
+            for (path_seg, sids, edge_ids) in paths {
+                if let Some(nodes) = nodes.as_mut() {
+                    nodes.items.extend(sids.iter().map(|(sid, _)| sid.0));
+                    nodes.id_prefsum[num_path + 1] = nodes.items.len() as u64;
+                }
+                if let (Some(edges), Some(edge_ids)) = (edges.as_mut(), edge_ids) {
+                    edges.items.extend(edge_ids);
+                    edges.id_prefsum[num_path + 1] = edges.items.len() as u64;
+                }
+                let bp_len: u32 = sids.iter().map(|(s, _)| graph_storage.node_len(s)).sum();
+                paths_len.insert(path_seg.clone(), (sids.len() as u32, bp_len));
+                if paths_to_collect.iter().any(|p| path_seg.is_part_of(p)) {
+                    collected_paths.insert(path_seg, sids);
+                }
+                num_path += 1;
+            }
+            Ok(())
+        })?;
+        log::info!("parsed {} paths/walks", num_path);
+        Ok(Self {
+            nodes,
+            edges,
+            paths_len,
+            collected_paths,
+        })
+    }
+
+    /// Returns the item table for the given count type, if it was computed
+    pub fn get_item_table(&self, count: CountType) -> Option<&ItemTable> {
+        match count {
+            CountType::Node | CountType::Bp => self.nodes.as_ref(),
+            CountType::Edge => self.edges.as_ref(),
+            CountType::Bubble | CountType::Allele => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_tables_contain_nodes_and_edges() {
+        let filename = "tests/test_files/t_repeats.gfa";
+        let (graph_storage, _) = GraphStorage::from_gfa(filename, false).unwrap();
+        let grammar = Grammar::from(CountType::Node);
+        let tables = PathTables::from_gfa(
+            filename,
+            &graph_storage,
+            &grammar,
+            &[CountType::Bp, CountType::Edge],
+            &[],
+        )
+        .unwrap();
+        let nodes = tables.get_item_table(CountType::Node).unwrap();
+        assert_eq!(nodes.id_prefsum, vec![0, 6, 10, 18]);
+        assert_eq!(nodes.items[..6], [1, 2, 3, 2, 3, 4]);
+        let edges = tables.get_item_table(CountType::Edge).unwrap();
+        assert_eq!(edges.id_prefsum, vec![0, 5, 8, 15]);
+        assert_eq!(tables.paths_len.len(), 3);
+        assert!(tables.collected_paths.is_empty());
+    }
+}
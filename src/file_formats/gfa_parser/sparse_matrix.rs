@@ -84,6 +84,15 @@ impl SparseMatrix {
         (self.r.clone(), self.c.iter().map(|&x| x as usize).collect())
     }
 
+    /// The values of the entries of `get_csr`, i.e. the multiplicities (all
+    /// one if only presence is stored)
+    pub fn get_csr_values(&self) -> Vec<usize> {
+        match &self.v {
+            Some(v) => v.iter().map(|&x| x as usize).collect(),
+            None => vec![1; self.c.len()],
+        }
+    }
+
     pub fn get_feature_occurrence_count(&self, feature: usize) -> usize {
         self.r[feature + 1] - self.r[feature]
     }
@@ -97,14 +106,14 @@ impl SparseMatrix {
         false
     }
 
-    pub fn insert_item_table(&mut self, number_of_features: usize, item_table: ItemTable) {
+    pub fn insert_item_table(&mut self, number_of_features: usize, item_table: &ItemTable) {
         log::info!(
             "Inserting item table with {} features, max feature: {}",
             number_of_features,
             item_table.items.iter().max().unwrap()
         );
-        self.r = compute_row_storage_space(&item_table, number_of_features);
-        (self.v, self.c) = compute_column_values(&item_table, &self.r, true);
+        self.r = compute_row_storage_space(item_table, number_of_features);
+        (self.v, self.c) = compute_column_values(item_table, &self.r, true);
     }
 
     pub fn get_counts_for_feature(&self, id: usize, num_paths: usize) -> Vec<usize> {
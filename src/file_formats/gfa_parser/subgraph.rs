@@ -0,0 +1,290 @@
+//! Small subgraphs around a region of a reference path, laid out by the
+//! `SubgraphLayout` analysis
+use std::collections::{HashMap, HashSet};
+use std::io::BufRead;
+
+use anyhow::anyhow;
+
+use crate::error::PanacusError;
+use crate::file_formats::gfa_parser::{
+    grammar::Grammar,
+    graph::{GraphStorage, ItemId, Orientation, PathSegment},
+    util::{
+        parse_path_identifier, parse_path_seq_to_item_vec, parse_walk_identifier,
+        parse_walk_seq_to_item_vec,
+    },
+};
+use crate::io::bufreader_from_compressed_gfa;
+use crate::util::CountType;
+
+/// The nodes of a region of a reference path, together with the nodes of the
+/// detours other paths take between them
+#[derive(Debug, Clone, PartialEq)]
+pub struct Subgraph {
+    /// Name and length (in bp) of the nodes
+    pub nodes: Vec<(String, usize)>,
+    /// Traversals of the subgraph by the paths, the reference comes first
+    pub traversals: Vec<Traversal>,
+}
+
+/// A contiguous stretch of a path within the subgraph
+#[derive(Debug, Clone, PartialEq)]
+pub struct Traversal {
+    pub path: String,
+    /// Indices into the nodes of the subgraph
+    pub steps: Vec<(usize, Orientation)>,
+}
+
+impl Subgraph {
+    /// Collects the nodes of each region and all stretches of paths that pass
+    /// through them. Detours of more than `max_nodes` steps are cut, and
+    /// subgraphs of more than `max_nodes` nodes are refused. The graph is
+    /// parsed once for all regions.
+    pub fn from_gfa(
+        gfa_file: &str,
+        regions: &[PathSegment],
+        max_nodes: usize,
+    ) -> Result<Vec<Self>, PanacusError> {
+        let (mut graph_storage, has_meta_node) = GraphStorage::from_gfa(gfa_file, false)?;
+        let mut grammar = Grammar::from(CountType::Node);
+        if has_meta_node {
+            grammar.parse_gfa_parallel(gfa_file, &mut graph_storage)?;
+        }
+
+        let mut regions_of_path: HashMap<String, Vec<usize>> = HashMap::new();
+        for (i, region) in regions.iter().enumerate() {
+            regions_of_path.entry(region.id()).or_default().push(i);
+        }
+        let mut region_nodes: Vec<HashSet<ItemId>> = vec![HashSet::new(); regions.len()];
+        let mut found = vec![false; regions.len()];
+        for_each_path(gfa_file, &graph_storage, &grammar, |path, steps| {
+            let Some(path_regions) = regions_of_path.get(&path.id()) else {
+                return Ok(());
+            };
+            let mut pos = path.start.unwrap_or(0);
+            for (node, _) in steps {
+                let len = graph_storage.node_len(&node) as usize;
+                for i in path_regions {
+                    let overlaps = match regions[*i].coords() {
+                        Some((start, end)) => pos < end && pos + len > start,
+                        None => true,
+                    };
+                    if overlaps {
+                        region_nodes[*i].insert(node);
+                    }
+                }
+                pos += len;
+            }
+            for i in path_regions {
+                found[*i] = true;
+            }
+            Ok(())
+        })?;
+        let mut node_regions: HashMap<ItemId, Vec<usize>> = HashMap::new();
+        for (i, region) in regions.iter().enumerate() {
+            if !found[i] {
+                return Err(PanacusError::PathNotFound {
+                    path: region.id(),
+                    file: gfa_file.to_string(),
+                });
+            }
+            if region_nodes[i].is_empty() {
+                return Err(anyhow!("region {} does not contain any node", region).into());
+            }
+            check_size(region, region_nodes[i].len(), max_nodes)?;
+            for node in region_nodes[i].iter() {
+                node_regions.entry(*node).or_default().push(i);
+            }
+        }
+
+        let mut node_indices: Vec<HashMap<ItemId, usize>> = vec![HashMap::new(); regions.len()];
+        let mut traversals: Vec<Vec<Traversal>> = vec![Vec::new(); regions.len()];
+        let mut reference_traversals = vec![0; regions.len()];
+        for_each_path(gfa_file, &graph_storage, &grammar, |path, steps| {
+            let mut hits: Vec<Vec<usize>> = vec![Vec::new(); regions.len()];
+            for (i, (node, _)) in steps.iter().enumerate() {
+                for region in node_regions.get(node).into_iter().flatten() {
+                    hits[*region].push(i);
+                }
+            }
+            for (r, region) in regions.iter().enumerate() {
+                let mut stretches: Vec<(usize, usize)> = Vec::new();
+                for i in hits[r].iter().copied() {
+                    match stretches.last_mut() {
+                        Some((_, end)) if i - *end <= max_nodes + 1 => *end = i,
+                        _ => stretches.push((i, i)),
+                    }
+                }
+                let node_index = &mut node_indices[r];
+                for (start, end) in stretches {
+                    let steps = steps[start..=end]
+                        .iter()
+                        .map(|(node, orientation)| {
+                            let next = node_index.len();
+                            (*node_index.entry(*node).or_insert(next), *orientation)
+                        })
+                        .collect();
+                    check_size(region, node_index.len(), max_nodes)?;
+                    let traversal = Traversal {
+                        path: path.to_string(),
+                        steps,
+                    };
+                    if path.id() == region.id() {
+                        traversals[r].insert(reference_traversals[r], traversal);
+                        reference_traversals[r] += 1;
+                    } else {
+                        traversals[r].push(traversal);
+                    }
+                }
+            }
+            Ok(())
+        })?;
+
+        let mut names: HashMap<ItemId, &[u8]> = node_indices
+            .iter()
+            .flat_map(|node_index| node_index.keys())
+            .map(|id| (*id, &[][..]))
+            .collect();
+        for (name, id) in graph_storage.node2id.iter() {
+            if let Some(entry) = names.get_mut(id) {
+                *entry = name.as_slice();
+            }
+        }
+        let subgraphs = node_indices
+            .into_iter()
+            .zip(traversals)
+            .map(|(node_index, traversals)| {
+                let mut nodes = vec![(String::new(), 0); node_index.len()];
+                for (id, idx) in node_index {
+                    nodes[idx] = (
+                        String::from_utf8_lossy(names[&id]).to_string(),
+                        graph_storage.node_len(&id) as usize,
+                    );
+                }
+                Self { nodes, traversals }
+            })
+            .collect();
+        Ok(subgraphs)
+    }
+}
+
+fn check_size(region: &PathSegment, nodes: usize, max_nodes: usize) -> Result<(), PanacusError> {
+    if nodes > max_nodes {
+        return Err(anyhow!(
+            "region {} spans more than {} nodes, layouts are only computed for small subgraphs",
+            region,
+            max_nodes
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Calls `f` with the identifier and the steps of each path and walk
+fn for_each_path<F>(
+    gfa_file: &str,
+    graph_storage: &GraphStorage,
+    grammar: &Grammar,
+    mut f: F,
+) -> Result<(), PanacusError>
+where
+    F: FnMut(PathSegment, Vec<(ItemId, Orientation)>) -> Result<(), PanacusError>,
+{
+    let mut reader = bufreader_from_compressed_gfa(gfa_file)?;
+    let mut buf = Vec::new();
+    let mut line = 0;
+    while reader
+        .read_until(b'\n', &mut buf)
+        .map_err(|e| PanacusError::file_read(gfa_file, e))?
+        > 0
+    {
+        line += 1;
+        let to_error = |msg| PanacusError::parse(gfa_file, line, msg);
+        match buf[0] {
+            b'P' => {
+                let (path, data) = parse_path_identifier(&buf);
+                f(
+                    path,
+                    parse_path_seq_to_item_vec(data, graph_storage).map_err(to_error)?,
+                )?;
+            }
+            b'W' => {
+                let (path, data) = parse_walk_identifier(&buf);
+                f(
+                    path,
+                    parse_walk_seq_to_item_vec(data, graph_storage, grammar).map_err(to_error)?,
+                )?;
+            }
+            _ => {}
+        }
+        buf.clear();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_names(subgraph: &Subgraph, traversal: &Traversal) -> Vec<String> {
+        traversal
+            .steps
+            .iter()
+            .map(|(node, _)| subgraph.nodes[*node].0.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_subgraph_contains_detours() {
+        let region = PathSegment::from_str_start_end("R", 0, 8);
+        let subgraph = Subgraph::from_gfa("tests/test_files/t_insertions.gfa", &[region], 100)
+            .unwrap()
+            .remove(0);
+        assert_eq!(subgraph.traversals.len(), 4);
+        assert_eq!(subgraph.traversals[0].path, "R");
+        assert_eq!(get_names(&subgraph, &subgraph.traversals[0]), ["1", "3"]);
+        let b = subgraph
+            .traversals
+            .iter()
+            .find(|t| t.path == "b#1#c")
+            .unwrap();
+        assert_eq!(get_names(&subgraph, b), ["1", "2", "6", "3"]);
+        let mut nodes = subgraph.nodes.clone();
+        nodes.sort();
+        assert_eq!(
+            nodes,
+            vec![
+                ("1".to_string(), 4),
+                ("2".to_string(), 2),
+                ("3".to_string(), 4),
+                ("6".to_string(), 5)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_subgraphs_of_multiple_regions() {
+        let file = "tests/test_files/t_insertions.gfa";
+        let regions = [
+            PathSegment::from_str_start_end("R", 0, 8),
+            PathSegment::from_str_start_end("R", 4, 12),
+        ];
+        let subgraphs = Subgraph::from_gfa(file, &regions, 100).unwrap();
+        assert_eq!(subgraphs.len(), 2);
+        for (region, subgraph) in regions.iter().zip(subgraphs) {
+            let single = Subgraph::from_gfa(file, std::slice::from_ref(region), 100).unwrap();
+            assert_eq!(single, vec![subgraph]);
+        }
+    }
+
+    #[test]
+    fn test_subgraph_too_large() {
+        let region = PathSegment::from_str("R");
+        assert!(Subgraph::from_gfa("tests/test_files/t_insertions.gfa", &[region], 2).is_err());
+        let region = PathSegment::from_str("S");
+        assert!(matches!(
+            Subgraph::from_gfa("tests/test_files/t_insertions.gfa", &[region], 100),
+            Err(PanacusError::PathNotFound { .. })
+        ));
+    }
+}
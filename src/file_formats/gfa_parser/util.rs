@@ -1,15 +1,14 @@
 use itertools::Itertools;
 use memchr::{memchr, memchr2};
+use std::collections::HashMap;
 use std::str::{self, FromStr};
 use std::time::Instant;
-use std::{
-    collections::HashMap,
-    io::{BufRead, BufReader, Read},
-};
 
 use rayon::prelude::*;
 
+use crate::error::PanacusError;
 use crate::file_formats::gfa_parser::grammar::Grammar;
+use crate::io::{for_each_gfa_chunk, get_lines};
 use crate::{
     file_formats::gfa_parser::Edge,
     util::{intersects, is_contained, ActiveTable, CountType, IntervalContainer, ItemTable},
@@ -19,25 +18,74 @@ use super::{abacus::GraphMask, graph::GraphStorage, ItemId, Orientation, PathSeg
 
 const CHUNK_SIZE: usize = 8192;
 
-pub fn parse_gfa_paths_walks<R: Read>(
-    data: &mut BufReader<R>,
-    graph_mask: &GraphMask,
-    graph_storage: &GraphStorage,
-    grammar: &Grammar,
-    count: &CountType,
-    paths_to_collect: &Vec<PathSegment>,
-) -> (
+/// Number of nodes and bps of each path
+pub type PathLengths = HashMap<PathSegment, (u32, u32)>;
+
+/// Item table, exclude table, partially covered bps of the subset, number
+/// of nodes and bps of each path and the collected paths
+pub type PathsWalksTables = (
     ItemTable,
     Option<ActiveTable>,
     Option<IntervalContainer>,
-    HashMap<PathSegment, (u32, u32)>,
+    PathLengths,
     HashMap<PathSegment, Vec<(ItemId, Orientation)>>,
-) {
+);
+
+/// Sequence of a P- or W-line, parsed (in parallel with the other lines of
+/// its chunk) before the tables are updated in order of the file
+enum ParsedSequence {
+    /// The path intersects neither with subset nor with exclude coordinates
+    Skipped,
+    /// Segment ids and bp length of a path that is fully contained in the
+    /// subset (and exclude) coordinates
+    Complete(Vec<ItemId>, u32),
+    /// Steps of a path that is only partially contained
+    Steps(Vec<(ItemId, Orientation)>),
+}
+
+struct ParsedPath<'a> {
+    path_seg: PathSegment,
+    include_coords: &'a [(usize, usize)],
+    exclude_coords: &'a [(usize, usize)],
+    start: usize,
+    sequence: ParsedSequence,
+}
+
+fn get_coords<'a>(
+    path_seg: &PathSegment,
+    coords: &Option<Vec<PathSegment>>,
+    coord_map: &'a HashMap<String, Vec<(usize, usize)>>,
+    default: &'a [(usize, usize)],
+) -> &'a [(usize, usize)] {
+    if coords.is_none() {
+        return default;
+    }
+    match coord_map.get(&path_seg.id()) {
+        None => &[],
+        Some(coords) => {
+            log::debug!(
+                "found coords {:?} for path segment {}",
+                &coords[..],
+                &path_seg.id()
+            );
+            &coords[..]
+        }
+    }
+}
+
+pub fn parse_gfa_paths_walks(
+    gfa_file: &str,
+    graph_mask: &GraphMask,
+    graph_storage: &GraphStorage,
+    grammar: &Grammar,
+    count: &CountType,
+    paths_to_collect: &[PathSegment],
+) -> Result<PathsWalksTables, PanacusError> {
     log::info!("parsing path + walk sequences");
     let mut item_table = ItemTable::new(graph_storage.path_segments.len());
 
     let (mut subset_covered_bps, mut exclude_table, include_map, exclude_map) =
-        graph_mask.load_optional_subsetting(graph_storage, count);
+        graph_mask.load_optional_subsetting(graph_storage, count)?;
 
     let mut collected_paths: HashMap<PathSegment, Vec<(ItemId, Orientation)>> = HashMap::new();
     let mut num_path = 0;
@@ -47,121 +95,126 @@ pub fn parse_gfa_paths_walks<R: Read>(
     // Used to check whether a node was already fully included in a previous path
     let mut fully_included = vec![false; graph_storage.node_count + 1];
 
-    let mut buf = vec![];
-    let timer = Instant::now();
-    while data.read_until(b'\n', &mut buf).unwrap_or(0) > 0 {
-        if buf[0] == b'P' || buf[0] == b'W' {
-            let (path_seg, buf_path_seg) = match buf[0] {
-                b'P' => parse_path_identifier(&buf),
-                b'W' => parse_walk_identifier(&buf),
-                _ => unreachable!(),
+    let parse_path = |line: &[u8]| -> Result<ParsedPath, String> {
+        let (path_seg, buf_path_seg) = match line[0] {
+            b'P' => parse_path_identifier(line),
+            b'W' => parse_walk_identifier(line),
+            _ => unreachable!(),
+        };
+        log::debug!("processing path {:?}", &path_seg);
+
+        let include_coords = get_coords(
+            &path_seg,
+            &graph_mask.include_coords,
+            &include_map,
+            &complete,
+        );
+        let exclude_coords = get_coords(&path_seg, &graph_mask.exclude_coords, &exclude_map, &[]);
+        let (start, end) = path_seg.coords().unwrap_or((0, usize::MAX));
+
+        // do not process the path sequence if path is neither part of subset nor exclude
+        let sequence = if graph_mask.include_coords.is_some()
+            && !intersects(include_coords, &(start, end))
+            && !intersects(exclude_coords, &(start, end))
+        {
+            log::debug!("path {} does not intersect with subset coordinates {:?} nor with exclude coordinates {:?} and therefore is skipped from processing", &path_seg, &include_coords, &exclude_coords);
+            ParsedSequence::Skipped
+        } else if count != &CountType::Edge
+            && (graph_mask.include_coords.is_none() || is_contained(include_coords, &(start, end)))
+            && (graph_mask.exclude_coords.is_none() || is_contained(exclude_coords, &(start, end)))
+        {
+            log::debug!("path {} is fully contained within subset coordinates {:?} and is eligible for full parallel processing", path_seg, include_coords);
+            let (segment_ids, bp_len) = match line[0] {
+                b'P' => parse_path_seq_segment_ids(buf_path_seg, graph_storage)?,
+                _ => parse_walk_seq_segment_ids(buf_path_seg, graph_storage, grammar)?,
             };
+            ParsedSequence::Complete(segment_ids, bp_len)
+        } else {
+            ParsedSequence::Steps(match line[0] {
+                b'P' => parse_path_seq_to_item_vec(buf_path_seg, graph_storage)?,
+                _ => parse_walk_seq_to_item_vec(buf_path_seg, graph_storage, grammar)?,
+            })
+        };
+        Ok(ParsedPath {
+            path_seg,
+            include_coords,
+            exclude_coords,
+            start,
+            sequence,
+        })
+    };
 
-            log::debug!("processing path {:?}", &path_seg);
-
-            let include_coords = if graph_mask.include_coords.is_none() {
-                &complete[..]
-            } else {
-                match include_map.get(&path_seg.id()) {
-                    None => &[],
-                    Some(coords) => {
-                        log::debug!(
-                            "found include coords {:?} for path segment {}",
-                            &coords[..],
-                            &path_seg.id()
-                        );
-                        &coords[..]
-                    }
-                }
-            };
-            let exclude_coords = if graph_mask.exclude_coords.is_none() {
-                &[]
-            } else {
-                match exclude_map.get(&path_seg.id()) {
-                    None => &[],
-                    Some(coords) => {
-                        log::debug!(
-                            "found exclude coords {:?} for path segment {}",
-                            &coords[..],
-                            &path_seg.id()
-                        );
-                        &coords[..]
-                    }
+    let timer = Instant::now();
+    let mut line_count = 0;
+    for_each_gfa_chunk(gfa_file, |chunk| {
+        let lines = get_lines(chunk);
+        let paths: Vec<ParsedPath> = lines
+            .par_iter()
+            .enumerate()
+            .filter(|(_, line)| line[0] == b'P' || line[0] == b'W')
+            .map(|(i, line)| {
+                parse_path(line)
+                    .map_err(|msg| PanacusError::parse(gfa_file, line_count + i + 1, msg))
+            })
+            .collect::<Result<_, _>>()?;
+        line_count += lines.len();
+        for ParsedPath {
+            path_seg,
+            include_coords,
+            exclude_coords,
+            start,
+            sequence,
+        } in paths
+        {
+            match sequence {
+                ParsedSequence::Skipped => {
+                    // update prefix sum
+                    item_table.id_prefsum[num_path + 1] += item_table.id_prefsum[num_path];
                 }
-            };
-
-            let (start, end) = path_seg.coords().unwrap_or((0, usize::MAX));
-
-            // do not process the path sequence if path is neither part of subset nor exclude
-            if graph_mask.include_coords.is_some()
-                && !intersects(include_coords, &(start, end))
-                && !intersects(exclude_coords, &(start, end))
-            {
-                log::debug!("path {} does not intersect with subset coordinates {:?} nor with exclude coordinates {:?} and therefore is skipped from processing", &path_seg, &include_coords, &exclude_coords);
-
-                // update prefix sum
-                item_table.id_prefsum[num_path + 1] += item_table.id_prefsum[num_path];
-
-                num_path += 1;
-                buf.clear();
-                continue;
-            }
-
-            if count != &CountType::Edge
-                && (graph_mask.include_coords.is_none()
-                    || is_contained(include_coords, &(start, end)))
-                && (graph_mask.exclude_coords.is_none()
-                    || is_contained(exclude_coords, &(start, end)))
-            {
-                log::debug!("path {} is fully contained within subset coordinates {:?} and is eligible for full parallel processing", path_seg, include_coords);
-                let ex = if exclude_coords.is_empty() {
-                    None
-                } else {
-                    exclude_table.as_mut()
-                };
-                let should_collect_path = paths_to_collect.iter().any(|p| path_seg.is_part_of(p));
-                let (num_added_nodes, bp_len, path) = match buf[0] {
-                    b'P' => parse_path_seq_update_tables(
-                        buf_path_seg,
-                        graph_storage,
-                        &mut item_table,
-                        ex,
-                        num_path,
-                        should_collect_path,
-                    ),
-                    b'W' => parse_walk_seq_update_tables(
-                        buf_path_seg,
-                        graph_storage,
-                        grammar,
+                ParsedSequence::Complete(segment_ids, bp_len) => {
+                    let ex = if exclude_coords.is_empty() {
+                        None
+                    } else {
+                        exclude_table.as_mut()
+                    };
+                    let should_collect_path =
+                        paths_to_collect.iter().any(|p| path_seg.is_part_of(p));
+                    let (num_added_nodes, path) = add_segment_ids_to_tables(
+                        segment_ids,
                         &mut item_table,
                         ex,
                         num_path,
                         should_collect_path,
-                    ),
-                    _ => unreachable!(),
-                };
-                if should_collect_path {
-                    log::info!("Full collected: {:?}", path_seg);
-                    collected_paths.insert(path_seg.clone(), path);
-                }
-                paths_len.insert(path_seg, (num_added_nodes, bp_len));
-            } else {
-                let sids = match buf[0] {
-                    b'P' => parse_path_seq_to_item_vec(buf_path_seg, graph_storage),
-                    b'W' => parse_walk_seq_to_item_vec(buf_path_seg, graph_storage, grammar),
-                    _ => unreachable!(),
-                };
-
-                if paths_to_collect.iter().any(|p| path_seg.is_part_of(p)) {
-                    log::info!("Collected: {:?}", path_seg);
-                    collected_paths.insert(path_seg.clone(), sids.clone());
+                    );
+                    if should_collect_path {
+                        log::info!("Full collected: {:?}", path_seg);
+                        collected_paths.insert(path_seg.clone(), path);
+                    }
+                    paths_len.insert(path_seg, (num_added_nodes, bp_len));
                 }
-                match count {
-                    CountType::Node | CountType::Bp => {
-                        let (node_len, bp_len) = update_tables(
+                ParsedSequence::Steps(sids) => {
+                    if paths_to_collect.iter().any(|p| path_seg.is_part_of(p)) {
+                        log::info!("Collected: {:?}", path_seg);
+                        collected_paths.insert(path_seg.clone(), sids.clone());
+                    }
+                    match count {
+                        CountType::Node | CountType::Bp => {
+                            let (node_len, bp_len) = update_tables(
+                                &mut item_table,
+                                &mut subset_covered_bps.as_mut(),
+                                &mut fully_included,
+                                &mut exclude_table.as_mut(),
+                                num_path,
+                                graph_storage,
+                                sids,
+                                include_coords,
+                                exclude_coords,
+                                start,
+                            );
+                            paths_len.insert(path_seg, (node_len as u32, bp_len as u32));
+                        }
+                        CountType::Edge => update_tables_edgecount(
                             &mut item_table,
-                            &mut subset_covered_bps.as_mut(),
-                            &mut fully_included,
                             &mut exclude_table.as_mut(),
                             num_path,
                             graph_storage,
@@ -169,57 +222,77 @@ pub fn parse_gfa_paths_walks<R: Read>(
                             include_coords,
                             exclude_coords,
                             start,
-                        );
-                        paths_len.insert(path_seg, (node_len as u32, bp_len as u32));
-                    }
-                    CountType::Edge => update_tables_edgecount(
-                        &mut item_table,
-                        &mut exclude_table.as_mut(),
-                        num_path,
-                        graph_storage,
-                        sids,
-                        include_coords,
-                        exclude_coords,
-                        start,
-                    ),
-                };
+                        ),
+                        CountType::Bubble | CountType::Allele => {
+                            unreachable!(
+                                "Bubble traversals are parsed in parse_gfa_bubble_traversals"
+                            )
+                        }
+                    };
+                }
             }
             num_path += 1;
         }
-        buf.clear();
-    }
+        Ok(())
+    })?;
     let duration = timer.elapsed();
     log::info!(
         "func done; count: {:?}; time elapsed: {:?}",
         count,
         duration
     );
-    (
+    Ok((
         item_table,
         exclude_table,
         subset_covered_bps,
         paths_len,
         collected_paths,
-    )
+    ))
 }
 
-pub fn parse_path_seq_update_tables(
+/// Segment ids and bp length of a path sequence
+fn parse_path_seq_segment_ids(
     data: &[u8],
     graph_storage: &GraphStorage,
-    item_table: &mut ItemTable,
-    exclude_table: Option<&mut ActiveTable>,
-    num_path: usize,
-    should_collect_path: bool,
-) -> (u32, u32, Vec<(ItemId, Orientation)>) {
-    let mut it = data.iter();
-    let end = it
+) -> Result<(Vec<ItemId>, u32), String> {
+    let end = data
+        .iter()
         .position(|x| x == &b'\t' || x == &b'\n' || x == &b'\r')
-        .unwrap();
+        .unwrap_or(data.len());
 
     log::debug!("parsing path sequences of size {} bytes..", end);
+    get_path_segment_ids(data, graph_storage, end, CHUNK_SIZE)
+}
 
-    let (segment_ids, bp_len) = get_path_segment_ids(data, graph_storage, end, CHUNK_SIZE);
+/// Segment ids (with meta-segments decompressed) and bp length of a walk
+/// sequence
+fn parse_walk_seq_segment_ids(
+    data: &[u8],
+    graph_storage: &GraphStorage,
+    grammar: &Grammar,
+) -> Result<(Vec<ItemId>, u32), String> {
+    // later codes assumes that data is non-empty...
+    if data.is_empty() {
+        return Ok((Vec::new(), 0));
+    }
+    let end = data
+        .iter()
+        .position(|x| x == &b'\t' || x == &b'\n' || x == &b'\r')
+        .unwrap_or(data.len());
 
+    log::debug!("parsing walk sequences of size {}..", end);
+    get_walk_segment_ids(data, graph_storage, grammar, end, CHUNK_SIZE)
+}
+
+/// Adds all segments of a fully contained path to the item table. Returns
+/// the number of added segments and, if it should be collected, the path.
+fn add_segment_ids_to_tables(
+    segment_ids: Vec<ItemId>,
+    item_table: &mut ItemTable,
+    exclude_table: Option<&mut ActiveTable>,
+    num_path: usize,
+    should_collect_path: bool,
+) -> (u32, Vec<(ItemId, Orientation)>) {
     // This function is only called with nodes, so orientation shouldn't matter
     let path = if should_collect_path {
         segment_ids
@@ -230,14 +303,13 @@ pub fn parse_path_seq_update_tables(
         Vec::new()
     };
 
-    segment_ids.into_iter().for_each(|segment_id| {
-        item_table.items.push(segment_id.0);
-        item_table.id_prefsum[num_path + 1] += 1;
-    });
+    let num_nodes_path = segment_ids.len() as u64;
+    item_table
+        .items
+        .extend(segment_ids.into_iter().map(|s| s.0));
+    item_table.id_prefsum[num_path + 1] += num_nodes_path;
 
     // compute prefix sum
-    let mut num_nodes_path = 0;
-    num_nodes_path += item_table.id_prefsum[num_path + 1];
     item_table.id_prefsum[num_path + 1] += item_table.id_prefsum[num_path];
 
     // is exclude table is given, we assume that all nodes of the path are excluded
@@ -251,7 +323,7 @@ pub fn parse_path_seq_update_tables(
     }
 
     log::debug!("..done");
-    (num_nodes_path as u32, bp_len, path)
+    (num_nodes_path as u32, path)
 }
 
 fn get_path_segment_ids(
@@ -259,7 +331,7 @@ fn get_path_segment_ids(
     graph_storage: &GraphStorage,
     end: usize,
     chunk_size: usize,
-) -> (Vec<ItemId>, u32) {
+) -> Result<(Vec<ItemId>, u32), String> {
     let (segment_ids, bp_lens): (Vec<_>, Vec<_>) = (0..end)
         .into_par_iter()
         .step_by(chunk_size)
@@ -289,78 +361,22 @@ fn get_path_segment_ids(
                 if curr_pos >= segment_end {
                     break;
                 }
-                let segment_id = get_segment_id(&data[curr_pos..segment_end], graph_storage);
+                let segment_id = get_segment_id(&data[curr_pos..segment_end], graph_storage)?;
                 bp_len += graph_storage.node_len(&segment_id);
                 segment_ids.push(segment_id);
                 // move curr_pos forward (after next comma)
                 curr_pos = segment_end + 1;
             }
-            (segment_ids, bp_len)
+            Ok((segment_ids, bp_len))
         })
+        .collect::<Result<Vec<_>, String>>()?
+        .into_iter()
         .unzip();
 
     let segment_ids = segment_ids.into_iter().concat();
     let bp_len = bp_lens.into_iter().sum();
 
-    (segment_ids, bp_len)
-}
-
-pub fn parse_walk_seq_update_tables(
-    data: &[u8],
-    graph_storage: &GraphStorage,
-    grammar: &Grammar,
-    item_table: &mut ItemTable,
-    exclude_table: Option<&mut ActiveTable>,
-    num_path: usize,
-    should_collect_path: bool,
-) -> (u32, u32, Vec<(ItemId, Orientation)>) {
-    // later codes assumes that data is non-empty...
-    if data.is_empty() {
-        return (0, 0, Vec::new());
-    }
-
-    let mut it = data.iter();
-    let end = it
-        .position(|x| x == &b'\t' || x == &b'\n' || x == &b'\r')
-        .unwrap();
-
-    log::debug!("parsing walk sequences of size {}..", end);
-
-    let (segment_ids, bp_len) = get_walk_segment_ids(data, graph_storage, grammar, end, CHUNK_SIZE);
-
-    let path = if should_collect_path {
-        segment_ids
-            .iter()
-            .map(|x| (*x, Orientation::Forward))
-            .collect()
-    } else {
-        Vec::new()
-    };
-
-    let initial_len = item_table.items.len();
-    item_table
-        .items
-        .extend(segment_ids.into_iter().map(|s| s.0));
-    let items_added = item_table.items.len() - initial_len;
-    item_table.id_prefsum[num_path + 1] += items_added as u64;
-
-    // compute prefix sum
-    let mut num_nodes_path = 0;
-    num_nodes_path += item_table.id_prefsum[num_path + 1];
-    item_table.id_prefsum[num_path + 1] += item_table.id_prefsum[num_path];
-
-    // is exclude table is given, we assume that all nodes of the path are excluded
-    if let Some(ex) = exclude_table {
-        log::error!("flagging nodes of path as excluded");
-        for j in (item_table.id_prefsum[num_path] as usize)
-            ..(item_table.id_prefsum[num_path + 1] as usize)
-        {
-            ex.items[item_table.items[j] as usize] |= true;
-        }
-    }
-
-    log::debug!("..done");
-    (num_nodes_path as u32, bp_len, path)
+    Ok((segment_ids, bp_len))
 }
 
 fn get_walk_segment_ids(
@@ -369,7 +385,7 @@ fn get_walk_segment_ids(
     grammar: &Grammar,
     end: usize,
     chunk_size: usize,
-) -> (Vec<ItemId>, u32) {
+) -> Result<(Vec<ItemId>, u32), String> {
     let (segment_ids, bp_lens): (Vec<_>, Vec<_>) = (0..end)
         .into_par_iter()
         .step_by(chunk_size)
@@ -399,7 +415,7 @@ fn get_walk_segment_ids(
                     &data[curr_pos..segment_end],
                     graph_storage,
                     grammar,
-                );
+                )?;
                 for segment in &segment_id {
                     bp_len += graph_storage.node_len(&segment);
                 }
@@ -407,13 +423,15 @@ fn get_walk_segment_ids(
                 // move curr_pos forward (after next comma)
                 curr_pos = segment_end;
             }
-            (segment_ids, bp_len)
+            Ok((segment_ids, bp_len))
         })
+        .collect::<Result<Vec<_>, String>>()?
+        .into_iter()
         .unzip();
 
     let segment_ids = segment_ids.into_iter().concat();
     let bp_len = bp_lens.into_iter().sum();
-    (segment_ids, bp_len)
+    Ok((segment_ids, bp_len))
 }
 
 pub fn parse_walk_identifier(data: &[u8]) -> (PathSegment, &[u8]) {
@@ -692,14 +710,32 @@ pub fn update_tables_edgecount(
     log::debug!("..done");
 }
 
+/// Identifier and steps of a P- or W-line
+pub fn parse_path_or_walk(
+    line: &[u8],
+    graph_storage: &GraphStorage,
+    grammar: &Grammar,
+) -> Result<(PathSegment, Vec<(ItemId, Orientation)>), String> {
+    if line[0] == b'P' {
+        let (path_seg, data) = parse_path_identifier(line);
+        Ok((path_seg, parse_path_seq_to_item_vec(data, graph_storage)?))
+    } else {
+        let (path_seg, data) = parse_walk_identifier(line);
+        Ok((
+            path_seg,
+            parse_walk_seq_to_item_vec(data, graph_storage, grammar)?,
+        ))
+    }
+}
+
 pub fn parse_walk_seq_to_item_vec(
     data: &[u8],
     graph_storage: &GraphStorage,
     grammar: &Grammar,
-) -> Vec<(ItemId, Orientation)> {
+) -> Result<Vec<(ItemId, Orientation)>, String> {
     // later codes assumes that data is non-empty...
     if data.is_empty() {
-        return Vec::new();
+        return Ok(Vec::new());
     }
 
     let mut it = data.iter();
@@ -733,7 +769,7 @@ pub fn parse_walk_seq_to_item_vec(
                 if curr_pos >= segment_end {
                     break;
                 }
-                let segment_id = get_walk_segment_id(&data[curr_pos..segment_end], graph_storage);
+                let segment_id = get_walk_segment_id(&data[curr_pos..segment_end], graph_storage)?;
                 let orientation = Orientation::from_lg(data[curr_pos]);
 
                 let sids = grammar.decompress_ordered(segment_id, orientation, graph_storage);
@@ -741,20 +777,20 @@ pub fn parse_walk_seq_to_item_vec(
                 // move curr_pos forward (after next comma)
                 curr_pos = segment_end;
             }
-            segment_ids
+            Ok(segment_ids)
         })
-        .collect();
+        .collect::<Result<_, String>>()?;
 
     let sids = segment_ids.into_iter().concat();
 
     log::debug!("..done");
-    sids
+    Ok(sids)
 }
 
 pub fn parse_path_seq_to_item_vec(
     data: &[u8],
     graph_storage: &GraphStorage,
-) -> Vec<(ItemId, Orientation)> {
+) -> Result<Vec<(ItemId, Orientation)>, String> {
     let mut it = data.iter();
     let end = it
         .position(|x| x == &b'\t' || x == &b'\n' || x == &b'\r')
@@ -790,90 +826,94 @@ pub fn parse_path_seq_to_item_vec(
                 if curr_pos >= segment_end {
                     break;
                 }
-                let segment_id = get_segment_id(&data[curr_pos..segment_end], graph_storage);
+                let segment_id = get_segment_id(&data[curr_pos..segment_end], graph_storage)?;
                 let orientation = Orientation::from_pm(data[segment_end - 1]);
                 segment_ids.push((segment_id, orientation));
                 // move curr_pos forward (after next comma)
                 curr_pos = segment_end + 1;
             }
-            segment_ids
+            Ok(segment_ids)
         })
-        .collect();
+        .collect::<Result<_, String>>()?;
 
     log::debug!("..done");
 
     let segment_ids = segment_ids.into_iter().concat();
-    segment_ids
+    Ok(segment_ids)
 }
 
-fn get_segment_id(node: &[u8], graph_storage: &GraphStorage) -> ItemId {
+fn get_segment_id(node: &[u8], graph_storage: &GraphStorage) -> Result<ItemId, String> {
     let segment_id = graph_storage
         .get_node_id(&node[0..node.len() - 1])
-        .unwrap_or_else(|| panic!("unknown node {}", str::from_utf8(node).unwrap()));
+        .ok_or_else(|| format!("unknown node {}", String::from_utf8_lossy(node)))?;
     // TODO: Is orientation really necessary?
     let orientation = node[node.len() - 1];
-    assert!(
-        orientation == b'-' || orientation == b'+',
-        "unknown orientation of segment {}",
-        str::from_utf8(node).unwrap()
-    );
+    if orientation != b'-' && orientation != b'+' {
+        return Err(format!(
+            "unknown orientation of segment {}",
+            String::from_utf8_lossy(node)
+        ));
+    }
     //plus_strands[rayon::current_thread_index().unwrap()] += (orientation == b'+') as u32;
-    segment_id
+    Ok(segment_id)
 }
 
-fn get_walk_segment_id(node: &[u8], graph_storage: &GraphStorage) -> ItemId {
+fn get_walk_segment_id(node: &[u8], graph_storage: &GraphStorage) -> Result<ItemId, String> {
     let segment_id = graph_storage
         .get_node_id(&node[1..node.len()])
-        .unwrap_or_else(|| panic!("unknown node {}", str::from_utf8(node).unwrap()));
+        .ok_or_else(|| format!("unknown node {}", String::from_utf8_lossy(node)))?;
     // TODO: Is orientation really necessary?
     let orientation = node[0];
-    assert!(
-        orientation == b'<' || orientation == b'>',
-        "unknown orientation of segment {}",
-        str::from_utf8(node).unwrap()
-    );
+    if orientation != b'<' && orientation != b'>' {
+        return Err(format!(
+            "unknown orientation of segment {}",
+            String::from_utf8_lossy(node)
+        ));
+    }
     //plus_strands[rayon::current_thread_index().unwrap()] += (orientation == b'+') as u32;
-    segment_id
+    Ok(segment_id)
 }
 
 fn get_walk_segment_id_grammar(
     node: &[u8],
     graph_storage: &GraphStorage,
     grammar: &Grammar,
-) -> Vec<ItemId> {
+) -> Result<Vec<ItemId>, String> {
     let segment_id = graph_storage
         .get_node_id(&node[1..node.len()])
-        .unwrap_or_else(|| panic!("unknown node {}", str::from_utf8(node).unwrap()));
+        .ok_or_else(|| format!("unknown node {}", String::from_utf8_lossy(node)))?;
     // TODO: Is orientation really necessary?
     let orientation = node[0];
-    assert!(
-        orientation == b'<' || orientation == b'>',
-        "unknown orientation of segment {}",
-        str::from_utf8(node).unwrap()
-    );
+    if orientation != b'<' && orientation != b'>' {
+        return Err(format!(
+            "unknown orientation of segment {}",
+            String::from_utf8_lossy(node)
+        ));
+    }
 
     let sids = grammar.decompress_unordered(segment_id, graph_storage);
     //plus_strands[rayon::current_thread_index().unwrap()] += (orientation == b'+') as u32;
-    sids
+    Ok(sids)
 }
 
 #[cfg(test)]
 fn get_walk_segment_id_with_orientation(
     node: &[u8],
     graph_storage: &GraphStorage,
-) -> (ItemId, Orientation) {
+) -> Result<(ItemId, Orientation), String> {
     let segment_id = graph_storage
         .get_node_id(&node[1..node.len()])
-        .unwrap_or_else(|| panic!("unknown node {}", str::from_utf8(node).unwrap()));
+        .ok_or_else(|| format!("unknown node {}", String::from_utf8_lossy(node)))?;
     // TODO: Is orientation really necessary?
     let orientation = node[0];
-    assert!(
-        orientation == b'<' || orientation == b'>',
-        "unknown orientation of segment {}",
-        str::from_utf8(node).unwrap()
-    );
+    if orientation != b'<' && orientation != b'>' {
+        return Err(format!(
+            "unknown orientation of segment {}",
+            String::from_utf8_lossy(node)
+        ));
+    }
     //plus_strands[rayon::current_thread_index().unwrap()] += (orientation == b'+') as u32;
-    (segment_id, Orientation::from_lg(orientation))
+    Ok((segment_id, Orientation::from_lg(orientation)))
 }
 
 #[cfg(test)]
@@ -882,7 +922,7 @@ fn get_walk_segment_ids_with_orientation(
     graph_storage: &GraphStorage,
     end: usize,
     chunk_size: usize,
-) -> (Vec<(ItemId, Orientation)>, u32) {
+) -> Result<(Vec<(ItemId, Orientation)>, u32), String> {
     let (segment_ids, bp_lens): (Vec<_>, Vec<_>) = (0..end)
         .step_by(chunk_size)
         .map(|chunk_start| {
@@ -910,39 +950,42 @@ fn get_walk_segment_ids_with_orientation(
                 let segment_id = get_walk_segment_id_with_orientation(
                     &data[curr_pos..segment_end],
                     graph_storage,
-                );
+                )?;
                 bp_len += graph_storage.node_len(&segment_id.0);
                 segment_ids.push(segment_id);
                 // move curr_pos forward (after next comma)
                 curr_pos = segment_end;
             }
-            (segment_ids, bp_len)
+            Ok((segment_ids, bp_len))
         })
+        .collect::<Result<Vec<_>, String>>()?
+        .into_iter()
         .unzip();
 
     let segment_ids = segment_ids.into_iter().concat();
     let bp_len = bp_lens.into_iter().sum();
-    (segment_ids, bp_len)
+    Ok((segment_ids, bp_len))
 }
 
 #[cfg(test)]
 fn get_segment_id_with_orientation(
     node: &[u8],
     graph_storage: &GraphStorage,
-) -> (ItemId, Orientation) {
+) -> Result<(ItemId, Orientation), String> {
     let segment_id = graph_storage
         .get_node_id(&node[0..node.len() - 1])
-        .unwrap_or_else(|| panic!("unknown node {}", str::from_utf8(node).unwrap()));
+        .ok_or_else(|| format!("unknown node {}", String::from_utf8_lossy(node)))?;
     // TODO: Is orientation really necessary?
     let orientation = node[node.len() - 1];
-    assert!(
-        orientation == b'-' || orientation == b'+',
-        "unknown orientation of segment {}",
-        str::from_utf8(node).unwrap()
-    );
+    if orientation != b'-' && orientation != b'+' {
+        return Err(format!(
+            "unknown orientation of segment {}",
+            String::from_utf8_lossy(node)
+        ));
+    }
     let orientation = Orientation::from_pm(orientation);
     //plus_strands[rayon::current_thread_index().unwrap()] += (orientation == b'+') as u32;
-    (segment_id, orientation)
+    Ok((segment_id, orientation))
 }
 
 #[cfg(test)]
@@ -951,7 +994,7 @@ fn get_path_segment_ids_with_orientation(
     graph_storage: &GraphStorage,
     end: usize,
     chunk_size: usize,
-) -> (Vec<(ItemId, Orientation)>, u32) {
+) -> Result<(Vec<(ItemId, Orientation)>, u32), String> {
     let (segment_ids, bp_lens): (Vec<_>, Vec<_>) = (0..end)
         .into_par_iter()
         .step_by(chunk_size)
@@ -982,25 +1025,59 @@ fn get_path_segment_ids_with_orientation(
                     break;
                 }
                 let segment_id =
-                    get_segment_id_with_orientation(&data[curr_pos..segment_end], graph_storage);
+                    get_segment_id_with_orientation(&data[curr_pos..segment_end], graph_storage)?;
                 bp_len += graph_storage.node_len(&segment_id.0);
                 segment_ids.push(segment_id);
                 // move curr_pos forward (after next comma)
                 curr_pos = segment_end + 1;
             }
-            (segment_ids, bp_len)
+            Ok((segment_ids, bp_len))
         })
+        .collect::<Result<Vec<_>, String>>()?
+        .into_iter()
         .unzip();
 
     let segment_ids = segment_ids.into_iter().concat();
     let bp_len = bp_lens.into_iter().sum();
 
-    (segment_ids, bp_len)
+    Ok((segment_ids, bp_len))
 }
 
 #[cfg(test)]
 mod tests {
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
     use super::*;
+    use crate::file_formats::gfa_parser::GraphMaskParameters;
+
+    #[test]
+    fn test_path_with_unknown_node() {
+        let lines = [
+            "P\tx\t1+,3+\t*\n",
+            "P\tx\t1+,2*\t*\n",
+            "W\ts\t1\tc\t*\t*\t>1>3\n",
+        ];
+        for line in lines {
+            let mut f = NamedTempFile::new().unwrap();
+            write!(f, "S\t1\tA\nS\t2\tC\n{}", line).unwrap();
+            let gfa_file = f.path().to_str().unwrap();
+            let (graph_storage, _) = GraphStorage::from_gfa(gfa_file, false).unwrap();
+            let graph_mask =
+                GraphMask::from_datamgr(&GraphMaskParameters::default(), &graph_storage).unwrap();
+            for count in [CountType::Node, CountType::Edge] {
+                let result = parse_gfa_paths_walks(
+                    gfa_file,
+                    &graph_mask,
+                    &graph_storage,
+                    &Grammar::from(count),
+                    &count,
+                    &[],
+                );
+                assert!(matches!(result, Err(PanacusError::Parse { line: 3, .. })));
+            }
+        }
+    }
 
     #[test]
     fn test_path_chunk_sizes() {
@@ -1009,7 +1086,8 @@ mod tests {
         let end = it
             .position(|x| x == &b'\t' || x == &b'\n' || x == &b'\r')
             .unwrap();
-        let (graph_storage, _) = GraphStorage::from_gfa("tests/test_files/t_groups.gfa", true);
+        let (graph_storage, _) =
+            GraphStorage::from_gfa("tests/test_files/t_groups.gfa", true).unwrap();
         let exp = vec![
             (ItemId(1), Orientation::Forward),
             (ItemId(3), Orientation::Forward),
@@ -1023,7 +1101,8 @@ mod tests {
             (ItemId(15), Orientation::Forward),
         ];
         for i in 1..35 {
-            let (res, _) = get_path_segment_ids_with_orientation(data, &graph_storage, end, i);
+            let (res, _) =
+                get_path_segment_ids_with_orientation(data, &graph_storage, end, i).unwrap();
             assert_eq!(res, exp);
         }
     }
@@ -1035,7 +1114,8 @@ mod tests {
         let end = it
             .position(|x| x == &b'\t' || x == &b'\n' || x == &b'\r')
             .unwrap();
-        let (graph_storage, _) = GraphStorage::from_gfa("tests/test_files/t_groups.gfa", true);
+        let (graph_storage, _) =
+            GraphStorage::from_gfa("tests/test_files/t_groups.gfa", true).unwrap();
         let exp = vec![
             (ItemId(1), Orientation::Forward),
             (ItemId(3), Orientation::Forward),
@@ -1049,7 +1129,8 @@ mod tests {
             (ItemId(15), Orientation::Forward),
         ];
         for i in 1..35 {
-            let (res, _) = get_walk_segment_ids_with_orientation(data, &graph_storage, end, i);
+            let (res, _) =
+                get_walk_segment_ids_with_orientation(data, &graph_storage, end, i).unwrap();
             assert_eq!(res, exp);
         }
     }
@@ -7,11 +7,12 @@ use std::{
 
 use anyhow::Result;
 use itertools::Itertools;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    analyses::info::FileInfo, coverage_matrix::CoverageMatrix, file_formats::FileFormatParser,
-    hist::Hist,
+    analyses::info::FileInfo, coverage_matrix::CoverageMatrix, error::PanacusError,
+    file_formats::FileFormatParser, hist::Hist,
 };
 
 pub struct VcfParser {
@@ -25,14 +26,16 @@ pub struct VcfParser {
 }
 
 impl FileFormatParser for VcfParser {
-    fn generate_hist(self: Box<Self>) -> crate::hist::Hist {
-        let file = File::open(&self.filename).expect("Can read file");
+    fn generate_hist(self: Box<Self>) -> Result<Hist, PanacusError> {
+        let file =
+            File::open(&self.filename).map_err(|e| PanacusError::file_read(&self.filename, e))?;
         let buf_reader = BufReader::new(file);
         self.generate_hist_from_reader(buf_reader)
     }
 
-    fn generate_matrix(self: Box<Self>) -> CoverageMatrix {
-        let file = File::open(&self.filename).expect("Can read file");
+    fn generate_matrix(self: Box<Self>) -> Result<CoverageMatrix, PanacusError> {
+        let file =
+            File::open(&self.filename).map_err(|e| PanacusError::file_read(&self.filename, e))?;
         let buf_reader = BufReader::new(file);
         self.generate_matrix_from_reader(buf_reader)
     }
@@ -76,37 +79,22 @@ impl VcfParser {
         count_type: VcfCountType,
         split_haplotypes: bool,
         exclude: Option<String>,
-    ) -> Result<Self> {
+    ) -> Result<Self, PanacusError> {
         let exclude = match exclude {
             Some(filename) => {
-                let f = File::open(filename).expect("Exclude file must exist");
+                let f = File::open(&filename).map_err(|e| PanacusError::file_read(&filename, e))?;
                 let mut reader = BufReader::new(f);
                 let mut buf = String::new();
                 reader
                     .read_to_string(&mut buf)
-                    .expect("Exclude file must be readable");
-                let res = buf
-                    .lines()
-                    .map(|l| {
-                        let mut fields = l.split('\t');
-                        let ref_name = fields
-                            .next()
-                            .expect("Exclude file should not contain empty lines")
-                            .to_owned();
-                        let start = fields
-                            .next()
-                            .expect("Three columns are required")
-                            .parse::<usize>()
-                            .unwrap();
-                        let end = fields
-                            .next()
-                            .expect("Three columns are required")
-                            .parse::<usize>()
-                            .unwrap();
-                        (ref_name, start, end)
+                    .map_err(|e| PanacusError::file_read(&filename, e))?;
+                buf.lines()
+                    .enumerate()
+                    .map(|(i, l)| {
+                        parse_exclude_line(l)
+                            .map_err(|msg| PanacusError::parse(&filename, i + 1, msg))
                     })
-                    .collect();
-                res
+                    .collect::<Result<Vec<_>, _>>()?
             }
             None => Vec::new(),
         };
@@ -120,9 +108,13 @@ impl VcfParser {
         })
     }
 
-    fn generate_hist_from_reader(self: Box<Self>, buf_reader: impl BufRead) -> Hist {
-        let mut lines = buf_reader.lines().map(|l| l.expect("Failed to read line"));
-        let mut header = VcfHeader::parse(&mut lines).expect("Failed to parse header");
+    fn generate_hist_from_reader(
+        self: Box<Self>,
+        buf_reader: impl BufRead,
+    ) -> Result<Hist, PanacusError> {
+        let mut lines = buf_reader.lines();
+        let mut header =
+            VcfHeader::parse(&mut lines).map_err(|e| PanacusError::file_read(&self.filename, e))?;
         let paths = std::mem::take(&mut header.paths);
         let run_id = format!(
             "{}-{}-{}",
@@ -133,10 +125,11 @@ impl VcfParser {
         let run_name = run_id.clone();
         let mut hist =
             Hist::from_maximum_coverage(paths.len(), self.count_type.to_string(), run_id, run_name);
-        for line in lines {
+        for (i, line) in lines.enumerate() {
+            let line = line.map_err(|e| PanacusError::file_read(&self.filename, e))?;
             let variants = self
                 .parse_variant_line_to_count(&line)
-                .expect("Failed to parse variant");
+                .map_err(|e| self.get_parse_error(header.lines + i + 1, e))?;
             for variant in variants {
                 let coverage = variant.value;
                 if coverage == 0 {
@@ -152,16 +145,17 @@ impl VcfParser {
                 }
             }
         }
-        hist
+        Ok(hist)
     }
 
     fn generate_matrix_from_reader(
         mut self: Box<Self>,
         buf_reader: impl BufRead,
-    ) -> CoverageMatrix {
-        let mut lines = buf_reader.lines().map(|l| l.expect("Failed to read line"));
+    ) -> Result<CoverageMatrix, PanacusError> {
+        let mut lines = buf_reader.lines();
 
-        let mut header = VcfHeader::parse(&mut lines).expect("Failed to parse header");
+        let mut header =
+            VcfHeader::parse(&mut lines).map_err(|e| PanacusError::file_read(&self.filename, e))?;
         let paths = std::mem::take(&mut header.paths);
 
         let run_id = format!(
@@ -184,12 +178,13 @@ impl VcfParser {
 
         let mut are_paths_initialized = false;
 
-        for line in lines {
+        for (i, line) in lines.enumerate() {
+            let line = line.map_err(|e| PanacusError::file_read(&self.filename, e))?;
             stats.num_variants += 1;
 
             let variants = self
                 .parse_variant_line_to_allele_list(&line)
-                .expect("Failed to parse variant");
+                .map_err(|e| self.get_parse_error(header.lines + i + 1, e))?;
 
             for variant in variants {
                 let feature = variant.value;
@@ -224,7 +219,7 @@ impl VcfParser {
         }
         let file_info = stats.to_file_info();
         matrix.set_file_info(file_info);
-        matrix
+        Ok(matrix)
     }
 
     fn generate_path_names(&self, paths: &[String], feature: &[Vec<u32>]) -> Vec<String> {
@@ -247,6 +242,10 @@ impl VcfParser {
             .collect()
     }
 
+    fn get_parse_error(&self, line: usize, error: anyhow::Error) -> PanacusError {
+        PanacusError::parse(&self.filename, line, error.to_string())
+    }
+
     fn get_split_haplotype_text(&self) -> &str {
         match self.split_haplotypes {
             true => "haplotypes",
@@ -272,43 +271,31 @@ impl VcfParser {
             } else {
                 vec![vec![vec![0; 1]; samples.len()]; num_alts]
             };
-            samples.into_iter().enumerate().for_each(|(idx, sample)| {
-                let sample_genotype = sample
-                    .split(":")
-                    .nth(genotype_position)
-                    .expect("Sample needs a genotype");
+            for (idx, sample) in samples.into_iter().enumerate() {
+                let sample_genotype = get_genotype(sample, genotype_position)?;
                 if !self.split_haplotypes {
-                    sample_genotype
-                        .split(&['|', '/'])
-                        .filter_map(|x| {
-                            if x == "." || x == "0" {
-                                None
-                            } else {
-                                Some(x.parse::<usize>().expect("Allele is integer"))
-                            }
-                        })
-                        .unique()
-                        .for_each(|x| {
-                            allele_lists[x - 1][idx][0] = 1;
-                        });
+                    for x in sample_genotype.split(&['|', '/']) {
+                        if x != "." && x != "0" {
+                            allele_lists[parse_allele(x, num_alts)? - 1][idx][0] = 1;
+                        }
+                    }
                 } else {
-                    sample_genotype.split(&['|', '/']).for_each(|x| {
-                        for i in 0..num_alts {
-                            if x == "." {
-                                allele_lists[i][idx].push(0);
+                    for x in sample_genotype.split(&['|', '/']) {
+                        let value = match x {
+                            "." => 0,
+                            x => parse_allele(x, num_alts)?,
+                        };
+                        for (i, alleles) in allele_lists.iter_mut().enumerate() {
+                            if (i + 1) == value {
+                                alleles[idx].push(1);
                             } else {
-                                let value = x.parse::<usize>().expect("Allele is integer");
-                                if (i + 1) == value {
-                                    allele_lists[i][idx].push(1);
-                                } else {
-                                    allele_lists[i][idx].push(0);
-                                }
+                                alleles[idx].push(0);
                             }
                         }
-                    });
+                    }
                 }
-            });
-            allele_lists
+            }
+            Ok(allele_lists)
         })
     }
 
@@ -316,35 +303,30 @@ impl VcfParser {
     fn parse_variant_line_to_count<'a>(&self, line: &'a str) -> Result<Vec<Variant<'a, usize>>> {
         self.parse_variant_line_base(line, |samples, num_alts, genotype_position| {
             let mut counts = vec![0; num_alts];
-            samples.for_each(|sample| {
-                let sample_genotype = sample
-                    .split(":")
-                    .nth(genotype_position)
-                    .expect("Sample needs a genotype");
+            for sample in samples {
+                let sample_genotype = get_genotype(sample, genotype_position)?;
                 if !self.split_haplotypes {
-                    sample_genotype
+                    let alleles = sample_genotype
                         .split(&['|', '/'])
-                        .filter_map(|x| {
-                            if x == "." || x == "0" {
-                                None
-                            } else {
-                                Some(x.parse::<usize>().expect("Allele is integer"))
-                            }
-                        })
+                        .filter(|x| *x != "." && *x != "0")
+                        .map(|x| parse_allele(x, num_alts))
+                        .collect::<Result<Vec<_>>>()?;
+                    alleles
+                        .into_iter()
                         .unique()
                         .for_each(|x| counts[x - 1] += 1);
                 } else {
-                    sample_genotype.split(&['|', '/']).for_each(|x| {
+                    for x in sample_genotype.split(&['|', '/']) {
                         if x != "." {
-                            let value = x.parse::<usize>().expect("Allele is integer");
+                            let value = parse_allele(x, num_alts)?;
                             if value > 0 {
                                 counts[value - 1] += 1;
                             }
                         }
-                    });
+                    }
                 }
-            });
-            counts
+            }
+            Ok(counts)
         })
     }
 
@@ -355,7 +337,7 @@ impl VcfParser {
     fn parse_variant_line_base<'a, T>(
         &self,
         line: &'a str,
-        process_samples: impl FnOnce(SplitWhitespace<'a>, usize, usize) -> Vec<T>,
+        process_samples: impl FnOnce(SplitWhitespace<'a>, usize, usize) -> Result<Vec<T>>,
     ) -> Result<Vec<Variant<'a, T>>> {
         let mut fields = line.trim().split_whitespace();
         let chrom = fields
@@ -394,7 +376,7 @@ impl VcfParser {
             .ok_or_else(|| anyhow::anyhow!("Variant needs format field!"))?;
         let genotype_position = format.split(":").position(|x| x == "GT").unwrap_or(0);
         let num_alts = alt_lengths.len();
-        let values = process_samples(fields, num_alts, genotype_position);
+        let values = process_samples(fields, num_alts, genotype_position)?;
         Ok(alt_lengths
             .into_iter()
             .zip(values.into_iter())
@@ -426,17 +408,24 @@ struct Variant<'a, T> {
 
 struct VcfHeader {
     paths: Vec<String>,
+    // Number of lines up to and including the #CHROM line
+    lines: usize,
 }
 
 impl VcfHeader {
-    fn parse<I, S>(lines: &mut I) -> Result<Self>
+    fn parse<I, S>(lines: &mut I) -> std::io::Result<Self>
     where
-        I: Iterator<Item = S>,
+        I: Iterator<Item = std::io::Result<S>>,
         S: AsRef<str>,
     {
-        let mut header = Self { paths: Vec::new() };
+        let mut header = Self {
+            paths: Vec::new(),
+            lines: 0,
+        };
 
         for line in lines {
+            header.lines += 1;
+            let line = line?;
             let line = line.as_ref().trim_end();
             if line.starts_with("##") {
                 continue;
@@ -450,8 +439,55 @@ impl VcfHeader {
     }
 }
 
+/// Returns the genotype (GT) field of a sample
+fn get_genotype(sample: &str, genotype_position: usize) -> Result<&str> {
+    sample
+        .split(':')
+        .nth(genotype_position)
+        .ok_or_else(|| anyhow::anyhow!("Sample {} needs a genotype!", sample))
+}
+
+/// Parses an allele of a genotype, which is either the reference (0) or one of
+/// the `num_alts` alt alleles
+fn parse_allele(allele: &str, num_alts: usize) -> Result<usize> {
+    let value = allele
+        .parse::<usize>()
+        .map_err(|_| anyhow::anyhow!("Allele {} is not an integer!", allele))?;
+    if value > num_alts {
+        anyhow::bail!(
+            "Allele {} does not exist, variant has {} alt alleles!",
+            value,
+            num_alts
+        );
+    }
+    Ok(value)
+}
+
+/// Parses a line (reference name, start, end) of an exclude file
+fn parse_exclude_line(line: &str) -> Result<(String, usize, usize), String> {
+    let fields: Vec<&str> = line.split('\t').collect();
+    if fields.len() < 3 {
+        return Err(format!(
+            "exclude file requires three columns, but line has {}",
+            fields.len()
+        ));
+    }
+    let parse_position = |x: &str| {
+        x.trim()
+            .parse::<usize>()
+            .map_err(|_| format!("`{}` is not an usize", x))
+    };
+    Ok((
+        fields[0].to_owned(),
+        parse_position(fields[1])?,
+        parse_position(fields[2])?,
+    ))
+}
+
 /// What is set for the length of a feature
-#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[derive(
+    Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema,
+)]
 pub enum VcfCountType {
     /// 1 for every feature (just count the number of variants)
     Variants,
@@ -524,7 +560,7 @@ chr10	7	>13046>13130_6	C	G	60	.	AC=1;AF=0.0212766;AN=47;NS=232;LV=0;ORIGIN=chr10
 
     #[test]
     fn test_vcf_header() {
-        let mut header_lines = VCF_HEADER.lines();
+        let mut header_lines = VCF_HEADER.lines().map(Ok);
         let vcf_header = VcfHeader::parse(&mut header_lines).unwrap();
         assert_eq!(vcf_header.paths.len(), 3);
     }
@@ -668,7 +704,9 @@ chr10	7	>13046>13130_6	C	G	60	.	AC=1;AF=0.0212766;AN=47;NS=232;LV=0;ORIGIN=chr10
     #[test]
     fn test_generate_hist_samples_allele_length() {
         let parser = Box::new(VcfParser::new("", VcfCountType::AltLength, false, None).unwrap());
-        let hist = parser.generate_hist_from_reader(VCF_TEST.as_bytes());
+        let hist = parser
+            .generate_hist_from_reader(VCF_TEST.as_bytes())
+            .unwrap();
         assert_eq!(hist.get_number_of_features(), 449);
         assert_eq!(hist.get_maximum_coverage(), 232);
     }
@@ -676,7 +714,9 @@ chr10	7	>13046>13130_6	C	G	60	.	AC=1;AF=0.0212766;AN=47;NS=232;LV=0;ORIGIN=chr10
     #[test]
     fn test_generate_hist_samples_variants() {
         let parser = Box::new(VcfParser::new("", VcfCountType::Variants, false, None).unwrap());
-        let hist = parser.generate_hist_from_reader(VCF_TEST.as_bytes());
+        let hist = parser
+            .generate_hist_from_reader(VCF_TEST.as_bytes())
+            .unwrap();
         assert_eq!(hist.get_number_of_features(), 13);
         assert_eq!(hist.get_maximum_coverage(), 232);
         let mut hist_values = vec![0; 232 + 1];
@@ -687,22 +727,96 @@ chr10	7	>13046>13130_6	C	G	60	.	AC=1;AF=0.0212766;AN=47;NS=232;LV=0;ORIGIN=chr10
     #[test]
     fn test_generate_matrix_samples_variants() {
         let parser = Box::new(VcfParser::new("", VcfCountType::Variants, false, None).unwrap());
-        let matrix = parser.generate_matrix_from_reader(VCF_TEST.as_bytes());
+        let matrix = parser
+            .generate_matrix_from_reader(VCF_TEST.as_bytes())
+            .unwrap();
         assert_eq!(matrix.get_feature_count(), 13);
         assert_eq!(matrix.get_path_names().len(), 232);
     }
 
     #[test]
-    #[should_panic]
-    fn test_hist_panic_on_emtpy_open() {
+    fn test_hist_error_on_emtpy_open() {
         let parser = Box::new(VcfParser::new("", VcfCountType::AltLength, false, None).unwrap());
-        parser.generate_hist();
+        assert!(matches!(
+            parser.generate_hist(),
+            Err(PanacusError::FileRead { .. })
+        ));
     }
 
     #[test]
-    #[should_panic]
-    fn test_matrix_panic_on_emtpy_open() {
+    fn test_matrix_error_on_emtpy_open() {
         let parser = Box::new(VcfParser::new("", VcfCountType::AltLength, false, None).unwrap());
-        parser.generate_matrix();
+        assert!(matches!(
+            parser.generate_matrix(),
+            Err(PanacusError::FileRead { .. })
+        ));
+    }
+
+    #[test]
+    fn test_variant_parse_error_contains_line() {
+        let vcf = format!("{}\n20\tx\trs6054257\tG\tA", VCF_HEADER);
+        let parser =
+            Box::new(VcfParser::new("t.vcf", VcfCountType::Variants, false, None).unwrap());
+        let error = parser
+            .generate_hist_from_reader(vcf.as_bytes())
+            .unwrap_err();
+        assert!(matches!(error, PanacusError::Parse { line: 20, .. }));
+    }
+
+    fn get_genotype_error(genotypes: &str, split_haplotypes: bool) -> PanacusError {
+        let vcf = format!(
+            "{}\n20\t14370\t.\tG\tA\t29\tPASS\t.\t{}",
+            VCF_HEADER, genotypes
+        );
+        let parser = Box::new(
+            VcfParser::new("t.vcf", VcfCountType::Variants, split_haplotypes, None).unwrap(),
+        );
+        parser
+            .generate_matrix_from_reader(vcf.as_bytes())
+            .unwrap_err()
+    }
+
+    #[test]
+    fn test_genotype_is_not_an_integer() {
+        for split_haplotypes in [false, true] {
+            let error = get_genotype_error("GT\t0|0\t1|x\t0/0", split_haplotypes);
+            assert!(matches!(error, PanacusError::Parse { line: 20, .. }));
+        }
+        let vcf = format!(
+            "{}\n20\t14370\t.\tG\tA\t29\tPASS\t.\tGT\t0|0\t1|x\t0/0",
+            VCF_HEADER
+        );
+        let parser =
+            Box::new(VcfParser::new("t.vcf", VcfCountType::Variants, false, None).unwrap());
+        assert!(matches!(
+            parser.generate_hist_from_reader(vcf.as_bytes()),
+            Err(PanacusError::Parse { line: 20, .. })
+        ));
+    }
+
+    #[test]
+    fn test_genotype_is_missing() {
+        for split_haplotypes in [false, true] {
+            let error = get_genotype_error("GQ:GT\t48:0|0\t48\t48:0/0", split_haplotypes);
+            assert!(matches!(error, PanacusError::Parse { line: 20, .. }));
+        }
+    }
+
+    #[test]
+    fn test_allele_is_out_of_range() {
+        for split_haplotypes in [false, true] {
+            let error = get_genotype_error("GT\t0|0\t0|2\t0/0", split_haplotypes);
+            assert!(matches!(error, PanacusError::Parse { line: 20, .. }));
+        }
+    }
+
+    #[test]
+    fn test_parse_exclude_line() {
+        assert_eq!(
+            parse_exclude_line("chr1\t10\t20"),
+            Ok(("chr1".to_string(), 10, 20))
+        );
+        assert!(parse_exclude_line("chr1\t10").is_err());
+        assert!(parse_exclude_line("chr1\ta\t20").is_err());
     }
 }
@@ -41,7 +41,7 @@ impl Hist {
         self.hist.is_empty() || self.hist.iter().all(|&v| v == 0)
     }
 
-    /// Subtracts correction from h[1], used to correct
+    /// Subtracts correction from `h[1]`, used to correct
     /// for an increase in singletons often seen in Histograms
     /// that can be a problem when fitting curves to the Growth
     /// curve / F_new curve
@@ -1,8 +1,13 @@
+pub mod comparison;
+mod data_caps;
+mod deferred;
+mod markdown;
+
 use base64::engine::general_purpose::STANDARD;
 use std::ffi::OsStr;
 use std::fs::File;
 use std::io::{BufReader, Read};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::{collections::HashMap, str::from_utf8};
 use std::{f64, fmt};
 
@@ -10,9 +15,12 @@ use base64::{engine::general_purpose, Engine};
 use handlebars::{to_json, Handlebars, RenderError};
 
 use itertools::Itertools;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use time::{macros::format_description, OffsetDateTime};
 
+use crate::error::PanacusError;
+use crate::provenance::Provenance;
 use crate::util::{get_default_plot_downloads, to_id};
 use shadow_rs::shadow;
 
@@ -46,16 +54,159 @@ pub const LINE_HBS: &[u8] = include_bytes!("../hbs/line.hbs");
 pub const PNG_HBS: &[u8] = include_bytes!("../hbs/png.hbs");
 pub const SVG_HBS: &[u8] = include_bytes!("../hbs/svg.hbs");
 pub const PDF_HBS: &[u8] = include_bytes!("../hbs/pdf.hbs");
+pub const ERROR_HBS: &[u8] = include_bytes!("../hbs/error.hbs");
+pub const TEXT_HBS: &[u8] = include_bytes!("../hbs/text.hbs");
 
 fn combine_vars(mut a: JsVars, b: JsVars) -> JsVars {
     for (k, v) in b {
-        if let Some(x) = a.get_mut(&k) {
-            x.extend(v);
-        }
+        a.entry(k).or_default().extend(v);
     }
     a
 }
 
+/// Options of the report: its title and description (see the `report:`
+/// block of the config) and the size of its data (see `report
+/// --max-plot-points`, `--inline-limit` and `--sidecar-dir`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReportOptions {
+    pub title: Option<String>,
+    /// In markdown, shown above the sections
+    pub description: Option<String>,
+    /// Plots with more values are downsampled or reduced to their largest
+    /// entries
+    pub max_plot_points: Option<usize>,
+    /// Sections with more bytes of data are stored compressed and decoded
+    /// when their tab is opened
+    pub inline_limit: usize,
+    /// Directory the data of all sections is written to instead
+    pub sidecar_dir: Option<PathBuf>,
+    /// Directory of the report, the sidecar files are referenced relative to
+    /// it (default: the current directory)
+    pub report_dir: Option<PathBuf>,
+    /// Provenance of the results (one per run of panacus that contributed to
+    /// the report), which can be downloaded from the report
+    pub provenance: Vec<Provenance>,
+}
+
+impl Default for ReportOptions {
+    fn default() -> Self {
+        Self {
+            title: None,
+            description: None,
+            max_plot_points: None,
+            inline_limit: 64 << 10,
+            sidecar_dir: None,
+            report_dir: None,
+            provenance: Vec::new(),
+        }
+    }
+}
+
+/// `display:` options of an analysis, which control how its sections are
+/// shown in the report. Defaults for all analyses can be given in the
+/// `report:` block of the config.
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord, JsonSchema,
+)]
+#[serde(deny_unknown_fields)]
+pub struct DisplayOptions {
+    /// Title of the sections, instead of the name of the analysis
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Text shown above the plots, in markdown
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Label of the x axis of bar and line plots
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub x_label: Option<String>,
+    /// Label of the y axis of bar and line plots
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub y_label: Option<String>,
+    /// Colors (e.g. `"#1f77b4"` or `steelblue`) of the series of the plots,
+    /// plots of a single series use the first one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub palette: Option<Vec<String>>,
+    /// Whether line plots start with a logarithmic x axis
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_x: Option<bool>,
+    /// Whether plots start with a logarithmic y axis
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_y: Option<bool>,
+    /// Number of hexagons along the x and y axis of hexbin plots (at least 2)
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_hexbin_resolution"
+    )]
+    pub hexbin_resolution: Option<[u32; 2]>,
+    /// Width of the plots in pixels
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    /// Height of the plots in pixels
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+}
+
+impl DisplayOptions {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Sets the options that are not given to the defaults
+    pub fn fill_defaults(&mut self, defaults: &Self) {
+        fn fill<T: Clone>(value: &mut Option<T>, default: &Option<T>) {
+            if value.is_none() {
+                *value = default.clone();
+            }
+        }
+        fill(&mut self.title, &defaults.title);
+        fill(&mut self.description, &defaults.description);
+        fill(&mut self.x_label, &defaults.x_label);
+        fill(&mut self.y_label, &defaults.y_label);
+        fill(&mut self.palette, &defaults.palette);
+        fill(&mut self.log_x, &defaults.log_x);
+        fill(&mut self.log_y, &defaults.log_y);
+        fill(&mut self.hexbin_resolution, &defaults.hexbin_resolution);
+        fill(&mut self.width, &defaults.width);
+        fill(&mut self.height, &defaults.height);
+    }
+
+    /// The options that are applied by the JavaScript of the report
+    fn to_js_object(&self) -> Option<String> {
+        let object = serde_json::json!({
+            "palette": self.palette,
+            "width": self.width,
+            "height": self.height,
+            "log_y": self.log_y,
+            "hexbin_resolution": self.hexbin_resolution,
+        });
+        let is_empty = object
+            .as_object()
+            .map_or(true, |o| o.values().all(|v| v.is_null()));
+        (!is_empty).then(|| object.to_string())
+    }
+}
+
+/// Hexbin plots need at least 2 hexagons along each axis
+pub fn check_hexbin_resolution(resolution: &Option<[u32; 2]>) -> Result<(), String> {
+    match resolution {
+        Some(resolution) if resolution.iter().any(|n| *n < 2) => Err(format!(
+            "hexbin_resolution {:?} needs at least 2 hexagons per axis",
+            resolution
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn deserialize_hexbin_resolution<'de, D>(deserializer: D) -> Result<Option<[u32; 2]>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let resolution = Option::deserialize(deserializer)?;
+    check_hexbin_resolution(&resolution).map_err(serde::de::Error::custom)?;
+    Ok(resolution)
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct AnalysisSection {
     pub analysis: String,
@@ -66,9 +217,25 @@ pub struct AnalysisSection {
     pub id: String,
     pub table: Option<String>,
     pub plot_downloads: Vec<(String, String)>,
+    #[serde(default, skip_serializing_if = "DisplayOptions::is_default")]
+    pub display: DisplayOptions,
 }
 
 impl AnalysisSection {
+    /// Title of the section in the report
+    pub fn get_title(&self) -> &str {
+        self.display.title.as_deref().unwrap_or(&self.analysis)
+    }
+
+    /// Applies the `display:` options of the analysis to the section and its
+    /// plots
+    pub fn apply_display(&mut self, display: &DisplayOptions) {
+        for item in &mut self.items {
+            item.apply_display(display);
+        }
+        self.display = display.clone();
+    }
+
     fn into_html(self, registry: &mut Handlebars) -> RenderedHTML {
         if !registry.has_template("analysis_tab") {
             registry
@@ -87,6 +254,7 @@ impl AnalysisSection {
                 ("name", "".to_string()),
             ])]
         };
+        let title = self.get_title().to_string();
         let items = self
             .items
             .into_iter()
@@ -101,10 +269,16 @@ impl AnalysisSection {
                 );
             }
         }
-        let js_objects = js_objects
+        let mut js_objects = js_objects
             .into_iter()
             .reduce(combine_vars)
             .unwrap_or_default();
+        if let Some(display) = self.display.to_js_object() {
+            js_objects
+                .entry("display".to_string())
+                .or_default()
+                .insert(self.id.clone(), display);
+        }
         let plot_downloads: Vec<HashMap<&str, String>> = self
             .plot_downloads
             .iter()
@@ -114,7 +288,11 @@ impl AnalysisSection {
             .collect();
         let vars = HashMap::from([
             ("id", to_json(&self.id)),
-            ("analysis", to_json(&self.analysis)),
+            ("analysis", to_json(title)),
+            (
+                "description",
+                to_json(self.display.description.as_deref().map(markdown::to_html)),
+            ),
             ("run_name", to_json(&self.run_name)),
             ("run_id", to_json(&self.run_id)),
             ("countable", to_json(&self.countable)),
@@ -131,11 +309,31 @@ impl AnalysisSection {
         Ok((registry.render("analysis_tab", &vars)?, js_objects))
     }
 
-    pub fn generate_custom_section(name: String, file: String) -> anyhow::Result<Vec<Self>> {
-        let id = name.to_lowercase().replace(&[' ', '|', '\\'], "-");
-        let id = format!("custom-{id}");
+    /// Creates the section of a custom analysis, showing a user-supplied
+    /// image (png, svg, pdf), Vega-Lite specification (json) or table (tsv,
+    /// csv) below an optional description
+    pub fn generate_custom_section(
+        name: &str,
+        file: &str,
+        description: Option<&str>,
+        run_name: &str,
+        run_id: &str,
+    ) -> Result<Self, PanacusError> {
+        let id = format!(
+            "custom-{}-{}",
+            run_id.to_lowercase().replace([' ', '|', '\\'], "-"),
+            to_id(name)
+        );
+        let mut items = Vec::new();
+        if let Some(description) = description {
+            items.push(ReportItem::Text {
+                id: format!("text-{id}"),
+                text: description.to_string(),
+            });
+        }
         let mut table: Option<String> = None;
         let mut plot_downloads = Vec::new();
+        let file = file.to_string();
         let report_item = match get_extension_from_filename(&file) {
             Some("svg") => {
                 plot_downloads = vec![("svg".to_string(), "Download as svg".to_string())];
@@ -152,26 +350,29 @@ impl AnalysisSection {
                 }
             }
             Some("json") => {
+                // The specification is embedded when the report is rendered,
+                // check it beforehand
+                let content = std::fs::read_to_string(&file)
+                    .map_err(|e| PanacusError::file_read(&file, e))?;
+                serde_json::from_str::<serde_json::Value>(&content).map_err(|e| {
+                    PanacusError::parse(&file, e.line(), format!("invalid JSON: {}", e))
+                })?;
                 plot_downloads = get_default_plot_downloads();
+                // The download buttons refer to the plot by the section id
                 ReportItem::Json {
-                    id: format!("json-{id}"),
+                    id: id.clone(),
                     file,
                 }
             }
             Some(t @ "csv") | Some(t @ "tsv") => {
-                let f = File::open(&file)?;
-                let mut reader = BufReader::new(f);
-                let mut buffer = String::new();
-                reader.read_to_string(&mut buffer)?;
+                let buffer = std::fs::read_to_string(&file)
+                    .map_err(|e| PanacusError::file_read(&file, e))?;
                 table = Some(format!("`{}`", buffer));
                 let split_char = if t == "csv" { "," } else { "\t" };
                 let mut lines = buffer.lines();
                 let header = lines
                     .next()
-                    .expect(&format!(
-                        "{} file {} should contain at least one line",
-                        t, file
-                    ))
+                    .ok_or_else(|| PanacusError::parse(&file, 1, "table has no header"))?
                     .split(split_char)
                     .map(|x| x.trim().to_owned())
                     .collect();
@@ -192,18 +393,62 @@ impl AnalysisSection {
                 id: format!("pdf-{id}"),
                 file,
             },
-            _ => unimplemented!("Other formats have not been implemented yet"),
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "custom file {} is not a png, svg, pdf, json, tsv or csv file",
+                    file
+                )
+                .into())
+            }
         };
-        Ok(vec![AnalysisSection {
-            id: id,
+        items.push(report_item);
+        Ok(AnalysisSection {
+            id,
             analysis: "Custom".to_string(),
-            run_name: "My Custom Run".to_string(),
-            run_id: "MyCustomRun".to_string(),
-            countable: name,
+            run_name: run_name.to_string(),
+            run_id: run_id.to_string(),
+            countable: name.to_string(),
             table,
-            items: vec![report_item],
+            items,
             plot_downloads,
-        }])
+            display: DisplayOptions::default(),
+        })
+    }
+
+    /// Creates the section of an analysis that failed, showing its error
+    pub fn generate_failed_section(
+        analysis: &str,
+        run_name: &str,
+        run_id: &str,
+        countable: &str,
+        error: &PanacusError,
+    ) -> Self {
+        let id = format!(
+            "failed-{}-{}-{}",
+            to_id(analysis),
+            run_id.to_lowercase().replace([' ', '|', '\\'], "-"),
+            countable
+        );
+        AnalysisSection {
+            id: id.clone(),
+            analysis: "Failed Analyses".to_string(),
+            run_name: run_name.to_string(),
+            run_id: run_id.to_string(),
+            countable: format!("{} ({})", analysis, countable),
+            table: None,
+            items: vec![ReportItem::Error {
+                id: format!("{id}-error"),
+                message: error.to_string(),
+            }],
+            plot_downloads: Vec::new(),
+            display: DisplayOptions::default(),
+        }
+    }
+}
+
+fn set_if_some<T: Clone>(value: &mut T, option: &Option<T>) {
+    if let Some(option) = option {
+        *value = option.clone();
     }
 }
 
@@ -232,38 +477,48 @@ fn get_js_objects_string(objects: JsVars) -> String {
 
 impl AnalysisSection {
     pub fn generate_report(
-        sections: Vec<Self>,
+        mut sections: Vec<Self>,
         registry: &mut Handlebars,
         filename: &str,
         config: &str,
+        options: &ReportOptions,
     ) -> Result<String, RenderError> {
         if !registry.has_template("report") {
             registry.register_template_string("report", from_utf8(REPORT_HBS).unwrap())?;
         }
+        if let Some(max_points) = options.max_plot_points {
+            for item in sections.iter_mut().flat_map(|s| s.items.iter_mut()) {
+                data_caps::cap_item(item, max_points);
+            }
+        }
 
         let tree = Self::get_tree(&sections, registry)?;
 
-        let (content, js_objects) = Self::generate_report_content(sections, registry, config)?;
+        let (content, js_objects) =
+            Self::generate_report_content(sections, registry, config, options)?;
         let mut vars = Self::get_variables();
         vars.insert("content", content);
         vars.insert("data_hook", get_js_objects_string(js_objects));
         vars.insert("fname", filename.to_string());
+        if let Some(title) = &options.title {
+            vars.insert("title", title.clone());
+        }
         vars.insert("tree", tree);
         registry.render("report", &vars)
     }
 
     fn get_tree(sections: &Vec<Self>, registry: &mut Handlebars) -> Result<String, RenderError> {
-        let analysis_names = sections.iter().map(|x| x.analysis.clone()).unique();
+        let analysis_names = sections.iter().map(|x| x.get_title()).unique();
         let mut analyses = Vec::new();
         for analysis_name in analysis_names {
             let run_ids = sections
                 .iter()
-                .filter(|x| x.analysis == analysis_name)
+                .filter(|x| x.get_title() == analysis_name)
                 .map(|x| (x.run_id.clone(), x.run_name.clone()))
                 .unique();
             let analysis_sections = sections
                 .iter()
-                .filter(|x| x.analysis == analysis_name)
+                .filter(|x| x.get_title() == analysis_name)
                 .collect::<Vec<_>>();
             let mut runs = Vec::new();
             for (run_id, run_name) in run_ids {
@@ -296,8 +551,8 @@ impl AnalysisSection {
                 runs.push(to_json(content));
             }
             let content = HashMap::from([
-                ("title", to_json(&analysis_name)),
-                ("id", to_json(to_id(&analysis_name))),
+                ("title", to_json(analysis_name)),
+                ("id", to_json(to_id(analysis_name))),
                 ("icon", to_json("icon-id")),
                 ("runs", to_json(runs)),
             ]);
@@ -375,6 +630,7 @@ impl AnalysisSection {
         sections: Vec<Self>,
         registry: &mut Handlebars,
         config: &str,
+        options: &ReportOptions,
     ) -> RenderedHTML {
         if !registry.has_template("report_content") {
             registry.register_template_string(
@@ -386,12 +642,21 @@ impl AnalysisSection {
         let sections = sections
             .into_iter()
             .map(|s| {
-                let (content, js_object) = s.into_html(registry).unwrap();
-                js_objects.push(js_object);
-                content
+                let id = s.id.clone();
+                let (content, js_object) = s.into_html(registry)?;
+                js_objects.push(deferred::defer_section_data(&id, js_object, options)?);
+                Ok(content)
             })
-            .collect::<Vec<String>>();
-        let text = registry.render("report_content", &sections)?;
+            .collect::<Result<Vec<String>, RenderError>>()?;
+        let vars = HashMap::from([
+            ("sections", to_json(sections)),
+            ("title", to_json(&options.title)),
+            (
+                "description",
+                to_json(options.description.as_deref().map(markdown::to_html)),
+            ),
+        ]);
+        let text = registry.render("report_content", &vars)?;
         let mut js_objects = js_objects
             .into_iter()
             .reduce(combine_vars)
@@ -401,6 +666,14 @@ impl AnalysisSection {
             "config".to_string(),
             HashMap::from([("first".to_string(), config_content)]),
         );
+        // Paths could contain `</script>`
+        let provenance = serde_json::to_string_pretty(&options.provenance)
+            .expect("provenance can be serialized")
+            .replace("</", "<\\/");
+        js_objects.insert(
+            "provenance".to_string(),
+            HashMap::from([("first".to_string(), provenance)]),
+        );
         Ok((text, js_objects))
     }
 }
@@ -473,6 +746,11 @@ pub enum ReportItem {
         id: String,
         file: String,
     },
+    /// SVG image drawn by an analysis, embedded without a file
+    SvgContent {
+        id: String,
+        svg: String,
+    },
     Json {
         id: String,
         file: String,
@@ -491,10 +769,18 @@ pub enum ReportItem {
         sequence: Vec<String>,
         values: Vec<Vec<Window>>,
     },
+    Error {
+        id: String,
+        message: String,
+    },
+    Text {
+        id: String,
+        text: String,
+    },
 }
 
 impl ReportItem {
-    fn get_id(&self) -> String {
+    pub fn get_id(&self) -> String {
         match self {
             Self::Bar { id, .. } => id.to_string(),
             Self::MultiBar { id, .. } => id.to_string(),
@@ -505,9 +791,12 @@ impl ReportItem {
             Self::Line { id, .. } => id.to_string(),
             Self::Png { id, .. } => id.to_string(),
             Self::Svg { id, .. } => id.to_string(),
+            Self::SvgContent { id, .. } => id.to_string(),
             Self::Json { id, .. } => id.to_string(),
             Self::Pdf { id, .. } => id.to_string(),
             Self::Chromosomal { id, .. } => id.to_string(),
+            Self::Error { id, .. } => id.to_string(),
+            Self::Text { id, .. } => id.to_string(),
         }
     }
 
@@ -521,10 +810,44 @@ impl ReportItem {
             Self::Hexbin { .. } => "Hexbin".to_string(),
             Self::Line { name, .. } => name.to_string(),
             Self::Png { .. } => "Png".to_string(),
-            Self::Svg { .. } => "Svg".to_string(),
+            Self::Svg { .. } | Self::SvgContent { .. } => "Svg".to_string(),
             Self::Json { .. } => "Json".to_string(),
             Self::Pdf { .. } => "Pdf".to_string(),
             Self::Chromosomal { .. } => "Chromosomal".to_string(),
+            Self::Error { .. } => "Error".to_string(),
+            Self::Text { .. } => "Text".to_string(),
+        }
+    }
+
+    /// Applies the axis labels and log scales of the `display:` options, the
+    /// other options are applied by the JavaScript of the report
+    fn apply_display(&mut self, display: &DisplayOptions) {
+        match self {
+            Self::Bar {
+                x_label, y_label, ..
+            }
+            | Self::MultiBar {
+                x_label, y_label, ..
+            }
+            | Self::SectionLine {
+                x_label, y_label, ..
+            } => {
+                set_if_some(x_label, &display.x_label);
+                set_if_some(y_label, &display.y_label);
+            }
+            Self::Line {
+                x_label,
+                y_label,
+                log_x,
+                log_y,
+                ..
+            } => {
+                set_if_some(x_label, &display.x_label);
+                set_if_some(y_label, &display.y_label);
+                set_if_some(log_x, &display.log_x);
+                set_if_some(log_y, &display.log_y);
+            }
+            _ => {}
         }
     }
 
@@ -908,15 +1231,17 @@ impl ReportItem {
                 ))
             }
             Self::Svg { id, file } => {
-                if !registry.has_template("svg") {
-                    registry.register_template_string("svg", from_utf8(SVG_HBS).unwrap())?;
-                }
                 let f = File::open(file)?;
                 let mut reader = BufReader::new(f);
                 let mut buffer = String::new();
                 reader.read_to_string(&mut buffer)?;
-                let svg_content = buffer;
-                let data = HashMap::from([("svg_content", &svg_content), ("id", &id)]);
+                Self::SvgContent { id, svg: buffer }.into_html(registry)
+            }
+            Self::SvgContent { id, svg } => {
+                if !registry.has_template("svg") {
+                    registry.register_template_string("svg", from_utf8(SVG_HBS).unwrap())?;
+                }
+                let data = HashMap::from([("svg_content", &svg), ("id", &id)]);
                 let js_object = format!("new DownloadHelper('{}', 'svg')", id,);
                 Ok((
                     registry.render("svg", &data)?,
@@ -962,6 +1287,26 @@ impl ReportItem {
                     HashMap::from([("datasets".to_string(), HashMap::new())]),
                 ))
             }
+            Self::Error { id, message } => {
+                if !registry.has_template("error") {
+                    registry.register_template_string("error", from_utf8(ERROR_HBS).unwrap())?;
+                }
+                let data = HashMap::from([("id", &id), ("message", &message)]);
+                Ok((
+                    registry.render("error", &data)?,
+                    HashMap::from([("datasets".to_string(), HashMap::new())]),
+                ))
+            }
+            Self::Text { id, text } => {
+                if !registry.has_template("text") {
+                    registry.register_template_string("text", from_utf8(TEXT_HBS).unwrap())?;
+                }
+                let data = HashMap::from([("id", &id), ("text", &text)]);
+                Ok((
+                    registry.render("text", &data)?,
+                    HashMap::from([("datasets".to_string(), HashMap::new())]),
+                ))
+            }
         }
     }
 }
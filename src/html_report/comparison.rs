@@ -0,0 +1,502 @@
+//! Comparison of the results of different runs (`render --compare`), e.g. of
+//! different graphs of the same samples. Sections of the same analysis and
+//! count type are matched, and their plots are overlaid in one plot per
+//! plot of the sections, together with a table of the differences to the
+//! first run.
+use std::collections::HashMap;
+
+use itertools::Itertools;
+
+use super::{AnalysisSection, DisplayOptions, ReportItem};
+use crate::util::{get_default_plot_downloads, to_id};
+
+/// Sections of these analyses are not compared
+const EXCLUDED_ANALYSES: [&str; 2] = ["Failed Analyses", "Custom"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PlotKind {
+    /// Bar and MultiBar plots, overlaid as MultiBar
+    Bar,
+    /// Line and SectionLine plots, overlaid as SectionLine
+    Line,
+}
+
+/// A single series of values of a plot
+#[derive(Debug, Clone, PartialEq)]
+struct Curve {
+    name: String,
+    labels: Vec<String>,
+    values: Vec<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Plot {
+    kind: PlotKind,
+    x_label: String,
+    y_label: String,
+    curves: Vec<Curve>,
+}
+
+impl Plot {
+    fn from_item(item: &ReportItem) -> Option<Self> {
+        let (kind, x_label, y_label, curves) = match item {
+            ReportItem::Bar {
+                x_label,
+                y_label,
+                labels,
+                values,
+                ..
+            } => (
+                PlotKind::Bar,
+                x_label,
+                y_label,
+                vec![Curve {
+                    name: String::new(),
+                    labels: labels.clone(),
+                    values: values.clone(),
+                }],
+            ),
+            ReportItem::MultiBar {
+                names,
+                x_label,
+                y_label,
+                labels,
+                values,
+                ..
+            } => (
+                PlotKind::Bar,
+                x_label,
+                y_label,
+                get_curves(names, labels, values),
+            ),
+            ReportItem::Line {
+                x_label,
+                y_label,
+                x_values,
+                y_values,
+                ..
+            } => (
+                PlotKind::Line,
+                x_label,
+                y_label,
+                vec![Curve {
+                    name: String::new(),
+                    labels: x_values.iter().map(|x| x.to_string()).collect(),
+                    values: y_values.iter().map(|y| *y as f64).collect(),
+                }],
+            ),
+            ReportItem::SectionLine {
+                names,
+                x_label,
+                y_label,
+                labels,
+                values,
+                ..
+            } => (
+                PlotKind::Line,
+                x_label,
+                y_label,
+                get_curves(names, labels, values),
+            ),
+            _ => return None,
+        };
+        Some(Self {
+            kind,
+            x_label: x_label.clone(),
+            y_label: y_label.clone(),
+            curves,
+        })
+    }
+}
+
+fn get_curves(names: &[String], labels: &[String], values: &[Vec<f64>]) -> Vec<Curve> {
+    names
+        .iter()
+        .zip(values)
+        .map(|(name, values)| Curve {
+            name: name.clone(),
+            labels: labels.to_vec(),
+            values: values.clone(),
+        })
+        .collect()
+}
+
+/// Creates a comparison section for each analysis and count type with
+/// sections of at least two runs. With `normalize`, the values of each curve
+/// are divided by its maximum, and sections of different count types (e.g.
+/// nodes and bps) are compared as well.
+pub fn compare_sections(sections: &[AnalysisSection], normalize: bool) -> Vec<AnalysisSection> {
+    let mut groups: Vec<((&str, &str), Vec<&AnalysisSection>)> = Vec::new();
+    for section in sections {
+        if EXCLUDED_ANALYSES.contains(&section.analysis.as_str()) {
+            continue;
+        }
+        let key = (
+            section.analysis.as_str(),
+            if normalize {
+                ""
+            } else {
+                section.countable.as_str()
+            },
+        );
+        match groups.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, group)) => group.push(section),
+            None => groups.push((key, vec![section])),
+        }
+    }
+    groups
+        .into_iter()
+        .filter(|(_, group)| group.len() > 1)
+        .map(|((analysis, _), group)| compare_group(analysis, &group, normalize))
+        .collect()
+}
+
+fn compare_group(analysis: &str, group: &[&AnalysisSection], normalize: bool) -> AnalysisSection {
+    let countables: Vec<&str> = group
+        .iter()
+        .map(|s| s.countable.as_str())
+        .unique()
+        .collect();
+    let countable = countables.join(", ");
+    let id = format!("cmp-{}-{}", to_id(analysis), to_id(&countables.join("-")));
+    let runs = get_run_names(group, normalize);
+    let plots: Vec<Vec<Plot>> = group
+        .iter()
+        .map(|s| s.items.iter().filter_map(Plot::from_item).collect())
+        .collect();
+    let n_plots = plots.iter().map(|p| p.len()).max().unwrap_or(0);
+    let mut items = Vec::new();
+    let mut tables = Vec::new();
+    for i in 0..n_plots {
+        // i-th plot of each run, if the run has it and it is of the same kind
+        // as the one of the first run having it
+        let mut run_plots = runs
+            .iter()
+            .zip(&plots)
+            .filter_map(|(run, plots)| plots.get(i).map(|plot| (run.as_str(), plot)))
+            .peekable();
+        let Some(kind) = run_plots.peek().map(|(_, plot)| plot.kind) else {
+            continue;
+        };
+        let run_plots: Vec<(&str, &Plot)> = run_plots.filter(|(_, p)| p.kind == kind).collect();
+        let item_id = format!("{id}-{}", i + 1);
+        let (item, table) = compare_plots(&item_id, &run_plots, normalize);
+        tables.push(get_table_text(&table));
+        items.push(item);
+        items.push(table);
+    }
+    AnalysisSection {
+        analysis: analysis.to_string(),
+        run_name: "Comparison".to_string(),
+        run_id: "comparison".to_string(),
+        countable,
+        items,
+        id,
+        table: Some(format!("`{}`", tables.join("\n"))),
+        plot_downloads: get_default_plot_downloads(),
+        display: DisplayOptions::default(),
+    }
+}
+
+/// Names of the runs of the sections, made unique
+fn get_run_names(group: &[&AnalysisSection], normalize: bool) -> Vec<String> {
+    let names: Vec<String> = group
+        .iter()
+        .map(|s| {
+            if normalize {
+                format!("{} ({})", s.run_name.trim(), s.countable)
+            } else {
+                s.run_name.trim().to_string()
+            }
+        })
+        .collect();
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for name in &names {
+        *counts.entry(name.as_str()).or_default() += 1;
+    }
+    let mut seen: HashMap<&str, usize> = HashMap::new();
+    names
+        .iter()
+        .map(|name| {
+            if counts[name.as_str()] == 1 {
+                return name.clone();
+            }
+            let n = seen.entry(name.as_str()).or_default();
+            *n += 1;
+            format!("{} [{}]", name, n)
+        })
+        .collect()
+}
+
+/// Labels of all curves, in order of their first appearance (sorted if all
+/// are finite numbers)
+fn merge_labels<'a, I: Iterator<Item = &'a Curve>>(curves: I) -> Vec<String> {
+    let mut labels: Vec<String> = curves
+        .flat_map(|c| c.labels.iter().cloned())
+        .unique()
+        .collect();
+    let numbers: Option<Vec<f64>> = labels
+        .iter()
+        .map(|l| l.parse::<f64>().ok().filter(|n| n.is_finite()))
+        .collect();
+    if let Some(numbers) = numbers {
+        let mut order: Vec<usize> = (0..labels.len()).collect();
+        order.sort_by(|&a, &b| numbers[a].partial_cmp(&numbers[b]).unwrap());
+        labels = order.into_iter().map(|i| labels[i].clone()).collect();
+    }
+    labels
+}
+
+/// Values of the curve at the labels, NaN for labels the curve has no value
+/// for
+fn align_values(curve: &Curve, labels: &[String], normalize: bool) -> Vec<f64> {
+    let values: HashMap<&str, f64> = curve
+        .labels
+        .iter()
+        .map(|l| l.as_str())
+        .zip(curve.values.iter().copied())
+        .collect();
+    let max = curve
+        .values
+        .iter()
+        .copied()
+        .filter(|v| v.is_finite())
+        .fold(0.0, f64::max);
+    labels
+        .iter()
+        .map(|l| match values.get(l.as_str()) {
+            Some(v) if normalize && max > 0.0 => v / max,
+            Some(v) => *v,
+            None => f64::NAN,
+        })
+        .collect()
+}
+
+/// Overlays the plots of the runs, and creates a table of the values and of
+/// their differences to the curve of the same name of the first run
+fn compare_plots(
+    id: &str,
+    run_plots: &[(&str, &Plot)],
+    normalize: bool,
+) -> (ReportItem, ReportItem) {
+    let (_, first) = run_plots[0];
+    let mut names = Vec::new();
+    let mut curves = Vec::new();
+    for (run, plot) in run_plots {
+        for curve in &plot.curves {
+            names.push(match (plot.curves.len(), curve.name.as_str()) {
+                (1, _) | (_, "") => run.to_string(),
+                _ => format!("{}: {}", run, curve.name),
+            });
+            curves.push(curve);
+        }
+    }
+    let labels = merge_labels(curves.iter().copied());
+    let values: Vec<Vec<f64>> = curves
+        .iter()
+        .map(|c| align_values(c, &labels, normalize))
+        .collect();
+    let y_label = if normalize {
+        "fraction of maximum".to_string()
+    } else {
+        first.y_label.clone()
+    };
+    let plot = match first.kind {
+        PlotKind::Bar => ReportItem::MultiBar {
+            id: id.to_string(),
+            names: names.clone(),
+            x_label: first.x_label.clone(),
+            y_label,
+            labels: labels.clone(),
+            values: values.clone(),
+            log_toggle: true,
+            curve: None,
+            alpha: None,
+        },
+        PlotKind::Line => ReportItem::SectionLine {
+            id: id.to_string(),
+            names: names.clone(),
+            x_label: first.x_label.clone(),
+            y_label,
+            labels: labels.clone(),
+            values: values.clone(),
+            section_separators: Vec::new(),
+            section_labels: Vec::new(),
+            log_toggle: true,
+        },
+    };
+
+    // Differences to the curves of the first run
+    let n_first = first.curves.len();
+    let mut header = vec![first.x_label.clone()];
+    header.extend(names.iter().cloned());
+    let mut differences = Vec::new();
+    for (i, curve) in curves.iter().enumerate().skip(n_first) {
+        let reference = first.curves.iter().position(|c| c.name == curve.name);
+        if let Some(reference) = reference {
+            header.push(format!("{} - {}", names[i], names[reference]));
+            differences.push((i, reference));
+        }
+    }
+    let rows = labels
+        .iter()
+        .enumerate()
+        .map(|(l, label)| {
+            let mut row = vec![label.clone()];
+            row.extend(values.iter().map(|v| format_value(v[l])));
+            row.extend(
+                differences
+                    .iter()
+                    .map(|(i, reference)| format_value(values[*i][l] - values[*reference][l])),
+            );
+            row
+        })
+        .collect();
+    let table = ReportItem::Table {
+        id: format!("{id}-diff"),
+        header,
+        values: rows,
+    };
+    (plot, table)
+}
+
+fn format_value(v: f64) -> String {
+    if v.is_nan() {
+        String::new()
+    } else if v.fract() == 0.0 {
+        format!("{}", v)
+    } else {
+        format!("{:.4}", v)
+    }
+}
+
+fn get_table_text(table: &ReportItem) -> String {
+    match table {
+        ReportItem::Table { header, values, .. } => {
+            let mut text = header.join("\t");
+            text.push('\n');
+            for row in values {
+                text.push_str(&row.join("\t"));
+                text.push('\n');
+            }
+            text
+        }
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_hist_section(run: &str, countable: &str, values: Vec<f64>) -> AnalysisSection {
+        AnalysisSection {
+            analysis: "Coverage Histogram".to_string(),
+            run_name: run.to_string(),
+            run_id: run.to_string(),
+            countable: countable.to_string(),
+            items: vec![ReportItem::Bar {
+                id: format!("cov-hist-{run}-{countable}"),
+                name: run.to_string(),
+                x_label: "taxa".to_string(),
+                y_label: format!("#{countable}s"),
+                labels: (0..values.len()).map(|i| i.to_string()).collect(),
+                values,
+                log_toggle: true,
+            }],
+            id: format!("cov-hist-{run}-{countable}"),
+            table: None,
+            plot_downloads: Vec::new(),
+            display: DisplayOptions::default(),
+        }
+    }
+
+    #[test]
+    fn test_compare_matches_analysis_and_countable() {
+        let sections = vec![
+            get_hist_section("a", "node", vec![1.0, 2.0]),
+            get_hist_section("a", "bp", vec![10.0, 20.0]),
+            get_hist_section("b", "node", vec![2.0, 2.0, 5.0]),
+        ];
+        let comparisons = compare_sections(&sections, false);
+        assert_eq!(comparisons.len(), 1);
+        let comparison = &comparisons[0];
+        assert_eq!(comparison.analysis, "Coverage Histogram");
+        assert_eq!(comparison.run_name, "Comparison");
+        assert_eq!(comparison.countable, "node");
+        assert_eq!(comparison.id, "cmp-coverage-histogram-node");
+        match &comparison.items[0] {
+            ReportItem::MultiBar {
+                names,
+                labels,
+                values,
+                ..
+            } => {
+                assert_eq!(names, &vec!["a".to_string(), "b".to_string()]);
+                assert_eq!(labels, &vec!["0", "1", "2"]);
+                assert_eq!(values[0][..2], [1.0, 2.0]);
+                assert!(values[0][2].is_nan());
+                assert_eq!(values[1], vec![2.0, 2.0, 5.0]);
+            }
+            item => panic!("expected MultiBar, got {:?}", item),
+        }
+        match &comparison.items[1] {
+            ReportItem::Table { header, values, .. } => {
+                assert_eq!(header, &vec!["taxa", "a", "b", "b - a"]);
+                assert_eq!(values[0], vec!["0", "1", "2", "1"]);
+                assert_eq!(values[2], vec!["2", "", "5", ""]);
+            }
+            item => panic!("expected Table, got {:?}", item),
+        }
+    }
+
+    #[test]
+    fn test_compare_normalized_count_types() {
+        let sections = vec![
+            get_hist_section("a", "node", vec![1.0, 4.0]),
+            get_hist_section("a", "bp", vec![10.0, 20.0]),
+        ];
+        let comparisons = compare_sections(&sections, true);
+        assert_eq!(comparisons.len(), 1);
+        assert_eq!(comparisons[0].countable, "node, bp");
+        assert_eq!(comparisons[0].id, "cmp-coverage-histogram-node-bp");
+        match &comparisons[0].items[0] {
+            ReportItem::MultiBar {
+                names,
+                values,
+                y_label,
+                ..
+            } => {
+                assert_eq!(names, &vec!["a (node)".to_string(), "a (bp)".to_string()]);
+                assert_eq!(values, &vec![vec![0.25, 1.0], vec![0.5, 1.0]]);
+                assert_eq!(y_label, "fraction of maximum");
+            }
+            item => panic!("expected MultiBar, got {:?}", item),
+        }
+    }
+
+    #[test]
+    fn test_run_names_are_unique() {
+        let a = get_hist_section("a", "node", vec![1.0]);
+        let b = get_hist_section("b", "node", vec![1.0]);
+        assert_eq!(
+            get_run_names(&[&a, &b, &a], false),
+            vec!["a [1]", "b", "a [2]"]
+        );
+    }
+
+    #[test]
+    fn test_merge_labels_sorts_numbers() {
+        let curve = |labels: &[&str]| Curve {
+            name: String::new(),
+            labels: labels.iter().map(|l| l.to_string()).collect(),
+            values: Vec::new(),
+        };
+        let curves = [curve(&["1", "2", "10"]), curve(&["3", "2"])];
+        assert_eq!(merge_labels(curves.iter()), vec!["1", "2", "3", "10"]);
+        let curves = [curve(&["x", "y"]), curve(&["z", "x"])];
+        assert_eq!(merge_labels(curves.iter()), vec!["x", "y", "z"]);
+        let curves = [curve(&["NaN", "2"]), curve(&["inf", "1"])];
+        assert_eq!(merge_labels(curves.iter()), vec!["NaN", "2", "inf", "1"]);
+    }
+}
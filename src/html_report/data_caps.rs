@@ -0,0 +1,254 @@
+//! Caps on the amount of data per plot (`--max-plot-points`), so that reports
+//! of large pangenomes stay responsive. Curves and windows along sequences
+//! are downsampled, categorical data (bars of groups, heatmap rows and
+//! columns) is reduced to its largest entries.
+use std::cmp::Ordering;
+
+use super::{ReportItem, Window};
+
+/// Reduces the data of the item to about `max_points` values
+pub fn cap_item(item: &mut ReportItem, max_points: usize) {
+    let max_points = max_points.max(2);
+    match item {
+        ReportItem::Bar { labels, values, .. } if values.len() > max_points => {
+            let indices = get_kept_indices(labels, values, max_points);
+            *labels = select(labels, &indices);
+            *values = select(values, &indices);
+        }
+        ReportItem::MultiBar {
+            labels,
+            values,
+            curve,
+            ..
+        } if labels.len() > max_points => {
+            let totals: Vec<f64> = (0..labels.len())
+                .map(|i| values.iter().filter_map(|v| v.get(i)).sum())
+                .collect();
+            let indices = get_kept_indices(labels, &totals, max_points);
+            if let Some(c) = curve.as_mut().filter(|c| c.len() == labels.len()) {
+                *c = select(c, &indices);
+            }
+            for v in values.iter_mut().filter(|v| v.len() == labels.len()) {
+                *v = select(v, &indices);
+            }
+            *labels = select(labels, &indices);
+        }
+        ReportItem::Line {
+            x_values, y_values, ..
+        } if x_values.len() > max_points => {
+            let indices = get_sample_indices(x_values.len(), max_points);
+            *x_values = select(x_values, &indices);
+            *y_values = select(y_values, &indices);
+        }
+        ReportItem::Heatmap {
+            x_labels,
+            y_labels,
+            values,
+            ..
+        } if x_labels.len() * y_labels.len() > max_points => {
+            let n = ((max_points as f64).sqrt() as usize).max(1);
+            let row_sums: Vec<f64> = values
+                .iter()
+                .map(|row| row.iter().map(|v| *v as f64).sum())
+                .collect();
+            let rows = get_largest_indices(&row_sums, n);
+            // Similarity matrices are symmetric, they keep the same groups on
+            // both axes
+            let columns = if x_labels == y_labels {
+                rows.clone()
+            } else {
+                let column_sums: Vec<f64> = (0..x_labels.len())
+                    .map(|j| {
+                        values
+                            .iter()
+                            .filter_map(|row| row.get(j))
+                            .map(|v| *v as f64)
+                            .sum()
+                    })
+                    .collect();
+                get_largest_indices(&column_sums, n)
+            };
+            *values = rows.iter().map(|i| select(&values[*i], &columns)).collect();
+            *y_labels = select(y_labels, &rows);
+            *x_labels = select(x_labels, &columns);
+        }
+        ReportItem::Hexbin { bins, .. } => {
+            // The bins themselves are few, only the names of their nodes are
+            // shortened
+            let per_bin = (max_points / bins.len().max(1)).max(1);
+            for bin in bins.iter_mut() {
+                bin.content.truncate(per_bin);
+            }
+        }
+        ReportItem::Chromosomal { values, .. } => {
+            for windows in values.iter_mut().filter(|w| w.len() > max_points) {
+                *windows = merge_windows(windows, max_points);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Numeric labels (e.g. coverages of a histogram) are downsampled, others
+/// (e.g. groups) are reduced to the ones with the largest values
+fn get_kept_indices(labels: &[String], values: &[f64], max_points: usize) -> Vec<usize> {
+    if labels.iter().all(|l| l.parse::<f64>().is_ok()) {
+        get_sample_indices(labels.len(), max_points)
+    } else {
+        get_largest_indices(values, max_points)
+    }
+}
+
+/// Every k-th index, such that at most `max_points` indices (plus the last
+/// one) are kept
+fn get_sample_indices(len: usize, max_points: usize) -> Vec<usize> {
+    let step = (len + max_points - 1) / max_points;
+    let mut indices: Vec<usize> = (0..len).step_by(step.max(1)).collect();
+    if len > 0 && indices.last() != Some(&(len - 1)) {
+        indices.push(len - 1);
+    }
+    indices
+}
+
+/// Indices of the `n` largest values, in their original order
+fn get_largest_indices(values: &[f64], n: usize) -> Vec<usize> {
+    let mut indices: Vec<usize> = (0..values.len()).collect();
+    indices.sort_by(|a, b| {
+        values[*b]
+            .partial_cmp(&values[*a])
+            .unwrap_or(Ordering::Equal)
+    });
+    indices.truncate(n);
+    indices.sort_unstable();
+    indices
+}
+
+fn select<T: Clone>(values: &[T], indices: &[usize]) -> Vec<T> {
+    indices
+        .iter()
+        .filter_map(|i| values.get(*i))
+        .cloned()
+        .collect()
+}
+
+/// Merges adjacent windows, their values are averaged
+fn merge_windows(windows: &[Window], max_points: usize) -> Vec<Window> {
+    let chunk_size = (windows.len() + max_points - 1) / max_points;
+    windows
+        .chunks(chunk_size)
+        .map(|chunk| {
+            let n_values = chunk.iter().map(|w| w.values.len()).max().unwrap_or(0);
+            let values = (0..n_values)
+                .map(|i| {
+                    let values: Vec<f64> = chunk
+                        .iter()
+                        .filter_map(|w| w.values.get(i))
+                        .copied()
+                        .collect();
+                    values.iter().sum::<f64>() / values.len() as f64
+                })
+                .collect();
+            Window {
+                start: chunk[0].start,
+                end: chunk[chunk.len() - 1].end,
+                values,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bar(labels: &[&str], values: &[f64]) -> ReportItem {
+        ReportItem::Bar {
+            id: "bar".to_string(),
+            name: "bar".to_string(),
+            x_label: "x".to_string(),
+            y_label: "y".to_string(),
+            labels: labels.iter().map(|l| l.to_string()).collect(),
+            values: values.to_vec(),
+            log_toggle: false,
+        }
+    }
+
+    #[test]
+    fn test_numeric_bars_are_downsampled() {
+        let labels: Vec<String> = (1..=10).map(|i| i.to_string()).collect();
+        let labels: Vec<&str> = labels.iter().map(|l| l.as_str()).collect();
+        let mut item = bar(&labels, &[1.0; 10]);
+        cap_item(&mut item, 4);
+        let ReportItem::Bar { labels, .. } = item else {
+            panic!("item is still a bar");
+        };
+        assert_eq!(labels, vec!["1", "4", "7", "10"]);
+    }
+
+    #[test]
+    fn test_categorical_bars_keep_largest() {
+        let mut item = bar(&["a", "b", "c", "d"], &[1.0, 4.0, 2.0, 3.0]);
+        cap_item(&mut item, 2);
+        let ReportItem::Bar { labels, values, .. } = item else {
+            panic!("item is still a bar");
+        };
+        assert_eq!(labels, vec!["b", "d"]);
+        assert_eq!(values, vec![4.0, 3.0]);
+    }
+
+    #[test]
+    fn test_symmetric_heatmap_keeps_same_groups() {
+        let labels: Vec<String> = ["a", "b", "c"].iter().map(|l| l.to_string()).collect();
+        let mut item = ReportItem::Heatmap {
+            id: "heatmap".to_string(),
+            name: "heatmap".to_string(),
+            x_labels: labels.clone(),
+            y_labels: labels,
+            values: vec![
+                vec![1.0, 0.0, 0.5],
+                vec![0.0, 1.0, 0.1],
+                vec![0.5, 0.1, 1.0],
+            ],
+        };
+        cap_item(&mut item, 4);
+        let ReportItem::Heatmap {
+            x_labels,
+            y_labels,
+            values,
+            ..
+        } = item
+        else {
+            panic!("item is still a heatmap");
+        };
+        assert_eq!(x_labels, vec!["a", "c"]);
+        assert_eq!(y_labels, x_labels);
+        assert_eq!(values, vec![vec![1.0, 0.5], vec![0.5, 1.0]]);
+    }
+
+    #[test]
+    fn test_windows_are_merged() {
+        let windows: Vec<Window> = (0..5)
+            .map(|i| Window {
+                start: i * 10,
+                end: (i + 1) * 10,
+                values: vec![i as f64],
+            })
+            .collect();
+        let merged = merge_windows(&windows, 2);
+        assert_eq!(
+            merged,
+            vec![
+                Window {
+                    start: 0,
+                    end: 30,
+                    values: vec![1.0]
+                },
+                Window {
+                    start: 30,
+                    end: 50,
+                    values: vec![3.5]
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,201 @@
+//! Section data that is not embedded in the report as plain JavaScript
+//! objects. Large sections (`--inline-limit`) are stored as gzip-compressed,
+//! base64-encoded blobs, or all sections are written to sidecar JavaScript
+//! files (`--sidecar-dir`), which are referenced relative to the directory of
+//! the report (`--report-path`). Either way, the data is only loaded and
+//! rendered when the tab of its section is opened.
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use flate2::{write::GzEncoder, Compression};
+use handlebars::RenderError;
+
+use super::{JsVars, ReportOptions};
+use crate::util::to_id;
+
+/// Moves the datasets and table of a section out of the inlined objects, if
+/// the options ask for it
+pub fn defer_section_data(
+    section_id: &str,
+    mut js_objects: JsVars,
+    options: &ReportOptions,
+) -> Result<JsVars, RenderError> {
+    let size: usize = ["datasets", "tables"]
+        .iter()
+        .filter_map(|k| js_objects.get(*k))
+        .flat_map(|v| v.values())
+        .map(|v| v.len())
+        .sum();
+    if size == 0 || (options.sidecar_dir.is_none() && size <= options.inline_limit) {
+        return Ok(js_objects);
+    }
+    let datasets = js_objects.remove("datasets").unwrap_or_default();
+    let tables = js_objects.remove("tables").unwrap_or_default();
+    let mut keys: Vec<&String> = datasets.keys().collect();
+    keys.sort();
+    insert(
+        &mut js_objects,
+        "sections",
+        section_id,
+        serde_json::to_string(&keys).expect("keys are strings"),
+    );
+
+    if let Some(dir) = &options.sidecar_dir {
+        std::fs::create_dir_all(dir)?;
+        let file = dir.join(format!("{}.js", to_id(section_id)));
+        let mut content = String::new();
+        for key in keys {
+            content.push_str(&format!(
+                "panacusDatasets[{}] = {};\n",
+                quote(key),
+                datasets[key]
+            ));
+        }
+        for (key, table) in &tables {
+            content.push_str(&format!("panacusTables[{}] = {};\n", quote(key), table));
+        }
+        std::fs::write(&file, content)?;
+        let report_dir = options.report_dir.as_deref().unwrap_or(Path::new(""));
+        // Forward slashes, as the path is used as URL
+        let src = get_relative_path(&file, report_dir)?
+            .to_string_lossy()
+            .replace('\\', "/");
+        insert(&mut js_objects, "sidecars", section_id, quote(&src));
+        for key in tables.keys() {
+            insert(&mut js_objects, "sidecar_tables", key, quote(&src));
+        }
+    } else {
+        for (key, dataset) in &datasets {
+            insert(&mut js_objects, "blobs", key, compress(dataset)?);
+        }
+        for (key, table) in &tables {
+            // Tables are template literals, the blob contains their content
+            let table = table.trim_start_matches('`').trim_end_matches('`');
+            insert(&mut js_objects, "table_blobs", key, compress(table)?);
+        }
+    }
+    Ok(js_objects)
+}
+
+/// Path of the file relative to the directory (both relative to the current
+/// directory or absolute)
+fn get_relative_path(file: &Path, dir: &Path) -> std::io::Result<PathBuf> {
+    let cwd = std::env::current_dir()?;
+    let file = normalize(&cwd.join(file));
+    let dir = normalize(&cwd.join(dir));
+    let common = file
+        .components()
+        .zip(dir.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let mut res: PathBuf = dir.components().skip(common).map(|_| "..").collect();
+    res.extend(file.components().skip(common));
+    Ok(res)
+}
+
+/// Resolves `.` and `..` of an absolute path without following links
+fn normalize(path: &Path) -> PathBuf {
+    let mut res = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                res.pop();
+            }
+            c => res.push(c),
+        }
+    }
+    res
+}
+
+fn insert(js_objects: &mut JsVars, name: &str, key: &str, value: String) {
+    js_objects
+        .entry(name.to_string())
+        .or_default()
+        .insert(key.to_string(), value);
+}
+
+fn quote(text: &str) -> String {
+    serde_json::to_string(text).expect("strings can be serialized")
+}
+
+/// Gzip-compresses the text, returns it base64-encoded as JavaScript string
+fn compress(text: &str) -> Result<String, RenderError> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(text.as_bytes())?;
+    let bytes = encoder.finish()?;
+    Ok(format!("\"{}\"", STANDARD.encode(bytes)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use std::collections::HashMap;
+    use std::io::Read;
+
+    fn get_js_objects() -> JsVars {
+        HashMap::from([
+            (
+                "datasets".to_string(),
+                HashMap::from([("plot".to_string(), "new Bar('plot')".to_string())]),
+            ),
+            (
+                "tables".to_string(),
+                HashMap::from([("section".to_string(), "`a\tb\n`".to_string())]),
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_small_sections_are_inlined() {
+        let js_objects =
+            defer_section_data("section", get_js_objects(), &ReportOptions::default()).unwrap();
+        assert_eq!(js_objects, get_js_objects());
+    }
+
+    #[test]
+    fn test_sidecars_are_relative_to_report() {
+        let dir = tempfile::tempdir().unwrap();
+        for (report_dir, src) in [
+            ("out", "data/section.js"),
+            ("html", "../out/data/section.js"),
+            ("out/data", "section.js"),
+        ] {
+            let options = ReportOptions {
+                sidecar_dir: Some(dir.path().join("out/data")),
+                report_dir: Some(dir.path().join(report_dir)),
+                ..ReportOptions::default()
+            };
+            let js_objects = defer_section_data("section", get_js_objects(), &options).unwrap();
+            assert_eq!(js_objects["sidecars"]["section"], format!("\"{}\"", src));
+            assert_eq!(
+                js_objects["sidecar_tables"]["section"],
+                format!("\"{}\"", src)
+            );
+        }
+        assert_eq!(
+            get_relative_path(Path::new("out/./data/a.js"), Path::new("")).unwrap(),
+            Path::new("out/data/a.js")
+        );
+    }
+
+    #[test]
+    fn test_large_sections_are_compressed() {
+        let options = ReportOptions {
+            inline_limit: 0,
+            ..ReportOptions::default()
+        };
+        let js_objects = defer_section_data("section", get_js_objects(), &options).unwrap();
+        assert!(!js_objects.contains_key("datasets"));
+        assert_eq!(js_objects["sections"]["section"], "[\"plot\"]");
+        let blob = js_objects["blobs"]["plot"].trim_matches('"');
+        let mut text = String::new();
+        GzDecoder::new(&STANDARD.decode(blob).unwrap()[..])
+            .read_to_string(&mut text)
+            .unwrap();
+        assert_eq!(text, "new Bar('plot')");
+        assert!(js_objects["table_blobs"].contains_key("section"));
+    }
+}
@@ -0,0 +1,155 @@
+//! Renders the descriptions of the report config (`report:` and `display:`
+//! blocks) from markdown to HTML. Only the commonly used subset is supported:
+//! paragraphs, headings, lists, code blocks and the inline `code`, **strong**,
+//! *emphasis* and [links](url). All other text is escaped.
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+static LINK: Lazy<Regex> = Lazy::new(|| Regex::new(r"\[([^\]]+)\]\(([^)\s]+)\)").unwrap());
+static STRONG: Lazy<Regex> = Lazy::new(|| Regex::new(r"\*\*([^*]+)\*\*").unwrap());
+static EMPHASIS: Lazy<Regex> = Lazy::new(|| Regex::new(r"\*([^*]+)\*").unwrap());
+static ORDERED_ITEM: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\d+\.\s+").unwrap());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Block {
+    None,
+    Paragraph,
+    List,
+    OrderedList,
+    Code,
+}
+
+pub fn to_html(text: &str) -> String {
+    let mut html = String::new();
+    let mut block = Block::None;
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if block == Block::Code {
+            if trimmed.starts_with("```") {
+                html.push_str("</code></pre>\n");
+                block = Block::None;
+            } else {
+                html.push_str(&escape(line));
+                html.push('\n');
+            }
+            continue;
+        }
+        if trimmed.is_empty() {
+            close(&mut html, &mut block);
+        } else if trimmed.starts_with("```") {
+            close(&mut html, &mut block);
+            html.push_str("<pre><code>");
+            block = Block::Code;
+        } else if let Some((level, heading)) = get_heading(trimmed) {
+            close(&mut html, &mut block);
+            html.push_str(&format!(
+                "<h{0}>{1}</h{0}>\n",
+                level,
+                to_inline_html(heading)
+            ));
+        } else if let Some(item) = trimmed
+            .strip_prefix("- ")
+            .or_else(|| trimmed.strip_prefix("* "))
+        {
+            open(&mut html, &mut block, Block::List);
+            html.push_str(&format!("<li>{}</li>\n", to_inline_html(item)));
+        } else if let Some(m) = ORDERED_ITEM.find(trimmed) {
+            open(&mut html, &mut block, Block::OrderedList);
+            html.push_str(&format!(
+                "<li>{}</li>\n",
+                to_inline_html(&trimmed[m.end()..])
+            ));
+        } else if block == Block::Paragraph {
+            html.push(' ');
+            html.push_str(&to_inline_html(trimmed));
+        } else {
+            open(&mut html, &mut block, Block::Paragraph);
+            html.push_str(&to_inline_html(trimmed));
+        }
+    }
+    if block == Block::Code {
+        html.push_str("</code></pre>\n");
+    } else {
+        close(&mut html, &mut block);
+    }
+    html
+}
+
+fn open(html: &mut String, block: &mut Block, new_block: Block) {
+    if *block == new_block {
+        return;
+    }
+    close(html, block);
+    html.push_str(match new_block {
+        Block::Paragraph => "<p>",
+        Block::List => "<ul>\n",
+        Block::OrderedList => "<ol>\n",
+        _ => "",
+    });
+    *block = new_block;
+}
+
+fn close(html: &mut String, block: &mut Block) {
+    html.push_str(match block {
+        Block::Paragraph => "</p>\n",
+        Block::List => "</ul>\n",
+        Block::OrderedList => "</ol>\n",
+        _ => "",
+    });
+    *block = Block::None;
+}
+
+fn get_heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    match line[level..].strip_prefix(' ') {
+        Some(heading) if (1..=6).contains(&level) => Some((level, heading.trim())),
+        _ => None,
+    }
+}
+
+/// Code spans are kept as they are, other text is formatted
+fn to_inline_html(text: &str) -> String {
+    text.split('`')
+        .enumerate()
+        .map(|(i, part)| {
+            if i % 2 == 1 {
+                format!("<code>{}</code>", escape(part))
+            } else {
+                let part = escape(part);
+                let part = LINK.replace_all(&part, "<a href=\"$2\">$1</a>");
+                let part = STRONG.replace_all(&part, "<strong>$1</strong>");
+                EMPHASIS.replace_all(&part, "<em>$1</em>").into_owned()
+            }
+        })
+        .collect()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blocks() {
+        let text = "# Pangenome\n\nGraph of\n**all** samples\n\n- one\n- two `<x>`\n\n1. first\n```\na < b\n```";
+        assert_eq!(
+            to_html(text),
+            "<h1>Pangenome</h1>\n<p>Graph of <strong>all</strong> samples</p>\n<ul>\n<li>one</li>\n<li>two <code>&lt;x&gt;</code></li>\n</ul>\n<ol>\n<li>first</li>\n</ol>\n<pre><code>a &lt; b\n</code></pre>\n"
+        );
+    }
+
+    #[test]
+    fn test_inline() {
+        assert_eq!(
+            to_inline_html("see [the *wiki*](https://example.org/a_b) <script>"),
+            "see <a href=\"https://example.org/a_b\">the <em>wiki</em></a> &lt;script&gt;"
+        );
+    }
+}
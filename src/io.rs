@@ -1,49 +1,299 @@
 /* standard use */
+use std::fs::File;
 use std::io::{BufRead, BufReader, Read};
 use std::io::{Error, ErrorKind};
 use std::str::{self, FromStr};
+use std::sync::atomic::Ordering;
 
 /* external use */
-use flate2::read::MultiGzDecoder;
+use flate2::read::{DeflateDecoder, MultiGzDecoder};
+use flate2::Crc;
+use memchr::{memchr, memrchr};
+use memmap2::Mmap;
 use quick_csv::Csv;
+use rayon::prelude::*;
 
 /* internal use */
+use crate::error::PanacusError;
 use crate::file_formats::gfa_parser::PathSegment;
+use crate::progress::{CountingReader, Progress, ProgressReader};
 use crate::util::*;
 
-pub fn bufreader_from_compressed_gfa(gfa_file: &str) -> BufReader<Box<dyn Read + Send>> {
+/// Opens a (possibly gzip-compressed) GFA file, whose progress is logged
+/// periodically while it is read
+pub fn bufreader_from_compressed_gfa(
+    gfa_file: &str,
+) -> Result<BufReader<Box<dyn Read + Send>>, PanacusError> {
     log::info!("loading graph from {}", &gfa_file);
-    if let Ok(f) = std::fs::File::open(gfa_file) {
-        let reader: Box<dyn Read + Send> = if gfa_file.ends_with(".gz") {
-            log::info!("assuming that {} is gzip compressed..", &gfa_file);
-            Box::new(MultiGzDecoder::new(f))
-        } else {
-            Box::new(f)
-        };
-        BufReader::new(reader)
+    let f = std::fs::File::open(gfa_file).map_err(|e| PanacusError::file_read(gfa_file, e))?;
+    let f = CountingReader::new(f);
+    let bytes = f.get_count();
+    let reader: Box<dyn Read + Send> = if gfa_file.ends_with(".gz") {
+        log::info!("assuming that {} is gzip compressed..", &gfa_file);
+        Box::new(MultiGzDecoder::new(f))
     } else {
-        log::error!(
-            "Could not read file {}, please make sure it exists and is readable!",
-            gfa_file
-        );
-        panic!("Could not read input file!")
+        Box::new(f)
+    };
+    Ok(BufReader::new(Box::new(ProgressReader::new(
+        reader,
+        bytes,
+        Progress::for_file(gfa_file),
+    ))))
+}
+
+/// Minimum size (in bytes) of the chunks of `for_each_gfa_chunk`
+const GFA_CHUNK_SIZE: usize = 64 << 20;
+/// Size (in compressed bytes) of the BGZF blocks that are decompressed in
+/// parallel
+const BGZF_BATCH_SIZE: usize = 16 << 20;
+/// Maximal size of the uncompressed data of a BGZF block
+const BGZF_MAX_BLOCK_SIZE: usize = 1 << 16;
+
+/// Calls `f` on consecutive chunks of complete lines of a GFA file (the last
+/// line of the file may lack its line break), so that the lines of a chunk
+/// can be parsed in parallel. Uncompressed files are memory-mapped,
+/// BGZF-compressed files are decompressed block-wise in parallel and other
+/// gzip-compressed files are decompressed as a stream. The progress is logged
+/// periodically.
+pub fn for_each_gfa_chunk<F>(gfa_file: &str, mut f: F) -> Result<(), PanacusError>
+where
+    F: FnMut(&[u8]) -> Result<(), PanacusError>,
+{
+    if gfa_file.ends_with(".gz") {
+        if is_bgzf(gfa_file)? {
+            log::info!("reading BGZF compressed blocks of {} in parallel", gfa_file);
+            let f_in = File::open(gfa_file).map_err(|e| PanacusError::file_read(gfa_file, e))?;
+            let reader = CountingReader::new(BufReader::new(f_in));
+            let bytes = reader.get_count();
+            let mut progress = Progress::for_file(gfa_file);
+            for_each_chunk_of_bgzf(reader, gfa_file, BGZF_BATCH_SIZE, |chunk| {
+                progress.update(bytes.load(Ordering::Relaxed), chunk);
+                f(chunk)
+            })?;
+            progress.finish();
+            return Ok(());
+        }
+        let reader = bufreader_from_compressed_gfa(gfa_file)?;
+        return for_each_chunk_of_stream(reader, gfa_file, GFA_CHUNK_SIZE, f);
+    }
+    log::info!("loading graph from {}", &gfa_file);
+    let f_in = File::open(gfa_file).map_err(|e| PanacusError::file_read(gfa_file, e))?;
+    let is_empty = f_in
+        .metadata()
+        .map_err(|e| PanacusError::file_read(gfa_file, e))?
+        .len()
+        == 0;
+    if is_empty {
+        return Ok(());
+    }
+    // Safety: the file is only read. As for any memory map, it must not be
+    // truncated by another process while it is parsed.
+    let data = match unsafe { Mmap::map(&f_in) } {
+        Ok(data) => data,
+        Err(e) => {
+            log::warn!(
+                "cannot memory-map {} ({}), reading it as a stream",
+                gfa_file,
+                e
+            );
+            let f_in = CountingReader::new(f_in);
+            let bytes = f_in.get_count();
+            let reader = ProgressReader::new(f_in, bytes, Progress::for_file(gfa_file));
+            return for_each_chunk_of_stream(BufReader::new(reader), gfa_file, GFA_CHUNK_SIZE, f);
+        }
+    };
+    let mut progress = Progress::new(gfa_file, Some(data.len() as u64));
+    let mut read = 0;
+    for_each_chunk_of_slice(&data, GFA_CHUNK_SIZE, |chunk| {
+        read += chunk.len() as u64;
+        progress.update(read, chunk);
+        f(chunk)
+    })?;
+    progress.finish();
+    Ok(())
+}
+
+fn for_each_chunk_of_slice<F>(data: &[u8], chunk_size: usize, mut f: F) -> Result<(), PanacusError>
+where
+    F: FnMut(&[u8]) -> Result<(), PanacusError>,
+{
+    let mut start = 0;
+    while start < data.len() {
+        let end = get_chunk_end(data, start + chunk_size);
+        f(&data[start..end])?;
+        start = end;
+    }
+    Ok(())
+}
+
+/// End of the chunk (after the next line break) that has at least the given
+/// end
+fn get_chunk_end(data: &[u8], min_end: usize) -> usize {
+    if min_end >= data.len() {
+        return data.len();
+    }
+    match memchr(b'\n', &data[min_end..]) {
+        Some(i) => min_end + i + 1,
+        None => data.len(),
+    }
+}
+
+/// Passes all complete lines of the buffer to `f` and keeps the rest
+fn flush_complete_lines<F>(buf: &mut Vec<u8>, f: &mut F) -> Result<(), PanacusError>
+where
+    F: FnMut(&[u8]) -> Result<(), PanacusError>,
+{
+    if let Some(i) = memrchr(b'\n', buf) {
+        f(&buf[..=i])?;
+        buf.drain(..=i);
     }
+    Ok(())
+}
+
+fn for_each_chunk_of_stream<R: Read, F>(
+    mut reader: R,
+    gfa_file: &str,
+    chunk_size: usize,
+    mut f: F,
+) -> Result<(), PanacusError>
+where
+    F: FnMut(&[u8]) -> Result<(), PanacusError>,
+{
+    let mut buf = Vec::new();
+    loop {
+        let n = (&mut reader)
+            .take(chunk_size as u64)
+            .read_to_end(&mut buf)
+            .map_err(|e| PanacusError::file_read(gfa_file, e))?;
+        if n == 0 {
+            break;
+        }
+        if buf.len() >= chunk_size {
+            flush_complete_lines(&mut buf, &mut f)?;
+        }
+    }
+    if !buf.is_empty() {
+        f(&buf)?;
+    }
+    Ok(())
+}
+
+/// Whether the file starts with a BGZF block, i.e. a gzip member with a `BC`
+/// extra subfield
+fn is_bgzf(gfa_file: &str) -> Result<bool, PanacusError> {
+    let f_in = File::open(gfa_file).map_err(|e| PanacusError::file_read(gfa_file, e))?;
+    Ok(matches!(
+        read_bgzf_block(&mut BufReader::new(f_in)),
+        Ok(Some(_))
+    ))
+}
+
+/// Reads the next BGZF block and returns its compressed data followed by the
+/// CRC32 and size of the uncompressed data, or `None` at the end of the file
+fn read_bgzf_block<R: Read>(reader: &mut R) -> Result<Option<Vec<u8>>, Error> {
+    let mut header = [0u8; 12];
+    match reader.read_exact(&mut header) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    if header[..3] != [0x1f, 0x8b, 8] || header[3] & 4 == 0 {
+        return Err(Error::new(ErrorKind::InvalidData, "not a BGZF block"));
+    }
+    let extra_len = u16::from_le_bytes([header[10], header[11]]) as usize;
+    let mut extra = vec![0u8; extra_len];
+    reader.read_exact(&mut extra)?;
+    let mut block_size = None;
+    let mut i = 0;
+    while i + 4 <= extra.len() {
+        let len = u16::from_le_bytes([extra[i + 2], extra[i + 3]]) as usize;
+        if extra[i] == b'B' && extra[i + 1] == b'C' && len == 2 && i + 6 <= extra.len() {
+            block_size = Some(u16::from_le_bytes([extra[i + 4], extra[i + 5]]) as usize + 1);
+        }
+        i += 4 + len;
+    }
+    let block_size = block_size
+        .filter(|&size| size >= 12 + extra_len + 8)
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "not a BGZF block"))?;
+    let mut data = vec![0u8; block_size - 12 - extra_len];
+    reader.read_exact(&mut data)?;
+    Ok(Some(data))
+}
+
+fn decompress_bgzf_block(block: &[u8]) -> Result<Vec<u8>, Error> {
+    let (data, trailer) = block.split_at(block.len() - 8);
+    let crc = u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+    let size = u32::from_le_bytes([trailer[4], trailer[5], trailer[6], trailer[7]]) as usize;
+    // The size is untrusted, BGZF blocks hold at most 64 KiB
+    let mut res = Vec::with_capacity(size.min(BGZF_MAX_BLOCK_SIZE));
+    DeflateDecoder::new(data).read_to_end(&mut res)?;
+    let mut actual_crc = Crc::new();
+    actual_crc.update(&res);
+    if res.len() != size || actual_crc.sum() != crc {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "corrupt BGZF block (checksum mismatch)",
+        ));
+    }
+    Ok(res)
+}
+
+fn for_each_chunk_of_bgzf<R: Read, F>(
+    mut reader: R,
+    gfa_file: &str,
+    batch_size: usize,
+    mut f: F,
+) -> Result<(), PanacusError>
+where
+    F: FnMut(&[u8]) -> Result<(), PanacusError>,
+{
+    let mut buf = Vec::new();
+    loop {
+        let mut blocks = Vec::new();
+        let mut read = 0;
+        while read < batch_size {
+            match read_bgzf_block(&mut reader).map_err(|e| PanacusError::file_read(gfa_file, e))? {
+                Some(block) => {
+                    read += block.len();
+                    blocks.push(block);
+                }
+                None => break,
+            }
+        }
+        if blocks.is_empty() {
+            break;
+        }
+        let decompressed = blocks
+            .par_iter()
+            .map(|block| decompress_bgzf_block(block))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| PanacusError::file_read(gfa_file, e))?;
+        for block in decompressed {
+            buf.extend_from_slice(&block);
+        }
+        flush_complete_lines(&mut buf, &mut f)?;
+    }
+    if !buf.is_empty() {
+        f(&buf)?;
+    }
+    Ok(())
+}
+
+/// Splits a chunk of `for_each_gfa_chunk` into its lines (with line breaks)
+pub fn get_lines(chunk: &[u8]) -> Vec<&[u8]> {
+    chunk.split_inclusive(|&c| c == b'\n').collect()
 }
 
 pub fn parse_bed_to_path_segments<R: Read>(
     data: &mut BufReader<R>,
+    file_name: &str,
     use_block_info: bool,
-) -> Vec<PathSegment> {
+) -> Result<Vec<PathSegment>, PanacusError> {
     // based on https://en.wikipedia.org/wiki/BED_(file_format)
     let mut segments = Vec::new();
 
     for (i, line) in data.lines().enumerate() {
-        let line = match line {
-            Ok(l) => l,
-            Err(e) => {
-                panic!("error reading line {}: {}", i + 1, e);
-            }
-        };
+        let line = line.map_err(|e| PanacusError::file_read(file_name, e))?;
 
         let fields = {
             let mut fields: Vec<&str> = line.split('\t').collect();
@@ -64,16 +314,13 @@ pub fn parse_bed_to_path_segments<R: Read>(
         if fields.len() == 1 {
             segments.push(PathSegment::from_str(path_name));
         } else if fields.len() >= 3 {
-            let start = usize::from_str(fields[1]).expect(&format!(
-                "error line {}: `{}` is not an usize",
-                i + 1,
-                fields[1]
-            ));
-            let end = usize::from_str(fields[2]).expect(&format!(
-                "error line {}: `{}` is not an usize",
-                i + 1,
-                fields[2]
-            ));
+            let parse_position = |x: &str| {
+                usize::from_str(x).map_err(|_| {
+                    PanacusError::parse(file_name, i + 1, format!("`{}` is not an usize", x))
+                })
+            };
+            let start = parse_position(fields[1])?;
+            let end = parse_position(fields[2])?;
 
             if use_block_info && fields.len() == 12 {
                 let block_count = fields[9].parse::<usize>().unwrap_or(0);
@@ -97,23 +344,25 @@ pub fn parse_bed_to_path_segments<R: Read>(
                         ));
                     }
                 } else {
-                    panic!(
-                        "error in block sizes/starts in line {}: counts do not match",
-                        i + 1
-                    );
+                    return Err(PanacusError::parse(
+                        file_name,
+                        i + 1,
+                        "counts of block sizes/starts do not match",
+                    ));
                 }
             } else {
                 segments.push(PathSegment::from_str_start_end(path_name, start, end));
             }
         } else {
-            panic!(
-                "error in line {}: row must have either 1, 3, or 12 columns, but has 2",
-                i + 1
-            );
+            return Err(PanacusError::parse(
+                file_name,
+                i + 1,
+                "row must have either 1, 3, or 12 columns, but has 2",
+            ));
         }
     }
 
-    segments
+    Ok(segments)
 }
 
 pub fn parse_groups<R: Read>(data: &mut BufReader<R>) -> Result<Vec<(PathSegment, String)>, Error> {
@@ -241,13 +490,84 @@ pub fn write_ordered_table(
     Ok(res)
 }
 
-pub fn write_metadata_comments() -> anyhow::Result<String> {
-    let mut res = format!(
-        "# {}\n",
-        std::env::args().collect::<Vec<String>>().join(" ")
-    );
-    let version = option_env!("GIT_HASH").unwrap_or(env!("CARGO_PKG_VERSION"));
-    let version = format!("# version {}\n", version);
-    res.push_str(&version);
-    Ok(res)
+/// The provenance of the run (see `provenance`) as comment lines
+pub fn write_metadata_comments() -> String {
+    crate::provenance::get().to_comments()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::DeflateEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    const GFA: &[u8] = b"S\t1\tACGT\nS\t2\tA\nL\t1\t+\t2\t+\t0M\nP\tx\t1+,2+\t*";
+
+    fn get_chunks<F>(for_each: F) -> Vec<Vec<u8>>
+    where
+        F: FnOnce(&mut dyn FnMut(&[u8]) -> Result<(), PanacusError>) -> Result<(), PanacusError>,
+    {
+        let mut chunks = Vec::new();
+        for_each(&mut |chunk| {
+            chunks.push(chunk.to_vec());
+            Ok(())
+        })
+        .unwrap();
+        chunks
+    }
+
+    fn bgzf_block(data: &[u8]) -> Vec<u8> {
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        let compressed = encoder.finish().unwrap();
+        let block_size = (18 + compressed.len() + 8 - 1) as u16;
+        let mut block = vec![
+            0x1f, 0x8b, 8, 4, 0, 0, 0, 0, 0, 0xff, 6, 0, b'B', b'C', 2, 0,
+        ];
+        block.extend(block_size.to_le_bytes());
+        block.extend(compressed);
+        let mut crc = Crc::new();
+        crc.update(data);
+        block.extend(crc.sum().to_le_bytes());
+        block.extend((data.len() as u32).to_le_bytes());
+        block
+    }
+
+    fn assert_complete_lines(chunks: &[Vec<u8>]) {
+        assert_eq!(chunks.concat(), GFA);
+        for chunk in &chunks[..chunks.len() - 1] {
+            assert_eq!(chunk.last(), Some(&b'\n'));
+        }
+    }
+
+    #[test]
+    fn test_chunks_of_slice_are_line_aligned() {
+        let chunks = get_chunks(|f| for_each_chunk_of_slice(GFA, 5, f));
+        assert_eq!(chunks.len(), 4);
+        assert_complete_lines(&chunks);
+    }
+
+    #[test]
+    fn test_chunks_of_stream_are_line_aligned() {
+        let chunks = get_chunks(|f| for_each_chunk_of_stream(GFA, "test.gfa", 5, f));
+        assert!(chunks.len() > 1);
+        assert_complete_lines(&chunks);
+    }
+
+    #[test]
+    fn test_bgzf_blocks_are_decompressed() {
+        // blocks split lines, the last block is the (empty) end-of-file marker
+        let mut data: Vec<u8> = GFA.chunks(7).flat_map(bgzf_block).collect();
+        data.extend(bgzf_block(b""));
+        let chunks = get_chunks(|f| for_each_chunk_of_bgzf(&data[..], "test.gfa.gz", 30, f));
+        assert!(chunks.len() > 1);
+        assert_complete_lines(&chunks);
+
+        let mut corrupt = bgzf_block(b"S\t1\tA\n");
+        let n = corrupt.len();
+        corrupt[n - 6] ^= 1;
+        assert!(for_each_chunk_of_bgzf(&corrupt[..], "test.gfa.gz", 30, |_| Ok(())).is_err());
+        assert!(read_bgzf_block(&mut &GFA[..]).is_err());
+    }
 }
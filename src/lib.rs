@@ -1,38 +1,90 @@
-/* private use */
+//! Panacus computes statistics of pangenome graphs (GFA) and variant
+//! files (VCF). Besides the command line interface (`run_cli`), it can be
+//! used as a library:
+//!
+//! 1. Create a parser for the input, e.g. a [`GfaParser`] with
+//!    [`GraphMaskParameters`] describing the subset, exclusion and grouping
+//!    of the paths.
+//! 2. Generate a coverage histogram ([`Hist`]) or a [`CoverageMatrix`] from
+//!    it with the methods of [`FileFormatParser`].
+//! 3. Run analyses (see [`analyses`]) on it. Each analysis provides a
+//!    `get_result` method returning a typed result, e.g.
+//!    [`analyses::growth::GrowthResult`]; the tables and HTML reports of the
+//!    command line interface are generated from the same results. All
+//!    analyses also provide their results in a common format, see
+//!    [`analyses::result_table`].
+//!
+//! ```
+//! use panacus::analyses::growth::Growth;
+//! use panacus::{CountType, FileFormatParser, GfaParser, GraphMaskParameters};
+//!
+//! let parser = GfaParser::new(
+//!     "tests/test_files/t_groups.gfa",
+//!     CountType::Node,
+//!     GraphMaskParameters::default(),
+//!     None,
+//!     false,
+//! )?;
+//! let hist = Box::new(parser).generate_hist()?;
+//! let mut growth = Growth::new(Some("1,2".to_string()), Some("0,0".to_string()), false, false);
+//! let result = growth.get_result(&hist)?;
+//! assert_eq!(result.growths.len(), 2);
+//! # Ok::<(), panacus::PanacusError>(())
+//! ```
 pub mod analyses;
 mod analysis_parameter;
 mod commands;
 pub mod coverage_matrix;
+pub mod error;
 pub mod file_formats;
 pub mod hist;
 mod html_report;
 mod io;
+mod multiqc;
+mod plots;
+mod profile;
+mod progress;
+mod provenance;
 mod util;
 
 use env_logger::Builder;
 use itertools::Itertools;
 use log::LevelFilter;
+use std::collections::HashMap;
 use std::io::Read;
+use std::sync::Arc;
 use std::{fmt::Debug, io::Write};
 use thiserror::Error;
 
+use analyses::result_table::{write_result_tables, OutputFormat, ResultTable};
 use analyses::Analysis;
-use analysis_parameter::AnalysisParameter;
 use clap::{Arg, ArgAction, ArgMatches, Command};
-use html_report::AnalysisSection;
+use plots::PlotExport;
 
 use std::fs::File;
 use std::io::BufReader;
+use std::path::{Path, PathBuf};
 
 use shadow_rs::shadow;
 
-use crate::analyses::{HistBasedAnalysis, MatrixBasedAnalysis};
-use crate::analysis_parameter::{FileRun, Grouping};
-use crate::coverage_matrix::CoverageMatrix;
-use crate::file_formats::gfa_parser::{GfaParser, GraphMaskParameters};
-use crate::file_formats::vcf_parser::VcfParser;
-use crate::file_formats::FileFormatParser;
-use crate::hist::Hist;
+pub use crate::analyses::{HistBasedAnalysis, MatrixBasedAnalysis};
+pub use crate::analysis_parameter::{
+    AnalysisParameter, ClusterMethod, ColorBy, FileRun, Grouping, PluginInput,
+};
+pub use crate::coverage_matrix::CoverageMatrix;
+pub use crate::error::PanacusError;
+pub use crate::file_formats::gfa_parser::{
+    CompactGraph, GfaParser, GraphMaskParameters, LowMemoryGfaParser, SharedGraph,
+    ThresholdContainer,
+};
+pub use crate::file_formats::vcf_parser::VcfParser;
+pub use crate::file_formats::FileFormatParser;
+pub use crate::hist::Hist;
+pub use crate::html_report::{
+    AnalysisSection, Bin, DisplayOptions, ReportItem, ReportOptions, Window,
+};
+pub use crate::provenance::{InputFile, Provenance};
+pub use crate::util::{CountType, Threshold};
 
 shadow!(build);
 
@@ -96,6 +148,9 @@ pub fn run_cli() -> Result<(), anyhow::Error> {
     let args = Command::new("panacus")
         .subcommand(commands::render::get_subcommand())
         .subcommand(commands::report::get_subcommand())
+        .subcommand(commands::config::get_subcommand())
+        .subcommand(commands::export_plots::get_subcommand())
+        .subcommand(commands::export_multiqc::get_subcommand())
         .subcommand(commands::hist::get_subcommand())
         .subcommand(commands::growth::get_subcommand())
         .subcommand(commands::info::get_subcommand())
@@ -105,6 +160,9 @@ pub fn run_cli() -> Result<(), anyhow::Error> {
         .subcommand(commands::similarity::get_subcommand())
         .subcommand(commands::coverage_colors::get_subcommand())
         .subcommand(commands::section_growth::get_subcommand())
+        .subcommand(commands::non_reference_anchors::get_subcommand())
+        .subcommand(commands::subgraph_layout::get_subcommand())
+        .subcommand(commands::copy_number::get_subcommand())
         .subcommand_required(true)
         .arg(
             Arg::new("threads")
@@ -124,17 +182,65 @@ pub fn run_cli() -> Result<(), anyhow::Error> {
                 .global(true)
                 .help("Set the number of threads used (default: use all threads)"),
         )
+        .arg(
+            Arg::new("strict")
+                .long("strict")
+                .action(ArgAction::SetTrue)
+                .global(true)
+                .help("Exit with an error if any analysis fails (instead of skipping it)"),
+        )
+        .arg(
+            Arg::new("output_format")
+                .long("output-format")
+                .value_name("FORMAT")
+                .global(true)
+                .ignore_case(true)
+                .value_parser(clap_enum_variants!(OutputFormat))
+                .help("Write the results of all analyses in a common machine-readable format instead of the analysis-specific tables/reports: tsv/csv (one value per line, in long format, after the provenance as comment lines), json (provenance and array of result tables) or jsonl (provenance, then one result table per line)"),
+        )
+        .arg(
+            Arg::new("max_memory")
+                .long("max-memory")
+                .value_name("SIZE")
+                .global(true)
+                .value_parser(util::parse_memory_size)
+                .help("Memory budget (e.g. 16G). Histograms of nodes/bps (hist, growth) are computed in a low-memory mode if the standard mode is estimated to need more. The low-memory mode needs numeric segment IDs and does not support subsets/exclusions of paths"),
+        )
+        .arg(
+            Arg::new("profile")
+                .long("profile")
+                .value_name("FILE")
+                .global(true)
+                .help("Write the wall time and peak memory of each stage (node/edge/path parsing, masking, each analysis) as JSON to FILE"),
+        )
+        .arg(
+            Arg::new("checksum")
+                .long("checksum")
+                .action(ArgAction::SetTrue)
+                .global(true)
+                .help("Record the SHA-256 checksums of the input files in the provenance of the outputs. Not done by default, as it reads each input file once more; without it, the inputs are only described by their size and modification time"),
+        )
         .long_version(build::CLAP_LONG_VERSION)
         .get_matches();
 
     set_verbosity(&args);
     set_number_of_threads(&args);
+    let strict = args.get_flag("strict");
+    let output_format = args.get_one::<OutputFormat>("output_format").copied();
+    let max_memory = args.get_one::<usize>("max_memory").copied();
+    let profile_file = args.get_one::<String>("profile").cloned();
+    let checksum = args.get_flag("checksum");
 
     let mut instructions: Vec<FileRun> = Vec::new();
     let mut shall_write_html = false;
     let mut dry_run = false;
     let mut json = false;
+    let mut validate = false;
+    let mut plots = None;
+    let mut multiqc = None;
+    let mut report_options = ReportOptions::default();
     let mut config_content = "EMPTY".to_string();
+    let mut config_files = Vec::new();
 
     if let Some(args) = args.subcommand_matches("render") {
         let json_files: Vec<String> = args
@@ -142,14 +248,13 @@ pub fn run_cli() -> Result<(), anyhow::Error> {
             .unwrap()
             .cloned()
             .collect();
-        let mut full_report = Vec::new();
-        for file_path in &json_files {
-            let file = File::open(file_path)?;
-            let reader = BufReader::new(file);
-
-            // Read the JSON contents of the file as an instance of `User`.
-            let report: Vec<AnalysisSection> = serde_json::from_reader(reader)?;
-            full_report.extend(report);
+        provenance::record(&[], &json_files, checksum)?;
+        let (mut full_report, mut provenance) = commands::render::read_json_reports(&json_files)?;
+        provenance.push(provenance::get());
+        if args.get_flag("compare") {
+            let comparisons =
+                html_report::comparison::compare_sections(&full_report, args.get_flag("normalize"));
+            full_report.extend(comparisons);
         }
         let mut registry = handlebars::Handlebars::new();
         let report_text = AnalysisSection::generate_report(
@@ -157,28 +262,60 @@ pub fn run_cli() -> Result<(), anyhow::Error> {
             &mut registry,
             &json_files[0],
             "-- GENERATED VIA RENDER --",
+            &ReportOptions {
+                provenance,
+                ..commands::render::get_report_options(args)
+            },
         )?;
         writeln!(&mut out, "{report_text}")?;
         return Ok(());
     }
 
+    if let Some(res) = commands::config::execute(&args, &mut out) {
+        res?;
+        out.flush()?;
+        return Ok(());
+    }
+
+    if let Some(res) = commands::export_plots::execute(&args) {
+        return res;
+    }
+
+    if let Some(res) = commands::export_multiqc::execute(&args) {
+        return res;
+    }
+
     if let Some(report) = commands::report::get_instructions(&args) {
+        let (report, report_config) = report?;
         shall_write_html = true;
         if let Some(report_matches) = args.subcommand_matches("report") {
             dry_run = report_matches.get_flag("dry_run");
             json = report_matches.get_flag("json");
+            validate = report_matches.get_flag("validate");
+            report_options = ReportOptions {
+                title: report_config.title,
+                description: report_config.description,
+                ..commands::render::get_report_options(report_matches)
+            };
+            multiqc = report_matches
+                .get_one::<String>("multiqc")
+                .map(PathBuf::from);
+            plots = report_matches.get_one::<String>("plots").map(|dir| {
+                commands::export_plots::get_plot_export(report_matches, PathBuf::from(dir))
+            });
             let config = report_matches
                 .get_one::<String>("yaml_file")
                 .expect("Contains required yaml config")
                 .to_owned();
-            let f = File::open(config)?;
+            let f = File::open(&config)?;
             let mut reader = BufReader::new(f);
             config_content = String::new();
             reader.read_to_string(&mut config_content)?;
             config_content.push_str("\n---------------------\n");
-            config_content.push_str(serde_yaml::to_string(&report.as_ref().unwrap())?.as_str());
+            config_content.push_str(serde_yaml::to_string(&report)?.as_str());
+            config_files.push(config);
         }
-        instructions.extend(report?);
+        instructions.extend(report);
     }
     if let Some(hist) = commands::hist::get_instructions(&args) {
         instructions.extend(hist?);
@@ -207,18 +344,58 @@ pub fn run_cli() -> Result<(), anyhow::Error> {
     if let Some(similarity) = commands::similarity::get_instructions(&args) {
         instructions.extend(similarity?);
     }
+    if let Some(anchors) = commands::non_reference_anchors::get_instructions(&args) {
+        instructions.extend(anchors?);
+    }
+    if let Some(layout) = commands::subgraph_layout::get_instructions(&args) {
+        instructions.extend(layout?);
+    }
+    if let Some(copy_number) = commands::copy_number::get_instructions(&args) {
+        instructions.extend(copy_number?);
+    }
 
     log::info!("{:?}", instructions);
 
+    if validate {
+        let problems = validate_instructions(&instructions);
+        for problem in &problems {
+            writeln!(out, "{problem}")?;
+        }
+        out.flush()?;
+        if !problems.is_empty() {
+            return Err(anyhow::anyhow!(
+                "config is invalid, found {} problem(s)",
+                problems.len()
+            ));
+        }
+        writeln!(out, "config is valid ({} runs)", instructions.len())?;
+        out.flush()?;
+        return Ok(());
+    }
+
     // ride on!
     if !dry_run {
-        execute_pipeline(
+        if profile_file.is_some() {
+            profile::enable();
+        }
+        provenance::record(&instructions, &config_files, checksum)?;
+        let res = execute_pipeline(
             instructions,
             &mut out,
             &config_content,
             shall_write_html,
             json,
-        )?;
+            strict,
+            output_format,
+            max_memory,
+            plots.as_ref(),
+            multiqc.as_deref(),
+            &report_options,
+        );
+        if let Some(profile_file) = profile_file {
+            profile::write(&profile_file)?;
+        }
+        res?;
     } else {
         println!("{:#?}", instructions);
     }
@@ -228,145 +405,476 @@ pub fn run_cli() -> Result<(), anyhow::Error> {
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 fn execute_pipeline<W: Write>(
     instructions: Vec<FileRun>,
     out: &mut std::io::BufWriter<W>,
     config_content: &str,
     shall_write_html: bool,
     json: bool,
+    strict: bool,
+    output_format: Option<OutputFormat>,
+    max_memory: Option<usize>,
+    plots: Option<&PlotExport>,
+    multiqc: Option<&Path>,
+    report_options: &ReportOptions,
 ) -> anyhow::Result<()> {
-    for file in instructions {
-        let (file_parser, analyses) = get_file_parser(file)?;
-        let (hist_based, matrix_based) = split_analyses(analyses);
-        log::info!("File parser generated");
-        if !matrix_based.is_empty() {
-            let matrix = file_parser.generate_matrix();
-            log::info!("Matrix generated");
-            let report = match shall_write_html {
-                true => get_matrix_reports(matrix, matrix_based, hist_based, config_content, json)?,
-                false => get_matrix_tables(matrix, matrix_based, hist_based)?,
-            };
-            writeln!(out, "{report}")?;
-        } else {
-            let hist = file_parser.generate_hist();
-            log::info!("Hist generated");
-            let report = match shall_write_html {
-                true => get_hist_reports(hist, hist_based, config_content, json)?,
-                false => get_hist_tables(hist, hist_based)?,
+    // Reports are written in the order of the instructions, as soon as all
+    // preceding runs are done (runs on the same graph are executed together)
+    let mut pending_reports: HashMap<usize, String> = HashMap::new();
+    let mut next_report = 0;
+    let mut results: Vec<Vec<ResultTable>> = vec![Vec::new(); instructions.len()];
+    for runs in plan_file_runs(instructions) {
+        let file_parsers = profile::stage("graph parsing", || get_file_parsers(runs, max_memory))?;
+        for (idx, file_parser, analyses) in file_parsers {
+            let (hist_based, matrix_based) = split_analyses(analyses);
+            log::info!("File parser generated");
+            if output_format.is_some() {
+                results[idx] = if !matrix_based.is_empty() {
+                    let matrix =
+                        profile::stage("matrix generation", || file_parser.generate_matrix())?;
+                    let mut tables = get_matrix_result_tables(&matrix, matrix_based, strict)?;
+                    let hist = matrix.get_hist();
+                    tables.extend(get_hist_result_tables(&hist, hist_based, strict)?);
+                    tables
+                } else {
+                    let hist = profile::stage("hist generation", || file_parser.generate_hist())?;
+                    get_hist_result_tables(&hist, hist_based, strict)?
+                };
+                continue;
+            }
+            let report = if !matrix_based.is_empty() {
+                let matrix = profile::stage("matrix generation", || file_parser.generate_matrix())?;
+                log::info!("Matrix generated");
+                match shall_write_html {
+                    true => {
+                        let mut sections = get_matrix_sections(&matrix, matrix_based, strict)?;
+                        let hist = matrix.get_hist();
+                        sections.extend(get_hist_sections(&hist, hist_based, strict)?);
+                        get_report(
+                            sections,
+                            config_content,
+                            json,
+                            plots,
+                            multiqc,
+                            report_options,
+                        )?
+                    }
+                    false => {
+                        let mut tables = get_matrix_tables(&matrix, matrix_based, strict)?;
+                        let hist = matrix.get_hist();
+                        tables.extend(get_hist_tables(&hist, hist_based, strict)?);
+                        tables.join("\n\n\n")
+                    }
+                }
+            } else {
+                let hist = profile::stage("hist generation", || file_parser.generate_hist())?;
+                log::info!("Hist generated");
+                match shall_write_html {
+                    true => get_report(
+                        get_hist_sections(&hist, hist_based, strict)?,
+                        config_content,
+                        json,
+                        plots,
+                        multiqc,
+                        report_options,
+                    )?,
+                    false => get_hist_tables(&hist, hist_based, strict)?.join("\n\n\n"),
+                }
             };
-            writeln!(out, "{report}")?;
+            pending_reports.insert(idx, report);
+            while let Some(report) = pending_reports.remove(&next_report) {
+                // Tables start with the provenance (with the runtime up to
+                // now), as comment lines
+                if !shall_write_html {
+                    write!(out, "{}", io::write_metadata_comments())?;
+                }
+                writeln!(out, "{report}")?;
+                out.flush()?;
+                next_report += 1;
+            }
         }
     }
+    if let Some(output_format) = output_format {
+        let results: Vec<ResultTable> = results.into_iter().flatten().collect();
+        let provenance = provenance::get();
+        writeln!(
+            out,
+            "{}",
+            write_result_tables(&results, output_format, Some(&provenance))?
+        )?;
+    }
     Ok(())
 }
 
-fn get_hist_reports(
-    hist: Hist,
-    hist_based: Vec<Box<dyn HistBasedAnalysis>>,
+/// Groups the runs by their input graph (in order of first appearance), so
+/// that each GFA file only needs to be parsed once. Each run keeps its index
+/// in the instructions.
+fn plan_file_runs(instructions: Vec<FileRun>) -> Vec<Vec<(usize, FileRun)>> {
+    let mut plan: Vec<Vec<(usize, FileRun)>> = Vec::new();
+    let mut graph_to_runs: HashMap<(String, bool), usize> = HashMap::new();
+    for (idx, file) in instructions.into_iter().enumerate() {
+        match &file {
+            FileRun::Gfa { graph, nice, .. } => {
+                let key = (graph.clone(), *nice);
+                match graph_to_runs.get(&key) {
+                    Some(&runs) => plan[runs].push((idx, file)),
+                    None => {
+                        graph_to_runs.insert(key, plan.len());
+                        plan.push(vec![(idx, file)]);
+                    }
+                }
+            }
+            FileRun::Vcf { .. } => plan.push(vec![(idx, file)]),
+        }
+    }
+    plan
+}
+
+type FileParsers = Vec<(usize, Box<dyn FileFormatParser>, Vec<AnalysisParameter>)>;
+
+/// Creates the parsers for runs on the same input (see `plan_file_runs`).
+/// If there are multiple runs on a GFA file, the graph is parsed only once
+/// and the item tables are computed together where possible. With a memory
+/// budget, runs that qualify may use the low-memory mode (see
+/// `get_low_memory_parsers`).
+fn get_file_parsers(
+    runs: Vec<(usize, FileRun)>,
+    max_memory: Option<usize>,
+) -> anyhow::Result<FileParsers> {
+    let (mut parsers, runs) = match max_memory {
+        Some(max_memory) => get_low_memory_parsers(runs, max_memory)?,
+        None => (Vec::new(), runs),
+    };
+    let shared_graph = match runs.as_slice() {
+        [_, _, ..] => match &runs[0].1 {
+            FileRun::Gfa { graph, nice, .. } => Some(SharedGraph::from_gfa(graph, *nice)?),
+            FileRun::Vcf { .. } => None,
+        },
+        _ => None,
+    };
+    let Some(shared_graph) = shared_graph else {
+        for (idx, file) in runs {
+            let (parser, analyses) = get_file_parser(file)?;
+            parsers.push((idx, parser, analyses));
+        }
+        return Ok(parsers);
+    };
+    log::info!(
+        "sharing graph {} between {} runs",
+        shared_graph.get_filename(),
+        runs.len()
+    );
+    let mut gfa_parsers = Vec::new();
+    for (idx, file) in runs {
+        if let FileRun::Gfa {
+            subset,
+            exclude,
+            grouping,
+            reference,
+            count_type,
+            analyses,
+            ..
+        } = file
+        {
+            let graph_mask_parameters = get_graph_mask_parameters(subset, exclude, grouping);
+            let parser = GfaParser::from_shared(
+                &shared_graph,
+                count_type,
+                graph_mask_parameters,
+                reference,
+            )?;
+            gfa_parsers.push((idx, parser, analyses));
+        }
+    }
+    GfaParser::share_path_tables(&mut gfa_parsers.iter_mut().map(|(_, p, _)| p).collect_vec())?;
+    parsers.extend(
+        gfa_parsers
+            .into_iter()
+            .map(|(idx, p, a)| (idx, Box::new(p) as Box<dyn FileFormatParser>, a)),
+    );
+    Ok(parsers)
+}
+
+/// Whether the run only needs a histogram of nodes/bps of all paths, which
+/// can be computed by `LowMemoryGfaParser`
+fn supports_low_memory(run: &FileRun) -> bool {
+    match run {
+        FileRun::Gfa {
+            subset,
+            exclude,
+            count_type,
+            analyses,
+            ..
+        } => {
+            subset.is_empty()
+                && exclude.is_empty()
+                && matches!(count_type, CountType::Node | CountType::Bp)
+                && analyses.iter().all(|a| a.is_hist_based())
+        }
+        FileRun::Vcf { .. } => false,
+    }
+}
+
+/// Creates low-memory parsers for the runs that support them, if the
+/// standard mode is estimated to exceed the memory budget. Returns the
+/// remaining runs, which use the standard mode.
+fn get_low_memory_parsers(
+    runs: Vec<(usize, FileRun)>,
+    max_memory: usize,
+) -> anyhow::Result<(FileParsers, Vec<(usize, FileRun)>)> {
+    let (low_memory, mut standard): (Vec<_>, Vec<_>) = runs
+        .into_iter()
+        .partition(|(_, run)| supports_low_memory(run));
+    let Some((_, FileRun::Gfa { graph, .. })) = low_memory.first() else {
+        return Ok((Vec::new(), standard));
+    };
+    if let Some(max_standard_memory) = CompactGraph::estimate_max_standard_memory(graph) {
+        if max_standard_memory <= max_memory {
+            log::info!(
+                "standard mode needs at most {:.1} MiB for {}, which is within the memory budget",
+                max_standard_memory as f64 / (1 << 20) as f64,
+                graph
+            );
+            standard.extend(low_memory);
+            return Ok((Vec::new(), standard));
+        }
+    }
+    let compact_graph = match CompactGraph::from_gfa(graph) {
+        Ok(compact_graph) => Arc::new(compact_graph),
+        Err(e) => {
+            log::warn!("cannot use low-memory mode: {e}");
+            standard.extend(low_memory);
+            return Ok((Vec::new(), standard));
+        }
+    };
+    let standard_memory = compact_graph.estimate_standard_memory();
+    if standard_memory <= max_memory {
+        log::info!(
+            "standard mode needs about {:.1} MiB for {}, which is within the memory budget",
+            standard_memory as f64 / (1 << 20) as f64,
+            graph
+        );
+        standard.extend(low_memory);
+        return Ok((Vec::new(), standard));
+    }
+    log::info!(
+        "standard mode needs about {:.1} MiB for {}, using low-memory mode",
+        standard_memory as f64 / (1 << 20) as f64,
+        graph
+    );
+    let mut parsers: FileParsers = Vec::new();
+    for (idx, file) in low_memory {
+        if let FileRun::Gfa {
+            subset,
+            exclude,
+            grouping,
+            count_type,
+            analyses,
+            ..
+        } = file
+        {
+            let graph_mask_parameters = get_graph_mask_parameters(subset, exclude, grouping);
+            let parser = LowMemoryGfaParser::new(
+                Arc::clone(&compact_graph),
+                count_type,
+                graph_mask_parameters,
+            )?;
+            let memory = parser.estimate_memory();
+            if memory > max_memory {
+                log::warn!(
+                    "low-memory mode needs about {:.1} MiB, which exceeds the memory budget",
+                    memory as f64 / (1 << 20) as f64
+                );
+            }
+            parsers.push((idx, Box::new(parser), analyses));
+        }
+    }
+    Ok((parsers, standard))
+}
+
+fn get_report(
+    sections: Vec<AnalysisSection>,
     config_content: &str,
     json: bool,
+    plots: Option<&PlotExport>,
+    multiqc: Option<&Path>,
+    report_options: &ReportOptions,
 ) -> anyhow::Result<String> {
-    let reports: Vec<AnalysisSection> = hist_based
-        .into_iter()
-        .filter_map(|mut x| {
-            log::info!("Doing hist-analysis: {}", x.get_type());
-            let out = x.generate_report_section(&hist).ok();
-            log::info!("Finished hist-analysis: {}", x.get_type());
-            out
-        })
-        .flatten()
-        .collect();
-    let mut registry = handlebars::Handlebars::new();
+    if let Some(plots) = plots {
+        plots.write(&sections)?;
+    }
+    if let Some(dir) = multiqc {
+        multiqc::write_multiqc(dir, &sections)?;
+    }
     if json {
-        let report = serde_json::to_string_pretty(&reports)?;
-        Ok(report)
+        let report = commands::render::JsonReport {
+            provenance: provenance::get(),
+            sections,
+        };
+        Ok(serde_json::to_string_pretty(&report)?)
     } else {
+        let mut registry = handlebars::Handlebars::new();
         let report = AnalysisSection::generate_report(
-            reports,
+            sections,
             &mut registry,
-            "<Placeholder Filename>",
+            report_options.title.as_deref().unwrap_or("report"),
             config_content,
+            &ReportOptions {
+                provenance: vec![provenance::get()],
+                ..report_options.clone()
+            },
         )?;
         Ok(report)
     }
 }
 
-fn get_hist_tables(
-    hist: Hist,
+/// Wraps the error of a failed analysis. In strict mode the error aborts the
+/// run, otherwise it is logged and the remaining analyses are still executed.
+fn handle_failed_analysis(
+    analysis: String,
+    run_id: &str,
+    error: PanacusError,
+    strict: bool,
+) -> Result<PanacusError, PanacusError> {
+    let error = PanacusError::Analysis {
+        analysis,
+        run: run_id.to_string(),
+        msg: error.to_string(),
+    };
+    if strict {
+        return Err(error);
+    }
+    log::error!("{}", error);
+    Ok(error)
+}
+
+fn get_hist_sections(
+    hist: &Hist,
     hist_based: Vec<Box<dyn HistBasedAnalysis>>,
-) -> anyhow::Result<String> {
-    let reports: Vec<String> = hist_based
-        .into_iter()
-        .filter_map(|mut x| x.generate_table(&hist).ok())
-        .collect();
-    let report = reports.into_iter().join("\n\n\n");
-    Ok(report)
+    strict: bool,
+) -> Result<Vec<AnalysisSection>, PanacusError> {
+    let mut sections = Vec::new();
+    for mut analysis in hist_based {
+        log::info!("Doing hist-analysis: {}", analysis.get_type());
+        let stage = format!("analysis: {}", analysis.get_type());
+        match profile::stage(&stage, || analysis.generate_report_section(hist)) {
+            Ok(s) => sections.extend(s),
+            Err(e) => {
+                let error =
+                    handle_failed_analysis(analysis.get_type(), hist.get_run_id(), e, strict)?;
+                sections.push(AnalysisSection::generate_failed_section(
+                    &analysis.get_type(),
+                    hist.get_run_name(),
+                    hist.get_run_id(),
+                    hist.get_feature_type(),
+                    &error,
+                ));
+            }
+        }
+        log::info!("Finished hist-analysis: {}", analysis.get_type());
+    }
+    Ok(sections)
 }
 
-fn get_matrix_tables(
-    matrix: CoverageMatrix,
+fn get_matrix_sections(
+    matrix: &CoverageMatrix,
     matrix_based: Vec<Box<dyn MatrixBasedAnalysis>>,
+    strict: bool,
+) -> Result<Vec<AnalysisSection>, PanacusError> {
+    let mut sections = Vec::new();
+    for mut analysis in matrix_based {
+        log::info!("Doing matrix-analysis: {}", analysis.get_type());
+        let stage = format!("analysis: {}", analysis.get_type());
+        match profile::stage(&stage, || analysis.generate_report_section(matrix)) {
+            Ok(s) => sections.extend(s),
+            Err(e) => {
+                let error =
+                    handle_failed_analysis(analysis.get_type(), matrix.get_run_id(), e, strict)?;
+                sections.push(AnalysisSection::generate_failed_section(
+                    &analysis.get_type(),
+                    matrix.get_run_name(),
+                    matrix.get_run_id(),
+                    matrix.get_feature_type(),
+                    &error,
+                ));
+            }
+        }
+        log::info!("Finished matrix-analysis: {}", analysis.get_type());
+    }
+    Ok(sections)
+}
+
+fn get_hist_tables(
+    hist: &Hist,
     hist_based: Vec<Box<dyn HistBasedAnalysis>>,
-) -> anyhow::Result<String> {
-    let mut reports: Vec<String> = matrix_based
-        .into_iter()
-        // TODO remove all filter_maps and replace with correct error handling
-        .filter_map(|mut x| x.generate_table(&matrix).ok())
-        .collect();
-    let hist = matrix.get_hist();
-    let mut hist_reports: Vec<String> = hist_based
-        .into_iter()
-        .filter_map(|mut x| x.generate_table(&hist).ok())
-        .collect();
-    reports.append(&mut hist_reports);
-    let report = reports.into_iter().join("\n\n\n");
-    Ok(report)
+    strict: bool,
+) -> Result<Vec<String>, PanacusError> {
+    let mut tables = Vec::new();
+    for mut analysis in hist_based {
+        let stage = format!("analysis: {}", analysis.get_type());
+        match profile::stage(&stage, || analysis.generate_table(hist)) {
+            Ok(table) => tables.push(table),
+            Err(e) => {
+                handle_failed_analysis(analysis.get_type(), hist.get_run_id(), e, strict)?;
+            }
+        }
+    }
+    Ok(tables)
 }
 
-fn get_matrix_reports(
-    matrix: CoverageMatrix,
+fn get_matrix_tables(
+    matrix: &CoverageMatrix,
     matrix_based: Vec<Box<dyn MatrixBasedAnalysis>>,
+    strict: bool,
+) -> Result<Vec<String>, PanacusError> {
+    let mut tables = Vec::new();
+    for mut analysis in matrix_based {
+        let stage = format!("analysis: {}", analysis.get_type());
+        match profile::stage(&stage, || analysis.generate_table(matrix)) {
+            Ok(table) => tables.push(table),
+            Err(e) => {
+                handle_failed_analysis(analysis.get_type(), matrix.get_run_id(), e, strict)?;
+            }
+        }
+    }
+    Ok(tables)
+}
+
+fn get_hist_result_tables(
+    hist: &Hist,
     hist_based: Vec<Box<dyn HistBasedAnalysis>>,
-    config_content: &str,
-    json: bool,
-) -> anyhow::Result<String> {
-    let mut reports: Vec<AnalysisSection> = matrix_based
-        .into_iter()
-        // TODO remove all filter_maps and replace with correct error handling
-        .filter_map(|mut x| {
-            log::info!("Doing matrix-analysis: {}", x.get_type());
-            let out = x.generate_report_section(&matrix).ok();
-            log::info!("Finished matrix-analysis: {}", x.get_type());
-            out
-        })
-        .flatten()
-        .collect();
-    let hist = matrix.get_hist();
-    let mut hist_reports: Vec<AnalysisSection> = hist_based
-        .into_iter()
-        .filter_map(|mut x| {
-            log::info!("Doing hist-analysis: {}", x.get_type());
-            let out = x.generate_report_section(&hist).ok();
-            log::info!("Finished hist-analysis: {}", x.get_type());
-            out
-        })
-        .flatten()
-        .collect();
-    reports.append(&mut hist_reports);
+    strict: bool,
+) -> Result<Vec<ResultTable>, PanacusError> {
+    let mut tables = Vec::new();
+    for mut analysis in hist_based {
+        let stage = format!("analysis: {}", analysis.get_type());
+        match profile::stage(&stage, || analysis.generate_result_tables(hist)) {
+            Ok(t) => tables.extend(t),
+            Err(e) => {
+                handle_failed_analysis(analysis.get_type(), hist.get_run_id(), e, strict)?;
+            }
+        }
+    }
+    Ok(tables)
+}
 
-    if json {
-        let report = serde_json::to_string_pretty(&reports)?;
-        Ok(report)
-    } else {
-        let mut registry = handlebars::Handlebars::new();
-        let report = AnalysisSection::generate_report(
-            reports,
-            &mut registry,
-            "<Placeholder Filename>",
-            config_content,
-        )?;
-        Ok(report)
+fn get_matrix_result_tables(
+    matrix: &CoverageMatrix,
+    matrix_based: Vec<Box<dyn MatrixBasedAnalysis>>,
+    strict: bool,
+) -> Result<Vec<ResultTable>, PanacusError> {
+    let mut tables = Vec::new();
+    for mut analysis in matrix_based {
+        let stage = format!("analysis: {}", analysis.get_type());
+        match profile::stage(&stage, || analysis.generate_result_tables(matrix)) {
+            Ok(t) => tables.extend(t),
+            Err(e) => {
+                handle_failed_analysis(analysis.get_type(), matrix.get_run_id(), e, strict)?;
+            }
+        }
     }
+    Ok(tables)
 }
 
 fn split_analyses(
@@ -387,6 +895,45 @@ fn split_analyses(
     )
 }
 
+/// Checks all runs without computing anything (see `FileRun::validate`).
+/// Each graph is parsed at most once to check the path selections and the
+/// groups referred to by the analyses.
+fn validate_instructions(instructions: &[FileRun]) -> Vec<String> {
+    let mut graphs: HashMap<(String, bool), Result<SharedGraph, String>> = HashMap::new();
+    let mut problems = Vec::new();
+    for (idx, run) in instructions.iter().enumerate() {
+        let run_problems = run.validate(|| match run.clone() {
+            FileRun::Gfa {
+                graph,
+                subset,
+                exclude,
+                grouping,
+                reference,
+                nice,
+                count_type,
+                ..
+            } => {
+                let shared = graphs.entry((graph.clone(), nice)).or_insert_with(|| {
+                    SharedGraph::from_gfa(&graph, nice).map_err(|e| e.to_string())
+                });
+                let shared = shared.as_ref().map_err(|e| e.clone())?;
+                let graph_mask_parameters = get_graph_mask_parameters(subset, exclude, grouping);
+                let parser =
+                    GfaParser::from_shared(shared, count_type, graph_mask_parameters, reference)
+                        .map_err(|e| e.to_string())?;
+                Ok(parser.get_group_names())
+            }
+            FileRun::Vcf { .. } => Ok(Vec::new()),
+        });
+        let run_name = match run.get_name() {
+            Some(name) => name.to_string(),
+            None => format!("config block {}", idx + 1),
+        };
+        problems.extend(run_problems.into_iter().map(|p| format!("{run_name}: {p}")));
+    }
+    problems
+}
+
 /// This match has to be extended if a new file type is added.
 fn get_file_parser(
     file: FileRun,
@@ -401,20 +948,9 @@ fn get_file_parser(
             nice,
             count_type,
             analyses,
+            ..
         } => {
-            let (groupby, groupby_sample, groupby_haplotype) = match grouping {
-                Some(Grouping::Sample) => (String::new(), true, false),
-                Some(Grouping::Haplotype) => (String::new(), false, true),
-                Some(Grouping::Custom(file)) => (file, false, false),
-                None => (String::new(), false, false),
-            };
-            let graph_mask_parameters = GraphMaskParameters {
-                positive_list: subset,
-                negative_list: exclude,
-                groupby,
-                groupby_sample,
-                groupby_haplotype,
-            };
+            let graph_mask_parameters = get_graph_mask_parameters(subset, exclude, grouping);
             Ok((
                 Box::new(GfaParser::new(
                     &graph,
@@ -432,6 +968,7 @@ fn get_file_parser(
             count_type,
             exclude,
             analyses,
+            ..
         } => Ok((
             Box::new(VcfParser::new(
                 &file,
@@ -444,8 +981,141 @@ fn get_file_parser(
     }
 }
 
+fn get_graph_mask_parameters(
+    subset: String,
+    exclude: String,
+    grouping: Option<Grouping>,
+) -> GraphMaskParameters {
+    let (groupby, groupby_sample, groupby_haplotype) = match grouping {
+        Some(Grouping::Sample) => (String::new(), true, false),
+        Some(Grouping::Haplotype) => (String::new(), false, true),
+        Some(Grouping::Custom(file)) => (file, false, false),
+        None => (String::new(), false, false),
+    };
+    GraphMaskParameters {
+        positive_list: subset,
+        negative_list: exclude,
+        groupby,
+        groupby_sample,
+        groupby_haplotype,
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum ConfigParseError {
     #[error("no config block with name {name} was found")]
     NameNotFound { name: String },
+    #[error("config block name {name} is used more than once")]
+    DuplicateName { name: String },
+    #[error("config file {file} is included recursively")]
+    CyclicInclude { file: String },
+    #[error("!Include in {file} is not an entry of the list of config blocks")]
+    MisplacedInclude { file: String },
+    #[error("variable {name} has different defaults")]
+    ConflictingDefaults { name: String },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::html_report::DisplayOptions;
+    use crate::util::CountType;
+
+    fn gfa_run(graph: &str, count_type: CountType) -> FileRun {
+        FileRun::Gfa {
+            name: None,
+            graph: graph.to_string(),
+            subset: String::new(),
+            exclude: String::new(),
+            grouping: None,
+            reference: None,
+            nice: false,
+            count_type,
+            analyses: vec![AnalysisParameter::Hist {
+                display: DisplayOptions::default(),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_runtime_of_tables_includes_computation() {
+        provenance::record(&[], &[], false).unwrap();
+        let mut run = gfa_run("tests/test_files/t_groups.gfa", CountType::Node);
+        if let FileRun::Gfa { analyses, .. } = &mut run {
+            analyses.push(AnalysisParameter::Plugin {
+                name: "Sleep".to_string(),
+                command: "sh".to_string(),
+                args: vec![
+                    "-c".to_string(),
+                    "cat > /dev/null; sleep 0.2; echo '[]'".to_string(),
+                ],
+                input: PluginInput::Hist,
+                display: DisplayOptions::default(),
+            });
+        }
+        let mut out = std::io::BufWriter::new(Vec::new());
+        execute_pipeline(
+            vec![run],
+            &mut out,
+            "",
+            false,
+            false,
+            true,
+            None,
+            None,
+            None,
+            None,
+            &ReportOptions::default(),
+        )
+        .unwrap();
+        let out = String::from_utf8(out.into_inner().unwrap()).unwrap();
+        let runtime: f64 = out
+            .lines()
+            .find_map(|l| l.strip_prefix("# runtime "))
+            .and_then(|r| r.strip_suffix('s'))
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(runtime >= 0.2, "runtime {} in\n{}", runtime, out);
+        assert!(out.starts_with("# "));
+    }
+
+    #[test]
+    fn test_plan_groups_runs_by_graph() {
+        let plan = plan_file_runs(vec![
+            gfa_run("a.gfa", CountType::Node),
+            gfa_run("b.gfa", CountType::Node),
+            gfa_run("a.gfa", CountType::Edge),
+        ]);
+        let indices: Vec<Vec<usize>> = plan
+            .iter()
+            .map(|runs| runs.iter().map(|(idx, _)| *idx).collect())
+            .collect();
+        assert_eq!(indices, vec![vec![0, 2], vec![1]]);
+    }
+
+    #[test]
+    fn test_low_memory_is_used_for_hists_within_budget_only() {
+        let graph = "tests/test_files/t_groups.gfa";
+        let mut info_run = gfa_run(graph, CountType::Node);
+        if let FileRun::Gfa { analyses, .. } = &mut info_run {
+            analyses.push(AnalysisParameter::Info {
+                display: DisplayOptions::default(),
+            });
+        }
+        assert!(supports_low_memory(&gfa_run(graph, CountType::Bp)));
+        assert!(!supports_low_memory(&gfa_run(graph, CountType::Edge)));
+        assert!(!supports_low_memory(&info_run));
+
+        let runs = vec![(0, gfa_run(graph, CountType::Node)), (1, info_run)];
+        let (parsers, standard) = get_low_memory_parsers(runs.clone(), 1).unwrap();
+        assert_eq!(
+            parsers.iter().map(|(idx, _, _)| *idx).collect_vec(),
+            vec![0]
+        );
+        assert_eq!(standard.iter().map(|(idx, _)| *idx).collect_vec(), vec![1]);
+        let (parsers, standard) = get_low_memory_parsers(runs, usize::MAX).unwrap();
+        assert!(parsers.is_empty());
+        assert_eq!(standard.len(), 2);
+    }
 }
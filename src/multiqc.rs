@@ -0,0 +1,319 @@
+//! MultiQC custom content files (`*_mqc.json`, `*_mqc.tsv`) of the report
+//! sections (`export-multiqc`, `report --multiqc DIR`)
+//!
+//! Histograms and growth curves are written as line graphs, the statistics
+//! of the info analysis as tables. Each run is a sample, except in the
+//! tables of per-group statistics, which have one sample per group. Files of
+//! different runs share the same MultiQC id if they show the same analysis
+//! and count type, so that MultiQC merges them into one plot.
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use serde_json::{json, Value};
+
+use crate::html_report::{AnalysisSection, ReportItem};
+use crate::util::to_id;
+
+/// Analyses that have a MultiQC representation
+const MULTIQC_ANALYSES: [&str; 4] = [
+    "Coverage Histogram",
+    "Pangenome Growth",
+    "Ordered Growth",
+    "Pangenome Info",
+];
+
+const PARENT_ID: &str = "panacus";
+
+#[derive(Debug, Clone, PartialEq)]
+enum MultiqcFile {
+    Json { name: String, content: Value },
+    Tsv { name: String, content: String },
+}
+
+/// Writes the MultiQC files of the sections to the directory, returns the
+/// written files
+pub fn write_multiqc(dir: &Path, sections: &[AnalysisSection]) -> anyhow::Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir)
+        .with_context(|| format!("could not create MultiQC directory {}", dir.display()))?;
+    let mut files = Vec::new();
+    for file in sections.iter().flat_map(get_section_files) {
+        let (name, content) = match file {
+            MultiqcFile::Json { name, content } => (
+                format!("{}_mqc.json", name),
+                serde_json::to_string_pretty(&content)?,
+            ),
+            MultiqcFile::Tsv { name, content } => (format!("{}_mqc.tsv", name), content),
+        };
+        let file = dir.join(name);
+        std::fs::write(&file, content)
+            .with_context(|| format!("could not write MultiQC file {}", file.display()))?;
+        files.push(file);
+    }
+    log::info!("wrote {} MultiQC files to {}", files.len(), dir.display());
+    Ok(files)
+}
+
+fn get_section_files(section: &AnalysisSection) -> Vec<MultiqcFile> {
+    if !MULTIQC_ANALYSES.contains(&section.analysis.as_str()) {
+        return Vec::new();
+    }
+    let name = to_id(&section.id);
+    let sample = section.run_name.trim();
+    let mut files = Vec::new();
+    for item in &section.items {
+        let i = files.len() + 1;
+        let name = if i == 1 {
+            name.clone()
+        } else {
+            format!("{}-{}", name, i)
+        };
+        match item {
+            ReportItem::Bar {
+                x_label,
+                y_label,
+                labels,
+                values,
+                log_toggle,
+                ..
+            } if are_numeric(labels) => {
+                let data = json!({ sample: get_points(labels, values) });
+                files.push(get_linegraph(
+                    name,
+                    section,
+                    x_label,
+                    y_label,
+                    *log_toggle,
+                    data,
+                ));
+            }
+            ReportItem::MultiBar {
+                names,
+                x_label,
+                y_label,
+                labels,
+                values,
+                log_toggle,
+                ..
+            } if are_numeric(labels) => {
+                let data: serde_json::Map<String, Value> = names
+                    .iter()
+                    .zip(values)
+                    .map(|(series, values)| {
+                        let sample = match names.len() {
+                            1 => sample.to_string(),
+                            _ => format!("{}: {}", sample, series),
+                        };
+                        (sample, get_points(labels, values))
+                    })
+                    .collect();
+                files.push(get_linegraph(
+                    name,
+                    section,
+                    x_label,
+                    y_label,
+                    *log_toggle,
+                    Value::Object(data),
+                ));
+            }
+            ReportItem::Table { header, values, .. } if is_statistics_table(header) => {
+                // One row per run, with the statistics as columns
+                let id = format!("{}_info", PARENT_ID);
+                let header = std::iter::once("Sample")
+                    .chain(values.iter().map(|row| row[0].as_str()))
+                    .collect::<Vec<_>>();
+                let row = std::iter::once(sample)
+                    .chain(values.iter().map(|row| row[1].as_str()))
+                    .collect::<Vec<_>>();
+                files.push(get_table(name, &id, &section.analysis, &header, &[row]));
+            }
+            ReportItem::Table { header, values, .. } if !header.is_empty() => {
+                // Per-group statistics, with one row per group
+                let id = format!("{}_groups_{}", PARENT_ID, to_id(&section.run_id));
+                let section_name = format!("Group Statistics: {}", sample);
+                let header = std::iter::once("Sample")
+                    .chain(header.iter().skip(1).map(|h| h.as_str()))
+                    .collect::<Vec<_>>();
+                let rows = values
+                    .iter()
+                    .map(|row| row.iter().map(|v| v.as_str()).collect())
+                    .collect::<Vec<Vec<&str>>>();
+                files.push(get_table(name, &id, &section_name, &header, &rows));
+            }
+            _ => {}
+        }
+    }
+    files
+}
+
+fn are_numeric(labels: &[String]) -> bool {
+    !labels.is_empty() && labels.iter().all(|l| l.parse::<f64>().is_ok())
+}
+
+fn is_statistics_table(header: &[String]) -> bool {
+    header == ["Statistic", "Value"]
+}
+
+fn get_points(labels: &[String], values: &[f64]) -> Value {
+    labels
+        .iter()
+        .zip(values)
+        .map(|(label, value)| json!([label.parse::<f64>().unwrap_or(f64::NAN), value]))
+        .collect()
+}
+
+fn get_linegraph(
+    name: String,
+    section: &AnalysisSection,
+    x_label: &str,
+    y_label: &str,
+    log_toggle: bool,
+    data: Value,
+) -> MultiqcFile {
+    let id = format!(
+        "{}_{}_{}",
+        PARENT_ID,
+        to_id(&section.analysis),
+        to_id(&section.countable)
+    );
+    let section_name = format!("{} ({})", section.analysis, section.countable);
+    MultiqcFile::Json {
+        name,
+        content: json!({
+            "id": id,
+            "section_name": section_name,
+            "parent_id": PARENT_ID,
+            "parent_name": PARENT_ID,
+            "plot_type": "linegraph",
+            "pconfig": {
+                "id": format!("{}_plot", id),
+                "title": format!("panacus: {}", section_name),
+                "xlab": x_label,
+                "ylab": y_label,
+                "logswitch": log_toggle,
+            },
+            "data": data,
+        }),
+    }
+}
+
+/// A table in MultiQC's TSV format, the configuration is given as YAML in
+/// the comment lines at the top
+fn get_table(
+    name: String,
+    id: &str,
+    section_name: &str,
+    header: &[&str],
+    rows: &[Vec<&str>],
+) -> MultiqcFile {
+    // JSON strings are valid YAML strings
+    let config = [
+        ("id", id),
+        ("section_name", section_name),
+        ("parent_id", PARENT_ID),
+        ("parent_name", PARENT_ID),
+        ("plot_type", "table"),
+    ];
+    let mut content = String::new();
+    for (key, value) in config {
+        content.push_str(&format!("# {}: {}\n", key, Value::from(value)));
+    }
+    for row in std::iter::once(header).chain(rows.iter().map(|r| r.as_slice())) {
+        let row: Vec<String> = row.iter().map(|v| v.replace(['\t', '\n'], " ")).collect();
+        content.push_str(&row.join("\t"));
+        content.push('\n');
+    }
+    MultiqcFile::Tsv { name, content }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::html_report::DisplayOptions;
+
+    fn get_section(analysis: &str, items: Vec<ReportItem>) -> AnalysisSection {
+        AnalysisSection {
+            analysis: analysis.to_string(),
+            run_name: "graph.gfa  ".to_string(),
+            run_id: "graph.gfa--".to_string(),
+            countable: "node".to_string(),
+            items,
+            id: "cov-hist-graph.gfa---node".to_string(),
+            table: None,
+            plot_downloads: Vec::new(),
+            display: DisplayOptions::default(),
+        }
+    }
+
+    #[test]
+    fn test_hist_is_linegraph_of_run() {
+        let section = get_section(
+            "Coverage Histogram",
+            vec![ReportItem::Bar {
+                id: "hist".to_string(),
+                name: "hist".to_string(),
+                x_label: "taxa".to_string(),
+                y_label: "#nodes".to_string(),
+                labels: vec!["1".to_string(), "2".to_string()],
+                values: vec![3.0, 4.0],
+                log_toggle: true,
+            }],
+        );
+        let files = get_section_files(&section);
+        assert_eq!(files.len(), 1);
+        let MultiqcFile::Json { name, content } = &files[0] else {
+            panic!("hist is not written as JSON");
+        };
+        assert_eq!(name, "cov-hist-graph.gfa---node");
+        assert_eq!(content["id"], "panacus_coverage-histogram_node");
+        assert_eq!(content["plot_type"], "linegraph");
+        assert_eq!(
+            content["data"]["graph.gfa"],
+            json!([[1.0, 3.0], [2.0, 4.0]])
+        );
+    }
+
+    #[test]
+    fn test_info_tables() {
+        let section = get_section(
+            "Pangenome Info",
+            vec![
+                ReportItem::Table {
+                    id: "info".to_string(),
+                    header: vec!["Statistic".to_string(), "Value".to_string()],
+                    values: vec![
+                        vec!["Number of nodes".to_string(), "5".to_string()],
+                        vec!["Number of edges".to_string(), "4".to_string()],
+                    ],
+                },
+                ReportItem::Table {
+                    id: "path-stats".to_string(),
+                    header: vec!["group".to_string(), "bp".to_string()],
+                    values: vec![
+                        vec!["a".to_string(), "7".to_string()],
+                        vec!["b".to_string(), "3".to_string()],
+                    ],
+                },
+            ],
+        );
+        let files = get_section_files(&section);
+        assert_eq!(files.len(), 2);
+        let MultiqcFile::Tsv { content, .. } = &files[0] else {
+            panic!("info is not written as TSV");
+        };
+        assert!(content.contains("# id: \"panacus_info\"\n"));
+        assert!(content.contains("# plot_type: \"table\"\n"));
+        assert!(content.ends_with("Sample\tNumber of nodes\tNumber of edges\ngraph.gfa\t5\t4\n"));
+        let MultiqcFile::Tsv { name, content } = &files[1] else {
+            panic!("group statistics are not written as TSV");
+        };
+        assert_eq!(name, "cov-hist-graph.gfa---node-2");
+        assert!(content.contains("# id: \"panacus_groups_graph.gfa--\"\n"));
+        assert!(content.ends_with("Sample\tbp\na\t7\nb\t3\n"));
+    }
+
+    #[test]
+    fn test_other_analyses_are_skipped() {
+        let section = get_section("Similarity Heatmap", Vec::new());
+        assert!(get_section_files(&section).is_empty());
+    }
+}
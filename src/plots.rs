@@ -0,0 +1,263 @@
+//! Static plots of the report sections as SVG, PNG or PDF files, drawn
+//! without a browser (`export-plots`, `report --plots DIR`)
+mod charts;
+mod pdf;
+pub mod scene;
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::Context;
+use once_cell::sync::Lazy;
+use resvg::{tiny_skia, usvg};
+use strum_macros::{EnumString, EnumVariantNames};
+
+use crate::html_report::AnalysisSection;
+use crate::util::to_id;
+
+use scene::Scene;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString, EnumVariantNames)]
+#[strum(serialize_all = "lowercase")]
+pub enum PlotFormat {
+    Svg,
+    Png,
+    Pdf,
+}
+
+impl PlotFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            Self::Svg => "svg",
+            Self::Png => "png",
+            Self::Pdf => "pdf",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlotOptions {
+    /// Size in pixels (SVG, PNG) or points (PDF)
+    pub width: f64,
+    pub height: f64,
+    /// Font of SVG and PNG plots, PDF plots always use Helvetica
+    pub font_family: String,
+    pub font_size: f64,
+    pub log_x: bool,
+    pub log_y: bool,
+    /// Pixels per pixel of the PNG plots
+    pub scale: f64,
+}
+
+impl Default for PlotOptions {
+    fn default() -> Self {
+        Self {
+            width: 800.0,
+            height: 500.0,
+            font_family: "sans-serif".to_string(),
+            font_size: 12.0,
+            log_x: false,
+            log_y: false,
+            scale: 2.0,
+        }
+    }
+}
+
+/// Where and how to write the plots
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlotExport {
+    pub dir: PathBuf,
+    pub formats: Vec<PlotFormat>,
+    pub options: PlotOptions,
+}
+
+impl PlotExport {
+    /// Writes the plots of all items of the sections, returns the written
+    /// files. Files are named after the section id, with the index of the
+    /// item if a section has multiple plots, and the variable of
+    /// chromosomal plots.
+    pub fn write(&self, sections: &[AnalysisSection]) -> anyhow::Result<Vec<PathBuf>> {
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("could not create plot directory {}", self.dir.display()))?;
+        let mut files = Vec::new();
+        for section in sections {
+            for (name, scene) in get_section_scenes(section, &self.options) {
+                for format in &self.formats {
+                    let file = self.dir.join(format!("{}.{}", name, format.extension()));
+                    write_scene(&scene, *format, self.options.scale, &file)?;
+                    files.push(file);
+                }
+            }
+        }
+        log::info!("wrote {} plots to {}", files.len(), self.dir.display());
+        Ok(files)
+    }
+}
+
+fn get_section_scenes(section: &AnalysisSection, options: &PlotOptions) -> Vec<(String, Scene)> {
+    let id = to_id(&section.id);
+    let title = format!(
+        "{} ({}): {}",
+        section.analysis,
+        section.countable,
+        section.run_name.trim()
+    );
+    let plots: Vec<Vec<(Option<String>, Scene)>> = section
+        .items
+        .iter()
+        .map(|item| charts::draw_item(item, &title, options))
+        .filter(|plots| !plots.is_empty())
+        .collect();
+    let is_single = plots.len() == 1;
+    plots
+        .into_iter()
+        .enumerate()
+        .flat_map(|(i, item_plots)| {
+            let id = if is_single {
+                id.clone()
+            } else {
+                format!("{}-{}", id, i + 1)
+            };
+            item_plots
+                .into_iter()
+                .map(move |(suffix, scene)| match suffix {
+                    Some(suffix) => (format!("{}-{}", id, to_id(&suffix)), scene),
+                    None => (id.clone(), scene),
+                })
+        })
+        .collect()
+}
+
+fn write_scene(scene: &Scene, format: PlotFormat, scale: f64, file: &Path) -> anyhow::Result<()> {
+    let content = match format {
+        PlotFormat::Svg => scene.to_svg().into_bytes(),
+        PlotFormat::Png => to_png(&scene.to_svg(), scale)?,
+        PlotFormat::Pdf => pdf::to_pdf(scene),
+    };
+    std::fs::write(file, content)
+        .with_context(|| format!("could not write plot to {}", file.display()))
+}
+
+/// System fonts, loaded once for all PNG plots
+static FONTS: Lazy<Arc<usvg::fontdb::Database>> = Lazy::new(|| {
+    let mut fonts = usvg::fontdb::Database::new();
+    fonts.load_system_fonts();
+    // The generic families default to fonts (e.g. Arial) that are often
+    // missing on Linux
+    let families: Vec<String> = fonts
+        .faces()
+        .flat_map(|face| face.families.iter().map(|(family, _)| family.clone()))
+        .collect();
+    let fallback = families
+        .iter()
+        .find(|f| f.contains("Sans") && !f.contains("Mono"))
+        .or_else(|| families.first());
+    match fallback {
+        Some(fallback) if !families.iter().any(|f| f == "Arial") => {
+            fonts.set_sans_serif_family(fallback.as_str());
+        }
+        Some(_) => {}
+        None => log::warn!("no system fonts found, texts of PNG plots will be missing"),
+    }
+    Arc::new(fonts)
+});
+
+fn to_png(svg: &str, scale: f64) -> anyhow::Result<Vec<u8>> {
+    let options = usvg::Options {
+        fontdb: Arc::clone(&FONTS),
+        ..usvg::Options::default()
+    };
+    let tree = usvg::Tree::from_str(svg, &options)?;
+    let size = tree.size();
+    let (width, height) = (
+        (size.width() as f64 * scale).ceil() as u32,
+        (size.height() as f64 * scale).ceil() as u32,
+    );
+    let mut pixmap = tiny_skia::Pixmap::new(width, height)
+        .ok_or_else(|| anyhow::anyhow!("invalid PNG size {}x{}", width, height))?;
+    resvg::render(
+        &tree,
+        tiny_skia::Transform::from_scale(scale as f32, scale as f32),
+        &mut pixmap.as_mut(),
+    );
+    Ok(pixmap.encode_png()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::html_report::{DisplayOptions, ReportItem};
+
+    fn get_section(items: Vec<ReportItem>) -> AnalysisSection {
+        AnalysisSection {
+            analysis: "Pangenome Growth".to_string(),
+            run_name: "test".to_string(),
+            run_id: "test-growth".to_string(),
+            countable: "Nodes".to_string(),
+            items,
+            id: "growth-Nodes".to_string(),
+            table: None,
+            plot_downloads: Vec::new(),
+            display: DisplayOptions::default(),
+        }
+    }
+
+    fn get_bar(id: &str) -> ReportItem {
+        ReportItem::Bar {
+            id: id.to_string(),
+            name: id.to_string(),
+            x_label: "taxa".to_string(),
+            y_label: "nodes".to_string(),
+            labels: vec!["1".to_string(), "2".to_string()],
+            values: vec![3.0, 4.0],
+            log_toggle: false,
+        }
+    }
+
+    #[test]
+    fn test_section_plots_are_named_after_section() {
+        let options = PlotOptions::default();
+        let table = ReportItem::Table {
+            id: "table".to_string(),
+            header: Vec::new(),
+            values: Vec::new(),
+        };
+        let section = get_section(vec![get_bar("a"), table]);
+        let names: Vec<String> = get_section_scenes(&section, &options)
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, vec!["growth-nodes"]);
+
+        let section = get_section(vec![get_bar("a"), get_bar("b")]);
+        let names: Vec<String> = get_section_scenes(&section, &options)
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, vec!["growth-nodes-1", "growth-nodes-2"]);
+    }
+
+    #[test]
+    fn test_write_plots_in_all_formats() {
+        let dir = tempfile::tempdir().unwrap();
+        let export = PlotExport {
+            dir: dir.path().join("plots"),
+            formats: vec![PlotFormat::Svg, PlotFormat::Png, PlotFormat::Pdf],
+            options: PlotOptions {
+                width: 200.0,
+                height: 100.0,
+                scale: 1.0,
+                ..PlotOptions::default()
+            },
+        };
+        let files = export.write(&[get_section(vec![get_bar("a")])]).unwrap();
+        assert_eq!(files.len(), 3);
+        let svg = std::fs::read_to_string(&files[0]).unwrap();
+        assert!(svg.starts_with("<svg"));
+        let png = std::fs::read(&files[1]).unwrap();
+        assert!(png.starts_with(b"\x89PNG"));
+        let pdf = std::fs::read(&files[2]).unwrap();
+        assert!(pdf.starts_with(b"%PDF-1.4"));
+        assert!(pdf.ends_with(b"%%EOF\n"));
+    }
+}
@@ -0,0 +1,1055 @@
+//! Draws the plots of the `ReportItem`s as `Scene`s, mirroring the Vega-Lite
+//! charts of the HTML report (see `etc/hook_after.js`)
+use crate::html_report::{Bin, ReportItem, Window};
+
+use super::scene::{text_width, Anchor, Color, Scene, Shape};
+use super::PlotOptions;
+
+/// Colors of the MultiBar and SectionLine series
+const SERIES_COLORS: [&str; 6] = [
+    "#f77189", "#bb9832", "#50b131", "#36ada4", "#3ba3ec", "#e866f4",
+];
+/// Colors of the sections of SectionLine plots (Vega's pastel1 scheme)
+const SECTION_COLORS: [&str; 9] = [
+    "#fbb4ae", "#b3cde3", "#ccebc5", "#decbe4", "#fed9a6", "#ffffcc", "#e5d8bd", "#fddaec",
+    "#f2f2f2",
+];
+const BAR_COLOR: &str = "#4c78a8";
+/// Plasma color scheme of chromosomal plots
+const PLASMA: [&str; 5] = ["#0d0887", "#7e03a8", "#cc4778", "#f89540", "#f0f921"];
+/// Blue-purple color scheme of hexbin plots
+const BLUE_PURPLE: [&str; 5] = ["#edf8fb", "#b3cde3", "#8c96c6", "#8856a7", "#810f7c"];
+/// Labels of MultiBar plots with at least this many labels are drawn as areas
+const MIN_AREA_LABELS: usize = 300;
+/// Text widths are estimated from Helvetica, other fonts (of SVG and PNG
+/// plots) may be wider
+const TEXT_WIDTH_MARGIN: f64 = 1.2;
+
+/// Draws the plots of a report item. Most items result in a single plot,
+/// chromosomal plots in one plot per variable (with the variable as suffix).
+/// Items without plot (e.g. tables) result in no plots.
+pub fn draw_item(
+    item: &ReportItem,
+    title: &str,
+    options: &PlotOptions,
+) -> Vec<(Option<String>, Scene)> {
+    match item {
+        ReportItem::Bar {
+            name,
+            x_label,
+            y_label,
+            labels,
+            values,
+            ..
+        } => {
+            let series = [Series {
+                name: name.clone(),
+                values: values.clone(),
+                color: Color::from_hex(BAR_COLOR),
+            }];
+            vec![(
+                None,
+                draw_bars(options, title, x_label, y_label, labels, &series, None),
+            )]
+        }
+        ReportItem::MultiBar {
+            names,
+            x_label,
+            y_label,
+            labels,
+            values,
+            curve,
+            alpha,
+            ..
+        } => {
+            let series: Vec<Series> = names
+                .iter()
+                .zip(values)
+                .enumerate()
+                .map(|(i, (name, values))| Series {
+                    name: name.clone(),
+                    values: values.clone(),
+                    color: Color::from_hex(SERIES_COLORS[i % SERIES_COLORS.len()]),
+                })
+                .collect();
+            let curve = curve.as_ref().map(|curve| Series {
+                name: match alpha {
+                    Some(alpha) => format!("fit (alpha = {:.3})", alpha),
+                    None => "fit".to_string(),
+                },
+                values: curve.clone(),
+                color: Color(255, 0, 0),
+            });
+            vec![(
+                None,
+                draw_bars(options, title, x_label, y_label, labels, &series, curve),
+            )]
+        }
+        ReportItem::SectionLine {
+            names,
+            x_label,
+            y_label,
+            labels,
+            values,
+            section_separators,
+            section_labels,
+            ..
+        } => vec![(
+            None,
+            draw_section_lines(
+                options,
+                title,
+                x_label,
+                y_label,
+                labels,
+                names,
+                values,
+                section_separators,
+                section_labels,
+            ),
+        )],
+        ReportItem::Line {
+            x_label,
+            y_label,
+            x_values,
+            y_values,
+            log_x,
+            log_y,
+            ..
+        } => vec![(
+            None,
+            draw_line(
+                options,
+                title,
+                x_label,
+                y_label,
+                x_values,
+                y_values,
+                *log_x || options.log_x,
+                *log_y || options.log_y,
+            ),
+        )],
+        ReportItem::Heatmap {
+            x_labels,
+            y_labels,
+            values,
+            ..
+        } => vec![(
+            None,
+            draw_heatmap(options, title, x_labels, y_labels, values),
+        )],
+        ReportItem::Hexbin { bins, .. } => vec![(None, draw_hexbin(options, title, bins))],
+        ReportItem::Chromosomal {
+            labels,
+            sequence,
+            values,
+            ..
+        } => labels
+            .iter()
+            .enumerate()
+            .map(|(i, label)| {
+                let title = format!("{} ({})", title, label);
+                (
+                    Some(label.clone()),
+                    draw_chromosomal(options, &title, i, sequence, values),
+                )
+            })
+            .collect(),
+        ReportItem::Table { .. }
+        | ReportItem::Png { .. }
+        | ReportItem::Svg { .. }
+        | ReportItem::SvgContent { .. }
+        | ReportItem::Json { .. }
+        | ReportItem::Pdf { .. }
+        | ReportItem::Error { .. }
+        | ReportItem::Text { .. } => Vec::new(),
+    }
+}
+
+struct Series {
+    name: String,
+    values: Vec<f64>,
+    color: Color,
+}
+
+/// Mapping of values to `[0, 1]`
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Scale {
+    min: f64,
+    max: f64,
+    log: bool,
+}
+
+impl Scale {
+    /// Scale covering the values with "nice" bounds. Log scales ignore
+    /// non-positive values.
+    fn from_values<I: IntoIterator<Item = f64>>(values: I, log: bool, include_zero: bool) -> Self {
+        let values: Vec<f64> = values
+            .into_iter()
+            .filter(|v| v.is_finite() && (!log || *v > 0.0))
+            .collect();
+        let lo = values.iter().copied().fold(f64::INFINITY, f64::min);
+        let hi = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        if log {
+            if values.is_empty() {
+                return Self {
+                    min: 1.0,
+                    max: 10.0,
+                    log,
+                };
+            }
+            let min = 10f64.powf(lo.log10().floor());
+            let mut max = 10f64.powf(hi.log10().ceil());
+            if max <= min {
+                max = min * 10.0;
+            }
+            return Self { min, max, log };
+        }
+        let (mut lo, mut hi) = if values.is_empty() {
+            (0.0, 1.0)
+        } else {
+            (lo, hi)
+        };
+        if include_zero {
+            lo = lo.min(0.0);
+            hi = hi.max(0.0);
+        }
+        if hi <= lo {
+            hi = lo + 1.0;
+        }
+        let step = nice_step((hi - lo) / 5.0);
+        Self {
+            min: (lo / step).floor() * step,
+            max: (hi / step).ceil() * step,
+            log,
+        }
+    }
+
+    fn frac(&self, v: f64) -> f64 {
+        if self.log {
+            let v = v.max(self.min);
+            (v.log10() - self.min.log10()) / (self.max.log10() - self.min.log10())
+        } else {
+            (v - self.min) / (self.max - self.min)
+        }
+    }
+
+    fn ticks(&self) -> Vec<f64> {
+        if self.log {
+            let lo = self.min.log10().round() as i32;
+            let hi = self.max.log10().round() as i32;
+            let every = ((hi - lo) / 8).max(1);
+            (lo..=hi)
+                .filter(|e| (e - lo) % every == 0)
+                .map(|e| 10f64.powi(e))
+                .collect()
+        } else {
+            let step = nice_step((self.max - self.min) / 5.0);
+            let n = ((self.max - self.min) / step).round() as usize;
+            (0..=n).map(|i| self.min + i as f64 * step).collect()
+        }
+    }
+}
+
+/// Step of 1, 2 or 5 times a power of 10 close to the given one
+fn nice_step(step: f64) -> f64 {
+    if step <= 0.0 || !step.is_finite() {
+        return 1.0;
+    }
+    let magnitude = 10f64.powf(step.log10().floor());
+    let residual = step / magnitude;
+    let nice = if residual <= 1.0 {
+        1.0
+    } else if residual <= 2.0 {
+        2.0
+    } else if residual <= 5.0 {
+        5.0
+    } else {
+        10.0
+    };
+    nice * magnitude
+}
+
+/// Formats axis ticks with SI suffixes, e.g. `1.5M`
+fn format_tick(v: f64) -> String {
+    let trim = |x: f64| {
+        let s = format!("{:.2}", x);
+        let s = s.trim_end_matches('0').trim_end_matches('.').to_string();
+        if s == "-0" {
+            "0".to_string()
+        } else {
+            s
+        }
+    };
+    let a = v.abs();
+    if a >= 1e9 {
+        format!("{}G", trim(v / 1e9))
+    } else if a >= 1e6 {
+        format!("{}M", trim(v / 1e6))
+    } else if a >= 1e4 {
+        format!("{}k", trim(v / 1e3))
+    } else if a > 0.0 && a < 0.01 {
+        format!("{:.0e}", v)
+    } else {
+        trim(v)
+    }
+}
+
+fn gradient(stops: &[&str], t: f64) -> Color {
+    let t = if t.is_finite() {
+        t.clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    let pos = t * (stops.len() - 1) as f64;
+    let i = (pos.floor() as usize).min(stops.len() - 2);
+    Color::from_hex(stops[i]).mix(Color::from_hex(stops[i + 1]), pos - i as f64)
+}
+
+/// Plot area within the scene
+#[derive(Debug, Clone, Copy)]
+struct Area {
+    x: f64,
+    y: f64,
+    w: f64,
+    h: f64,
+}
+
+impl Area {
+    fn px(&self, frac: f64) -> f64 {
+        self.x + frac * self.w
+    }
+
+    fn py(&self, frac: f64) -> f64 {
+        self.y + (1.0 - frac) * self.h
+    }
+
+    fn bottom(&self) -> f64 {
+        self.y + self.h
+    }
+
+    fn right(&self) -> f64 {
+        self.x + self.w
+    }
+}
+
+/// Scene with title and the area left for the plot, given the widths of the
+/// y tick labels and of the legend
+fn new_scene(
+    options: &PlotOptions,
+    title: &str,
+    y_ticks: &[String],
+    legend: &[String],
+) -> (Scene, Area) {
+    let font = options.font_size;
+    let mut scene = Scene::new(options.width, options.height, &options.font_family);
+    scene.shapes.push(Shape::Text {
+        x: options.width / 2.0,
+        y: 1.6 * font,
+        text: title.to_string(),
+        size: 1.25 * font,
+        anchor: Anchor::Middle,
+        vertical: false,
+        bold: true,
+    });
+    let y_tick_width = y_ticks
+        .iter()
+        .map(|t| TEXT_WIDTH_MARGIN * text_width(t, font))
+        .fold(0.0, f64::max);
+    let legend_width = legend
+        .iter()
+        .map(|t| TEXT_WIDTH_MARGIN * text_width(t, font) + 1.5 * font + 10.0)
+        .fold(0.0, f64::max);
+    let left = 10.0 + 1.5 * font + y_tick_width + 10.0;
+    let top = 3.0 * font;
+    let right = 15.0 + legend_width;
+    let bottom = 2.5 * font + 20.0;
+    let area = Area {
+        x: left,
+        y: top,
+        w: (options.width - left - right).max(10.0),
+        h: (options.height - top - bottom).max(10.0),
+    };
+    (scene, area)
+}
+
+fn draw_y_axis(scene: &mut Scene, options: &PlotOptions, area: &Area, scale: &Scale, label: &str) {
+    let font = options.font_size;
+    for tick in scale.ticks() {
+        let y = area.py(scale.frac(tick));
+        scene.line(vec![(area.x, y), (area.right(), y)], Color::LIGHT_GRAY, 1.0);
+        scene.line(vec![(area.x - 5.0, y), (area.x, y)], Color::GRAY, 1.0);
+        scene.text(
+            area.x - 8.0,
+            y + 0.35 * font,
+            &format_tick(tick),
+            font,
+            Anchor::End,
+        );
+    }
+    scene.shapes.push(Shape::Text {
+        x: 10.0 + font,
+        y: area.y + area.h / 2.0,
+        text: label.to_string(),
+        size: font,
+        anchor: Anchor::Middle,
+        vertical: true,
+        bold: true,
+    });
+}
+
+fn draw_x_label(scene: &mut Scene, options: &PlotOptions, area: &Area, label: &str) {
+    scene.shapes.push(Shape::Text {
+        x: area.x + area.w / 2.0,
+        y: area.bottom() + 2.2 * options.font_size + 12.0,
+        text: label.to_string(),
+        size: options.font_size,
+        anchor: Anchor::Middle,
+        vertical: false,
+        bold: true,
+    });
+}
+
+fn draw_x_axis(scene: &mut Scene, options: &PlotOptions, area: &Area, scale: &Scale, label: &str) {
+    let font = options.font_size;
+    for tick in scale.ticks() {
+        let x = area.px(scale.frac(tick));
+        scene.line(
+            vec![(x, area.y), (x, area.bottom())],
+            Color::LIGHT_GRAY,
+            1.0,
+        );
+        scene.line(
+            vec![(x, area.bottom()), (x, area.bottom() + 5.0)],
+            Color::GRAY,
+            1.0,
+        );
+        scene.text(
+            x,
+            area.bottom() + 7.0 + font,
+            &format_tick(tick),
+            font,
+            Anchor::Middle,
+        );
+    }
+    draw_x_label(scene, options, area, label);
+}
+
+/// Axis of categorical labels at the centers of `labels.len()` bands, labels
+/// are thinned out so that they do not overlap
+fn draw_band_axis(
+    scene: &mut Scene,
+    options: &PlotOptions,
+    area: &Area,
+    labels: &[String],
+    label: &str,
+) {
+    let font = options.font_size;
+    let band = area.w / labels.len().max(1) as f64;
+    let widest = labels
+        .iter()
+        .map(|l| TEXT_WIDTH_MARGIN * text_width(l, font))
+        .fold(0.0, f64::max)
+        + font;
+    let every = ((widest / band).ceil() as usize).max(1);
+    for (i, l) in labels.iter().enumerate().step_by(every) {
+        let x = area.x + (i as f64 + 0.5) * band;
+        scene.line(
+            vec![(x, area.bottom()), (x, area.bottom() + 5.0)],
+            Color::GRAY,
+            1.0,
+        );
+        scene.text(x, area.bottom() + 7.0 + font, l, font, Anchor::Middle);
+    }
+    draw_x_label(scene, options, area, label);
+}
+
+fn draw_legend(scene: &mut Scene, options: &PlotOptions, area: &Area, entries: &[(String, Color)]) {
+    let font = options.font_size;
+    for (i, (name, color)) in entries.iter().enumerate() {
+        let y = area.y + i as f64 * 1.5 * font;
+        scene.rect(area.right() + 15.0, y, font, font, *color);
+        scene.text(
+            area.right() + 15.0 + 1.5 * font,
+            y + 0.85 * font,
+            name,
+            font,
+            Anchor::Start,
+        );
+    }
+}
+
+fn draw_frame(scene: &mut Scene, area: &Area) {
+    scene.shapes.push(Shape::Frame {
+        x: area.x,
+        y: area.y,
+        width: area.w,
+        height: area.h,
+        stroke: Color::GRAY,
+    });
+}
+
+fn value_scale(options: &PlotOptions, series: &[Series]) -> Scale {
+    Scale::from_values(
+        series.iter().flat_map(|s| s.values.iter().copied()),
+        options.log_y,
+        true,
+    )
+}
+
+fn tick_labels(scale: &Scale) -> Vec<String> {
+    scale.ticks().into_iter().map(format_tick).collect()
+}
+
+/// Bars of one or more (overlapping) series, drawn as areas if there are
+/// many labels, with an optional curve (e.g. a fit)
+fn draw_bars(
+    options: &PlotOptions,
+    title: &str,
+    x_label: &str,
+    y_label: &str,
+    labels: &[String],
+    series: &[Series],
+    curve: Option<Series>,
+) -> Scene {
+    let mut all: Vec<&Series> = series.iter().collect();
+    all.extend(curve.iter());
+    let scale = value_scale(options, series);
+    let legend: Vec<(String, Color)> = if all.len() > 1 {
+        all.iter().map(|s| (s.name.clone(), s.color)).collect()
+    } else {
+        Vec::new()
+    };
+    let legend_names: Vec<String> = legend.iter().map(|(n, _)| n.clone()).collect();
+    let (mut scene, area) = new_scene(options, title, &tick_labels(&scale), &legend_names);
+    draw_y_axis(&mut scene, options, &area, &scale, y_label);
+    let band = area.w / labels.len().max(1) as f64;
+    let base = area.py(scale.frac(if scale.log { scale.min } else { 0.0 }));
+    let center = |i: usize| area.x + (i as f64 + 0.5) * band;
+    for s in series {
+        if labels.len() >= MIN_AREA_LABELS {
+            let mut points: Vec<(f64, f64)> = s
+                .values
+                .iter()
+                .enumerate()
+                .map(|(i, v)| (center(i), area.py(scale.frac(*v))))
+                .collect();
+            points.push((center(s.values.len().saturating_sub(1)), base));
+            points.push((center(0), base));
+            scene.shapes.push(Shape::Polygon {
+                points,
+                fill: s.color,
+                opacity: 1.0,
+            });
+        } else {
+            for (i, v) in s.values.iter().enumerate() {
+                if !v.is_finite() || (scale.log && *v <= 0.0) {
+                    continue;
+                }
+                let y = area.py(scale.frac(*v));
+                scene.rect(
+                    center(i) - 0.4 * band,
+                    y.min(base),
+                    0.8 * band,
+                    (base - y).abs(),
+                    s.color,
+                );
+            }
+        }
+    }
+    if let Some(curve) = &curve {
+        let points = curve
+            .values
+            .iter()
+            .enumerate()
+            .filter(|(_, v)| v.is_finite())
+            .map(|(i, v)| (center(i), area.py(scale.frac(*v).clamp(0.0, 1.0))))
+            .collect();
+        scene.line(points, curve.color, 2.0);
+    }
+    draw_band_axis(&mut scene, options, &area, labels, x_label);
+    draw_frame(&mut scene, &area);
+    draw_legend(&mut scene, options, &area, &legend);
+    scene
+}
+
+/// x coordinates of labels: the labels themselves if they are all numeric,
+/// their index otherwise
+fn numeric_labels(labels: &[String]) -> Vec<f64> {
+    let numeric: Option<Vec<f64>> = labels.iter().map(|l| l.parse::<f64>().ok()).collect();
+    numeric.unwrap_or_else(|| (0..labels.len()).map(|i| i as f64).collect())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn draw_section_lines(
+    options: &PlotOptions,
+    title: &str,
+    x_label: &str,
+    y_label: &str,
+    labels: &[String],
+    names: &[String],
+    values: &[Vec<f64>],
+    separators: &[f64],
+    section_labels: &[String],
+) -> Scene {
+    let xs = numeric_labels(labels);
+    let series: Vec<Series> = names
+        .iter()
+        .zip(values)
+        .enumerate()
+        .map(|(i, (name, values))| Series {
+            name: name.clone(),
+            values: values.clone(),
+            color: Color::from_hex(SERIES_COLORS[i % SERIES_COLORS.len()]),
+        })
+        .collect();
+    let y_scale = value_scale(options, &series);
+    let x_scale = Scale {
+        min: xs.iter().copied().fold(0.0, f64::min),
+        max: xs.iter().copied().fold(1.0, f64::max),
+        log: false,
+    };
+    let mut legend: Vec<(String, Color)> =
+        series.iter().map(|s| (s.name.clone(), s.color)).collect();
+    legend.extend(section_labels.iter().enumerate().map(|(i, l)| {
+        (
+            l.clone(),
+            Color::from_hex(SECTION_COLORS[i % SECTION_COLORS.len()]),
+        )
+    }));
+    let legend_names: Vec<String> = legend.iter().map(|(n, _)| n.clone()).collect();
+    let (mut scene, area) = new_scene(options, title, &tick_labels(&y_scale), &legend_names);
+    // Sections as background, from the previous separator to the next one
+    for (i, _) in section_labels.iter().enumerate() {
+        let start = if i == 0 {
+            x_scale.min
+        } else {
+            separators[i - 1]
+        };
+        let end = if i + 1 == section_labels.len() {
+            x_scale.max
+        } else {
+            separators.get(i).copied().unwrap_or(x_scale.max)
+        };
+        let (x0, x1) = (
+            area.px(x_scale.frac(start).clamp(0.0, 1.0)),
+            area.px(x_scale.frac(end).clamp(0.0, 1.0)),
+        );
+        scene.shapes.push(Shape::Rect {
+            x: x0,
+            y: area.y,
+            width: (x1 - x0).max(0.0),
+            height: area.h,
+            fill: Color::from_hex(SECTION_COLORS[i % SECTION_COLORS.len()]),
+            opacity: 0.7,
+        });
+    }
+    draw_y_axis(&mut scene, options, &area, &y_scale, y_label);
+    for s in &series {
+        let points = xs
+            .iter()
+            .zip(&s.values)
+            .filter(|(_, v)| v.is_finite() && (!y_scale.log || **v > 0.0))
+            .map(|(x, v)| (area.px(x_scale.frac(*x)), area.py(y_scale.frac(*v))))
+            .collect();
+        scene.line(points, s.color, 2.0);
+    }
+    draw_x_axis(&mut scene, options, &area, &x_scale, x_label);
+    draw_frame(&mut scene, &area);
+    draw_legend(&mut scene, options, &area, &legend);
+    scene
+}
+
+#[allow(clippy::too_many_arguments)]
+fn draw_line(
+    options: &PlotOptions,
+    title: &str,
+    x_label: &str,
+    y_label: &str,
+    x_values: &[f32],
+    y_values: &[f32],
+    log_x: bool,
+    log_y: bool,
+) -> Scene {
+    let x_scale = Scale::from_values(x_values.iter().map(|x| *x as f64), log_x, false);
+    let y_scale = Scale::from_values(y_values.iter().map(|y| *y as f64), log_y, false);
+    let (mut scene, area) = new_scene(options, title, &tick_labels(&y_scale), &[]);
+    draw_y_axis(&mut scene, options, &area, &y_scale, y_label);
+    let points = x_values
+        .iter()
+        .zip(y_values)
+        .map(|(x, y)| (*x as f64, *y as f64))
+        .filter(|(x, y)| {
+            x.is_finite() && y.is_finite() && (!log_x || *x > 0.0) && (!log_y || *y > 0.0)
+        })
+        .map(|(x, y)| (area.px(x_scale.frac(x)), area.py(y_scale.frac(y))))
+        .collect();
+    scene.line(points, Color::from_hex(BAR_COLOR), 2.0);
+    draw_x_axis(&mut scene, options, &area, &x_scale, x_label);
+    draw_frame(&mut scene, &area);
+    scene
+}
+
+/// Color bar of a continuous color scale right of the plot area
+fn draw_color_bar<F: Fn(f64) -> Color>(
+    scene: &mut Scene,
+    options: &PlotOptions,
+    area: &Area,
+    color: F,
+    min_label: &str,
+    max_label: &str,
+) {
+    let steps = 50;
+    let x = area.right() + 15.0;
+    let h = area.h.min(200.0);
+    for i in 0..steps {
+        let t = i as f64 / steps as f64;
+        scene.rect(
+            x,
+            area.y + (1.0 - t) * h - h / steps as f64,
+            options.font_size,
+            h / steps as f64 + 0.5,
+            color(t),
+        );
+    }
+    let font = options.font_size;
+    scene.text(
+        x + 1.5 * font,
+        area.y + 0.85 * font,
+        max_label,
+        font,
+        Anchor::Start,
+    );
+    scene.text(x + 1.5 * font, area.y + h, min_label, font, Anchor::Start);
+}
+
+fn draw_heatmap(
+    options: &PlotOptions,
+    title: &str,
+    x_labels: &[String],
+    y_labels: &[String],
+    values: &[Vec<f32>],
+) -> Scene {
+    let min = values
+        .iter()
+        .flatten()
+        .copied()
+        .filter(|v| v.is_finite())
+        .fold(f32::INFINITY, f32::min) as f64;
+    let max = 1.0_f64.max(min + f64::EPSILON);
+    let min = if min.is_finite() { min } else { 0.0 };
+    let color = |t: f64| Color(139, 0, 0).mix(Color::WHITE, t);
+    let legend = [format_tick(min), format_tick(max)];
+    let (mut scene, mut area) = new_scene(options, title, y_labels, &legend);
+    // Square cells
+    let cell = (area.w / x_labels.len().max(1) as f64).min(area.h / y_labels.len().max(1) as f64);
+    area.w = cell * x_labels.len() as f64;
+    area.h = cell * y_labels.len() as f64;
+    for (row_i, row) in values.iter().enumerate() {
+        for (col_i, v) in row.iter().enumerate() {
+            // as in the report, rows of values belong to the x labels
+            scene.rect(
+                area.x + row_i as f64 * cell,
+                area.y + col_i as f64 * cell,
+                cell + 0.3,
+                cell + 0.3,
+                color((*v as f64 - min) / (max - min)),
+            );
+        }
+    }
+    let font = options.font_size;
+    let every = ((1.2 * font / cell).ceil() as usize).max(1);
+    for (i, l) in y_labels.iter().enumerate().step_by(every) {
+        let y = area.y + (i as f64 + 0.5) * cell + 0.35 * font;
+        scene.text(area.x - 6.0, y, l, font, Anchor::End);
+    }
+    for (i, l) in x_labels.iter().enumerate().step_by(every) {
+        scene.shapes.push(Shape::Text {
+            x: area.x + (i as f64 + 0.5) * cell + 0.35 * font,
+            y: area.bottom() + 6.0,
+            text: l.clone(),
+            size: font,
+            anchor: Anchor::End,
+            vertical: true,
+            bold: false,
+        });
+    }
+    draw_frame(&mut scene, &area);
+    draw_color_bar(&mut scene, options, &area, color, &legend[0], &legend[1]);
+    scene
+}
+
+fn draw_hexbin(options: &PlotOptions, title: &str, bins: &[Bin]) -> Scene {
+    // bins without a finite center cannot be placed
+    let bins: Vec<&Bin> = bins
+        .iter()
+        .filter(|b| b.x.is_finite() && b.y.is_finite())
+        .collect();
+    // Smallest distance of bin centers, the axes leave room for a hexagon
+    // around the outermost centers
+    let min_step = |mut values: Vec<f64>| {
+        values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        values.dedup();
+        let step = values
+            .windows(2)
+            .map(|w| w[1] - w[0])
+            .fold(f64::INFINITY, f64::min);
+        if step.is_finite() {
+            step
+        } else {
+            1.0
+        }
+    };
+    let x_step = min_step(bins.iter().map(|b| b.x).collect());
+    let y_step = min_step(bins.iter().map(|b| b.y).collect());
+    let x_scale = Scale::from_values(
+        bins.iter()
+            .flat_map(|b| [b.x - x_step / 2.0, b.x + x_step / 2.0]),
+        false,
+        false,
+    );
+    let y_scale = Scale::from_values(
+        bins.iter()
+            .flat_map(|b| [b.y - y_step / 2.0, b.y + y_step / 2.0]),
+        false,
+        false,
+    );
+    let max_size = bins.iter().map(|b| b.size).max().unwrap_or(1).max(1);
+    let legend = [format_tick(1.0), format_tick(max_size as f64)];
+    let (mut scene, area) = new_scene(options, title, &tick_labels(&y_scale), &legend);
+    draw_y_axis(&mut scene, options, &area, &y_scale, "log10 length in bp");
+    let radius = (0.55
+        * (x_step / (x_scale.max - x_scale.min) * area.w)
+            .min(y_step / (y_scale.max - y_scale.min) * area.h))
+    .clamp(2.0, 20.0);
+    // log-scaled color as in the report
+    let color = |size: u64| {
+        gradient(
+            &BLUE_PURPLE,
+            (size.max(1) as f64).ln() / (max_size as f64).ln().max(f64::EPSILON),
+        )
+    };
+    for bin in bins {
+        let (cx, cy) = (area.px(x_scale.frac(bin.x)), area.py(y_scale.frac(bin.y)));
+        let points = (0..6)
+            .map(|k| {
+                let angle = std::f64::consts::PI / 3.0 * k as f64 + std::f64::consts::PI / 6.0;
+                (cx + radius * angle.cos(), cy + radius * angle.sin())
+            })
+            .collect();
+        scene.shapes.push(Shape::Polygon {
+            points,
+            fill: color(bin.size),
+            opacity: 1.0,
+        });
+    }
+    draw_x_axis(&mut scene, options, &area, &x_scale, "coverage");
+    draw_frame(&mut scene, &area);
+    draw_color_bar(
+        &mut scene,
+        options,
+        &area,
+        |t| gradient(&BLUE_PURPLE, t),
+        &legend[0],
+        &legend[1],
+    );
+    scene
+}
+
+/// One horizontal strip per sequence, with the windows colored by the
+/// variable with the given index (windows with value 0 are left out, as in
+/// the report)
+fn draw_chromosomal(
+    options: &PlotOptions,
+    title: &str,
+    variable: usize,
+    sequences: &[String],
+    values: &[Vec<Window>],
+) -> Scene {
+    let get_value = |w: &Window| w.values.get(variable).copied().unwrap_or(0.0);
+    let all = values.iter().flatten().map(get_value).filter(|v| *v != 0.0);
+    let min = all.clone().fold(f64::INFINITY, f64::min);
+    let max = all.fold(f64::NEG_INFINITY, f64::max);
+    let (min, max) = if min.is_finite() {
+        (min, max)
+    } else {
+        (0.0, 1.0)
+    };
+    let legend = [format_tick(min), format_tick(max)];
+    let seq_ends: Vec<f64> = values
+        .iter()
+        .map(|windows| windows.iter().map(|w| w.end as f64).fold(0.0, f64::max))
+        .collect();
+    let max_end = seq_ends.iter().copied().fold(1.0, f64::max);
+    let (mut scene, area) = new_scene(options, title, &[], &legend);
+    let font = options.font_size;
+    let row = area.h / sequences.len().max(1) as f64;
+    let strip = (row - 3.5 * font).clamp(4.0, 4.0 * font);
+    for (i, (name, windows)) in sequences.iter().zip(values).enumerate() {
+        let y = area.y + i as f64 * row + 1.2 * font;
+        let scale = Scale {
+            min: 0.0,
+            max: seq_ends[i].max(1.0),
+            log: false,
+        };
+        let strip_area = Area {
+            x: area.x,
+            y,
+            w: area.w * scale.max / max_end,
+            h: strip,
+        };
+        scene.text(area.x, y - 0.3 * font, name, font, Anchor::Start);
+        for w in windows {
+            let v = get_value(w);
+            if v == 0.0 {
+                continue;
+            }
+            let x0 = strip_area.px(scale.frac(w.start as f64));
+            let x1 = strip_area.px(scale.frac(w.end as f64));
+            scene.rect(
+                x0,
+                y,
+                (x1 - x0).max(0.5),
+                strip,
+                gradient(&PLASMA, (v - min) / (max - min).max(f64::EPSILON)),
+            );
+        }
+        draw_frame(&mut scene, &strip_area);
+        for tick in scale.ticks() {
+            let x = strip_area.px(scale.frac(tick));
+            scene.line(vec![(x, y + strip), (x, y + strip + 4.0)], Color::GRAY, 1.0);
+            scene.text(
+                x,
+                y + strip + 5.0 + font,
+                &format_tick(tick),
+                font,
+                Anchor::Middle,
+            );
+        }
+    }
+    draw_x_label(&mut scene, options, &area, "Position in bps");
+    draw_color_bar(
+        &mut scene,
+        options,
+        &area,
+        |t| gradient(&PLASMA, t),
+        &legend[0],
+        &legend[1],
+    );
+    scene
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scale_has_nice_bounds_and_ticks() {
+        let scale = Scale::from_values(vec![3.0, 47.0], false, true);
+        assert_eq!((scale.min, scale.max), (0.0, 50.0));
+        assert_eq!(scale.ticks(), vec![0.0, 10.0, 20.0, 30.0, 40.0, 50.0]);
+        assert_eq!(scale.frac(25.0), 0.5);
+
+        let scale = Scale::from_values(vec![0.0, 3.0, 4500.0], true, true);
+        assert_eq!((scale.min, scale.max), (1.0, 10000.0));
+        assert_eq!(scale.ticks(), vec![1.0, 10.0, 100.0, 1000.0, 10000.0]);
+        assert_eq!(scale.frac(100.0), 0.5);
+    }
+
+    #[test]
+    fn test_format_tick() {
+        assert_eq!(format_tick(0.0), "0");
+        assert_eq!(format_tick(2.5), "2.5");
+        assert_eq!(format_tick(1500.0), "1500");
+        assert_eq!(format_tick(25000.0), "25k");
+        assert_eq!(format_tick(1_500_000.0), "1.5M");
+    }
+
+    #[test]
+    fn test_draw_bar_has_a_rect_per_value() {
+        let item = ReportItem::Bar {
+            id: "hist".to_string(),
+            name: "hist".to_string(),
+            x_label: "taxa".to_string(),
+            y_label: "nodes".to_string(),
+            labels: vec!["1".to_string(), "2".to_string(), "3".to_string()],
+            values: vec![5.0, 3.0, -1.0],
+            log_toggle: true,
+        };
+        let options = PlotOptions::default();
+        let plots = draw_item(&item, "hist", &options);
+        assert_eq!(plots.len(), 1);
+        let (suffix, scene) = &plots[0];
+        assert_eq!(suffix, &None);
+        let bars: Vec<&Shape> = scene
+            .shapes
+            .iter()
+            .filter(
+                |s| matches!(s, Shape::Rect { fill, .. } if *fill == Color::from_hex(BAR_COLOR)),
+            )
+            .collect();
+        assert_eq!(bars.len(), 3);
+
+        // the negative bar is left out on a log scale
+        let options = PlotOptions {
+            log_y: true,
+            ..PlotOptions::default()
+        };
+        let (_, scene) = &draw_item(&item, "hist", &options)[0];
+        let bars = scene
+            .shapes
+            .iter()
+            .filter(
+                |s| matches!(s, Shape::Rect { fill, .. } if *fill == Color::from_hex(BAR_COLOR)),
+            )
+            .count();
+        assert_eq!(bars, 2);
+    }
+
+    #[test]
+    fn test_draw_hexbin_skips_bins_without_center() {
+        let bin = |x: f64, y: f64| Bin {
+            size: 1,
+            x,
+            y,
+            content: Vec::new(),
+        };
+        let item = ReportItem::Hexbin {
+            id: "hexbin".to_string(),
+            bins: vec![bin(1.0, 2.0), bin(f64::NAN, 3.0), bin(2.0, 2.5)],
+            threshold: 0,
+        };
+        let (_, scene) = &draw_item(&item, "hexbin", &PlotOptions::default())[0];
+        let hexagons = scene
+            .shapes
+            .iter()
+            .filter(|s| matches!(s, Shape::Polygon { .. }))
+            .count();
+        assert_eq!(hexagons, 2);
+    }
+
+    #[test]
+    fn test_chromosomal_gives_a_plot_per_variable() {
+        let item = ReportItem::Chromosomal {
+            id: "chrom".to_string(),
+            name: "chrom".to_string(),
+            labels: vec!["Growth".to_string(), "Coverage".to_string()],
+            is_diverging: false,
+            contains_outliers: false,
+            sequence: vec!["chr1".to_string()],
+            values: vec![vec![Window {
+                start: 0,
+                end: 100,
+                values: vec![1.0, 2.0],
+            }]],
+        };
+        let suffixes: Vec<Option<String>> = draw_item(&item, "chrom", &PlotOptions::default())
+            .into_iter()
+            .map(|(s, _)| s)
+            .collect();
+        assert_eq!(
+            suffixes,
+            vec![Some("Growth".to_string()), Some("Coverage".to_string())]
+        );
+    }
+}
@@ -0,0 +1,228 @@
+//! Writes a `Scene` as a single-page PDF, using the standard Helvetica fonts
+//! (which need not be embedded)
+use std::fmt::Write;
+
+use super::scene::{fmt_num, text_width, Anchor, Color, Scene, Shape};
+
+pub fn to_pdf(scene: &Scene) -> Vec<u8> {
+    let mut opacities: Vec<String> = Vec::new();
+    let mut content = String::new();
+    let h = scene.height;
+    for shape in &scene.shapes {
+        content.push_str("q\n");
+        // Writing to a string does not fail
+        let _ = match shape {
+            Shape::Rect {
+                x,
+                y,
+                width,
+                height,
+                fill,
+                opacity,
+            } => {
+                set_opacity(&mut content, &mut opacities, *opacity);
+                writeln!(
+                    content,
+                    "{} {} {} {} {} re f",
+                    fill_color(*fill),
+                    fmt_num(*x),
+                    fmt_num(h - y - height),
+                    fmt_num(*width),
+                    fmt_num(*height)
+                )
+            }
+            Shape::Frame {
+                x,
+                y,
+                width,
+                height,
+                stroke,
+            } => writeln!(
+                content,
+                "{} 1 w {} {} {} {} re S",
+                stroke_color(*stroke),
+                fmt_num(*x),
+                fmt_num(h - y - height),
+                fmt_num(*width),
+                fmt_num(*height)
+            ),
+            Shape::Line {
+                points,
+                stroke,
+                width,
+                dashed,
+            } => writeln!(
+                content,
+                "{} {} w {}{} S",
+                stroke_color(*stroke),
+                fmt_num(*width),
+                if *dashed { "[4 3] 0 d " } else { "" },
+                path(points, h)
+            ),
+            Shape::Polygon {
+                points,
+                fill,
+                opacity,
+            } => {
+                set_opacity(&mut content, &mut opacities, *opacity);
+                writeln!(content, "{} {} h f", fill_color(*fill), path(points, h))
+            }
+            Shape::Text {
+                x,
+                y,
+                text,
+                size,
+                anchor,
+                vertical,
+                bold,
+            } => {
+                let offset = match anchor {
+                    Anchor::Start => 0.0,
+                    Anchor::Middle => text_width(text, *size) / 2.0,
+                    Anchor::End => text_width(text, *size),
+                };
+                let matrix = if *vertical {
+                    format!("0 1 -1 0 {} {}", fmt_num(*x), fmt_num(h - y - offset))
+                } else {
+                    format!("1 0 0 1 {} {}", fmt_num(x - offset), fmt_num(h - y))
+                };
+                writeln!(
+                    content,
+                    "BT {} /F{} {} Tf {} Tm ({}) Tj ET",
+                    fill_color(Color::BLACK),
+                    if *bold { 2 } else { 1 },
+                    fmt_num(*size),
+                    matrix,
+                    escape_text(text)
+                )
+            }
+        };
+        content.push_str("Q\n");
+    }
+
+    let ext_g_states = opacities
+        .iter()
+        .enumerate()
+        .map(|(i, opacity)| format!("/GS{} << /Type /ExtGState /ca {} >>", i, opacity))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let content = encode_latin1(&content);
+    let mut objects: Vec<Vec<u8>> = vec![
+        b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+        b"<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_vec(),
+        format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Contents 4 0 R /Resources << /Font << /F1 5 0 R /F2 6 0 R >> /ExtGState << {} >> >> >>",
+            fmt_num(scene.width),
+            fmt_num(scene.height),
+            ext_g_states
+        )
+        .into_bytes(),
+    ];
+    let mut stream = format!("<< /Length {} >>\nstream\n", content.len()).into_bytes();
+    stream.extend_from_slice(&content);
+    stream.extend_from_slice(b"\nendstream");
+    objects.push(stream);
+    for font in ["Helvetica", "Helvetica-Bold"] {
+        objects.push(
+            format!(
+                "<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding /WinAnsiEncoding >>",
+                font
+            )
+            .into_bytes(),
+        );
+    }
+
+    let mut pdf = b"%PDF-1.4\n".to_vec();
+    let mut offsets = Vec::new();
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(format!("{} 0 obj\n", i + 1).as_bytes());
+        pdf.extend_from_slice(object);
+        pdf.extend_from_slice(b"\nendobj\n");
+    }
+    let xref = pdf.len();
+    pdf.extend_from_slice(
+        format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes(),
+    );
+    for offset in offsets {
+        pdf.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+    }
+    pdf.extend_from_slice(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref
+        )
+        .as_bytes(),
+    );
+    pdf
+}
+
+fn set_opacity(content: &mut String, opacities: &mut Vec<String>, opacity: f64) {
+    if opacity >= 1.0 {
+        return;
+    }
+    let opacity = fmt_num(opacity);
+    let idx = match opacities.iter().position(|o| *o == opacity) {
+        Some(idx) => idx,
+        None => {
+            opacities.push(opacity);
+            opacities.len() - 1
+        }
+    };
+    content.push_str(&format!("/GS{} gs\n", idx));
+}
+
+fn fill_color(c: Color) -> String {
+    format!("{} rg", rgb(c))
+}
+
+fn stroke_color(c: Color) -> String {
+    format!("{} RG", rgb(c))
+}
+
+fn rgb(c: Color) -> String {
+    format!(
+        "{} {} {}",
+        fmt_num(c.0 as f64 / 255.0),
+        fmt_num(c.1 as f64 / 255.0),
+        fmt_num(c.2 as f64 / 255.0)
+    )
+}
+
+fn path(points: &[(f64, f64)], h: f64) -> String {
+    points
+        .iter()
+        .enumerate()
+        .map(|(i, (x, y))| {
+            format!(
+                "{} {} {}",
+                fmt_num(*x),
+                fmt_num(h - y),
+                if i == 0 { "m" } else { "l" }
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('(', "\\(")
+        .replace(')', "\\)")
+}
+
+/// The standard fonts only cover (roughly) Latin-1, other characters are
+/// replaced
+fn encode_latin1(text: &str) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '≥' => bytes.extend_from_slice(b">="),
+            '≤' => bytes.extend_from_slice(b"<="),
+            c if (c as u32) < 256 => bytes.push(c as u8),
+            _ => bytes.push(b'?'),
+        }
+    }
+    bytes
+}
@@ -0,0 +1,297 @@
+//! A plot as a list of simple shapes, which can be written as SVG or PDF.
+//! Coordinates are in pixels (SVG user units, PDF points) from the top left.
+use std::fmt::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Color(pub u8, pub u8, pub u8);
+
+impl Color {
+    pub const BLACK: Self = Self(0, 0, 0);
+    pub const WHITE: Self = Self(255, 255, 255);
+    pub const GRAY: Self = Self(128, 128, 128);
+    pub const LIGHT_GRAY: Self = Self(221, 221, 221);
+
+    /// Parses colors like `#4c78a8`
+    pub fn from_hex(hex: &str) -> Self {
+        let hex = hex.trim_start_matches('#');
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap_or(0);
+        Self(channel(0), channel(2), channel(4))
+    }
+
+    /// Linear interpolation between two colors, `t` in `[0, 1]`
+    pub fn mix(self, other: Self, t: f64) -> Self {
+        let t = t.clamp(0.0, 1.0);
+        let channel = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * t).round() as u8;
+        Self(
+            channel(self.0, other.0),
+            channel(self.1, other.1),
+            channel(self.2, other.2),
+        )
+    }
+
+    pub fn to_hex(self) -> String {
+        format!("#{:02x}{:02x}{:02x}", self.0, self.1, self.2)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Anchor {
+    Start,
+    Middle,
+    End,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Shape {
+    Rect {
+        x: f64,
+        y: f64,
+        width: f64,
+        height: f64,
+        fill: Color,
+        opacity: f64,
+    },
+    /// Outline of a rectangle
+    Frame {
+        x: f64,
+        y: f64,
+        width: f64,
+        height: f64,
+        stroke: Color,
+    },
+    Line {
+        points: Vec<(f64, f64)>,
+        stroke: Color,
+        width: f64,
+        dashed: bool,
+    },
+    Polygon {
+        points: Vec<(f64, f64)>,
+        fill: Color,
+        opacity: f64,
+    },
+    Text {
+        x: f64,
+        y: f64,
+        text: String,
+        size: f64,
+        anchor: Anchor,
+        /// Rotated by -90 degrees (bottom to top)
+        vertical: bool,
+        bold: bool,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scene {
+    pub width: f64,
+    pub height: f64,
+    pub font_family: String,
+    pub shapes: Vec<Shape>,
+}
+
+impl Scene {
+    pub fn new(width: f64, height: f64, font_family: &str) -> Self {
+        Self {
+            width,
+            height,
+            font_family: font_family.to_string(),
+            shapes: Vec::new(),
+        }
+    }
+
+    /// Adds a filled rectangle, empty rectangles are left out
+    pub fn rect(&mut self, x: f64, y: f64, width: f64, height: f64, fill: Color) {
+        if width <= 0.0 || height <= 0.0 {
+            return;
+        }
+        self.shapes.push(Shape::Rect {
+            x,
+            y,
+            width,
+            height,
+            fill,
+            opacity: 1.0,
+        });
+    }
+
+    pub fn line(&mut self, points: Vec<(f64, f64)>, stroke: Color, width: f64) {
+        self.shapes.push(Shape::Line {
+            points,
+            stroke,
+            width,
+            dashed: false,
+        });
+    }
+
+    pub fn text(&mut self, x: f64, y: f64, text: &str, size: f64, anchor: Anchor) {
+        self.shapes.push(Shape::Text {
+            x,
+            y,
+            text: text.to_string(),
+            size,
+            anchor,
+            vertical: false,
+            bold: false,
+        });
+    }
+
+    pub fn to_svg(&self) -> String {
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\" font-family=\"{}\">\n",
+            escape_xml(&self.font_family),
+            w = fmt_num(self.width),
+            h = fmt_num(self.height),
+        );
+        svg.push_str("<rect width=\"100%\" height=\"100%\" fill=\"#ffffff\"/>\n");
+        for shape in &self.shapes {
+            // Writing to a string does not fail
+            let _ = match shape {
+                Shape::Rect {
+                    x,
+                    y,
+                    width,
+                    height,
+                    fill,
+                    opacity,
+                } => writeln!(
+                    svg,
+                    "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"{}/>",
+                    fmt_num(*x),
+                    fmt_num(*y),
+                    fmt_num(*width),
+                    fmt_num(*height),
+                    fill.to_hex(),
+                    fmt_opacity(*opacity)
+                ),
+                Shape::Frame {
+                    x,
+                    y,
+                    width,
+                    height,
+                    stroke,
+                } => writeln!(
+                    svg,
+                    "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"none\" stroke=\"{}\"/>",
+                    fmt_num(*x),
+                    fmt_num(*y),
+                    fmt_num(*width),
+                    fmt_num(*height),
+                    stroke.to_hex()
+                ),
+                Shape::Line {
+                    points,
+                    stroke,
+                    width,
+                    dashed,
+                } => writeln!(
+                    svg,
+                    "<polyline points=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"{}\"{}/>",
+                    fmt_points(points),
+                    stroke.to_hex(),
+                    fmt_num(*width),
+                    if *dashed {
+                        " stroke-dasharray=\"4 3\""
+                    } else {
+                        ""
+                    }
+                ),
+                Shape::Polygon {
+                    points,
+                    fill,
+                    opacity,
+                } => writeln!(
+                    svg,
+                    "<polygon points=\"{}\" fill=\"{}\"{}/>",
+                    fmt_points(points),
+                    fill.to_hex(),
+                    fmt_opacity(*opacity)
+                ),
+                Shape::Text {
+                    x,
+                    y,
+                    text,
+                    size,
+                    anchor,
+                    vertical,
+                    bold,
+                } => writeln!(
+                    svg,
+                    "<text x=\"{x}\" y=\"{y}\" font-size=\"{}\" text-anchor=\"{}\"{}{}>{}</text>",
+                    fmt_num(*size),
+                    match anchor {
+                        Anchor::Start => "start",
+                        Anchor::Middle => "middle",
+                        Anchor::End => "end",
+                    },
+                    if *bold { " font-weight=\"bold\"" } else { "" },
+                    if *vertical {
+                        format!(" transform=\"rotate(-90 {x} {y})\"")
+                    } else {
+                        String::new()
+                    },
+                    escape_xml(text),
+                    x = fmt_num(*x),
+                    y = fmt_num(*y),
+                ),
+            };
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+/// Widths of the ASCII characters 32 to 126 in Helvetica (in 1/1000 of the
+/// font size), used to lay out text independently of the output format
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556,
+    556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556, 1015, 667, 667, 722, 722, 667,
+    611, 778, 722, 278, 500, 667, 556, 833, 722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667,
+    667, 611, 278, 278, 278, 469, 556, 333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500,
+    222, 833, 556, 556, 556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+
+/// Estimated width of a text in pixels
+pub fn text_width(text: &str, size: f64) -> f64 {
+    text.chars()
+        .map(|c| match c as u32 {
+            32..=126 => HELVETICA_WIDTHS[c as usize - 32] as f64,
+            _ => 556.0,
+        })
+        .sum::<f64>()
+        * size
+        / 1000.0
+}
+
+pub fn fmt_num(x: f64) -> String {
+    let s = format!("{:.2}", x);
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    if s == "-0" {
+        "0".to_string()
+    } else {
+        s.to_string()
+    }
+}
+
+fn fmt_points(points: &[(f64, f64)]) -> String {
+    points
+        .iter()
+        .map(|(x, y)| format!("{},{}", fmt_num(*x), fmt_num(*y)))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn fmt_opacity(opacity: f64) -> String {
+    if opacity < 1.0 {
+        format!(" fill-opacity=\"{}\"", fmt_num(opacity))
+    } else {
+        String::new()
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
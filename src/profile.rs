@@ -0,0 +1,215 @@
+//! Wall time and peak memory of the stages of a run (`--profile FILE`).
+//!
+//! Stages are recorded on the thread that enables the profile (the main
+//! thread of the command line interface) and may be nested, e.g. the node
+//! parsing is part of the graph parsing. The peak memory is the peak resident
+//! set size of the process, which is reset at the start of each stage where
+//! the kernel supports it (Linux `/proc/self/clear_refs`); otherwise it is the
+//! peak of the process up to the end of the stage.
+use std::cell::RefCell;
+use std::time::Instant;
+
+use anyhow::Context;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StageProfile {
+    pub name: String,
+    pub wall_time_secs: f64,
+    /// Peak resident set size in bytes, if it is known
+    pub peak_memory_bytes: Option<u64>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub stages: Vec<StageProfile>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Profile {
+    pub command: String,
+    pub threads: usize,
+    pub wall_time_secs: f64,
+    pub peak_memory_bytes: Option<u64>,
+    pub stages: Vec<StageProfile>,
+}
+
+struct OpenStage {
+    name: String,
+    start: Instant,
+    peak_memory: Option<u64>,
+    stages: Vec<StageProfile>,
+}
+
+impl OpenStage {
+    fn new(name: String) -> Self {
+        Self {
+            name,
+            start: Instant::now(),
+            peak_memory: None,
+            stages: Vec::new(),
+        }
+    }
+
+    fn update_peak_memory(&mut self, peak_memory: Option<u64>) {
+        self.peak_memory = self.peak_memory.max(peak_memory);
+    }
+}
+
+struct Profiler {
+    /// The whole run, followed by the currently open (nested) stages
+    open: Vec<OpenStage>,
+    memory: Box<dyn Fn(bool) -> Option<u64>>,
+}
+
+impl Profiler {
+    /// `memory(reset)` returns the current peak memory, and resets it if
+    /// `reset` is set
+    fn new(memory: Box<dyn Fn(bool) -> Option<u64>>) -> Self {
+        Self {
+            open: vec![OpenStage::new(String::new())],
+            memory,
+        }
+    }
+
+    fn start(&mut self, name: String) {
+        let peak_memory = (self.memory)(true);
+        if let Some(parent) = self.open.last_mut() {
+            parent.update_peak_memory(peak_memory);
+        }
+        self.open.push(OpenStage::new(name));
+    }
+
+    fn end(&mut self) {
+        if self.open.len() < 2 {
+            return;
+        }
+        let mut stage = self.open.pop().expect("stage is open");
+        stage.update_peak_memory((self.memory)(false));
+        let parent = self.open.last_mut().expect("run is open");
+        parent.update_peak_memory(stage.peak_memory);
+        parent.stages.push(StageProfile {
+            name: stage.name,
+            wall_time_secs: stage.start.elapsed().as_secs_f64(),
+            peak_memory_bytes: stage.peak_memory,
+            stages: stage.stages,
+        });
+    }
+
+    fn finish(mut self) -> Profile {
+        while self.open.len() > 1 {
+            self.end();
+        }
+        let mut run = self.open.pop().expect("run is open");
+        run.update_peak_memory((self.memory)(false));
+        Profile {
+            command: std::env::args().collect::<Vec<String>>().join(" "),
+            threads: rayon::current_num_threads(),
+            wall_time_secs: run.start.elapsed().as_secs_f64(),
+            peak_memory_bytes: run.peak_memory,
+            stages: run.stages,
+        }
+    }
+}
+
+thread_local! {
+    static PROFILER: RefCell<Option<Profiler>> = const { RefCell::new(None) };
+}
+
+/// Starts recording the stages of the current thread
+pub fn enable() {
+    PROFILER.with(|p| *p.borrow_mut() = Some(Profiler::new(Box::new(get_peak_memory))));
+}
+
+/// Runs `f` as a stage of the profile (if the profile is enabled)
+pub fn stage<T, F: FnOnce() -> T>(name: &str, f: F) -> T {
+    let is_enabled = PROFILER.with(|p| match p.borrow_mut().as_mut() {
+        Some(profiler) => {
+            profiler.start(name.to_string());
+            true
+        }
+        None => false,
+    });
+    let res = f();
+    if is_enabled {
+        PROFILER.with(|p| {
+            if let Some(profiler) = p.borrow_mut().as_mut() {
+                profiler.end();
+            }
+        });
+    }
+    res
+}
+
+/// Stops recording and writes the profile as JSON, if it was enabled
+pub fn write(filename: &str) -> anyhow::Result<()> {
+    let Some(profiler) = PROFILER.with(|p| p.borrow_mut().take()) else {
+        return Ok(());
+    };
+    let profile = profiler.finish();
+    let file = std::fs::File::create(filename)
+        .with_context(|| format!("could not write profile to {filename}"))?;
+    serde_json::to_writer_pretty(file, &profile)?;
+    log::info!("wrote profile to {}", filename);
+    Ok(())
+}
+
+/// Peak resident set size of the process (`VmHWM`), optionally resetting it
+/// afterwards. Only available on Linux.
+fn get_peak_memory(reset: bool) -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let peak = status
+        .lines()
+        .find_map(|l| l.strip_prefix("VmHWM:"))
+        .and_then(|l| l.trim().trim_end_matches("kB").trim().parse::<u64>().ok())
+        .map(|kb| kb * 1024);
+    if reset {
+        // Fails silently if not supported, the peak then includes the
+        // previous stages
+        let _ = std::fs::write("/proc/self/clear_refs", "5");
+    }
+    peak
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_profiler_nests_stages_and_propagates_peaks() {
+        // peak memory increases with every call, reset sets it back to 0
+        let memory = Rc::new(RefCell::new(0));
+        let m = Rc::clone(&memory);
+        let mut profiler = Profiler::new(Box::new(move |reset| {
+            let mut m = m.borrow_mut();
+            *m += 10;
+            let peak = *m;
+            if reset {
+                *m = 0;
+            }
+            Some(peak)
+        }));
+        profiler.start("graph parsing".to_string());
+        profiler.start("node parsing".to_string());
+        profiler.end();
+        profiler.start("edge parsing".to_string());
+        *memory.borrow_mut() += 100;
+        profiler.end();
+        profiler.end();
+        profiler.start("analysis".to_string());
+        let profile = profiler.finish();
+
+        let names: Vec<_> = profile.stages.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["graph parsing", "analysis"]);
+        let graph = &profile.stages[0];
+        assert_eq!(graph.stages.len(), 2);
+        assert_eq!(graph.stages[0].peak_memory_bytes, Some(10));
+        assert_eq!(graph.stages[1].peak_memory_bytes, Some(110));
+        assert_eq!(graph.peak_memory_bytes, Some(120));
+        assert_eq!(profile.peak_memory_bytes, Some(130));
+        assert!(profile.stages[1].stages.is_empty());
+    }
+
+    #[test]
+    fn test_stage_is_transparent_without_profile() {
+        assert_eq!(stage("test", || 42), 42);
+    }
+}
@@ -0,0 +1,236 @@
+use memchr::memchr_iter;
+use std::io::Read;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Minimum time between two progress logs
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Periodically logs the progress of reading a (GFA) file: bytes read, lines
+/// parsed, paths/walks processed and the estimated remaining time. Bytes
+/// refer to the file on disk (i.e. compressed bytes for compressed files),
+/// lines and paths to the (decompressed) content.
+pub struct Progress {
+    name: String,
+    total_bytes: Option<u64>,
+    bytes: u64,
+    lines: u64,
+    paths: u64,
+    at_line_start: bool,
+    start: Instant,
+    last_report: Instant,
+    interval: Duration,
+}
+
+impl Progress {
+    pub fn new(name: &str, total_bytes: Option<u64>) -> Self {
+        let now = Instant::now();
+        Self {
+            name: name.to_owned(),
+            total_bytes: total_bytes.filter(|&total| total > 0),
+            bytes: 0,
+            lines: 0,
+            paths: 0,
+            at_line_start: true,
+            start: now,
+            last_report: now,
+            interval: PROGRESS_INTERVAL,
+        }
+    }
+
+    /// Creates a progress for reading the given file, its size is used to
+    /// estimate the remaining time
+    pub fn for_file(filename: &str) -> Self {
+        let total_bytes = std::fs::metadata(filename).map(|m| m.len()).ok();
+        Self::new(filename, total_bytes)
+    }
+
+    /// Counts the lines and paths/walks of the data that was read, and sets
+    /// the number of bytes read from the file so far
+    pub fn update(&mut self, bytes: u64, data: &[u8]) {
+        let is_path = |c: Option<&u8>| matches!(c, Some(b'P') | Some(b'W'));
+        if self.at_line_start && is_path(data.first()) {
+            self.paths += 1;
+        }
+        for i in memchr_iter(b'\n', data) {
+            self.lines += 1;
+            if is_path(data.get(i + 1)) {
+                self.paths += 1;
+            }
+        }
+        if let Some(&c) = data.last() {
+            self.at_line_start = c == b'\n';
+        }
+        self.bytes = bytes;
+        if self.last_report.elapsed() >= self.interval {
+            self.last_report = Instant::now();
+            log::info!("{}", self.get_message());
+        }
+    }
+
+    /// Logs the totals (at debug level)
+    pub fn finish(&self) {
+        log::debug!(
+            "read {} in {:.1}s: {} lines, {} paths/walks",
+            self.name,
+            self.start.elapsed().as_secs_f64(),
+            self.lines,
+            self.paths
+        );
+    }
+
+    fn get_message(&self) -> String {
+        let mut msg = format!("reading {}: {}", self.name, format_bytes(self.bytes));
+        if let Some(total) = self.total_bytes {
+            msg.push_str(&format!(
+                " of {} ({:.1}%)",
+                format_bytes(total),
+                100.0 * self.bytes as f64 / total as f64
+            ));
+        }
+        msg.push_str(&format!(
+            ", {} lines, {} paths/walks",
+            self.lines, self.paths
+        ));
+        if let Some(eta) = self.get_eta() {
+            msg.push_str(&format!(", ETA {}", format_duration(eta)));
+        }
+        msg
+    }
+
+    /// Estimates the remaining time from the speed so far
+    fn get_eta(&self) -> Option<Duration> {
+        let total = self.total_bytes?;
+        if self.bytes == 0 || self.bytes > total {
+            return None;
+        }
+        let elapsed = self.start.elapsed().as_secs_f64();
+        let remaining = elapsed * (total - self.bytes) as f64 / self.bytes as f64;
+        Some(Duration::from_secs_f64(remaining))
+    }
+}
+
+/// Counts the bytes read from the wrapped reader
+pub struct CountingReader<R> {
+    inner: R,
+    count: Arc<AtomicU64>,
+}
+
+impl<R> CountingReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            count: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    pub fn get_count(&self) -> Arc<AtomicU64> {
+        Arc::clone(&self.count)
+    }
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count.fetch_add(n as u64, Ordering::Relaxed);
+        Ok(n)
+    }
+}
+
+/// Reports the progress of reading the wrapped (decompressed) reader, where
+/// `bytes` counts the bytes read from the underlying file
+pub struct ProgressReader<R> {
+    inner: R,
+    bytes: Arc<AtomicU64>,
+    progress: Progress,
+    is_finished: bool,
+}
+
+impl<R> ProgressReader<R> {
+    pub fn new(inner: R, bytes: Arc<AtomicU64>, progress: Progress) -> Self {
+        Self {
+            inner,
+            bytes,
+            progress,
+            is_finished: false,
+        }
+    }
+}
+
+impl<R: Read> Read for ProgressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.progress
+            .update(self.bytes.load(Ordering::Relaxed), &buf[..n]);
+        if n == 0 && !buf.is_empty() && !self.is_finished {
+            self.is_finished = true;
+            self.progress.finish();
+        }
+        Ok(n)
+    }
+}
+
+pub fn format_bytes(bytes: u64) -> String {
+    let units = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < units.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, units[unit])
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_counts_lines_and_paths_across_reads() {
+        let mut progress = Progress::new("test", Some(100));
+        progress.update(10, b"S\t1\tA\nP\tx\t1");
+        progress.update(20, b"+\t*\nW\ty\t0\tz");
+        progress.update(30, b"\t0\t1\t>1\nPP\n");
+        assert_eq!(progress.lines, 4);
+        assert_eq!(progress.paths, 3);
+        assert_eq!(progress.bytes, 30);
+        assert!(progress
+            .get_message()
+            .starts_with("reading test: 30 B of 100 B (30.0%), 4 lines, 3 paths/walks"));
+    }
+
+    #[test]
+    fn test_progress_reader_counts_file_bytes() {
+        let data = b"S\t1\tA\nP\tx\t1+\t*\n".to_vec();
+        let counting = CountingReader::new(&data[..]);
+        let bytes = counting.get_count();
+        let mut reader = ProgressReader::new(
+            counting,
+            bytes,
+            Progress::new("test", Some(data.len() as u64)),
+        );
+        let mut content = Vec::new();
+        reader.read_to_end(&mut content).unwrap();
+        assert_eq!(content, data);
+        assert_eq!(reader.progress.bytes, data.len() as u64);
+        assert_eq!(reader.progress.lines, 2);
+        assert_eq!(reader.progress.paths, 1);
+    }
+
+    #[test]
+    fn test_format_bytes_and_duration() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(3 << 29), "1.5 GiB");
+        assert_eq!(format_duration(Duration::from_secs(3723)), "1:02:03");
+    }
+}
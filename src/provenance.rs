@@ -0,0 +1,215 @@
+//! Provenance of the results: the version of panacus, the command line, the
+//! input files (with size, modification time and, with `--checksum`, SHA-256
+//! checksum), the resolved runs of the config, the number of threads, and the
+//! date and runtime of the run.
+//!
+//! The provenance is recorded once per process, before any results are
+//! computed (see `record`), and written into every output: as comment lines
+//! of tables, as `provenance` of JSON outputs and into the HTML report. JSON
+//! reports merged by `render` keep their provenance.
+use std::fs::File;
+use std::path::Path;
+use std::time::{Instant, SystemTime};
+
+use anyhow::Context;
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use time::{macros::format_description, OffsetDateTime};
+
+use crate::analysis_parameter::FileRun;
+use crate::profile;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Provenance {
+    pub version: String,
+    /// Commit panacus was built from, empty if unknown
+    pub git_hash: String,
+    pub command: String,
+    /// Start of the run (UTC)
+    pub date: String,
+    /// Seconds from the start of the run until the output was written
+    pub runtime_secs: f64,
+    pub threads: usize,
+    pub inputs: Vec<InputFile>,
+    /// The runs as they were executed, after the config was resolved
+    pub runs: Vec<FileRun>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InputFile {
+    pub path: String,
+    pub size: u64,
+    /// Modification time (UTC), if the file system provides it
+    pub modified: Option<String>,
+    /// Only computed with `--checksum`, as it reads the whole file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
+
+struct Recorded {
+    start: Instant,
+    provenance: Provenance,
+}
+
+static RECORDED: OnceCell<Recorded> = OnceCell::new();
+
+/// Records the provenance of this process. The inputs are the given files
+/// followed by the input files of the runs; only the first call has an
+/// effect. The checksums of the inputs are only computed if `checksum` is set.
+pub fn record(runs: &[FileRun], files: &[String], checksum: bool) -> anyhow::Result<()> {
+    if RECORDED.get().is_some() {
+        return Ok(());
+    }
+    let start = Instant::now();
+    let mut paths: Vec<&str> = files.iter().map(|f| f.as_str()).collect();
+    for path in runs.iter().flat_map(|r| r.get_input_files()) {
+        if !paths.contains(&path) {
+            paths.push(path);
+        }
+    }
+    let paths = paths.into_iter().filter(|path| Path::new(path).is_file());
+    let inputs = if checksum {
+        profile::stage("checksums", || {
+            paths
+                .map(|path| get_input_file(path, true))
+                .collect::<anyhow::Result<Vec<_>>>()
+        })?
+    } else {
+        paths
+            .map(|path| get_input_file(path, false))
+            .collect::<anyhow::Result<Vec<_>>>()?
+    };
+    let provenance = Provenance {
+        inputs,
+        runs: runs.to_vec(),
+        ..Provenance::new(OffsetDateTime::now_utc())
+    };
+    let _ = RECORDED.set(Recorded { start, provenance });
+    Ok(())
+}
+
+/// The recorded provenance, with the runtime up to now. Without a recorded
+/// provenance, only the version, command, date and threads are known.
+pub fn get() -> Provenance {
+    match RECORDED.get() {
+        Some(recorded) => Provenance {
+            runtime_secs: recorded.start.elapsed().as_secs_f64(),
+            ..recorded.provenance.clone()
+        },
+        None => Provenance::new(OffsetDateTime::now_utc()),
+    }
+}
+
+impl Provenance {
+    fn new(date: OffsetDateTime) -> Self {
+        Self {
+            version: crate::build::PKG_VERSION.to_string(),
+            git_hash: crate::build::COMMIT_HASH.to_string(),
+            command: std::env::args().collect::<Vec<String>>().join(" "),
+            date: format_date(date),
+            runtime_secs: 0.0,
+            threads: rayon::current_num_threads(),
+            inputs: Vec::new(),
+            runs: Vec::new(),
+        }
+    }
+
+    /// Whether both are the provenance of the same run, written at different
+    /// times (e.g. the reports of the runs of one config)
+    pub fn is_same_run(&self, other: &Self) -> bool {
+        self.command == other.command && self.date == other.date
+    }
+
+    /// The provenance as comment lines of a table
+    pub fn to_comments(&self) -> String {
+        let mut res = format!("# {}\n", self.command);
+        res.push_str(&format!("# version {}", self.version));
+        if !self.git_hash.is_empty() {
+            res.push_str(&format!(" ({})", self.git_hash));
+        }
+        res.push('\n');
+        res.push_str(&format!("# date {}\n", self.date));
+        res.push_str(&format!("# runtime {:.3}s\n", self.runtime_secs));
+        res.push_str(&format!("# threads {}\n", self.threads));
+        for input in &self.inputs {
+            res.push_str(&format!(
+                "# input {}\tsize={}\tmodified={}",
+                input.path,
+                input.size,
+                input.modified.as_deref().unwrap_or("NA"),
+            ));
+            if let Some(sha256) = &input.sha256 {
+                res.push_str(&format!("\tsha256={}", sha256));
+            }
+            res.push('\n');
+        }
+        for run in &self.runs {
+            let run = serde_json::to_string(run).expect("runs can be serialized");
+            res.push_str(&format!("# run {}\n", run));
+        }
+        res
+    }
+}
+
+fn get_input_file(path: &str, checksum: bool) -> anyhow::Result<InputFile> {
+    let mut file = File::open(path).with_context(|| format!("cannot read input file {}", path))?;
+    let metadata = file.metadata()?;
+    let sha256 = if checksum {
+        let mut hasher = Sha256::new();
+        std::io::copy(&mut file, &mut hasher)
+            .with_context(|| format!("cannot compute checksum of {}", path))?;
+        Some(format!("{:x}", hasher.finalize()))
+    } else {
+        None
+    };
+    Ok(InputFile {
+        path: path.to_string(),
+        size: metadata.len(),
+        modified: metadata
+            .modified()
+            .ok()
+            .map(|t: SystemTime| format_date(t.into())),
+        sha256,
+    })
+}
+
+fn format_date(date: OffsetDateTime) -> String {
+    date.format(&format_description!(
+        "[year]-[month]-[day]T[hour]:[minute]:[second]Z"
+    ))
+    .expect("date can be formatted")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_input_file_checksum() {
+        let file = get_input_file("tests/test_files/t_groups.hist.tsv", true).unwrap();
+        let content = std::fs::read("tests/test_files/t_groups.hist.tsv").unwrap();
+        assert_eq!(file.size, content.len() as u64);
+        assert_eq!(file.sha256, Some(format!("{:x}", Sha256::digest(&content))));
+        assert!(file.modified.is_some());
+        let file = get_input_file("tests/test_files/t_groups.hist.tsv", false).unwrap();
+        assert_eq!(file.sha256, None);
+    }
+
+    #[test]
+    fn test_comments() {
+        let provenance = Provenance {
+            inputs: vec![InputFile {
+                path: "a.gfa".to_string(),
+                size: 3,
+                modified: None,
+                sha256: Some("abc".to_string()),
+            }],
+            ..Provenance::new(OffsetDateTime::UNIX_EPOCH)
+        };
+        let comments = provenance.to_comments();
+        assert!(comments.contains("# date 1970-01-01T00:00:00Z\n"));
+        assert!(comments.contains("# input a.gfa\tsize=3\tmodified=NA\tsha256=abc\n"));
+        assert!(comments.lines().all(|l| l.starts_with("# ")));
+    }
+}
@@ -3,6 +3,7 @@ use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::fmt;
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 /* external use */
 use strum_macros::{EnumIter, EnumString, EnumVariantNames};
@@ -39,12 +40,15 @@ unsafe impl Sync for Wrap<Vec<Vec<u64>>> {}
     Ord,
     Serialize,
     Deserialize,
+    JsonSchema,
 )]
 #[strum(serialize_all = "lowercase")]
 pub enum CountType {
     Node,
     Bp,
     Edge,
+    Bubble,
+    Allele,
 }
 
 impl Default for CountType {
@@ -62,6 +66,8 @@ impl fmt::Display for CountType {
                 CountType::Node => "node",
                 CountType::Edge => "edge",
                 CountType::Bp => "bp",
+                CountType::Bubble => "bubble",
+                CountType::Allele => "allele",
             }
         )
     }
@@ -530,6 +536,31 @@ pub fn to_id(s: &str) -> String {
         .replace(&[' ', '|', '/', '\\', '\'', '"'], "-")
 }
 
+/// Parses a memory size like `512M`, `16G` or a number of bytes
+pub fn parse_memory_size(s: &str) -> Result<usize, String> {
+    let s = s.trim();
+    let (number, factor) = match s.char_indices().last() {
+        Some((i, unit)) if unit.is_ascii_alphabetic() => {
+            let factor: usize = match unit.to_ascii_uppercase() {
+                'K' => 1 << 10,
+                'M' => 1 << 20,
+                'G' => 1 << 30,
+                'T' => 1 << 40,
+                _ => return Err(format!("unknown unit {unit} in memory size {s}")),
+            };
+            (&s[..i], factor)
+        }
+        _ => (s, 1),
+    };
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("{s} is not a memory size (e.g. 512M or 16G)"))?;
+    if number < 0.0 {
+        return Err(format!("memory size {s} is negative"));
+    }
+    Ok((number * factor as f64) as usize)
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -558,4 +589,13 @@ mod tests {
         ic.add(ItemId(0), 14, 17);
         assert_eq!(ic.map.get(&ItemId(0)), Some(&vec![(0, 12), (13, 20)]));
     }
+
+    #[test]
+    fn test_parse_memory_size() {
+        assert_eq!(parse_memory_size("1000"), Ok(1000));
+        assert_eq!(parse_memory_size("512M"), Ok(512 << 20));
+        assert_eq!(parse_memory_size("1.5g"), Ok(3 << 29));
+        assert!(parse_memory_size("16X").is_err());
+        assert!(parse_memory_size("G").is_err());
+    }
 }
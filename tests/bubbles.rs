@@ -0,0 +1,52 @@
+use assert_cmd::prelude::*; // Add methods on commands
+use predicates::prelude::*; // Used for writing assertions
+use std::process::Command; // Run programs
+
+#[ignore]
+#[test]
+fn bubble_hist_gets_written_successfully() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("panacus")?;
+
+    cmd.arg("hist")
+        .arg("-c")
+        .arg("bubble")
+        .arg("tests/test_files/t_insertions.gfa");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("count\tbubble"))
+        .stdout(predicate::str::contains("4\t2"));
+    Ok(())
+}
+
+#[ignore]
+#[test]
+fn bubble_hist_rejects_excluded_coordinates() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("panacus")?;
+
+    cmd.arg("hist")
+        .arg("-c")
+        .arg("bubble")
+        .arg("-e")
+        .arg("tests/test_files/t_insertions.bed")
+        .arg("tests/test_files/t_insertions.gfa");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("do not support excluded paths"));
+    Ok(())
+}
+
+#[ignore]
+#[test]
+fn allele_table_gets_written_successfully() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("panacus")?;
+
+    cmd.arg("table")
+        .arg("-c")
+        .arg("allele")
+        .arg("tests/test_files/t_insertions.gfa");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(">1>3:>2>6"))
+        .stdout(predicate::str::contains(">3>5:-"));
+    Ok(())
+}
@@ -0,0 +1,45 @@
+use assert_cmd::prelude::*; // Add methods on commands
+use predicates::prelude::*; // Used for writing assertions
+use std::process::Command; // Run programs
+
+#[ignore]
+#[test]
+fn config_schema_is_valid_json() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("panacus")?;
+
+    cmd.arg("config").arg("schema");
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let schema: serde_json::Value = serde_json::from_slice(&output)?;
+    // Either the list of runs, or the runs with a `report:` block
+    assert_eq!(schema["anyOf"][0]["type"], "array");
+    assert!(schema["definitions"]["ReportFile"]["properties"]["runs"].is_object());
+    assert!(schema["definitions"]["AnalysisParameter"]["anyOf"].is_array());
+    Ok(())
+}
+
+#[ignore]
+#[test]
+fn config_init_creates_valid_config() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("panacus")?;
+
+    cmd.arg("config")
+        .arg("init")
+        .arg("tests/test_files/t_repeats.gfa");
+    let output = cmd
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("grouping: Haplotype"))
+        .stdout(predicate::str::contains("!Similarity"))
+        .get_output()
+        .stdout
+        .clone();
+    let config = tempfile::Builder::new().suffix(".yaml").tempfile()?;
+    std::fs::write(config.path(), output)?;
+
+    let mut cmd = Command::cargo_bin("panacus")?;
+    cmd.arg("report").arg("--validate").arg(config.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("config is valid (1 runs)"));
+    Ok(())
+}
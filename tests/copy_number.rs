@@ -0,0 +1,53 @@
+use assert_cmd::prelude::*; // Add methods on commands
+use predicates::prelude::*; // Used for writing assertions
+use std::process::Command; // Run programs
+
+#[ignore]
+#[test]
+fn copy_number_table_gets_written_successfully() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("panacus")?;
+
+    cmd.arg("copy-number").arg("tests/test_files/t_repeats.gfa");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("hist\t2\t2\t0\t0"))
+        .stdout(predicate::str::contains("hist\t3\t0\t0\t2"))
+        .stdout(predicate::str::contains(
+            "repeats\ta#1#c\t4\t2\t11\t6\t0.5455\t2",
+        ))
+        .stdout(predicate::str::contains(
+            "repeats\tb#1#c\t4\t0\t11\t0\t0.0000\t1",
+        ))
+        .stdout(predicate::str::contains("variable\t2\t2\t1\t3\t3\t2"))
+        .stdout(predicate::str::contains("variable\t1\t").not());
+    Ok(())
+}
+
+#[ignore]
+#[test]
+fn copy_number_hist_is_capped() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("panacus")?;
+
+    cmd.arg("copy-number")
+        .arg("tests/test_files/t_repeats.gfa")
+        .arg("-m")
+        .arg("2");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("hist\t≥2\t2\t0\t2"));
+    Ok(())
+}
+
+#[ignore]
+#[test]
+fn table_reports_multiplicity() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("panacus")?;
+
+    cmd.arg("table")
+        .arg("tests/test_files/t_repeats.gfa")
+        .arg("--multiplicity");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("2\t2\t1\t3\n"));
+    Ok(())
+}
@@ -0,0 +1,51 @@
+use assert_cmd::prelude::*; // Add methods on commands
+use predicates::prelude::*; // Used for writing assertions
+use std::process::Command; // Run programs
+
+#[ignore]
+#[test]
+fn coverage_colors_writes_colored_graph() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let output = dir.path().join("colored.gfa");
+    let mut cmd = Command::cargo_bin("panacus")?;
+
+    cmd.arg("coverage-colors")
+        .arg("tests/test_files/t_groups.gfa")
+        .arg("--color-by")
+        .arg("class")
+        .arg("--output")
+        .arg(&output);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Name,Color\n"));
+
+    let graph = std::fs::read_to_string("tests/test_files/t_groups.gfa")?;
+    let colored = std::fs::read_to_string(&output)?;
+    assert_eq!(graph.lines().count(), colored.lines().count());
+    for (line, colored_line) in graph.lines().zip(colored.lines()) {
+        if line.starts_with("S\t") {
+            assert!(colored_line.starts_with(line));
+            assert!(colored_line.contains("\tCL:z:#"));
+        } else {
+            assert_eq!(line, colored_line);
+        }
+    }
+    Ok(())
+}
+
+#[ignore]
+#[test]
+fn coverage_colors_by_unknown_group_is_reported() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("panacus")?;
+
+    cmd.arg("coverage-colors")
+        .arg("tests/test_files/t_groups.gfa")
+        .arg("--color-by")
+        .arg("group")
+        .arg("--group")
+        .arg("no-such-group");
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains("no-such-group is not a group"));
+    Ok(())
+}
@@ -33,3 +33,41 @@ fn growth_html_gets_written_successfully() -> Result<(), Box<dyn std::error::Err
         .stdout(predicate::str::contains("panacus\thist\tgrowth"));
     Ok(())
 }
+
+#[ignore]
+#[test]
+fn growth_length_classes_get_written_successfully() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("panacus")?;
+
+    cmd.arg("growth")
+        .arg("tests/test_files/t_insertions.gfa")
+        .arg("-c")
+        .arg("bp")
+        .arg("-L")
+        .arg("1,3,5");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "length\t1-2bp\t1-2bp\t3-4bp\t3-4bp\t≥5bp\t≥5bp",
+        ))
+        .stdout(predicate::str::contains("4\t0\t2\t12\t15\t0\t5"));
+    Ok(())
+}
+
+#[ignore]
+#[test]
+fn growth_length_classes_reject_hist_and_alpha() -> Result<(), Box<dyn std::error::Error>> {
+    for flag in ["--hist", "--alpha"] {
+        let mut cmd = Command::cargo_bin("panacus")?;
+
+        cmd.arg("growth")
+            .arg("tests/test_files/t_insertions.gfa")
+            .arg("-L")
+            .arg("1,3,5")
+            .arg(flag);
+        cmd.assert()
+            .failure()
+            .stderr(predicate::str::contains("cannot be used with"));
+    }
+    Ok(())
+}
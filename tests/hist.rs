@@ -31,3 +31,94 @@ fn hist_html_gets_written_successfully() -> Result<(), Box<dyn std::error::Error
         .stdout(predicate::str::contains("panacus\thist"));
     Ok(())
 }
+
+#[ignore]
+#[test]
+fn hist_fails_on_missing_file() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("panacus")?;
+
+    cmd.arg("hist").arg("tests/test_files/does_not_exist.gfa");
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "could not read file tests/test_files/does_not_exist.gfa",
+    ));
+    Ok(())
+}
+
+#[ignore]
+#[test]
+fn hist_with_low_memory_equals_standard_hist() -> Result<(), Box<dyn std::error::Error>> {
+    // Tables without the header, which contains the command line
+    let get_hist = |max_memory: Option<&str>| -> Result<String, Box<dyn std::error::Error>> {
+        let mut cmd = Command::cargo_bin("panacus")?;
+        cmd.arg("hist")
+            .arg("tests/test_files/t_groups.gfa")
+            .arg("-c")
+            .arg("bp");
+        if let Some(max_memory) = max_memory {
+            cmd.arg("--max-memory").arg(max_memory);
+        }
+        let output = String::from_utf8(cmd.assert().success().get_output().stdout.clone())?;
+        Ok(output.lines().filter(|l| !l.starts_with('#')).collect())
+    };
+    assert_eq!(get_hist(Some("1"))?, get_hist(None)?);
+    assert_eq!(get_hist(Some("1G"))?, get_hist(None)?);
+    Ok(())
+}
+
+#[ignore]
+#[test]
+fn hist_of_bgzf_compressed_graph_equals_uncompressed() -> Result<(), Box<dyn std::error::Error>> {
+    // Tables without the header, which contains the command line
+    let get_hist = |graph: &str| -> Result<String, Box<dyn std::error::Error>> {
+        let mut cmd = Command::cargo_bin("panacus")?;
+        cmd.arg("hist").arg(graph).arg("-t").arg("2");
+        let output = String::from_utf8(cmd.assert().success().get_output().stdout.clone())?;
+        Ok(output
+            .lines()
+            .filter(|l| !l.starts_with('#'))
+            .map(|l| l.replace(graph, "GRAPH"))
+            .collect())
+    };
+    assert_eq!(
+        get_hist("tests/test_files/t_groups_bgzf.gfa.gz")?,
+        get_hist("tests/test_files/t_groups.gfa")?
+    );
+    Ok(())
+}
+
+#[ignore]
+#[test]
+fn hist_writes_profile_of_stages() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let profile_file = dir.path().join("profile.json");
+    let mut cmd = Command::cargo_bin("panacus")?;
+
+    cmd.arg("hist")
+        .arg("tests/test_files/t_groups.gfa")
+        .arg("--profile")
+        .arg(&profile_file);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("panacus\thist"));
+    let profile: serde_json::Value = serde_json::from_reader(std::fs::File::open(&profile_file)?)?;
+    let get_names = |stages: &serde_json::Value| -> Vec<String> {
+        stages
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|s| s["name"].as_str().unwrap().to_string())
+            .collect()
+    };
+    let stages = &profile["stages"];
+    assert_eq!(
+        get_names(stages),
+        vec!["graph parsing", "hist generation", "analysis: Hist"]
+    );
+    assert_eq!(
+        get_names(&stages[0]["stages"]),
+        vec!["node parsing", "edge parsing", "masking"]
+    );
+    assert_eq!(get_names(&stages[1]["stages"]), vec!["path parsing"]);
+    assert!(profile["wall_time_secs"].as_f64().unwrap() >= 0.0);
+    Ok(())
+}
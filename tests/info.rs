@@ -48,3 +48,27 @@ fn info_table_groups_get_written() -> Result<(), Box<dyn std::error::Error>> {
         .stdout(predicate::str::contains("group\ty\tnode\t10"));
     Ok(())
 }
+
+#[ignore]
+#[test]
+fn info_path_stats_get_written() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("panacus")?;
+
+    cmd.arg("info")
+        .arg("tests/test_files/t_path_stats.gfa")
+        .arg("-H")
+        .arg("-r")
+        .arg("R");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "path-stats\tgroup\tnodes\tbp\tunique-nodes\tsegments\tn-bp\tprivate-bp\treference-coverage",
+        ))
+        .stdout(predicate::str::contains(
+            "path-stats\ta#1\t4\t11\t4\t1\t4\t4\t1.0000",
+        ))
+        .stdout(predicate::str::contains(
+            "path-stats\tb#1\t3\t9\t3\t2\t0\t3\t0.8571",
+        ));
+    Ok(())
+}
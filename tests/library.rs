@@ -0,0 +1,69 @@
+use panacus::analyses::copy_number::CopyNumber;
+use panacus::analyses::growth::Growth;
+use panacus::analyses::similarity::Similarity;
+use panacus::ClusterMethod;
+use panacus::{CountType, FileFormatParser, GfaParser, GraphMaskParameters, PanacusError};
+
+fn get_parser(graph: &str, count_type: CountType) -> Result<Box<GfaParser>, PanacusError> {
+    Ok(Box::new(GfaParser::new(
+        graph,
+        count_type,
+        GraphMaskParameters::default(),
+        None,
+        false,
+    )?))
+}
+
+#[ignore]
+#[test]
+fn growth_result_is_computed_in_process() -> Result<(), PanacusError> {
+    let hist = get_parser("tests/test_files/t_groups.gfa", CountType::Node)?.generate_hist()?;
+    let mut growth = Growth::new(None, None, false, false);
+    let result = growth.get_result(&hist)?;
+    assert_eq!(result.growths.len(), 1);
+    let growth = &result.growths[0];
+    assert_eq!(growth.len(), hist.get_maximum_coverage() + 1);
+    // With all taxa, the growth is the number of countables
+    assert_eq!(
+        growth[hist.get_maximum_coverage()],
+        hist.get_hist_values()[1..].iter().sum::<usize>() as f64
+    );
+    Ok(())
+}
+
+#[ignore]
+#[test]
+fn similarity_result_is_computed_in_process() -> Result<(), PanacusError> {
+    let matrix =
+        get_parser("tests/test_files/t_repeats.gfa", CountType::Node)?.generate_matrix()?;
+    let mut similarity = Similarity::new(ClusterMethod::default());
+    let result = similarity.get_result(&matrix)?;
+    assert_eq!(result.labels.len(), 3);
+    for (i, row) in result.values.iter().enumerate() {
+        assert_eq!(row[i], 1.0);
+    }
+    Ok(())
+}
+
+#[ignore]
+#[test]
+fn copy_number_result_is_computed_in_process() -> Result<(), PanacusError> {
+    let matrix = get_parser("tests/test_files/t_repeats.gfa", CountType::Bp)?.generate_matrix()?;
+    let mut copy_number = CopyNumber::new(10);
+    let result = copy_number.get_result(&matrix)?;
+    let repeats: Vec<(String, usize)> = result
+        .repeats
+        .iter()
+        .map(|r| (r.group.clone(), r.repeat_bp))
+        .collect();
+    assert_eq!(
+        repeats,
+        vec![
+            ("a#1#c".to_string(), 6),
+            ("b#1#c".to_string(), 0),
+            ("c#1#c".to_string(), 6)
+        ]
+    );
+    assert_eq!(result.variable.len(), 2);
+    Ok(())
+}
@@ -0,0 +1,41 @@
+use assert_cmd::prelude::*; // Add methods on commands
+use predicates::prelude::*; // Used for writing assertions
+use std::process::Command; // Run programs
+
+#[ignore]
+#[test]
+fn non_reference_anchors_table_gets_written_successfully() -> Result<(), Box<dyn std::error::Error>>
+{
+    let mut cmd = Command::cargo_bin("panacus")?;
+
+    cmd.arg("non-reference-anchors")
+        .arg("tests/test_files/t_insertions.gfa")
+        .arg("-r")
+        .arg("R");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "#reference\tstart\tend\tanchor\tnodes\tbp\thaplotypes\talleles",
+        ))
+        .stdout(predicate::str::contains("R\t0\t4\t1\t1\t2\t2\t1"))
+        .stdout(predicate::str::contains("R\t4\t8\t3\t2\t8\t2\t2"));
+    Ok(())
+}
+
+#[ignore]
+#[test]
+fn non_reference_anchors_min_length_filters_clusters() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("panacus")?;
+
+    cmd.arg("non-reference-anchors")
+        .arg("tests/test_files/t_insertions.gfa")
+        .arg("-r")
+        .arg("R")
+        .arg("-m")
+        .arg("3");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("R\t0\t4\t1").not())
+        .stdout(predicate::str::contains("R\t4\t8\t3\t2\t8\t2\t2"));
+    Ok(())
+}
@@ -0,0 +1,71 @@
+use assert_cmd::prelude::*; // Add methods on commands
+use predicates::prelude::*; // Used for writing assertions
+use std::process::Command; // Run programs
+
+#[ignore]
+#[test]
+fn growth_tsv_is_written_in_long_format() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("panacus")?;
+
+    cmd.arg("growth")
+        .arg("tests/test_files/t_groups.gfa")
+        .arg("-q")
+        .arg("0,1")
+        .arg("-l")
+        .arg("1,1")
+        .arg("--output-format")
+        .arg("tsv");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::starts_with("# "))
+        .stdout(predicate::str::contains(
+            "\nanalysis\ttable\trun\tcount\trow\tcolumn\tunit\tcoverage\tquorum\tvalue\n",
+        ))
+        .stdout(predicate::str::contains("\tnode\t3\tgrowth\tnode\t1\t1\t"));
+    Ok(())
+}
+
+#[ignore]
+#[test]
+fn hist_json_is_valid() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("panacus")?;
+
+    cmd.arg("hist")
+        .arg("tests/test_files/t_groups.gfa")
+        .arg("--output-format")
+        .arg("json");
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let output: serde_json::Value = serde_json::from_slice(&output)?;
+    assert_eq!(
+        output["provenance"]["inputs"][0]["path"],
+        "tests/test_files/t_groups.gfa"
+    );
+    let tables = &output["tables"];
+    assert_eq!(tables[0]["analysis"], "Hist");
+    assert_eq!(tables[0]["columns"][1]["unit"], "node");
+    Ok(())
+}
+
+#[ignore]
+#[test]
+fn report_jsonl_has_one_line_per_table() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("panacus")?;
+
+    cmd.arg("report")
+        .arg("tests/test_files/t_groups_shared.yaml")
+        .arg("--output-format")
+        .arg("jsonl");
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let output = String::from_utf8(output)?;
+    let mut lines = output.lines();
+    let provenance: serde_json::Value = serde_json::from_str(lines.next().unwrap())?;
+    assert_eq!(
+        provenance["provenance"]["runs"].as_array().unwrap().len(),
+        3
+    );
+    for line in lines {
+        let table: serde_json::Value = serde_json::from_str(line)?;
+        assert!(table["rows"].is_array());
+    }
+    Ok(())
+}
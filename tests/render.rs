@@ -0,0 +1,83 @@
+use assert_cmd::prelude::*; // Add methods on commands
+use predicates::prelude::*; // Used for writing assertions
+use std::process::Command; // Run programs
+
+fn write_json_report(dir: &std::path::Path) -> Result<String, Box<dyn std::error::Error>> {
+    let json = Command::cargo_bin("panacus")?
+        .arg("report")
+        .arg("--json")
+        .arg("--checksum")
+        .arg("tests/test_files/t_groups_shared.yaml")
+        .output()?
+        .stdout;
+    let json_file = dir.join("report.json");
+    std::fs::write(&json_file, json)?;
+    Ok(json_file.to_string_lossy().to_string())
+}
+
+#[ignore]
+#[test]
+fn render_compares_runs_of_same_count_type() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let json_file = write_json_report(dir.path())?;
+    let mut cmd = Command::cargo_bin("panacus")?;
+
+    cmd.arg("render")
+        .arg("--compare")
+        .arg(&json_file)
+        .arg(&json_file);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("cmp-coverage-histogram-node"))
+        .stdout(predicate::str::contains("cmp-coverage-histogram-bp"))
+        .stdout(predicate::str::contains(
+            "tests/test_files/t_groups.gfa [2] - tests/test_files/t_groups.gfa [1]",
+        ));
+    Ok(())
+}
+
+#[ignore]
+#[test]
+fn render_compares_normalized_count_types() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let json_file = write_json_report(dir.path())?;
+    let mut cmd = Command::cargo_bin("panacus")?;
+
+    cmd.arg("render")
+        .arg("--compare")
+        .arg("--normalize")
+        .arg(&json_file);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "cmp-coverage-histogram-node-edge-bp",
+        ))
+        .stdout(predicate::str::contains("fraction of maximum"));
+    Ok(())
+}
+
+#[ignore]
+#[test]
+fn render_keeps_provenance_of_json_reports() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let json_file = write_json_report(dir.path())?;
+    let text = std::fs::read_to_string(&json_file)?;
+    // One report per run, the first one is enough
+    let report: serde_json::Value = serde_json::Deserializer::from_str(&text)
+        .into_iter()
+        .next()
+        .unwrap()?;
+    let graph = &report["provenance"]["inputs"][1];
+    assert_eq!(graph["path"], "tests/test_files/t_groups.gfa");
+    assert_eq!(graph["sha256"].as_str().unwrap().len(), 64);
+    let mut cmd = Command::cargo_bin("panacus")?;
+
+    cmd.arg("render").arg(&json_file);
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let output = String::from_utf8(output)?;
+    // The provenance of the config run, followed by the one of render
+    assert_eq!(output.matches("\"command\": ").count(), 2);
+    assert!(output.contains("t_groups_shared.yaml"));
+    assert!(output.contains("btn-download-provenance"));
+    Ok(())
+}
@@ -0,0 +1,374 @@
+use assert_cmd::prelude::*; // Add methods on commands
+use predicates::prelude::*; // Used for writing assertions
+use std::process::Command; // Run programs
+
+#[ignore]
+#[test]
+fn report_with_shared_graph_gets_written_successfully() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("panacus")?;
+
+    cmd.arg("report")
+        .arg("--json")
+        .arg("tests/test_files/t_groups_shared.yaml");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "count\\tnode\\n\\t\\n\\t\\n0\\t5\\n1\\t0\\n2\\t10\\n",
+        ))
+        .stdout(predicate::str::contains(
+            "count\\tedge\\n\\t\\n\\t\\n0\\t11\\n1\\t4\\n2\\t5\\n",
+        ))
+        .stdout(predicate::str::contains(
+            "count\\tbp\\n\\t\\n\\t\\n0\\t5\\n1\\t0\\n2\\t50\\n",
+        ));
+    Ok(())
+}
+
+#[ignore]
+#[test]
+fn report_shows_failed_analysis() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("panacus")?;
+
+    cmd.arg("report")
+        .arg("--json")
+        .arg("tests/test_files/t_failing_analysis.yaml");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "\"analysis\": \"Failed Analyses\"",
+        ))
+        .stdout(predicate::str::contains(
+            "Copy numbers are not available for edge counts",
+        ))
+        .stdout(predicate::str::contains(
+            "\"analysis\": \"Coverage Histogram\"",
+        ));
+    Ok(())
+}
+
+#[ignore]
+#[test]
+fn report_fails_in_strict_mode() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("panacus")?;
+
+    cmd.arg("report")
+        .arg("--strict")
+        .arg("tests/test_files/t_failing_analysis.yaml");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("analysis CopyNumber failed"));
+    Ok(())
+}
+
+#[ignore]
+#[test]
+fn report_runs_only_selected_config_blocks() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("panacus")?;
+
+    cmd.arg("report")
+        .arg("--json")
+        .arg("--only")
+        .arg("nodes")
+        .arg("--variable")
+        .arg("COUNT=Bp")
+        .arg("tests/test_files/t_named_runs.yaml");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("count\\tbp\\n"))
+        .stdout(predicate::str::contains("count\\tnode\\n").not())
+        .stdout(predicate::str::contains("count\\tedge\\n").not());
+    Ok(())
+}
+
+#[ignore]
+#[test]
+fn report_includes_other_configs() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("panacus")?;
+
+    cmd.arg("report")
+        .arg("--dry-run")
+        .arg("--skip")
+        .arg("edges")
+        .arg("tests/test_files/t_named_runs.yaml");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("count_type: Node").count(2))
+        .stdout(predicate::str::contains("count_type: Edge").count(1))
+        .stdout(predicate::str::contains("count_type: Bp").count(1));
+    Ok(())
+}
+
+#[ignore]
+#[test]
+fn report_fails_on_unknown_config_block() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("panacus")?;
+
+    cmd.arg("report")
+        .arg("--only")
+        .arg("unknown")
+        .arg("tests/test_files/t_named_runs.yaml");
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "no config block with name unknown was found",
+    ));
+    Ok(())
+}
+
+#[ignore]
+#[test]
+fn report_shows_custom_content() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("panacus")?;
+
+    cmd.arg("report").arg("tests/test_files/t_custom.yaml");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Sizes of the assemblies the graph was built from",
+        ))
+        .stdout(predicate::str::contains("<td>1042</td>"))
+        .stdout(predicate::str::contains(
+            "new VegaPlot('custom-tests/test_files/t_groups.gfa---sizes-plot'",
+        ));
+    Ok(())
+}
+
+#[ignore]
+#[test]
+fn report_shows_plugin_sections() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("panacus")?;
+
+    cmd.arg("report")
+        .arg("--json")
+        .arg("tests/test_files/t_plugin.yaml");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("\"analysis\": \"Input Size\""))
+        .stdout(predicate::str::contains("\"id\": \"plugin-hist\""))
+        .stdout(predicate::str::contains("\"analysis\": \"Matrix Size\""))
+        .stdout(predicate::str::contains("\"id\": \"plugin-matrix\""))
+        .stdout(predicate::str::contains("Failed Analyses").not());
+    Ok(())
+}
+
+#[ignore]
+#[test]
+fn report_validates_config() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("panacus")?;
+
+    cmd.arg("report")
+        .arg("--validate")
+        .arg("tests/test_files/t_named_runs.yaml");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("config is valid (5 runs)"));
+    Ok(())
+}
+
+#[ignore]
+#[test]
+fn report_validation_lists_all_problems() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("panacus")?;
+
+    cmd.arg("report")
+        .arg("--validate")
+        .arg("tests/test_files/t_invalid_config.yaml");
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains(
+            "missing-graph: graph file tests/test_files/does_not_exist.gfa does not exist",
+        ))
+        .stdout(predicate::str::contains(
+            "invalid-analyses: Growth: invalid thresholds",
+        ))
+        .stdout(predicate::str::contains(
+            "invalid-analyses: OrderedGrowth: z in tests/test_files/t_invalid_order.txt is not a group of the graph",
+        ))
+        .stdout(predicate::str::contains(
+            "invalid-analyses: Custom: custom file tests/test_files/t_groups.gfa is not a png, svg, pdf, json, tsv or csv file",
+        ))
+        .stdout(predicate::str::contains("y#1 in").not())
+        .stderr(predicate::str::contains("found 4 problem(s)"));
+    Ok(())
+}
+
+#[ignore]
+#[test]
+fn report_exports_plots() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let mut cmd = Command::cargo_bin("panacus")?;
+
+    cmd.arg("report")
+        .arg("--plots")
+        .arg(dir.path())
+        .arg("--plot-format")
+        .arg("svg,png,pdf")
+        .arg("tests/test_files/t_groups_shared.yaml");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("<!DOCTYPE html>"));
+    for countable in ["node", "edge", "bp"] {
+        let plot = format!("cov-hist-tests-test_files-t_groups.gfa---{countable}");
+        let svg = std::fs::read_to_string(dir.path().join(format!("{plot}.svg")))?;
+        assert!(svg.starts_with("<svg"));
+        let png = std::fs::read(dir.path().join(format!("{plot}.png")))?;
+        assert!(png.starts_with(b"\x89PNG"));
+        let pdf = std::fs::read(dir.path().join(format!("{plot}.pdf")))?;
+        assert!(pdf.starts_with(b"%PDF"));
+    }
+    Ok(())
+}
+
+#[ignore]
+#[test]
+fn export_plots_of_json_report() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let json = Command::cargo_bin("panacus")?
+        .arg("report")
+        .arg("--json")
+        .arg("tests/test_files/t_groups_shared.yaml")
+        .output()?
+        .stdout;
+    let json_file = dir.path().join("report.json");
+    std::fs::write(&json_file, json)?;
+    let mut cmd = Command::cargo_bin("panacus")?;
+
+    cmd.arg("export-plots")
+        .arg("-o")
+        .arg(dir.path().join("plots"))
+        .arg("--plot-width")
+        .arg("400")
+        .arg(&json_file);
+    cmd.assert().success();
+    let svg = std::fs::read_to_string(
+        dir.path()
+            .join("plots/cov-hist-tests-test_files-t_groups.gfa---node.svg"),
+    )?;
+    assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"400\""));
+    assert!(svg.contains("Coverage Histogram (node)"));
+    Ok(())
+}
+
+#[ignore]
+#[test]
+fn report_exports_multiqc_files() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let mut cmd = Command::cargo_bin("panacus")?;
+
+    cmd.arg("report")
+        .arg("--multiqc")
+        .arg(dir.path())
+        .arg("tests/test_files/t_groups_shared.yaml");
+    cmd.assert().success();
+    for countable in ["node", "edge", "bp"] {
+        let file = format!("cov-hist-tests-test_files-t_groups.gfa---{countable}_mqc.json");
+        let content: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(dir.path().join(file))?)?;
+        assert_eq!(
+            content["id"],
+            format!("panacus_coverage-histogram_{countable}")
+        );
+        assert_eq!(content["plot_type"], "linegraph");
+        assert!(content["data"]["tests/test_files/t_groups.gfa"].is_array());
+    }
+    Ok(())
+}
+
+#[ignore]
+#[test]
+fn report_compresses_large_sections() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("panacus")?;
+
+    cmd.arg("report")
+        .arg("--inline-limit")
+        .arg("0")
+        .arg("tests/test_files/t_groups_shared.yaml");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "\"blobs\": {\"cov-hist-tests/test_files/t_groups.gfa---node\": \"H4sI",
+        ))
+        .stdout(predicate::str::contains("\"table_blobs\": {"))
+        .stdout(predicate::str::contains("new Bar(").not());
+    Ok(())
+}
+
+#[ignore]
+#[test]
+fn report_writes_sidecar_files() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let mut cmd = Command::cargo_bin("panacus")?;
+
+    cmd.arg("report")
+        .arg("--sidecar-dir")
+        .arg(dir.path().join("data"))
+        .arg("--report-path")
+        .arg(dir.path().join("report.html"))
+        .arg("tests/test_files/t_groups_shared.yaml");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("\"sidecars\": {"))
+        .stdout(predicate::str::contains(
+            "\"data/cov-hist-tests-test_files-t_groups.gfa---node.js\"",
+        ))
+        .stdout(predicate::str::contains("new Bar(").not());
+    let sidecar = std::fs::read_to_string(
+        dir.path()
+            .join("data/cov-hist-tests-test_files-t_groups.gfa---node.js"),
+    )?;
+    assert!(sidecar
+        .contains("panacusDatasets[\"cov-hist-tests/test_files/t_groups.gfa---node\"] = new Bar("));
+    assert!(
+        sidecar.contains("panacusTables[\"cov-hist-tests/test_files/t_groups.gfa---node\"] = `")
+    );
+    Ok(())
+}
+
+#[ignore]
+#[test]
+fn export_multiqc_of_json_report() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let json = Command::cargo_bin("panacus")?
+        .arg("report")
+        .arg("--json")
+        .arg("tests/test_files/t_path_stats.yaml")
+        .output()?
+        .stdout;
+    let json_file = dir.path().join("report.json");
+    std::fs::write(&json_file, json)?;
+    let mut cmd = Command::cargo_bin("panacus")?;
+
+    cmd.arg("export-multiqc")
+        .arg("-o")
+        .arg(dir.path().join("multiqc"))
+        .arg(&json_file);
+    cmd.assert().success();
+    let tsv = std::fs::read_to_string(
+        dir.path()
+            .join("multiqc/tests-test_files-t_path_stats.gfa---file_mqc.tsv"),
+    )?;
+    assert!(tsv.starts_with("# id: \"panacus_info\"\n"));
+    assert!(tsv.contains("\ntests/test_files/t_path_stats.gfa\t5\t5\t"));
+    Ok(())
+}
+
+#[ignore]
+#[test]
+fn report_applies_display_options() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("panacus")?;
+
+    cmd.arg("report").arg("tests/test_files/t_display.yaml");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "<title>Pangenome of t_groups</title>",
+        ))
+        .stdout(predicate::str::contains(
+            "<p>Coverage of the <strong>nodes</strong> of all paths.</p>",
+        ))
+        .stdout(predicate::str::contains(
+            "<p>Number of <em>paths</em> per node</p>",
+        ))
+        .stdout(predicate::str::contains("Node Coverage"))
+        .stdout(predicate::str::contains("\"display\": {"))
+        .stdout(predicate::str::contains("\"log_y\":true"));
+    Ok(())
+}
@@ -0,0 +1,34 @@
+use assert_cmd::prelude::*; // Add methods on commands
+use predicates::prelude::*; // Used for writing assertions
+use std::process::Command; // Run programs
+
+#[ignore]
+#[test]
+fn subgraph_layout_table_gets_written() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("panacus")?;
+
+    cmd.arg("subgraph-layout")
+        .arg("tests/test_files/t_insertions.gfa")
+        .arg("tests/test_files/t_insertions.bed");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "region\tnode\tlength\tcoverage\tx1\ty1\tx2\ty2\n",
+        ))
+        .stdout(predicate::str::contains("R:0-12\t6\t5\t1\t"));
+    Ok(())
+}
+
+#[ignore]
+#[test]
+fn subgraph_layout_is_drawn_in_report() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("panacus")?;
+
+    cmd.arg("report").arg("tests/test_files/t_layout.yaml");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Subgraph Layout"))
+        .stdout(predicate::str::contains("<polyline"))
+        .stdout(predicate::str::contains("paths through R:0-12"));
+    Ok(())
+}
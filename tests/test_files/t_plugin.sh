@@ -0,0 +1,9 @@
+#!/bin/sh
+# Plugin for the tests: reports the type and size of its input, which is
+# read from the file given as argument or from stdin
+input=$(if [ -n "$1" ]; then cat "$1"; else cat; fi)
+case "$input" in
+  *'"input":"matrix"'*) type=matrix ;;
+  *) type=hist ;;
+esac
+echo "[{\"items\": [{\"Table\": {\"id\": \"plugin-$type\", \"header\": [\"input\", \"bytes\"], \"values\": [[\"$type\", \"${#input}\"]]}}]}]"